@@ -0,0 +1,25 @@
+use tauri::State;
+
+use crate::db::business;
+use crate::error::AppResult;
+use crate::models::{BusinessDetails, BusinessProfile};
+use crate::DbState;
+
+#[tauri::command]
+pub fn get_business_profile(state: State<DbState>) -> AppResult<BusinessProfile> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    business::get_business_profile(&conn)
+}
+
+#[tauri::command]
+pub fn update_business_profile(
+    state: State<DbState>,
+    input: BusinessDetails,
+) -> AppResult<BusinessProfile> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    business::update_business_profile(&conn, input)
+}
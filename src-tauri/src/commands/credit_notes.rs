@@ -0,0 +1,33 @@
+use tauri::State;
+
+use crate::db::credit_notes;
+use crate::error::AppResult;
+use crate::models::{CreateCreditNote, CreditNote};
+use crate::DbState;
+
+#[tauri::command]
+pub fn create_credit_note(state: State<DbState>, input: CreateCreditNote) -> AppResult<CreditNote> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    credit_notes::create_credit_note(&conn, input)
+}
+
+#[tauri::command]
+pub fn get_credit_note(state: State<DbState>, id: String) -> AppResult<CreditNote> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    credit_notes::get_credit_note(&conn, &id)
+}
+
+#[tauri::command]
+pub fn list_credit_notes(
+    state: State<DbState>,
+    invoice_id: Option<String>,
+) -> AppResult<Vec<CreditNote>> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    credit_notes::list_credit_notes(&conn, invoice_id.as_deref())
+}
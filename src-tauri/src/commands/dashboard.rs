@@ -55,3 +55,11 @@ pub fn get_estimate_accuracy(
     })?;
     dashboard::get_estimate_accuracy(&conn)
 }
+
+#[tauri::command]
+pub fn get_aging_report(state: State<DbState>) -> AppResult<Vec<dashboard::AgingBucket>> {
+    let conn = state.0.lock().map_err(|e| {
+        AppError::Database(rusqlite::Error::InvalidParameterName(e.to_string()))
+    })?;
+    dashboard::get_aging_report(&conn, chrono::Local::now().date_naive())
+}
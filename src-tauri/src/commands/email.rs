@@ -0,0 +1,48 @@
+use tauri::State;
+
+use crate::db::emails;
+use crate::error::{AppError, AppResult};
+use crate::models::{InvoiceEmail, PageSize};
+use crate::services::email;
+use crate::DbState;
+
+/// Emails the invoice PDF to the client and logs the attempt. A failed send is
+/// logged too, then returned as the error.
+#[tauri::command]
+pub async fn send_invoice_email(
+    state: State<'_, DbState>,
+    invoice_id: String,
+    page_size: Option<PageSize>,
+) -> AppResult<InvoiceEmail> {
+    // Compose under the lock, then release it while talking to the SMTP server
+    let outgoing = {
+        let conn = state.0.lock().map_err(|e| {
+            AppError::Database(rusqlite::Error::InvalidParameterName(e.to_string()))
+        })?;
+        email::compose_invoice_email(&conn, &invoice_id, page_size.unwrap_or_default())?
+    };
+
+    let result = email::send(&outgoing).await;
+
+    let conn = state.0.lock().map_err(|e| {
+        AppError::Database(rusqlite::Error::InvalidParameterName(e.to_string()))
+    })?;
+    let error = result.as_ref().err().map(|e| e.to_string());
+    let logged = emails::record_email(
+        &conn,
+        &invoice_id,
+        outgoing.invoice_updated_at,
+        &outgoing.recipient,
+        &outgoing.subject,
+        error.as_deref(),
+    )?;
+    result.map(|_| logged)
+}
+
+#[tauri::command]
+pub fn list_invoice_emails(state: State<DbState>, invoice_id: String) -> AppResult<Vec<InvoiceEmail>> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    emails::list_invoice_emails(&conn, &invoice_id)
+}
@@ -1,8 +1,13 @@
 use tauri::State;
 
-use crate::db::{invoices, time_entries};
+use crate::db::{invoices, taxes, time_entries};
 use crate::error::AppResult;
-use crate::models::{Invoice, InvoiceLineItem, InvoiceStatus, TimeEntry};
+use crate::models::{
+    CreateInvoiceFromEntries, Discount, Invoice, InvoiceLineItem, InvoiceStatus, InvoiceTax,
+    LineItemUnit, TimeEntry, UpdateInvoice, UpdateLineItem,
+};
+use crate::services::invoice;
+use crate::services::numbering::{self, DocumentKind};
 use crate::DbState;
 
 #[tauri::command]
@@ -10,14 +15,62 @@ pub fn create_invoice(
     state: State<DbState>,
     client_id: String,
     issue_date: String,
-    due_date: String,
+    due_date: Option<String>,
     notes: Option<String>,
     tax_rate: Option<f64>,
 ) -> AppResult<Invoice> {
     let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
         rusqlite::Error::InvalidParameterName(e.to_string()),
     ))?;
-    invoices::create_invoice(&conn, &client_id, &issue_date, &due_date, notes.as_deref(), tax_rate)
+    invoices::create_invoice(
+        &conn,
+        &client_id,
+        &issue_date,
+        due_date.as_deref(),
+        notes.as_deref(),
+        tax_rate,
+    )
+}
+
+#[tauri::command]
+pub fn update_invoice(state: State<DbState>, id: String, input: UpdateInvoice) -> AppResult<Invoice> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    invoices::update_invoice(&conn, &id, input)
+}
+
+/// Copies an invoice into a new draft issued on `issue_date`, or today.
+#[tauri::command]
+pub fn duplicate_invoice(
+    state: State<DbState>,
+    id: String,
+    issue_date: Option<String>,
+    due_date: Option<String>,
+) -> AppResult<Invoice> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    let issue_date = issue_date.unwrap_or_else(|| {
+        chrono::Local::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .unwrap_or_default()
+            .and_utc()
+            .to_rfc3339()
+    });
+    invoices::duplicate_invoice(&conn, &id, &issue_date, due_date.as_deref())
+}
+
+#[tauri::command]
+pub fn create_invoice_from_entries(
+    state: State<DbState>,
+    input: CreateInvoiceFromEntries,
+) -> AppResult<Invoice> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    invoice::create_invoice_from_entries(&conn, input)
 }
 
 #[tauri::command]
@@ -64,13 +117,47 @@ pub fn add_line_item(
     invoice_id: String,
     description: String,
     quantity: f64,
-    unit_price: f64,
+    unit_price: i64,
+    unit: Option<LineItemUnit>,
     sort_order: i32,
 ) -> AppResult<InvoiceLineItem> {
     let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
         rusqlite::Error::InvalidParameterName(e.to_string()),
     ))?;
-    invoices::add_line_item(&conn, &invoice_id, &description, quantity, unit_price, sort_order)
+    invoices::add_line_item(
+        &conn,
+        &invoice_id,
+        &description,
+        quantity,
+        unit_price,
+        unit.unwrap_or_default(),
+        sort_order,
+    )
+}
+
+#[tauri::command]
+pub fn update_line_item(
+    state: State<DbState>,
+    invoice_id: String,
+    line_item_id: String,
+    input: UpdateLineItem,
+) -> AppResult<InvoiceLineItem> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    invoices::update_line_item(&conn, &invoice_id, &line_item_id, input)
+}
+
+#[tauri::command]
+pub fn reorder_line_items(
+    state: State<DbState>,
+    invoice_id: String,
+    line_item_ids: Vec<String>,
+) -> AppResult<Vec<InvoiceLineItem>> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    invoices::reorder_line_items(&conn, &invoice_id, &line_item_ids)
 }
 
 #[tauri::command]
@@ -104,3 +191,75 @@ pub fn set_payment_link(state: State<DbState>, id: String, link: String) -> AppR
     ))?;
     invoices::set_payment_link(&conn, &id, &link)
 }
+
+#[tauri::command]
+pub fn mark_overdue_invoices(state: State<DbState>) -> AppResult<Vec<Invoice>> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    invoices::mark_overdue_invoices(&conn, chrono::Local::now().date_naive(), chrono::Utc::now())
+}
+
+/// The number the next invoice for `client_id` (or a client without a prefix) would get today.
+#[tauri::command]
+pub fn preview_invoice_number(state: State<DbState>, client_id: Option<String>) -> AppResult<String> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    let prefix = match client_id {
+        Some(id) => crate::db::clients::get_client(&conn, &id)?.invoice_prefix,
+        None => None,
+    };
+    numbering::preview_number(
+        &conn,
+        DocumentKind::Invoice,
+        prefix.as_deref(),
+        chrono::Local::now().date_naive(),
+    )
+}
+
+#[tauri::command]
+pub fn set_line_item_taxes(
+    state: State<DbState>,
+    invoice_id: String,
+    line_item_id: String,
+    tax_ids: Vec<String>,
+) -> AppResult<InvoiceLineItem> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    invoices::set_line_item_taxes(&conn, &invoice_id, &line_item_id, &tax_ids)
+}
+
+#[tauri::command]
+pub fn get_invoice_taxes(state: State<DbState>, invoice_id: String) -> AppResult<Vec<InvoiceTax>> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    taxes::get_invoice_taxes(&conn, &invoice_id)
+}
+
+#[tauri::command]
+pub fn set_line_item_discount(
+    state: State<DbState>,
+    invoice_id: String,
+    line_item_id: String,
+    discount: Option<Discount>,
+) -> AppResult<InvoiceLineItem> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    invoices::set_line_item_discount(&conn, &invoice_id, &line_item_id, discount)
+}
+
+#[tauri::command]
+pub fn set_invoice_discount(
+    state: State<DbState>,
+    invoice_id: String,
+    discount: Option<Discount>,
+) -> AppResult<Invoice> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    invoices::set_invoice_discount(&conn, &invoice_id, discount)
+}
@@ -1,9 +1,16 @@
+pub mod business;
 pub mod clients;
+pub mod credit_notes;
 pub mod dashboard;
+pub mod email;
 pub mod estimates;
 pub mod invoices;
+pub mod payments;
 pub mod pdf;
 pub mod projects;
+pub mod recurring_invoices;
 pub mod settings;
 pub mod stripe;
+pub mod taxes;
+pub mod templates;
 pub mod timer;
@@ -0,0 +1,30 @@
+use tauri::State;
+
+use crate::db::payments;
+use crate::error::AppResult;
+use crate::models::{Payment, RecordPayment};
+use crate::DbState;
+
+#[tauri::command]
+pub fn record_payment(state: State<DbState>, input: RecordPayment) -> AppResult<Payment> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    payments::record_payment(&conn, input)
+}
+
+#[tauri::command]
+pub fn list_payments(state: State<DbState>, invoice_id: String) -> AppResult<Vec<Payment>> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    payments::list_payments(&conn, &invoice_id)
+}
+
+#[tauri::command]
+pub fn void_payment(state: State<DbState>, id: String) -> AppResult<Payment> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    payments::void_payment(&conn, &id)
+}
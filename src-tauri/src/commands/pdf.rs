@@ -2,42 +2,73 @@ use std::path::PathBuf;
 use tauri::State;
 
 use crate::error::AppResult;
+use crate::models::PageSize;
 use crate::services::pdf;
 use crate::DbState;
 
 #[tauri::command]
-pub fn render_invoice_html(
+pub fn render_invoice_html(state: State<DbState>, invoice_id: String) -> AppResult<String> {
+    let conn = state.0.lock().map_err(|e| {
+        crate::error::AppError::Database(rusqlite::Error::InvalidParameterName(e.to_string()))
+    })?;
+    pdf::render_invoice_html(&conn, &invoice_id)
+}
+
+#[tauri::command]
+pub fn export_invoice_html(
     state: State<DbState>,
     invoice_id: String,
-    business_name: String,
-    business_email: String,
-    business_address: String,
+    output_dir: String,
 ) -> AppResult<String> {
     let conn = state.0.lock().map_err(|e| {
         crate::error::AppError::Database(rusqlite::Error::InvalidParameterName(e.to_string()))
     })?;
-    pdf::render_invoice_html(&conn, &invoice_id, &business_name, &business_email, &business_address)
+    let path = pdf::save_invoice_html(&conn, &invoice_id, &PathBuf::from(output_dir))?;
+    Ok(path.to_string_lossy().to_string())
 }
 
 #[tauri::command]
-pub fn export_invoice_html(
+pub fn export_invoice_pdf(
     state: State<DbState>,
     invoice_id: String,
-    business_name: String,
-    business_email: String,
-    business_address: String,
     output_dir: String,
+    page_size: Option<PageSize>,
 ) -> AppResult<String> {
     let conn = state.0.lock().map_err(|e| {
         crate::error::AppError::Database(rusqlite::Error::InvalidParameterName(e.to_string()))
     })?;
-    let path = pdf::save_invoice_html(
+    let path = pdf::save_invoice_pdf(
         &conn,
         &invoice_id,
-        &business_name,
-        &business_email,
-        &business_address,
         &PathBuf::from(output_dir),
+        page_size.unwrap_or_default(),
+    )?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub fn render_credit_note_html(state: State<DbState>, credit_note_id: String) -> AppResult<String> {
+    let conn = state.0.lock().map_err(|e| {
+        crate::error::AppError::Database(rusqlite::Error::InvalidParameterName(e.to_string()))
+    })?;
+    pdf::render_credit_note_html(&conn, &credit_note_id)
+}
+
+#[tauri::command]
+pub fn export_credit_note_pdf(
+    state: State<DbState>,
+    credit_note_id: String,
+    output_dir: String,
+    page_size: Option<PageSize>,
+) -> AppResult<String> {
+    let conn = state.0.lock().map_err(|e| {
+        crate::error::AppError::Database(rusqlite::Error::InvalidParameterName(e.to_string()))
+    })?;
+    let path = pdf::save_credit_note_pdf(
+        &conn,
+        &credit_note_id,
+        &PathBuf::from(output_dir),
+        page_size.unwrap_or_default(),
     )?;
     Ok(path.to_string_lossy().to_string())
 }
@@ -0,0 +1,73 @@
+use tauri::State;
+
+use crate::db::recurring_invoices;
+use crate::error::AppResult;
+use crate::models::{CreateRecurringInvoice, Invoice, RecurringInvoice, RecurringOccurrence};
+use crate::services::recurring;
+use crate::DbState;
+
+#[tauri::command]
+pub fn create_recurring_invoice(
+    state: State<DbState>,
+    input: CreateRecurringInvoice,
+) -> AppResult<RecurringInvoice> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    recurring_invoices::create_recurring_invoice(&conn, input)
+}
+
+#[tauri::command]
+pub fn get_recurring_invoice(state: State<DbState>, id: String) -> AppResult<RecurringInvoice> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    recurring_invoices::get_recurring_invoice(&conn, &id)
+}
+
+#[tauri::command]
+pub fn list_recurring_invoices(state: State<DbState>) -> AppResult<Vec<RecurringInvoice>> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    recurring_invoices::list_recurring_invoices(&conn)
+}
+
+#[tauri::command]
+pub fn set_recurring_invoice_active(
+    state: State<DbState>,
+    id: String,
+    is_active: bool,
+) -> AppResult<RecurringInvoice> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    recurring_invoices::set_recurring_invoice_active(&conn, &id, is_active)
+}
+
+#[tauri::command]
+pub fn delete_recurring_invoice(state: State<DbState>, id: String) -> AppResult<()> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    recurring_invoices::delete_recurring_invoice(&conn, &id)
+}
+
+#[tauri::command]
+pub fn list_recurring_occurrences(
+    state: State<DbState>,
+    recurring_invoice_id: String,
+) -> AppResult<Vec<RecurringOccurrence>> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    recurring_invoices::list_occurrences(&conn, &recurring_invoice_id)
+}
+
+#[tauri::command]
+pub fn generate_due_recurring_invoices(state: State<DbState>) -> AppResult<Vec<Invoice>> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    recurring::generate_due_invoices(&conn, chrono::Local::now().date_naive())
+}
@@ -1,25 +1,21 @@
-use rusqlite::params;
 use tauri::State;
 
+use crate::db::{business, clients, settings, with_savepoint};
 use crate::error::AppResult;
 use crate::models::AppSetting;
+use crate::services::{currency, email, locale, money, numbering, overlap, terms, timer};
 use crate::DbState;
 
+/// Write-only secrets such as the SMTP password read as unset.
 #[tauri::command]
 pub fn get_setting(state: State<DbState>, key: String) -> AppResult<Option<String>> {
+    if settings::is_write_only(&key) {
+        return Ok(None);
+    }
     let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
         rusqlite::Error::InvalidParameterName(e.to_string()),
     ))?;
-    let result = conn.query_row(
-        "SELECT value FROM app_settings WHERE key = ?1",
-        params![key],
-        |row| row.get::<_, String>(0),
-    );
-    match result {
-        Ok(value) => Ok(Some(value)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(crate::error::AppError::Database(e)),
-    }
+    settings::get_setting(&conn, &key)
 }
 
 #[tauri::command]
@@ -27,11 +23,19 @@ pub fn set_setting(state: State<DbState>, key: String, value: String) -> AppResu
     let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
         rusqlite::Error::InvalidParameterName(e.to_string()),
     ))?;
-    conn.execute(
-        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
-        params![key, value],
-    )?;
-    Ok(())
+    numbering::validate_setting(&conn, &key, &value)?;
+    terms::validate_setting(&key, &value)?;
+    locale::validate_setting(&key, &value)?;
+    email::validate_setting(&key, &value)?;
+    overlap::validate_setting(&key, &value)?;
+    timer::validate_setting(&key, &value)?;
+    money::validate_setting(&key, &value)?;
+    currency::validate_setting(&key, &value)?;
+    with_savepoint(&conn, "set_setting", || {
+        business::sync_legacy_setting(&conn, &key, &value)?;
+        clients::sync_default_currency(&conn, &key, &value)?;
+        settings::set_setting(&conn, &key, &value)
+    })
 }
 
 #[tauri::command]
@@ -39,14 +43,5 @@ pub fn get_all_settings(state: State<DbState>) -> AppResult<Vec<AppSetting>> {
     let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
         rusqlite::Error::InvalidParameterName(e.to_string()),
     ))?;
-    let mut stmt = conn.prepare("SELECT key, value FROM app_settings")?;
-    let settings = stmt
-        .query_map([], |row| {
-            Ok(AppSetting {
-                key: row.get(0)?,
-                value: row.get(1)?,
-            })
-        })?
-        .collect::<Result<Vec<_>, _>>()?;
-    Ok(settings)
+    settings::get_all_settings(&conn)
 }
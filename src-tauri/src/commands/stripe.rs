@@ -1,6 +1,6 @@
 use tauri::State;
 
-use crate::db::invoices;
+use crate::db::{invoices, payments};
 use crate::error::{AppError, AppResult};
 use crate::services::{licensing, stripe};
 use crate::DbState;
@@ -12,24 +12,23 @@ pub async fn create_payment_link(
     invoice_id: String,
 ) -> AppResult<String> {
     // Check tier and get invoice data under the lock, then release
-    let (total, description) = {
+    let (amount, currency_code, description) = {
         let conn = state.0.lock().map_err(|e| {
             AppError::Database(rusqlite::Error::InvalidParameterName(e.to_string()))
         })?;
 
         licensing::check_stripe_access(&conn)?;
 
-        let invoice = invoices::get_invoice(&conn, &invoice_id)?;
+        let invoice = payments::ensure_payable(&conn, &invoice_id)?;
         let desc = format!("Invoice {}", invoice.invoice_number);
-        (invoice.total, desc)
+        // Balances are stored in minor units, which is what Stripe expects
+        (invoice.balance_due, invoice.currency, desc)
     };
 
-    let amount_cents = (total * 100.0) as i64;
-
     let url = stripe::create_checkout_session(
         &api_key,
-        amount_cents,
-        "usd",
+        amount,
+        &currency_code.to_lowercase(),
         &description,
         "https://example.com/payment-success",
         "https://example.com/payment-cancelled",
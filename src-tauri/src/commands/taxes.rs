@@ -0,0 +1,46 @@
+use tauri::State;
+
+use crate::db::taxes;
+use crate::error::AppResult;
+use crate::models::{CreateTax, Tax, UpdateTax};
+use crate::DbState;
+
+#[tauri::command]
+pub fn create_tax(state: State<DbState>, input: CreateTax) -> AppResult<Tax> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    taxes::create_tax(&conn, input)
+}
+
+#[tauri::command]
+pub fn get_tax(state: State<DbState>, id: String) -> AppResult<Tax> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    taxes::get_tax(&conn, &id)
+}
+
+#[tauri::command]
+pub fn list_taxes(state: State<DbState>, include_inactive: Option<bool>) -> AppResult<Vec<Tax>> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    taxes::list_taxes(&conn, include_inactive.unwrap_or(false))
+}
+
+#[tauri::command]
+pub fn update_tax(state: State<DbState>, id: String, input: UpdateTax) -> AppResult<Tax> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    taxes::update_tax(&conn, &id, input)
+}
+
+#[tauri::command]
+pub fn delete_tax(state: State<DbState>, id: String) -> AppResult<()> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    taxes::delete_tax(&conn, &id)
+}
@@ -0,0 +1,67 @@
+use tauri::State;
+
+use crate::db::templates;
+use crate::error::AppResult;
+use crate::models::{CreateInvoiceTemplate, InvoiceTemplate, UpdateInvoiceTemplate};
+use crate::services::pdf;
+use crate::DbState;
+
+#[tauri::command]
+pub fn create_template(state: State<DbState>, input: CreateInvoiceTemplate) -> AppResult<InvoiceTemplate> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    templates::create_template(&conn, input)
+}
+
+#[tauri::command]
+pub fn get_template(state: State<DbState>, id: String) -> AppResult<InvoiceTemplate> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    templates::get_template(&conn, &id)
+}
+
+#[tauri::command]
+pub fn list_templates(state: State<DbState>) -> AppResult<Vec<InvoiceTemplate>> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    templates::list_templates(&conn)
+}
+
+#[tauri::command]
+pub fn update_template(
+    state: State<DbState>,
+    id: String,
+    input: UpdateInvoiceTemplate,
+) -> AppResult<InvoiceTemplate> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    templates::update_template(&conn, &id, input)
+}
+
+#[tauri::command]
+pub fn delete_template(state: State<DbState>, id: String) -> AppResult<()> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    templates::delete_template(&conn, &id)
+}
+
+/// Renders an unsaved template with sample data.
+#[tauri::command]
+pub fn preview_template(
+    body: String,
+    brand_color: Option<String>,
+    logo: Option<String>,
+) -> AppResult<String> {
+    pdf::preview_template(&body, brand_color.as_deref(), logo.as_deref())
+}
+
+/// The built-in template, as a starting point for a custom one.
+#[tauri::command]
+pub fn get_builtin_template() -> String {
+    pdf::BUILTIN_TEMPLATE.to_string()
+}
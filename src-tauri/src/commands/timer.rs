@@ -1,8 +1,12 @@
+use chrono::{DateTime, Utc};
 use tauri::State;
 
 use crate::db::time_entries;
 use crate::error::AppResult;
-use crate::models::{ActiveTimer, CreateManualTimeEntry, TimeEntry, TimerState};
+use crate::models::{
+    ActiveTimer, CreateManualTimeEntry, IdleAction, SwitchTimer, TimeConflict, TimeEntry,
+    TimeEntryRevision, TimeSegment, TimerState, UpdateTimeEntry,
+};
 use crate::DbState;
 
 #[tauri::command]
@@ -10,35 +14,72 @@ pub fn start_timer(
     state: State<DbState>,
     project_id: String,
     description: Option<String>,
+    name: Option<String>,
 ) -> AppResult<ActiveTimer> {
     let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
         rusqlite::Error::InvalidParameterName(e.to_string()),
     ))?;
-    time_entries::start_timer(&conn, &project_id, description.as_deref())
+    time_entries::start_timer(&conn, &project_id, description.as_deref(), name.as_deref())
 }
 
 #[tauri::command]
-pub fn stop_timer(state: State<DbState>) -> AppResult<TimeEntry> {
+pub fn stop_timer(
+    state: State<DbState>,
+    timer_id: Option<i32>,
+    idle: Option<IdleAction>,
+) -> AppResult<TimeEntry> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    time_entries::stop_timer(&conn, timer_id, idle)
+}
+
+#[tauri::command]
+pub fn pause_timer(state: State<DbState>, timer_id: Option<i32>) -> AppResult<ActiveTimer> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    time_entries::pause_timer(&conn, timer_id)
+}
+
+#[tauri::command]
+pub fn resume_timer(
+    state: State<DbState>,
+    timer_id: Option<i32>,
+    idle: Option<IdleAction>,
+) -> AppResult<ActiveTimer> {
     let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
         rusqlite::Error::InvalidParameterName(e.to_string()),
     ))?;
-    time_entries::stop_timer(&conn)
+    time_entries::resume_timer(&conn, timer_id, idle)
 }
 
 #[tauri::command]
-pub fn pause_timer(state: State<DbState>) -> AppResult<ActiveTimer> {
+pub fn switch_timer(state: State<DbState>, input: SwitchTimer) -> AppResult<ActiveTimer> {
     let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
         rusqlite::Error::InvalidParameterName(e.to_string()),
     ))?;
-    time_entries::pause_timer(&conn)
+    time_entries::switch_timer(&conn, input)
 }
 
 #[tauri::command]
-pub fn resume_timer(state: State<DbState>) -> AppResult<ActiveTimer> {
+pub fn list_timers(state: State<DbState>) -> AppResult<Vec<TimerState>> {
     let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
         rusqlite::Error::InvalidParameterName(e.to_string()),
     ))?;
-    time_entries::resume_timer(&conn)
+    time_entries::list_timer_states(&conn)
+}
+
+/// `at` is when the activity happened; it defaults to now.
+#[tauri::command]
+pub fn timer_heartbeat(
+    state: State<DbState>,
+    at: Option<DateTime<Utc>>,
+) -> AppResult<TimerState> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    time_entries::record_timer_activity(&conn, at.unwrap_or_else(Utc::now))
 }
 
 #[tauri::command]
@@ -71,6 +112,40 @@ pub fn list_time_entries(
     time_entries::list_time_entries_by_project(&conn, &project_id)
 }
 
+#[tauri::command]
+pub fn update_time_entry(
+    state: State<DbState>,
+    id: String,
+    input: UpdateTimeEntry,
+) -> AppResult<TimeEntry> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    time_entries::update_time_entry(&conn, &id, input)
+}
+
+#[tauri::command]
+pub fn list_time_entry_revisions(
+    state: State<DbState>,
+    id: String,
+) -> AppResult<Vec<TimeEntryRevision>> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    time_entries::list_time_entry_revisions(&conn, &id)
+}
+
+#[tauri::command]
+pub fn list_time_entry_segments(
+    state: State<DbState>,
+    id: String,
+) -> AppResult<Vec<TimeSegment>> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    time_entries::list_time_entry_segments(&conn, &id)
+}
+
 #[tauri::command]
 pub fn delete_time_entry(state: State<DbState>, id: String) -> AppResult<()> {
     let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
@@ -78,3 +153,11 @@ pub fn delete_time_entry(state: State<DbState>, id: String) -> AppResult<()> {
     ))?;
     time_entries::delete_time_entry(&conn, &id)
 }
+
+#[tauri::command]
+pub fn find_time_conflicts(state: State<DbState>) -> AppResult<Vec<TimeConflict>> {
+    let conn = state.0.lock().map_err(|e| crate::error::AppError::Database(
+        rusqlite::Error::InvalidParameterName(e.to_string()),
+    ))?;
+    time_entries::find_time_conflicts(&conn)
+}
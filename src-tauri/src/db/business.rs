@@ -0,0 +1,249 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{BusinessDetails, BusinessProfile};
+use crate::services::branding;
+
+fn row_to_profile(row: &rusqlite::Row) -> rusqlite::Result<BusinessProfile> {
+    Ok(BusinessProfile {
+        legal_name: row.get("legal_name")?,
+        email: row.get("email")?,
+        phone: row.get("phone")?,
+        website: row.get("website")?,
+        address_lines: row
+            .get::<_, Option<String>>("address")?
+            .map(|address| address.lines().map(str::to_string).collect())
+            .unwrap_or_default(),
+        tax_id: row.get("tax_id")?,
+        iban: row.get("iban")?,
+        bank_details: row.get("bank_details")?,
+        logo_path: row.get("logo_path")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+/// An empty profile until one is saved.
+pub fn get_business_profile(conn: &Connection) -> AppResult<BusinessProfile> {
+    let profile = conn
+        .query_row("SELECT * FROM business_profile WHERE id = 1", [], row_to_profile)
+        .optional()?;
+    Ok(profile.unwrap_or_default())
+}
+
+pub fn update_business_profile(
+    conn: &Connection,
+    input: BusinessDetails,
+) -> AppResult<BusinessProfile> {
+    let profile = with_details(get_business_profile(conn)?, &normalize(input)?);
+    let address = (!profile.address_lines.is_empty()).then(|| profile.address_lines.join("\n"));
+
+    conn.execute(
+        "INSERT INTO business_profile (id, legal_name, email, phone, website, address, tax_id, iban, bank_details, logo_path, updated_at)
+         VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+         ON CONFLICT(id) DO UPDATE SET
+             legal_name = excluded.legal_name, email = excluded.email, phone = excluded.phone,
+             website = excluded.website, address = excluded.address, tax_id = excluded.tax_id,
+             iban = excluded.iban, bank_details = excluded.bank_details,
+             logo_path = excluded.logo_path, updated_at = excluded.updated_at",
+        params![
+            profile.legal_name,
+            profile.email,
+            profile.phone,
+            profile.website,
+            address,
+            profile.tax_id,
+            profile.iban,
+            profile.bank_details,
+            profile.logo_path,
+            Utc::now().to_rfc3339(),
+        ],
+    )?;
+    get_business_profile(conn)
+}
+
+/// Trims every field and checks the IBAN and logo, so stored details always render.
+/// Blank strings stay blank: they clear the field they're applied to.
+pub(crate) fn normalize(details: BusinessDetails) -> AppResult<BusinessDetails> {
+    let trim = |value: Option<String>| value.map(|v| v.trim().to_string());
+    let iban = match trim(details.iban) {
+        Some(iban) if !iban.is_empty() => Some(normalize_iban(&iban)?),
+        other => other,
+    };
+    let logo_path = trim(details.logo_path);
+    if let Some(path) = logo_path.as_deref().filter(|p| !p.is_empty()) {
+        branding::logo_from_file(Path::new(path))?;
+    }
+    Ok(BusinessDetails {
+        legal_name: trim(details.legal_name),
+        email: trim(details.email),
+        phone: trim(details.phone),
+        website: trim(details.website),
+        address_lines: details.address_lines.map(|lines| {
+            lines
+                .iter()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect()
+        }),
+        tax_id: trim(details.tax_id),
+        iban,
+        bank_details: trim(details.bank_details),
+        logo_path,
+    })
+}
+
+/// `profile` with `details` applied on top, e.g. an invoice's override.
+pub fn with_details(profile: BusinessProfile, details: &BusinessDetails) -> BusinessProfile {
+    let pick = |current: Option<String>, new: &Option<String>| match new {
+        Some(value) if value.is_empty() => None,
+        Some(value) => Some(value.clone()),
+        None => current,
+    };
+    BusinessProfile {
+        legal_name: details.legal_name.clone().unwrap_or(profile.legal_name),
+        email: pick(profile.email, &details.email),
+        phone: pick(profile.phone, &details.phone),
+        website: pick(profile.website, &details.website),
+        address_lines: details.address_lines.clone().unwrap_or(profile.address_lines),
+        tax_id: pick(profile.tax_id, &details.tax_id),
+        iban: pick(profile.iban, &details.iban),
+        bank_details: pick(profile.bank_details, &details.bank_details),
+        logo_path: pick(profile.logo_path, &details.logo_path),
+        updated_at: profile.updated_at,
+    }
+}
+
+/// Strips spaces, upper-cases and verifies the ISO 13616 check digits.
+fn normalize_iban(value: &str) -> AppResult<String> {
+    let iban: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase();
+    let well_formed = (15..=34).contains(&iban.len())
+        && iban.chars().all(|c| c.is_ascii_alphanumeric())
+        && iban[..2].chars().all(|c| c.is_ascii_uppercase())
+        && iban[2..4].chars().all(|c| c.is_ascii_digit());
+    if !well_formed {
+        return Err(AppError::Validation(format!("Not a valid IBAN: {value}")));
+    }
+
+    let remainder = iban[4..].chars().chain(iban[..4].chars()).fold(0u32, |acc, c| {
+        let digit = c.to_digit(36).unwrap_or(0);
+        if digit < 10 {
+            (acc * 10 + digit) % 97
+        } else {
+            (acc * 100 + digit) % 97
+        }
+    });
+    if remainder != 1 {
+        return Err(AppError::Validation(format!(
+            "IBAN check digits don't match: {value}"
+        )));
+    }
+    Ok(iban)
+}
+
+/// Groups an IBAN in fours for printing.
+pub fn format_iban(iban: &str) -> String {
+    iban.as_bytes()
+        .chunks(4)
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The settings page still saves `business_name`, `business_email` and
+/// `business_address` as settings; mirror them into the profile.
+pub(crate) fn sync_legacy_setting(conn: &Connection, key: &str, value: &str) -> AppResult<()> {
+    let details = match key {
+        "business_name" => BusinessDetails {
+            legal_name: Some(value.to_string()),
+            ..Default::default()
+        },
+        "business_email" => BusinessDetails {
+            email: Some(value.to_string()),
+            ..Default::default()
+        },
+        "business_address" => BusinessDetails {
+            address_lines: Some(value.lines().map(str::to_string).collect()),
+            ..Default::default()
+        },
+        _ => return Ok(()),
+    };
+    update_business_profile(conn, details)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db_in_memory;
+
+    #[test]
+    fn test_update_business_profile() {
+        let conn = init_db_in_memory().unwrap();
+        assert_eq!(get_business_profile(&conn).unwrap(), BusinessProfile::default());
+
+        let profile = update_business_profile(
+            &conn,
+            BusinessDetails {
+                legal_name: Some("  Studio Nord GmbH ".to_string()),
+                email: Some("hello@studionord.de".to_string()),
+                address_lines: Some(vec![
+                    "Torstraße 1".to_string(),
+                    " ".to_string(),
+                    "10119 Berlin".to_string(),
+                ]),
+                tax_id: Some("DE123456789".to_string()),
+                iban: Some("de89 3704 0044 0532 0130 00".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(profile.legal_name, "Studio Nord GmbH");
+        assert_eq!(profile.address_lines, vec!["Torstraße 1", "10119 Berlin"]);
+        assert_eq!(profile.iban.as_deref(), Some("DE89370400440532013000"));
+        assert_eq!(format_iban(profile.iban.as_deref().unwrap()), "DE89 3704 0044 0532 0130 00");
+        assert!(profile.updated_at.is_some());
+
+        // Unset fields are kept, blank ones cleared
+        let profile = update_business_profile(
+            &conn,
+            BusinessDetails {
+                phone: Some("+49 30 1234567".to_string()),
+                tax_id: Some(String::new()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(profile.email.as_deref(), Some("hello@studionord.de"));
+        assert_eq!(profile.phone.as_deref(), Some("+49 30 1234567"));
+        assert_eq!(profile.tax_id, None);
+
+        for bad in [
+            BusinessDetails { iban: Some("DE88 3704 0044 0532 0130 00".to_string()), ..Default::default() },
+            BusinessDetails { iban: Some("not an iban".to_string()), ..Default::default() },
+            BusinessDetails { logo_path: Some("/nonexistent/logo.png".to_string()), ..Default::default() },
+        ] {
+            assert!(matches!(update_business_profile(&conn, bad), Err(AppError::Validation(_))));
+        }
+        assert_eq!(get_business_profile(&conn).unwrap(), profile);
+    }
+
+    #[test]
+    fn test_legacy_settings_update_the_profile() {
+        let conn = init_db_in_memory().unwrap();
+        sync_legacy_setting(&conn, "business_name", "Acme Design").unwrap();
+        sync_legacy_setting(&conn, "business_address", "1 Main St\nSpringfield").unwrap();
+        sync_legacy_setting(&conn, "default_currency", "EUR").unwrap();
+
+        let profile = get_business_profile(&conn).unwrap();
+        assert_eq!(profile.legal_name, "Acme Design");
+        assert_eq!(profile.address_lines, vec!["1 Main St", "Springfield"]);
+        assert_eq!(profile.email, None);
+    }
+}
@@ -3,15 +3,71 @@ use rusqlite::{params, Connection};
 use uuid::Uuid;
 
 use crate::error::{AppError, AppResult};
-use crate::models::{Client, CreateClient, UpdateClient};
+use crate::db::{templates, with_savepoint};
+use crate::models::{Client, CreateClient, PaymentTerms, TaxTreatment, UpdateClient};
+use crate::services::{currency, locale, numbering};
+
+fn row_to_client(row: &rusqlite::Row, default_currency: &str) -> rusqlite::Result<Client> {
+    let currency: Option<String> = row.get("currency")?;
+    Ok(Client {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        email: row.get("email")?,
+        company: row.get("company")?,
+        address: row.get("address")?,
+        phone: row.get("phone")?,
+        notes: row.get("notes")?,
+        hourly_rate: row.get("hourly_rate")?,
+        billing_currency: currency.clone().unwrap_or_else(|| default_currency.to_string()),
+        currency,
+        invoice_prefix: row.get("invoice_prefix")?,
+        tax_treatment: TaxTreatment::from_str(&row.get::<_, String>("tax_treatment")?)
+            .unwrap_or_default(),
+        payment_terms: row
+            .get::<_, Option<String>>("payment_terms")?
+            .and_then(|terms| PaymentTerms::from_str(&terms)),
+        template_id: row.get("template_id")?,
+        locale: row.get("locale")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+/// A blank prefix clears it.
+fn normalize_prefix(prefix: &str) -> AppResult<Option<String>> {
+    let prefix = prefix.trim();
+    numbering::validate_client_prefix(prefix)?;
+    Ok((!prefix.is_empty()).then(|| prefix.to_string()))
+}
 
 pub fn create_client(conn: &Connection, input: CreateClient) -> AppResult<Client> {
     let id = Uuid::new_v4().to_string();
     let now = Utc::now();
+    let currency = input
+        .currency
+        .as_deref()
+        .map(currency::normalize_code)
+        .transpose()?;
+    let invoice_prefix = input
+        .invoice_prefix
+        .as_deref()
+        .map(normalize_prefix)
+        .transpose()?
+        .flatten();
+    let template_id = match &input.template_id {
+        Some(template_id) => templates::normalize_id(conn, template_id)?,
+        None => None,
+    };
+    let locale = input
+        .locale
+        .as_deref()
+        .map(locale::normalize_code)
+        .transpose()?
+        .flatten();
 
     conn.execute(
-        "INSERT INTO clients (id, name, email, company, address, phone, notes, hourly_rate, created_at, updated_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        "INSERT INTO clients (id, name, email, company, address, phone, notes, hourly_rate, currency, invoice_prefix, tax_treatment, payment_terms, template_id, locale, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
         params![
             id,
             input.name,
@@ -21,6 +77,12 @@ pub fn create_client(conn: &Connection, input: CreateClient) -> AppResult<Client
             input.phone,
             input.notes,
             input.hourly_rate,
+            currency,
+            invoice_prefix,
+            input.tax_treatment.unwrap_or_default().as_str(),
+            input.payment_terms.map(|terms| terms.as_string()),
+            template_id,
+            locale,
             now.to_rfc3339(),
             now.to_rfc3339(),
         ],
@@ -30,19 +92,9 @@ pub fn create_client(conn: &Connection, input: CreateClient) -> AppResult<Client
 }
 
 pub fn get_client(conn: &Connection, id: &str) -> AppResult<Client> {
+    let default_currency = currency::default_currency(conn)?;
     conn.query_row("SELECT * FROM clients WHERE id = ?1", params![id], |row| {
-        Ok(Client {
-            id: row.get("id")?,
-            name: row.get("name")?,
-            email: row.get("email")?,
-            company: row.get("company")?,
-            address: row.get("address")?,
-            phone: row.get("phone")?,
-            notes: row.get("notes")?,
-            hourly_rate: row.get("hourly_rate")?,
-            created_at: row.get("created_at")?,
-            updated_at: row.get("updated_at")?,
-        })
+        row_to_client(row, &default_currency)
     })
     .map_err(|e| match e {
         rusqlite::Error::QueryReturnedNoRows => {
@@ -53,32 +105,83 @@ pub fn get_client(conn: &Connection, id: &str) -> AppResult<Client> {
 }
 
 pub fn list_clients(conn: &Connection) -> AppResult<Vec<Client>> {
+    let default_currency = currency::default_currency(conn)?;
     let mut stmt = conn.prepare("SELECT * FROM clients ORDER BY name ASC")?;
     let clients = stmt
-        .query_map([], |row| {
-            Ok(Client {
-                id: row.get("id")?,
-                name: row.get("name")?,
-                email: row.get("email")?,
-                company: row.get("company")?,
-                address: row.get("address")?,
-                phone: row.get("phone")?,
-                notes: row.get("notes")?,
-                hourly_rate: row.get("hourly_rate")?,
-                created_at: row.get("created_at")?,
-                updated_at: row.get("updated_at")?,
-            })
-        })?
+        .query_map([], |row| row_to_client(row, &default_currency))?
         .collect::<Result<Vec<_>, _>>()?;
     Ok(clients)
 }
 
+/// Rates are stored in minor units of the client's currency. Moving to a currency with
+/// a different number of decimals rescales them so they keep their value.
+fn rescale_rates(conn: &Connection, client_id: &str, from: &str, to: &str) -> AppResult<()> {
+    if currency::minor_digits(from) == currency::minor_digits(to) {
+        return Ok(());
+    }
+    for (table, column, scope) in [
+        ("clients", "hourly_rate", "id = ?1"),
+        ("projects", "hourly_rate", "client_id = ?1"),
+        (
+            "recurring_invoice_items",
+            "unit_price",
+            "recurring_invoice_id IN (SELECT id FROM recurring_invoices WHERE client_id = ?1)",
+        ),
+    ] {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT rowid, {column} FROM {table} WHERE {scope} AND {column} IS NOT NULL"
+        ))?;
+        let amounts = stmt
+            .query_map(params![client_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (rowid, amount) in amounts {
+            conn.execute(
+                &format!("UPDATE {table} SET {column} = ?1 WHERE rowid = ?2"),
+                params![currency::rescale(amount, from, to), rowid],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Clients without a currency of their own bill in `default_currency`; when it changes,
+/// their rates are rescaled like an explicit currency change.
+pub(crate) fn sync_default_currency(conn: &Connection, key: &str, value: &str) -> AppResult<()> {
+    if key != "default_currency" {
+        return Ok(());
+    }
+    let from = currency::default_currency(conn)?;
+    let to = currency::normalize_code(value)?;
+    let mut stmt = conn.prepare("SELECT id FROM clients WHERE currency IS NULL")?;
+    let client_ids = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    for client_id in client_ids {
+        rescale_rates(conn, &client_id, &from, &to)?;
+    }
+    Ok(())
+}
+
 pub fn update_client(conn: &Connection, id: &str, input: UpdateClient) -> AppResult<Client> {
     // Verify client exists
-    get_client(conn, id)?;
+    let client = get_client(conn, id)?;
+    let invoice_prefix = input.invoice_prefix.as_deref().map(normalize_prefix).transpose()?;
+    let currency = input.currency.as_deref().map(currency::normalize_code).transpose()?;
 
     let now = Utc::now();
 
+    // Before any new rate is set, since that is already in the new currency
+    if let Some(code) = currency {
+        with_savepoint(conn, "change_client_currency", || {
+            rescale_rates(conn, id, &client.billing_currency, &code)?;
+            conn.execute(
+                "UPDATE clients SET currency = ?1, updated_at = ?2 WHERE id = ?3",
+                params![code, now.to_rfc3339(), id],
+            )?;
+            Ok(())
+        })?;
+    }
+
     if let Some(name) = &input.name {
         conn.execute(
             "UPDATE clients SET name = ?1, updated_at = ?2 WHERE id = ?3",
@@ -121,6 +224,36 @@ pub fn update_client(conn: &Connection, id: &str, input: UpdateClient) -> AppRes
             params![hourly_rate, now.to_rfc3339(), id],
         )?;
     }
+    if let Some(prefix) = invoice_prefix {
+        conn.execute(
+            "UPDATE clients SET invoice_prefix = ?1, updated_at = ?2 WHERE id = ?3",
+            params![prefix, now.to_rfc3339(), id],
+        )?;
+    }
+    if let Some(treatment) = &input.tax_treatment {
+        conn.execute(
+            "UPDATE clients SET tax_treatment = ?1, updated_at = ?2 WHERE id = ?3",
+            params![treatment.as_str(), now.to_rfc3339(), id],
+        )?;
+    }
+    if let Some(terms) = &input.payment_terms {
+        conn.execute(
+            "UPDATE clients SET payment_terms = ?1, updated_at = ?2 WHERE id = ?3",
+            params![terms.as_string(), now.to_rfc3339(), id],
+        )?;
+    }
+    if let Some(template_id) = &input.template_id {
+        conn.execute(
+            "UPDATE clients SET template_id = ?1, updated_at = ?2 WHERE id = ?3",
+            params![templates::normalize_id(conn, template_id)?, now.to_rfc3339(), id],
+        )?;
+    }
+    if let Some(code) = &input.locale {
+        conn.execute(
+            "UPDATE clients SET locale = ?1, updated_at = ?2 WHERE id = ?3",
+            params![locale::normalize_code(code)?, now.to_rfc3339(), id],
+        )?;
+    }
 
     get_client(conn, id)
 }
@@ -154,14 +287,15 @@ mod tests {
                 address: None,
                 phone: None,
                 notes: None,
-                hourly_rate: Some(150.0),
+                hourly_rate: Some(15000),
+                ..Default::default()
             },
         )
         .unwrap();
 
         assert_eq!(client.name, "Acme Corp");
         assert_eq!(client.email, Some("hello@acme.com".to_string()));
-        assert_eq!(client.hourly_rate, Some(150.0));
+        assert_eq!(client.hourly_rate, Some(15000));
 
         let fetched = get_client(&conn, &client.id).unwrap();
         assert_eq!(fetched.name, "Acme Corp");
@@ -180,6 +314,7 @@ mod tests {
                 phone: None,
                 notes: None,
                 hourly_rate: None,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -194,6 +329,7 @@ mod tests {
                 phone: None,
                 notes: None,
                 hourly_rate: None,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -216,6 +352,7 @@ mod tests {
                 phone: None,
                 notes: None,
                 hourly_rate: None,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -230,14 +367,27 @@ mod tests {
                 address: None,
                 phone: None,
                 notes: None,
-                hourly_rate: Some(200.0),
+                hourly_rate: Some(20000),
+                ..Default::default()
             },
         )
         .unwrap();
 
         assert_eq!(updated.name, "New Name");
         assert_eq!(updated.email, Some("new@email.com".to_string()));
-        assert_eq!(updated.hourly_rate, Some(200.0));
+        assert_eq!(updated.hourly_rate, Some(20000));
+
+        let path_prefix = update_client(
+            &conn,
+            &client.id,
+            UpdateClient {
+                name: Some("Renamed".to_string()),
+                invoice_prefix: Some("../ACME".to_string()),
+                ..Default::default()
+            },
+        );
+        assert!(matches!(path_prefix, Err(AppError::Validation(_))));
+        assert_eq!(get_client(&conn, &client.id).unwrap().name, "New Name");
     }
 
     #[test]
@@ -253,6 +403,7 @@ mod tests {
                 phone: None,
                 notes: None,
                 hourly_rate: None,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -267,4 +418,86 @@ mod tests {
         let result = get_client(&conn, "nonexistent-id");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_currency_change_rescales_rates() {
+        let conn = setup();
+        let client = create_client(
+            &conn,
+            CreateClient {
+                name: "Acme".to_string(),
+                hourly_rate: Some(9550),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let project = crate::db::projects::create_project(
+            &conn,
+            crate::models::CreateProject {
+                client_id: client.id.clone(),
+                name: "Site".to_string(),
+                description: None,
+                status: None,
+                hourly_rate: Some(12000),
+                budget_hours: None,
+            },
+        )
+        .unwrap();
+        let project_rate = || {
+            crate::db::projects::get_project(&conn, &project.id)
+                .unwrap()
+                .hourly_rate
+        };
+
+        // USD to JPY: 95.50 becomes 96 yen
+        let updated = update_client(
+            &conn,
+            &client.id,
+            UpdateClient {
+                currency: Some("jpy".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!((updated.currency.as_deref(), updated.hourly_rate), (Some("JPY"), Some(96)));
+        assert_eq!(project_rate(), Some(120));
+
+        // A rate given with the change is already in the new currency
+        let updated = update_client(
+            &conn,
+            &client.id,
+            UpdateClient {
+                currency: Some("EUR".to_string()),
+                hourly_rate: Some(8000),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(updated.hourly_rate, Some(8000));
+        assert_eq!(project_rate(), Some(12000));
+    }
+
+    #[test]
+    fn test_default_currency_change_rescales_clients_without_one() {
+        let conn = setup();
+        let rate = |currency: Option<&str>| {
+            create_client(
+                &conn,
+                CreateClient {
+                    name: "Client".to_string(),
+                    hourly_rate: Some(9550),
+                    currency: currency.map(str::to_string),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .id
+        };
+        let (on_default, in_euros) = (rate(None), rate(Some("EUR")));
+
+        sync_default_currency(&conn, "default_currency", "KWD").unwrap();
+        assert_eq!(get_client(&conn, &on_default).unwrap().hourly_rate, Some(95500));
+        assert_eq!(get_client(&conn, &in_euros).unwrap().hourly_rate, Some(9550));
+        assert!(sync_default_currency(&conn, "default_currency", "XXX").is_err());
+    }
 }
@@ -0,0 +1,342 @@
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use uuid::Uuid;
+
+use crate::db::{invoices, payments, taxes, with_savepoint};
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    CreateCreditNote, CreditNote, CreditNoteLineItem, InvoiceStatus, TaxTreatment,
+};
+use crate::services::{money, tax};
+use crate::services::numbering::{self, DocumentKind};
+
+fn row_to_credit_note(row: &rusqlite::Row) -> rusqlite::Result<CreditNote> {
+    Ok(CreditNote {
+        id: row.get("id")?,
+        credit_note_number: row.get("credit_note_number")?,
+        invoice_id: row.get("invoice_id")?,
+        client_id: row.get("client_id")?,
+        issue_date: row.get("issue_date")?,
+        currency: row.get("currency")?,
+        subtotal: row.get("subtotal")?,
+        tax_rate: row.get("tax_rate")?,
+        tax_treatment: TaxTreatment::from_str(&row.get::<_, String>("tax_treatment")?)
+            .unwrap_or_default(),
+        tax_amount: row.get("tax_amount")?,
+        total: row.get("total")?,
+        applied_amount: row.get("applied_amount")?,
+        refund_amount: row.get("refund_amount")?,
+        reason: row.get("reason")?,
+        line_items: Vec::new(),
+        taxes: Vec::new(),
+        created_at: row.get("created_at")?,
+    })
+}
+
+fn get_line_items(conn: &Connection, credit_note_id: &str) -> AppResult<Vec<CreditNoteLineItem>> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM credit_note_line_items WHERE credit_note_id = ?1 ORDER BY sort_order",
+    )?;
+    let items = stmt
+        .query_map(params![credit_note_id], |row| {
+            Ok(CreditNoteLineItem {
+                id: row.get("id")?,
+                credit_note_id: row.get("credit_note_id")?,
+                description: row.get("description")?,
+                quantity: row.get("quantity")?,
+                unit_price: row.get("unit_price")?,
+                amount: row.get("amount")?,
+                sort_order: row.get("sort_order")?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(items)
+}
+
+/// Issues a credit note against an invoice. The credit is applied to the invoice's open
+/// balance first; anything beyond it is recorded as a refund owed to the client.
+pub fn create_credit_note(conn: &Connection, input: CreateCreditNote) -> AppResult<CreditNote> {
+    let invoice = invoices::get_invoice(conn, &input.invoice_id)?;
+    if !matches!(
+        invoice.status,
+        InvoiceStatus::Sent
+            | InvoiceStatus::PartiallyPaid
+            | InvoiceStatus::Overdue
+            | InvoiceStatus::Paid
+    ) {
+        return Err(AppError::Validation(format!(
+            "Invoice {} is {}; credit notes can only be issued against issued invoices",
+            invoice.invoice_number,
+            invoice.status.as_str()
+        )));
+    }
+    if input.line_items.is_empty() {
+        return Err(AppError::Validation(
+            "A credit note needs at least one line item".to_string(),
+        ));
+    }
+
+    let mode = money::rounding_mode(conn)?;
+    let mut amounts = Vec::with_capacity(input.line_items.len());
+    let mut lines = Vec::with_capacity(input.line_items.len());
+    for item in &input.line_items {
+        let amount = money::line_amount(item.quantity, item.unit_price, mode);
+        if amount >= 0 {
+            return Err(AppError::Validation(format!(
+                "Credit note line \"{}\" must have a negative amount",
+                item.description
+            )));
+        }
+        amounts.push(amount);
+        lines.push(tax::TaxableLine {
+            amount,
+            taxes: item
+                .tax_ids
+                .iter()
+                .map(|id| taxes::get_tax(conn, id))
+                .collect::<AppResult<_>>()?,
+        });
+    }
+    let subtotal: i64 = amounts.iter().sum();
+    // Tax is reversed the way the invoice charged it
+    let tax_lines = tax::compute(&lines, invoice.tax_rate, invoice.tax_treatment, mode);
+    let tax_amount: i64 = tax_lines.iter().map(|t| t.amount).sum();
+    let total = subtotal + tax_amount;
+
+    let already_credited: i64 = conn.query_row(
+        "SELECT COALESCE(SUM(-total), 0) FROM credit_notes WHERE invoice_id = ?1",
+        params![invoice.id],
+        |row| row.get(0),
+    )?;
+    if already_credited - total > invoice.total {
+        return Err(AppError::Validation(format!(
+            "Credit of {} exceeds the {} left to credit on invoice {}",
+            -total,
+            invoice.total - already_credited,
+            invoice.invoice_number
+        )));
+    }
+
+    let applied_amount = (-total).min(invoice.balance_due.max(0));
+    let refund_amount = -total - applied_amount;
+
+    let id = Uuid::new_v4().to_string();
+    with_savepoint(conn, "create_credit_note", || {
+        let invoice_prefix: Option<String> = conn.query_row(
+            "SELECT invoice_prefix FROM clients WHERE id = ?1",
+            params![invoice.client_id],
+            |row| row.get(0),
+        )?;
+        let credit_note_number = numbering::next_number(
+            conn,
+            DocumentKind::CreditNote,
+            invoice_prefix.as_deref(),
+            input.issue_date,
+        )?;
+        conn.execute(
+            "INSERT INTO credit_notes (id, credit_note_number, invoice_id, client_id, issue_date, currency, subtotal, tax_rate, tax_treatment, tax_amount, total, applied_amount, refund_amount, reason, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            params![
+                id,
+                credit_note_number,
+                invoice.id,
+                invoice.client_id,
+                input.issue_date,
+                invoice.currency,
+                subtotal,
+                invoice.tax_rate,
+                invoice.tax_treatment.as_str(),
+                tax_amount,
+                total,
+                applied_amount,
+                refund_amount,
+                input.reason,
+                Utc::now().to_rfc3339(),
+            ],
+        )?;
+        for (i, (item, amount)) in input.line_items.iter().zip(amounts).enumerate() {
+            conn.execute(
+                "INSERT INTO credit_note_line_items (id, credit_note_id, description, quantity, unit_price, amount, sort_order)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    Uuid::new_v4().to_string(),
+                    id,
+                    item.description,
+                    item.quantity,
+                    item.unit_price,
+                    amount,
+                    i as i32,
+                ],
+            )?;
+        }
+        taxes::insert_credit_note_taxes(conn, &id, &tax_lines)?;
+        payments::settle(conn, &invoice.id)?;
+        Ok(())
+    })?;
+
+    get_credit_note(conn, &id)
+}
+
+pub fn get_credit_note(conn: &Connection, id: &str) -> AppResult<CreditNote> {
+    let mut credit_note = conn
+        .query_row(
+            "SELECT * FROM credit_notes WHERE id = ?1",
+            params![id],
+            row_to_credit_note,
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => {
+                AppError::NotFound(format!("Credit note not found: {id}"))
+            }
+            _ => AppError::Database(e),
+        })?;
+    credit_note.line_items = get_line_items(conn, id)?;
+    credit_note.taxes = taxes::get_credit_note_taxes(conn, id)?;
+    Ok(credit_note)
+}
+
+/// Credit notes, newest first, optionally only those against one invoice.
+pub fn list_credit_notes(
+    conn: &Connection,
+    invoice_id: Option<&str>,
+) -> AppResult<Vec<CreditNote>> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM credit_notes WHERE ?1 IS NULL OR invoice_id = ?1
+         ORDER BY issue_date DESC, credit_note_number DESC",
+    )?;
+    let mut credit_notes = stmt
+        .query_map(params![invoice_id], row_to_credit_note)?
+        .collect::<Result<Vec<_>, _>>()?;
+    for credit_note in &mut credit_notes {
+        credit_note.line_items = get_line_items(conn, &credit_note.id)?;
+        credit_note.taxes = taxes::get_credit_note_taxes(conn, &credit_note.id)?;
+    }
+    Ok(credit_notes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::clients::create_client;
+    use crate::db::init_db_in_memory;
+    use crate::models::{
+        CreateClient, CreditNoteLineInput, Invoice, LineItemUnit, PaymentMethod, RecordPayment,
+    };
+    use chrono::NaiveDate;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn setup() -> (Connection, Invoice) {
+        let conn = init_db_in_memory().expect("Failed to init test DB");
+        let client = create_client(
+            &conn,
+            CreateClient {
+                name: "Test".to_string(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let invoice = invoices::create_invoice(
+            &conn,
+            &client.id,
+            "2026-01-01T00:00:00Z",
+            Some("2026-01-31T00:00:00Z"),
+            None,
+            Some(10.0),
+        )
+        .unwrap();
+        invoices::add_line_item(&conn, &invoice.id, "Work", 10.0, 10000, LineItemUnit::Hours, 0).unwrap();
+        let invoice =
+            invoices::update_invoice_status(&conn, &invoice.id, InvoiceStatus::Sent).unwrap();
+        (conn, invoice)
+    }
+
+    fn credit(invoice_id: &str, quantity: f64, unit_price: i64) -> CreateCreditNote {
+        CreateCreditNote {
+            invoice_id: invoice_id.to_string(),
+            issue_date: date(2026, 2, 10),
+            reason: Some("Billed hours corrected".to_string()),
+            line_items: vec![CreditNoteLineInput {
+                description: "Work".to_string(),
+                quantity,
+                unit_price,
+                tax_ids: Vec::new(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_credit_note_reduces_open_balance() {
+        let (conn, invoice) = setup();
+        let note = create_credit_note(&conn, credit(&invoice.id, 2.0, -10000)).unwrap();
+
+        assert_eq!(note.credit_note_number, "CN-2026-001");
+        assert_eq!(
+            (note.subtotal, note.tax_amount, note.total),
+            (-20000, -2000, -22000)
+        );
+        assert_eq!((note.applied_amount, note.refund_amount), (22000, 0));
+        assert_eq!(note.line_items[0].amount, -20000);
+
+        let credited = invoices::get_invoice(&conn, &invoice.id).unwrap();
+        assert_eq!(credited.amount_credited, 22000);
+        assert_eq!(credited.balance_due, 88000);
+        assert_eq!(credited.status, InvoiceStatus::PartiallyPaid);
+
+        let second = create_credit_note(&conn, credit(&invoice.id, 8.0, -10000)).unwrap();
+        assert_eq!(second.credit_note_number, "CN-2026-002");
+        let settled = invoices::get_invoice(&conn, &invoice.id).unwrap();
+        assert_eq!(settled.balance_due, 0);
+        assert_eq!(settled.status, InvoiceStatus::Paid);
+        assert_eq!(
+            list_credit_notes(&conn, Some(&invoice.id)).unwrap().len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_credit_on_paid_invoice_is_a_refund() {
+        let (conn, invoice) = setup();
+        payments::record_payment(
+            &conn,
+            RecordPayment {
+                invoice_id: invoice.id.clone(),
+                amount: 110000,
+                payment_date: date(2026, 1, 20),
+                method: PaymentMethod::BankTransfer,
+                reference: None,
+            },
+        )
+        .unwrap();
+
+        let note = create_credit_note(&conn, credit(&invoice.id, 1.0, -10000)).unwrap();
+        assert_eq!((note.applied_amount, note.refund_amount), (0, 11000));
+        let paid = invoices::get_invoice(&conn, &invoice.id).unwrap();
+        assert_eq!(paid.status, InvoiceStatus::Paid);
+        assert_eq!(paid.balance_due, 0);
+    }
+
+    #[test]
+    fn test_rejects_invalid_credit_notes() {
+        let (conn, invoice) = setup();
+        let positive = create_credit_note(&conn, credit(&invoice.id, 1.0, 10000));
+        assert!(matches!(positive, Err(AppError::Validation(_))));
+        let too_large = create_credit_note(&conn, credit(&invoice.id, 11.0, -10000));
+        assert!(matches!(too_large, Err(AppError::Validation(_))));
+
+        let draft = invoices::create_invoice(
+            &conn,
+            &invoice.client_id,
+            "2026-01-01T00:00:00Z",
+            Some("2026-01-31T00:00:00Z"),
+            None,
+            None,
+        )
+        .unwrap();
+        let against_draft = create_credit_note(&conn, credit(&draft.id, 1.0, -100));
+        assert!(matches!(against_draft, Err(AppError::Validation(_))));
+        assert!(list_credit_notes(&conn, None).unwrap().is_empty());
+    }
+}
@@ -1,12 +1,27 @@
+use chrono::NaiveDate;
 use rusqlite::{params, Connection};
 use serde::Serialize;
 
 use crate::error::AppResult;
+use crate::services::currency;
+
+/// An amount in a single currency. Totals across invoices are reported per currency
+/// rather than summed, since invoices can be billed in different currencies.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct CurrencyAmount {
+    pub currency: String,
+    /// Minor units of `currency`.
+    pub amount: i64,
+}
 
 #[derive(Debug, Serialize)]
 pub struct DashboardSummary {
-    pub total_revenue: f64,
-    pub outstanding_amount: f64,
+    pub revenue_by_currency: Vec<CurrencyAmount>,
+    pub outstanding_by_currency: Vec<CurrencyAmount>,
+    /// The part of `outstanding_by_currency` that is past due.
+    pub overdue_by_currency: Vec<CurrencyAmount>,
+    /// Line and invoice discounts given on issued invoices.
+    pub discounts_by_currency: Vec<CurrencyAmount>,
     pub hours_this_week: f64,
     pub hours_this_month: f64,
     pub active_projects: i32,
@@ -16,9 +31,13 @@ pub struct DashboardSummary {
 #[derive(Debug, Serialize)]
 pub struct RevenueByClient {
     pub client_name: String,
-    pub total_revenue: f64,
+    pub currency: String,
+    /// Minor units of `currency`.
+    pub total_revenue: i64,
+    /// Hours billed in `currency`, plus unbilled hours in the client's billing currency.
     pub total_hours: f64,
-    pub effective_rate: f64,
+    /// Minor units of `currency` per hour.
+    pub effective_rate: i64,
 }
 
 #[derive(Debug, Serialize)]
@@ -31,7 +50,24 @@ pub struct HoursByProject {
 #[derive(Debug, Serialize)]
 pub struct MonthlyRevenue {
     pub month: String,
-    pub revenue: f64,
+    pub currency: String,
+    /// Minor units of `currency`.
+    pub revenue: i64,
+}
+
+/// Balances due for one client in one currency, bucketed by days past due.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct AgingBucket {
+    pub client_id: String,
+    pub client_name: String,
+    pub currency: String,
+    /// Not yet due.
+    pub current: i64,
+    pub days_1_30: i64,
+    pub days_31_60: i64,
+    pub days_61_90: i64,
+    pub days_over_90: i64,
+    pub total: i64,
 }
 
 #[derive(Debug, Serialize)]
@@ -42,22 +78,50 @@ pub struct EstimateAccuracy {
     pub accuracy_pct: f64,
 }
 
-pub fn get_dashboard_summary(conn: &Connection) -> AppResult<DashboardSummary> {
-    let total_revenue: f64 = conn
-        .query_row(
-            "SELECT COALESCE(SUM(total), 0) FROM invoices WHERE status = 'paid'",
-            [],
-            |row| row.get(0),
-        )
-        .unwrap_or(0.0);
+/// Runs a `SELECT currency, amount ... GROUP BY currency` query.
+fn amounts_by_currency(conn: &Connection, sql: &str) -> AppResult<Vec<CurrencyAmount>> {
+    let mut stmt = conn.prepare(sql)?;
+    let totals = stmt
+        .query_map([], |row| {
+            Ok(CurrencyAmount {
+                currency: row.get(0)?,
+                amount: row.get(1)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(totals)
+}
 
-    let outstanding_amount: f64 = conn
-        .query_row(
-            "SELECT COALESCE(SUM(total), 0) FROM invoices WHERE status IN ('sent', 'overdue')",
-            [],
-            |row| row.get(0),
-        )
-        .unwrap_or(0.0);
+pub fn get_dashboard_summary(conn: &Connection) -> AppResult<DashboardSummary> {
+    // Revenue is cash received: payments less credit-note refunds, not invoice totals
+    let revenue_by_currency = amounts_by_currency(
+        conn,
+        "SELECT i.currency, SUM(r.amount) FROM revenue_entries r
+         JOIN invoices i ON i.id = r.invoice_id
+         GROUP BY i.currency
+         ORDER BY i.currency ASC",
+    )?;
+    let outstanding_by_currency = amounts_by_currency(
+        conn,
+        "SELECT currency, SUM(total - amount_paid - amount_credited) FROM invoices
+         WHERE status IN ('sent', 'partially_paid', 'overdue')
+         GROUP BY currency
+         ORDER BY currency ASC",
+    )?;
+    let overdue_by_currency = amounts_by_currency(
+        conn,
+        "SELECT currency, SUM(total - amount_paid - amount_credited) FROM invoices
+         WHERE status = 'overdue'
+         GROUP BY currency
+         ORDER BY currency ASC",
+    )?;
+    let discounts_by_currency = amounts_by_currency(
+        conn,
+        "SELECT currency, SUM(line_discount_amount + discount_amount) FROM invoices
+         WHERE status NOT IN ('draft', 'cancelled') AND line_discount_amount + discount_amount > 0
+         GROUP BY currency
+         ORDER BY currency ASC",
+    )?;
 
     let hours_this_week: f64 = conn
         .query_row(
@@ -90,15 +154,17 @@ pub fn get_dashboard_summary(conn: &Connection) -> AppResult<DashboardSummary> {
 
     let pending_invoices: i32 = conn
         .query_row(
-            "SELECT COUNT(*) FROM invoices WHERE status IN ('sent', 'overdue')",
+            "SELECT COUNT(*) FROM invoices WHERE status IN ('sent', 'partially_paid', 'overdue')",
             [],
             |row| row.get(0),
         )
         .unwrap_or(0);
 
     Ok(DashboardSummary {
-        total_revenue,
-        outstanding_amount,
+        revenue_by_currency,
+        outstanding_by_currency,
+        overdue_by_currency,
+        discounts_by_currency,
         hours_this_week,
         hours_this_month,
         active_projects,
@@ -106,32 +172,55 @@ pub fn get_dashboard_summary(conn: &Connection) -> AppResult<DashboardSummary> {
     })
 }
 
+/// One row per client and currency. Hours count toward the currency of the invoice
+/// they were billed on; unbilled hours count toward the client's billing currency.
 pub fn get_revenue_by_client(conn: &Connection) -> AppResult<Vec<RevenueByClient>> {
+    let default_currency = currency::default_currency(conn)?;
     let mut stmt = conn.prepare(
-        "SELECT c.name,
-                COALESCE(SUM(i.total), 0) as revenue,
-                COALESCE(SUM(te.total_hours), 0) as hours
-         FROM clients c
-         LEFT JOIN invoices i ON i.client_id = c.id AND i.status = 'paid'
-         LEFT JOIN (
-             SELECT p.client_id, SUM(te.duration_secs) / 3600.0 as total_hours
+        "WITH revenue AS (
+             SELECT i.client_id, i.currency, SUM(r.amount) as revenue
+             FROM revenue_entries r
+             JOIN invoices i ON i.id = r.invoice_id
+             GROUP BY i.client_id, i.currency
+         ), hours AS (
+             SELECT p.client_id,
+                    COALESCE(i.currency, c.currency, ?1) as currency,
+                    SUM(te.duration_secs) / 3600.0 as total_hours
              FROM time_entries te
              JOIN projects p ON p.id = te.project_id
-             GROUP BY p.client_id
-         ) te ON te.client_id = c.id
-         GROUP BY c.id, c.name
-         HAVING revenue > 0 OR hours > 0
+             JOIN clients c ON c.id = p.client_id
+             LEFT JOIN invoices i ON i.id = te.invoice_id
+             GROUP BY p.client_id, COALESCE(i.currency, c.currency, ?1)
+         )
+         SELECT c.name,
+                k.currency,
+                COALESCE(r.revenue, 0) as revenue,
+                COALESCE(h.total_hours, 0) as hours
+         FROM (
+             SELECT client_id, currency FROM revenue
+             UNION
+             SELECT client_id, currency FROM hours
+         ) k
+         JOIN clients c ON c.id = k.client_id
+         LEFT JOIN revenue r ON r.client_id = k.client_id AND r.currency = k.currency
+         LEFT JOIN hours h ON h.client_id = k.client_id AND h.currency = k.currency
+         WHERE COALESCE(r.revenue, 0) > 0 OR COALESCE(h.total_hours, 0) > 0
          ORDER BY revenue DESC",
     )?;
 
     let results = stmt
-        .query_map([], |row| {
+        .query_map(params![default_currency], |row| {
             let name: String = row.get(0)?;
-            let revenue: f64 = row.get(1)?;
-            let hours: f64 = row.get(2)?;
-            let effective_rate = if hours > 0.0 { revenue / hours } else { 0.0 };
+            let revenue: i64 = row.get(2)?;
+            let hours: f64 = row.get(3)?;
+            let effective_rate = if hours > 0.0 {
+                (revenue as f64 / hours).round() as i64
+            } else {
+                0
+            };
             Ok(RevenueByClient {
                 client_name: name,
+                currency: row.get(1)?,
                 total_revenue: revenue,
                 total_hours: hours,
                 effective_rate,
@@ -174,20 +263,63 @@ pub fn get_monthly_revenue(conn: &Connection, months: Option<i32>) -> AppResult<
     let months = months.unwrap_or(12);
     let modifier = format!("-{months} months");
     let mut stmt = conn.prepare(
-        "SELECT strftime('%Y-%m', issue_date) as month,
-                COALESCE(SUM(total), 0) as revenue
-         FROM invoices
-         WHERE status = 'paid'
-             AND issue_date >= datetime('now', ?1)
-         GROUP BY month
-         ORDER BY month ASC",
+        "SELECT strftime('%Y-%m', r.entry_date) as month,
+                i.currency,
+                COALESCE(SUM(r.amount), 0) as revenue
+         FROM revenue_entries r
+         JOIN invoices i ON i.id = r.invoice_id
+         WHERE r.entry_date >= date('now', ?1)
+         GROUP BY month, i.currency
+         ORDER BY month ASC, i.currency ASC",
     )?;
 
     let results = stmt
         .query_map(params![modifier], |row| {
             Ok(MonthlyRevenue {
                 month: row.get(0)?,
-                revenue: row.get(1)?,
+                currency: row.get(1)?,
+                revenue: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(results)
+}
+
+/// Open balances bucketed by whole calendar days past the due date as of `as_of`, so
+/// an invoice counts as current for the whole of its due date.
+pub fn get_aging_report(conn: &Connection, as_of: NaiveDate) -> AppResult<Vec<AgingBucket>> {
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.name, i.currency,
+                SUM(CASE WHEN i.days_late <= 0 THEN i.balance ELSE 0 END),
+                SUM(CASE WHEN i.days_late BETWEEN 1 AND 30 THEN i.balance ELSE 0 END),
+                SUM(CASE WHEN i.days_late BETWEEN 31 AND 60 THEN i.balance ELSE 0 END),
+                SUM(CASE WHEN i.days_late BETWEEN 61 AND 90 THEN i.balance ELSE 0 END),
+                SUM(CASE WHEN i.days_late > 90 THEN i.balance ELSE 0 END),
+                SUM(i.balance)
+         FROM (
+             SELECT client_id, currency, total - amount_paid - amount_credited AS balance,
+                    CAST(julianday(?1) - julianday(date(due_date)) AS INTEGER) AS days_late
+             FROM invoices
+             WHERE status IN ('sent', 'partially_paid', 'overdue')
+         ) i
+         JOIN clients c ON c.id = i.client_id
+         GROUP BY c.id, c.name, i.currency
+         ORDER BY c.name ASC, i.currency ASC",
+    )?;
+
+    let results = stmt
+        .query_map(params![as_of.to_string()], |row| {
+            Ok(AgingBucket {
+                client_id: row.get(0)?,
+                client_name: row.get(1)?,
+                currency: row.get(2)?,
+                current: row.get(3)?,
+                days_1_30: row.get(4)?,
+                days_31_60: row.get(5)?,
+                days_61_90: row.get(6)?,
+                days_over_90: row.get(7)?,
+                total: row.get(8)?,
             })
         })?
         .collect::<Result<Vec<_>, _>>()?;
@@ -235,12 +367,26 @@ mod tests {
     use super::*;
     use crate::db::init_db_in_memory;
 
+    fn record_payment(conn: &Connection, invoice_id: &str, amount: i64) {
+        conn.execute(
+            "INSERT INTO payments (id, invoice_id, amount, payment_date, method)
+             VALUES (lower(hex(randomblob(8))), ?1, ?2, date('now'), 'bank_transfer')",
+            params![invoice_id, amount],
+        )
+        .unwrap();
+        conn.execute(
+            "UPDATE invoices SET amount_paid = amount_paid + ?1 WHERE id = ?2",
+            params![amount, invoice_id],
+        )
+        .unwrap();
+    }
+
     #[test]
     fn test_empty_dashboard_summary() {
         let conn = init_db_in_memory().expect("init db");
         let summary = get_dashboard_summary(&conn).unwrap();
-        assert_eq!(summary.total_revenue, 0.0);
-        assert_eq!(summary.outstanding_amount, 0.0);
+        assert!(summary.revenue_by_currency.is_empty());
+        assert!(summary.outstanding_by_currency.is_empty());
         assert_eq!(summary.active_projects, 0);
         assert_eq!(summary.pending_invoices, 0);
     }
@@ -261,25 +407,52 @@ mod tests {
         )
         .unwrap();
 
-        // Create paid and sent invoices
+        // Create paid and partially paid invoices
         conn.execute(
             "INSERT INTO invoices (id, invoice_number, client_id, status, issue_date, due_date, total)
-             VALUES ('i1', 'INV-001', 'c1', 'paid', datetime('now'), datetime('now', '+30 days'), 1500.0)",
+             VALUES ('i1', 'INV-001', 'c1', 'paid', datetime('now'), datetime('now', '+30 days'), 150000)",
             [],
         )
         .unwrap();
         conn.execute(
             "INSERT INTO invoices (id, invoice_number, client_id, status, issue_date, due_date, total)
-             VALUES ('i2', 'INV-002', 'c1', 'sent', datetime('now'), datetime('now', '+30 days'), 500.0)",
+             VALUES ('i2', 'INV-002', 'c1', 'partially_paid', datetime('now'), datetime('now', '+30 days'), 50000)",
             [],
         )
         .unwrap();
+        record_payment(&conn, "i1", 150000);
+        record_payment(&conn, "i2", 20000);
 
+        // Revenue counts cash received, including the partial payment
         let summary = get_dashboard_summary(&conn).unwrap();
-        assert_eq!(summary.total_revenue, 1500.0);
-        assert_eq!(summary.outstanding_amount, 500.0);
+        assert_eq!(
+            summary.revenue_by_currency,
+            vec![CurrencyAmount { currency: "USD".to_string(), amount: 170000 }]
+        );
+        assert_eq!(
+            summary.outstanding_by_currency,
+            vec![CurrencyAmount { currency: "USD".to_string(), amount: 30000 }]
+        );
         assert_eq!(summary.active_projects, 1);
         assert_eq!(summary.pending_invoices, 1);
+        assert!(summary.discounts_by_currency.is_empty());
+
+        // Discounts on drafts aren't given yet
+        conn.execute(
+            "UPDATE invoices SET line_discount_amount = 5000, discount_amount = 10000 WHERE id = 'i1'",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO invoices (id, invoice_number, client_id, status, issue_date, due_date, discount_amount)
+             VALUES ('i3', 'INV-003', 'c1', 'draft', datetime('now'), datetime('now', '+30 days'), 2000)",
+            [],
+        )
+        .unwrap();
+        assert_eq!(
+            get_dashboard_summary(&conn).unwrap().discounts_by_currency,
+            vec![CurrencyAmount { currency: "USD".to_string(), amount: 15000 }]
+        );
     }
 
     #[test]
@@ -292,14 +465,164 @@ mod tests {
         .unwrap();
         conn.execute(
             "INSERT INTO invoices (id, invoice_number, client_id, status, issue_date, due_date, total)
-             VALUES ('i1', 'INV-001', 'c1', 'paid', datetime('now'), datetime('now', '+30 days'), 2000.0)",
+             VALUES ('i1', 'INV-001', 'c1', 'paid', datetime('now'), datetime('now', '+30 days'), 200000)",
             [],
         )
         .unwrap();
+        record_payment(&conn, "i1", 200000);
 
         let revenue = get_revenue_by_client(&conn).unwrap();
         assert_eq!(revenue.len(), 1);
         assert_eq!(revenue[0].client_name, "Client A");
-        assert_eq!(revenue[0].total_revenue, 2000.0);
+        assert_eq!(revenue[0].total_revenue, 200000);
+        assert_eq!(revenue[0].currency, "USD");
+    }
+
+    #[test]
+    fn test_credit_note_refunds_net_against_revenue() {
+        let conn = init_db_in_memory().expect("init db");
+        conn.execute(
+            "INSERT INTO clients (id, name) VALUES ('c1', 'Client A')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO invoices (id, invoice_number, client_id, status, issue_date, due_date, total)
+             VALUES ('i1', 'INV-001', 'c1', 'paid', datetime('now'), datetime('now', '+30 days'), 100000)",
+            [],
+        )
+        .unwrap();
+        record_payment(&conn, "i1", 100000);
+        conn.execute(
+            "INSERT INTO credit_notes (id, credit_note_number, invoice_id, client_id, issue_date, currency, subtotal, tax_amount, total, applied_amount, refund_amount)
+             VALUES ('cn1', 'CN-001', 'i1', 'c1', date('now'), 'USD', -25000, 0, -25000, 0, 25000)",
+            [],
+        )
+        .unwrap();
+
+        let summary = get_dashboard_summary(&conn).unwrap();
+        assert_eq!(
+            summary.revenue_by_currency,
+            vec![CurrencyAmount { currency: "USD".to_string(), amount: 75000 }]
+        );
+        assert_eq!(get_revenue_by_client(&conn).unwrap()[0].total_revenue, 75000);
+        assert_eq!(get_monthly_revenue(&conn, None).unwrap()[0].revenue, 75000);
+    }
+
+    #[test]
+    fn test_aging_report_buckets() {
+        let conn = init_db_in_memory().expect("init db");
+        conn.execute(
+            "INSERT INTO clients (id, name) VALUES ('c1', 'Client A')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO invoices (id, invoice_number, client_id, status, issue_date, due_date, total)
+             VALUES ('i1', 'INV-001', 'c1', 'sent', '2025-05-01', '2025-06-10T00:00:00Z', 100),
+                    ('i2', 'INV-002', 'c1', 'overdue', '2025-04-01', '2025-05-31T00:00:00Z', 200),
+                    ('i3', 'INV-003', 'c1', 'overdue', '2025-03-01', '2025-04-15T00:00:00Z', 300),
+                    ('i4', 'INV-004', 'c1', 'overdue', '2025-01-01', '2025-03-20T00:00:00+00:00', 400),
+                    ('i5', 'INV-005', 'c1', 'overdue', '2024-12-01', '2025-01-01T00:00:00Z', 500),
+                    ('i6', 'INV-006', 'c1', 'paid', '2024-12-01', '2025-01-01T00:00:00Z', 600)",
+            [],
+        )
+        .unwrap();
+
+        let as_of = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let report = get_aging_report(&conn, as_of).unwrap();
+        assert_eq!(
+            report,
+            vec![AgingBucket {
+                client_id: "c1".to_string(),
+                client_name: "Client A".to_string(),
+                currency: "USD".to_string(),
+                current: 100,
+                days_1_30: 200,
+                days_31_60: 300,
+                days_61_90: 400,
+                days_over_90: 500,
+                total: 1500,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_totals_are_grouped_by_currency() {
+        let conn = init_db_in_memory().expect("init db");
+        conn.execute(
+            "INSERT INTO clients (id, name, currency) VALUES ('c1', 'Client A', 'EUR')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO invoices (id, invoice_number, client_id, status, issue_date, due_date, currency, total)
+             VALUES ('i1', 'INV-001', 'c1', 'paid', datetime('now'), datetime('now', '+30 days'), 'EUR', 100000),
+                    ('i2', 'INV-002', 'c1', 'paid', datetime('now'), datetime('now', '+30 days'), 'GBP', 30000),
+                    ('i3', 'INV-003', 'c1', 'paid', datetime('now'), datetime('now', '+30 days'), 'EUR', 25000)",
+            [],
+        )
+        .unwrap();
+        record_payment(&conn, "i1", 100000);
+        record_payment(&conn, "i2", 30000);
+        record_payment(&conn, "i3", 25000);
+
+        let summary = get_dashboard_summary(&conn).unwrap();
+        assert_eq!(
+            summary.revenue_by_currency,
+            vec![
+                CurrencyAmount { currency: "EUR".to_string(), amount: 125000 },
+                CurrencyAmount { currency: "GBP".to_string(), amount: 30000 },
+            ]
+        );
+
+        let by_client = get_revenue_by_client(&conn).unwrap();
+        assert_eq!(by_client.len(), 2);
+        assert_eq!(by_client[0].currency, "EUR");
+        assert_eq!(by_client[0].total_revenue, 125000);
+
+        let monthly = get_monthly_revenue(&conn, None).unwrap();
+        assert_eq!(monthly.len(), 2);
+        assert!(monthly.iter().all(|m| m.month == monthly[0].month));
+    }
+
+    #[test]
+    fn test_client_hours_are_split_by_billed_currency() {
+        let conn = init_db_in_memory().expect("init db");
+        conn.execute(
+            "INSERT INTO clients (id, name, currency) VALUES ('c1', 'Client A', 'EUR')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO projects (id, client_id, name, status) VALUES ('p1', 'c1', 'Project A', 'active')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO invoices (id, invoice_number, client_id, status, issue_date, due_date, currency, total)
+             VALUES ('i1', 'INV-001', 'c1', 'paid', datetime('now'), datetime('now', '+30 days'), 'EUR', 40000),
+                    ('i2', 'INV-002', 'c1', 'paid', datetime('now'), datetime('now', '+30 days'), 'GBP', 30000)",
+            [],
+        )
+        .unwrap();
+        record_payment(&conn, "i1", 40000);
+        record_payment(&conn, "i2", 30000);
+        // 4h billed in EUR, 3h billed in GBP and 1h not yet billed
+        conn.execute(
+            "INSERT INTO time_entries (id, project_id, start_time, end_time, duration_secs, invoice_id)
+             VALUES ('t1', 'p1', datetime('now'), datetime('now'), 14400, 'i1'),
+                    ('t2', 'p1', datetime('now'), datetime('now'), 10800, 'i2'),
+                    ('t3', 'p1', datetime('now'), datetime('now'), 3600, NULL)",
+            [],
+        )
+        .unwrap();
+
+        let by_client = get_revenue_by_client(&conn).unwrap();
+        let rows: Vec<_> = by_client
+            .iter()
+            .map(|r| (r.currency.as_str(), r.total_revenue, r.total_hours, r.effective_rate))
+            .collect();
+        assert_eq!(rows, vec![("EUR", 40000, 5.0, 8000), ("GBP", 30000, 3.0, 10000)]);
     }
 }
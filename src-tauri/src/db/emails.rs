@@ -0,0 +1,159 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use uuid::Uuid;
+
+use crate::db::invoices;
+use crate::error::AppResult;
+use crate::models::{EmailStatus, InvoiceEmail, InvoiceStatus};
+
+fn row_to_email(row: &rusqlite::Row) -> rusqlite::Result<InvoiceEmail> {
+    let status: String = row.get("status")?;
+    Ok(InvoiceEmail {
+        id: row.get("id")?,
+        invoice_id: row.get("invoice_id")?,
+        recipient: row.get("recipient")?,
+        subject: row.get("subject")?,
+        status: EmailStatus::from_str(&status).unwrap_or(EmailStatus::Failed),
+        error: row.get("error")?,
+        warning: row.get("warning")?,
+        sent_at: row.get("sent_at")?,
+    })
+}
+
+/// Logs a send attempt; `error` is `None` when the SMTP server accepted the message.
+/// A draft that was delivered becomes `sent`, unless it changed after the message was
+/// rendered at `invoice_updated_at`: then the client holds a different document, so the
+/// draft is kept and the log entry carries a warning instead.
+pub fn record_email(
+    conn: &Connection,
+    invoice_id: &str,
+    invoice_updated_at: DateTime<Utc>,
+    recipient: &str,
+    subject: &str,
+    error: Option<&str>,
+) -> AppResult<InvoiceEmail> {
+    let invoice = invoices::get_invoice(conn, invoice_id)?;
+    let status = if error.is_none() { EmailStatus::Sent } else { EmailStatus::Failed };
+    let issue = status == EmailStatus::Sent && invoice.status == InvoiceStatus::Draft;
+    let changed = issue && invoice.updated_at != invoice_updated_at;
+    let id = Uuid::new_v4().to_string();
+
+    // Logged on its own, so a failed status change can't undo the record of a delivery
+    conn.execute(
+        "INSERT INTO invoice_emails (id, invoice_id, recipient, subject, status, error, warning, sent_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            id,
+            invoice_id,
+            recipient,
+            subject,
+            status.as_str(),
+            error,
+            changed.then_some("The invoice changed while it was being sent, so it was left as a draft"),
+            Utc::now().to_rfc3339(),
+        ],
+    )?;
+    if issue && !changed {
+        if let Err(e) = invoices::update_invoice_status(conn, invoice_id, InvoiceStatus::Sent) {
+            conn.execute(
+                "UPDATE invoice_emails SET warning = ?1 WHERE id = ?2",
+                params![format!("The invoice was left as a draft: {e}"), id],
+            )?;
+        }
+    }
+    let email =
+        conn.query_row("SELECT * FROM invoice_emails WHERE id = ?1", params![id], row_to_email)?;
+    Ok(email)
+}
+
+/// Newest first.
+pub fn list_invoice_emails(conn: &Connection, invoice_id: &str) -> AppResult<Vec<InvoiceEmail>> {
+    invoices::get_invoice(conn, invoice_id)?;
+    let mut stmt = conn.prepare(
+        "SELECT * FROM invoice_emails WHERE invoice_id = ?1 ORDER BY sent_at DESC, rowid DESC",
+    )?;
+    let emails = stmt
+        .query_map(params![invoice_id], row_to_email)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(emails)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::clients::create_client;
+    use crate::db::init_db_in_memory;
+    use crate::error::AppError;
+    use crate::models::CreateClient;
+
+    #[test]
+    fn test_record_email_logs_attempts_and_sends_drafts() {
+        let conn = init_db_in_memory().unwrap();
+        let client = create_client(
+            &conn,
+            CreateClient {
+                name: "Test".to_string(),
+                email: Some("ap@test.example".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let invoice =
+            invoices::create_invoice(&conn, &client.id, "2025-01-01T00:00:00Z", None, None, None)
+                .unwrap();
+        let status = |conn: &Connection| invoices::get_invoice(conn, &invoice.id).unwrap().status;
+        let rendered = |conn: &Connection| invoices::get_invoice(conn, &invoice.id).unwrap().updated_at;
+
+        let failed = record_email(&conn, &invoice.id, rendered(&conn), "ap@test.example", "Invoice", Some("connection refused")).unwrap();
+        assert_eq!(failed.status, EmailStatus::Failed);
+        assert_eq!(failed.error.as_deref(), Some("connection refused"));
+        assert_eq!(status(&conn), InvoiceStatus::Draft);
+
+        let sent = record_email(&conn, &invoice.id, rendered(&conn), "ap@test.example", "Invoice", None).unwrap();
+        assert_eq!((sent.status, sent.error, sent.warning), (EmailStatus::Sent, None, None));
+        assert_eq!(status(&conn), InvoiceStatus::Sent);
+
+        // Resending leaves the status alone
+        record_email(&conn, &invoice.id, rendered(&conn), "ap@test.example", "Reminder", None).unwrap();
+        assert_eq!(status(&conn), InvoiceStatus::Sent);
+
+        let log = list_invoice_emails(&conn, &invoice.id).unwrap();
+        let subjects: Vec<_> = log.iter().map(|e| (e.subject.as_str(), e.status)).collect();
+        assert_eq!(
+            subjects,
+            vec![
+                ("Reminder", EmailStatus::Sent),
+                ("Invoice", EmailStatus::Sent),
+                ("Invoice", EmailStatus::Failed),
+            ]
+        );
+        assert!(matches!(list_invoice_emails(&conn, "missing"), Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_draft_changed_during_send_stays_draft() {
+        let conn = init_db_in_memory().unwrap();
+        let client = create_client(
+            &conn,
+            CreateClient {
+                name: "Test".to_string(),
+                email: Some("ap@test.example".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let invoice =
+            invoices::create_invoice(&conn, &client.id, "2025-01-01T00:00:00Z", None, None, None)
+                .unwrap();
+        // Edited after the message was rendered from `invoice`
+        invoices::add_line_item(&conn, &invoice.id, "Late addition", 1.0, 5000, Default::default(), 0)
+            .unwrap();
+
+        let sent = record_email(&conn, &invoice.id, invoice.updated_at, "ap@test.example", "Invoice", None)
+            .unwrap();
+        assert_eq!(sent.status, EmailStatus::Sent);
+        assert!(sent.warning.is_some());
+        assert_eq!(invoices::get_invoice(&conn, &invoice.id).unwrap().status, InvoiceStatus::Draft);
+        assert_eq!(list_invoice_emails(&conn, &invoice.id).unwrap().len(), 1);
+    }
+}
@@ -1,12 +1,21 @@
-use chrono::Utc;
+use chrono::{DateTime, NaiveDate, Utc};
 use rusqlite::{params, Connection};
 use uuid::Uuid;
 
 use crate::error::{AppError, AppResult};
-use crate::models::{Invoice, InvoiceLineItem, InvoiceStatus};
+use crate::models::{
+    BusinessDetails, Discount, Invoice, InvoiceLineItem, InvoiceStatus, LineItemUnit, PaymentMethod,
+    PaymentTerms, RecordPayment, TaxTreatment, UpdateInvoice, UpdateLineItem,
+};
+use crate::db::{business, clients, payments, taxes, templates, with_savepoint};
+use crate::services::numbering::{self, DocumentKind};
+use crate::services::{currency, money, tax, terms};
 
 fn row_to_invoice(row: &rusqlite::Row) -> rusqlite::Result<Invoice> {
     let status_str: String = row.get("status")?;
+    let total: i64 = row.get("total")?;
+    let amount_paid: i64 = row.get("amount_paid")?;
+    let amount_credited: i64 = row.get("amount_credited")?;
     Ok(Invoice {
         id: row.get("id")?,
         invoice_number: row.get("invoice_number")?,
@@ -14,50 +23,197 @@ fn row_to_invoice(row: &rusqlite::Row) -> rusqlite::Result<Invoice> {
         status: InvoiceStatus::from_str(&status_str).unwrap_or(InvoiceStatus::Draft),
         issue_date: row.get("issue_date")?,
         due_date: row.get("due_date")?,
+        payment_terms: row
+            .get::<_, Option<String>>("payment_terms")?
+            .and_then(|terms| PaymentTerms::from_str(&terms)),
+        currency: row.get("currency")?,
         subtotal: row.get("subtotal")?,
+        line_discount_amount: row.get("line_discount_amount")?,
+        discount: Discount::from_columns(
+            row.get::<_, Option<String>>("discount_kind")?.as_deref(),
+            row.get("discount_value")?,
+        ),
+        discount_amount: row.get("discount_amount")?,
         tax_rate: row.get("tax_rate")?,
+        tax_treatment: TaxTreatment::from_str(&row.get::<_, String>("tax_treatment")?)
+            .unwrap_or_default(),
         tax_amount: row.get("tax_amount")?,
-        total: row.get("total")?,
+        total,
+        amount_paid,
+        amount_credited,
+        balance_due: total - amount_paid - amount_credited,
         notes: row.get("notes")?,
         payment_link: row.get("payment_link")?,
+        overdue_at: row.get("overdue_at")?,
+        template_id: row.get("template_id")?,
+        business_override: row
+            .get::<_, Option<String>>("business_override")?
+            .and_then(|json| serde_json::from_str(&json).ok()),
         created_at: row.get("created_at")?,
         updated_at: row.get("updated_at")?,
     })
 }
 
-pub fn generate_invoice_number(conn: &Connection) -> AppResult<String> {
-    let year = Utc::now().format("%Y").to_string();
-    let count: i32 = conn
-        .query_row(
-            "SELECT COUNT(*) FROM invoices WHERE invoice_number LIKE ?1",
-            params![format!("INV-{year}-%")],
-            |row| row.get(0),
-        )
-        .unwrap_or(0);
+/// The calendar date of an ISO 8601 date or timestamp.
+fn parse_date(value: &str, label: &str) -> AppResult<NaiveDate> {
+    NaiveDate::parse_from_str(value.get(..10).unwrap_or(value), "%Y-%m-%d")
+        .map_err(|_| AppError::Validation(format!("Invalid {label} date: {value}")))
+}
 
-    Ok(format!("INV-{year}-{:03}", count + 1))
+fn to_timestamp(date: NaiveDate) -> String {
+    date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().to_rfc3339()
 }
 
+/// Creates a draft invoice. Without a `due_date`, it is derived from the client's
+/// payment terms (or the app default) and those terms print on the invoice.
 pub fn create_invoice(
     conn: &Connection,
     client_id: &str,
     issue_date: &str,
-    due_date: &str,
+    due_date: Option<&str>,
+    notes: Option<&str>,
+    tax_rate: Option<f64>,
+) -> AppResult<Invoice> {
+    insert_invoice(conn, client_id, issue_date, due_date, None, notes, tax_rate)
+}
+
+/// Creates a draft invoice due on `terms`, or on the client's terms when `None`.
+pub fn create_invoice_on_terms(
+    conn: &Connection,
+    client_id: &str,
+    issue_date: &str,
+    terms: Option<PaymentTerms>,
+    notes: Option<&str>,
+    tax_rate: Option<f64>,
+) -> AppResult<Invoice> {
+    insert_invoice(conn, client_id, issue_date, None, terms, notes, tax_rate)
+}
+
+fn insert_invoice(
+    conn: &Connection,
+    client_id: &str,
+    issue_date: &str,
+    due_date: Option<&str>,
+    terms: Option<PaymentTerms>,
     notes: Option<&str>,
     tax_rate: Option<f64>,
 ) -> AppResult<Invoice> {
     let id = Uuid::new_v4().to_string();
-    let invoice_number = generate_invoice_number(conn)?;
+    let client = clients::get_client(conn, client_id)?;
+    let currency = currency::resolve(conn, client.currency.as_deref())?;
+    let issued_on = parse_date(issue_date, "issue")?;
+    let (due_date, payment_terms) = match due_date {
+        Some(due_date) => {
+            parse_date(due_date, "due")?;
+            (due_date.to_string(), None)
+        }
+        None => {
+            let payment_terms = terms::resolve(conn, terms.or(client.payment_terms))?;
+            (to_timestamp(terms::due_date(payment_terms, issued_on)), Some(payment_terms))
+        }
+    };
 
-    conn.execute(
-        "INSERT INTO invoices (id, invoice_number, client_id, status, issue_date, due_date, notes, tax_rate, subtotal, tax_amount, total)
-         VALUES (?1, ?2, ?3, 'draft', ?4, ?5, ?6, ?7, 0, 0, 0)",
-        params![id, invoice_number, client_id, issue_date, due_date, notes, tax_rate],
-    )?;
+    // The number is taken in the same savepoint as the insert, so a failed insert
+    // hands it back
+    with_savepoint(conn, "create_invoice", || {
+        let invoice_number = numbering::next_number(
+            conn,
+            DocumentKind::Invoice,
+            client.invoice_prefix.as_deref(),
+            issued_on,
+        )?;
+        conn.execute(
+            "INSERT INTO invoices (id, invoice_number, client_id, status, issue_date, due_date, payment_terms, currency, notes, tax_rate, tax_treatment, subtotal, tax_amount, total)
+             VALUES (?1, ?2, ?3, 'draft', ?4, ?5, ?6, ?7, ?8, ?9, ?10, 0, 0, 0)",
+            params![
+                id,
+                invoice_number,
+                client_id,
+                issue_date,
+                due_date,
+                payment_terms.map(|terms| terms.as_string()),
+                currency,
+                notes,
+                tax_rate,
+                client.tax_treatment.as_str()
+            ],
+        )?;
+        Ok(())
+    })?;
 
     get_invoice(conn, &id)
 }
 
+/// Copies an invoice and its line items into a new draft with the next number. The
+/// copy has no payments, payment link or time entries; its due date follows
+/// `due_date` or the client's payment terms. Taxes deactivated since are dropped.
+pub fn duplicate_invoice(
+    conn: &Connection,
+    id: &str,
+    issue_date: &str,
+    due_date: Option<&str>,
+) -> AppResult<Invoice> {
+    let source = get_invoice(conn, id)?;
+    let items = get_line_items(conn, id)?;
+
+    with_savepoint(conn, "duplicate_invoice", || {
+        let copy = create_invoice(
+            conn,
+            &source.client_id,
+            issue_date,
+            due_date,
+            source.notes.as_deref(),
+            source.tax_rate,
+        )?;
+        if copy.currency != source.currency {
+            return Err(AppError::Validation(format!(
+                "Invoice {} is in {}, but the client now bills in {}",
+                source.invoice_number, source.currency, copy.currency
+            )));
+        }
+
+        for item in &items {
+            let line_item_id = Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO invoice_line_items (id, invoice_id, description, quantity, unit_price, unit, amount, discount_kind, discount_value, sort_order)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    line_item_id,
+                    copy.id,
+                    item.description,
+                    item.quantity,
+                    item.unit_price,
+                    item.unit.as_str(),
+                    item.amount,
+                    item.discount.map(|d| d.kind_str()),
+                    item.discount.map(|d| d.value()),
+                    item.sort_order,
+                ],
+            )?;
+            for tax_id in &item.tax_ids {
+                conn.execute(
+                    "INSERT INTO invoice_line_item_taxes (line_item_id, tax_id)
+                     SELECT ?1, id FROM taxes WHERE id = ?2 AND is_active = 1",
+                    params![line_item_id, tax_id],
+                )?;
+            }
+        }
+        conn.execute(
+            "UPDATE invoices SET discount_kind = ?1, discount_value = ?2, template_id = ?3, business_override = ?4
+             WHERE id = ?5",
+            params![
+                source.discount.map(|d| d.kind_str()),
+                source.discount.map(|d| d.value()),
+                source.template_id,
+                source.business_override.as_ref().map(serde_json::to_string).transpose()?,
+                copy.id
+            ],
+        )?;
+
+        update_invoice_totals(conn, &copy.id)
+    })
+}
+
 pub fn get_invoice(conn: &Connection, id: &str) -> AppResult<Invoice> {
     conn.query_row("SELECT * FROM invoices WHERE id = ?1", params![id], |row| {
         row_to_invoice(row)
@@ -87,39 +243,304 @@ pub fn list_invoices(conn: &Connection, status: Option<&str>) -> AppResult<Vec<I
     }
 }
 
+/// Edits a draft invoice. The invoice keeps its number when it moves to another
+/// client, and picks up that client's tax treatment. Moving is refused once time
+/// entries are billed on it, or when the new client bills in another currency and
+/// the invoice already has line items.
+///
+/// A due date derived from payment terms follows the issue date and client; setting
+/// `due_date` by hand drops the terms.
+pub fn update_invoice(conn: &Connection, id: &str, input: UpdateInvoice) -> AppResult<Invoice> {
+    let invoice = ensure_draft(conn, id)?;
+    let new_client = input.client_id.as_deref().filter(|client_id| *client_id != invoice.client_id);
+    let issue_date = match &input.issue_date {
+        Some(date) => parse_date(date, "issue")?,
+        None => invoice.issue_date.date_naive(),
+    };
+    let rederive = input.due_date.is_none()
+        && invoice.payment_terms.is_some()
+        && (input.issue_date.is_some() || new_client.is_some());
+    let derived = if rederive {
+        let client = clients::get_client(conn, new_client.unwrap_or(&invoice.client_id))?;
+        let payment_terms = terms::resolve(conn, client.payment_terms)?;
+        Some((payment_terms, terms::due_date(payment_terms, issue_date)))
+    } else {
+        None
+    };
+    let due_date = match (&input.due_date, derived) {
+        (Some(date), _) => parse_date(date, "due")?,
+        (None, Some((_, date))) => date,
+        (None, None) => invoice.due_date.date_naive(),
+    };
+    if due_date < issue_date {
+        return Err(AppError::Validation(format!(
+            "Due date {due_date} is before the issue date {issue_date}"
+        )));
+    }
+    if let Some(rate) = input.tax_rate {
+        if !(0.0..=100.0).contains(&rate) {
+            return Err(AppError::Validation(format!(
+                "Tax rate must be between 0 and 100 percent, got {rate}"
+            )));
+        }
+    }
+
+    let new_currency = match new_client {
+        Some(client_id) => Some(client_currency_for_move(conn, &invoice, client_id)?),
+        None => None,
+    };
+    let template_id = match &input.template_id {
+        Some(template_id) => Some(templates::normalize_id(conn, template_id)?),
+        None => None,
+    };
+    // Stored as JSON; an override with nothing set is stored as NULL
+    let business_override = match input.business_override {
+        Some(details) => {
+            let details = business::normalize(details)?;
+            let json = (details != BusinessDetails::default())
+                .then(|| serde_json::to_string(&details))
+                .transpose()?;
+            Some(json)
+        }
+        None => None,
+    };
+
+    with_savepoint(conn, "update_invoice", || {
+        let now = Utc::now();
+        if let (Some(client_id), Some(currency)) = (new_client, &new_currency) {
+            conn.execute(
+                "UPDATE invoices SET client_id = ?1, currency = ?2, updated_at = ?3 WHERE id = ?4",
+                params![client_id, currency, now.to_rfc3339(), id],
+            )?;
+        }
+        if let Some(date) = &input.issue_date {
+            conn.execute(
+                "UPDATE invoices SET issue_date = ?1, updated_at = ?2 WHERE id = ?3",
+                params![date, now.to_rfc3339(), id],
+            )?;
+        }
+        if let Some(date) = &input.due_date {
+            conn.execute(
+                "UPDATE invoices SET due_date = ?1, payment_terms = NULL, updated_at = ?2 WHERE id = ?3",
+                params![date, now.to_rfc3339(), id],
+            )?;
+        }
+        if let Some((payment_terms, date)) = derived {
+            conn.execute(
+                "UPDATE invoices SET due_date = ?1, payment_terms = ?2, updated_at = ?3 WHERE id = ?4",
+                params![to_timestamp(date), payment_terms.as_string(), now.to_rfc3339(), id],
+            )?;
+        }
+        if let Some(notes) = &input.notes {
+            conn.execute(
+                "UPDATE invoices SET notes = ?1, updated_at = ?2 WHERE id = ?3",
+                params![notes, now.to_rfc3339(), id],
+            )?;
+        }
+        if let Some(rate) = input.tax_rate {
+            conn.execute(
+                "UPDATE invoices SET tax_rate = ?1, updated_at = ?2 WHERE id = ?3",
+                params![rate, now.to_rfc3339(), id],
+            )?;
+        }
+        if let Some(template_id) = &template_id {
+            conn.execute(
+                "UPDATE invoices SET template_id = ?1, updated_at = ?2 WHERE id = ?3",
+                params![template_id, now.to_rfc3339(), id],
+            )?;
+        }
+        if let Some(business_override) = &business_override {
+            conn.execute(
+                "UPDATE invoices SET business_override = ?1, updated_at = ?2 WHERE id = ?3",
+                params![business_override, now.to_rfc3339(), id],
+            )?;
+        }
+
+        if new_client.is_some() || input.tax_rate.is_some() {
+            update_invoice_totals(conn, id)
+        } else {
+            get_invoice(conn, id)
+        }
+    })
+}
+
+/// The currency `invoice` would be billed in for `client_id`, if it can move there.
+fn client_currency_for_move(conn: &Connection, invoice: &Invoice, client_id: &str) -> AppResult<String> {
+    let client = clients::get_client(conn, client_id)?;
+    let billed_entries: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM time_entries WHERE invoice_id = ?1",
+        params![invoice.id],
+        |row| row.get(0),
+    )?;
+    if billed_entries > 0 {
+        return Err(AppError::Validation(format!(
+            "Invoice {} bills {billed_entries} time entries; it can't move to another client",
+            invoice.invoice_number
+        )));
+    }
+
+    let currency = currency::resolve(conn, client.currency.as_deref())?;
+    if currency != invoice.currency && !get_line_items(conn, &invoice.id)?.is_empty() {
+        return Err(AppError::Validation(format!(
+            "{} is billed in {currency}, but invoice {} already has {} line items",
+            client.name, invoice.invoice_number, invoice.currency
+        )));
+    }
+    Ok(currency)
+}
+
 pub fn update_invoice_status(
     conn: &Connection,
     id: &str,
     status: InvoiceStatus,
 ) -> AppResult<Invoice> {
-    let now = Utc::now();
-    let affected = conn.execute(
-        "UPDATE invoices SET status = ?1, updated_at = ?2 WHERE id = ?3",
-        params![status.as_str(), now.to_rfc3339(), id],
-    )?;
-    if affected == 0 {
-        return Err(AppError::NotFound(format!("Invoice not found: {id}")));
+    let invoice = get_invoice(conn, id)?;
+    if !invoice.status.can_transition_to(&status) {
+        return Err(AppError::Validation(format!(
+            "Invoice {} cannot move from {} to {}",
+            invoice.invoice_number,
+            invoice.status.as_str(),
+            status.as_str()
+        )));
     }
-    get_invoice(conn, id)
+
+    // Marking an invoice paid settles it: the outstanding balance is recorded as a payment
+    if status == InvoiceStatus::Paid && invoice.balance_due > 0 {
+        payments::record_payment(
+            conn,
+            RecordPayment {
+                invoice_id: id.to_string(),
+                amount: invoice.balance_due,
+                payment_date: Utc::now().date_naive(),
+                method: PaymentMethod::Other,
+                reference: None,
+            },
+        )?;
+        return get_invoice(conn, id);
+    }
+
+    with_savepoint(conn, "update_invoice_status", || {
+        let now = Utc::now();
+        conn.execute(
+            "UPDATE invoices SET status = ?1, updated_at = ?2 WHERE id = ?3",
+            params![status.as_str(), now.to_rfc3339(), id],
+        )?;
+        // A cancelled invoice no longer bills its hours, so they can go on another one
+        if status == InvoiceStatus::Cancelled {
+            conn.execute(
+                "UPDATE time_entries SET invoice_id = NULL, line_item_id = NULL WHERE invoice_id = ?1",
+                params![id],
+            )?;
+        }
+        get_invoice(conn, id)
+    })
 }
 
-pub fn update_invoice_totals(conn: &Connection, invoice_id: &str) -> AppResult<Invoice> {
-    let subtotal: f64 = conn
-        .query_row(
-            "SELECT COALESCE(SUM(amount), 0) FROM invoice_line_items WHERE invoice_id = ?1",
-            params![invoice_id],
-            |row| row.get(0),
-        )
-        .unwrap_or(0.0);
+/// Line items and totals are frozen once an invoice leaves `draft`.
+pub fn ensure_draft(conn: &Connection, id: &str) -> AppResult<Invoice> {
+    let invoice = get_invoice(conn, id)?;
+    if invoice.status != InvoiceStatus::Draft {
+        return Err(AppError::Validation(format!(
+            "Invoice {} is {}; only draft invoices can be changed",
+            invoice.invoice_number,
+            invoice.status.as_str()
+        )));
+    }
+    Ok(invoice)
+}
+
+/// Whether `invoice` fell due before `today`. It is still on time for the whole of
+/// its due date.
+pub(crate) fn is_past_due(invoice: &Invoice, today: NaiveDate) -> bool {
+    invoice.due_date.date_naive() < today
+}
+
+/// Moves every unpaid invoice due before `today` to `overdue`, stamping `overdue_at`
+/// with `now`. Uses the same calendar-date comparison as [`is_past_due`]. Returns the
+/// invoices that changed.
+pub fn mark_overdue_invoices(
+    conn: &Connection,
+    today: NaiveDate,
+    now: DateTime<Utc>,
+) -> AppResult<Vec<Invoice>> {
+    let mut stmt = conn.prepare(
+        "UPDATE invoices SET status = 'overdue', overdue_at = ?1, updated_at = ?1
+         WHERE status IN ('sent', 'partially_paid') AND date(due_date) < date(?2)
+         RETURNING *",
+    )?;
+    let invoices = stmt
+        .query_map(params![now.to_rfc3339(), today.to_string()], row_to_invoice)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(invoices)
+}
 
+/// Recomputes a draft's subtotal, discounts, tax lines and total from its line items,
+/// picking up the client's current tax treatment. Line discounts apply first, then the
+/// invoice discount, which is spread over the lines in proportion so each tax is
+/// charged on its lines' discounted amounts.
+pub fn update_invoice_totals(conn: &Connection, invoice_id: &str) -> AppResult<Invoice> {
     let invoice = get_invoice(conn, invoice_id)?;
-    let tax_rate = invoice.tax_rate.unwrap_or(0.0);
-    let tax_amount = subtotal * (tax_rate / 100.0);
-    let total = subtotal + tax_amount;
+    let treatment: String = conn.query_row(
+        "SELECT tax_treatment FROM clients WHERE id = ?1",
+        params![invoice.client_id],
+        |row| row.get(0),
+    )?;
+    let treatment = TaxTreatment::from_str(&treatment).unwrap_or_default();
+
+    let mode = money::rounding_mode(conn)?;
+    let items = get_line_items(conn, invoice_id)?;
+    let line_discounts: Vec<i64> = items
+        .iter()
+        .map(|item| money::discount_amount(item.amount, item.discount, mode))
+        .collect();
+    let discounted: Vec<i64> = items
+        .iter()
+        .zip(&line_discounts)
+        .map(|(item, discount)| item.amount - discount)
+        .collect();
+    let discount_amount =
+        money::discount_amount(discounted.iter().sum(), invoice.discount, mode);
+    let shares = money::allocate(discount_amount, &discounted);
 
+    let mut lines = Vec::with_capacity(items.len());
+    for (i, item) in items.iter().enumerate() {
+        lines.push(tax::TaxableLine {
+            amount: discounted[i] - shares[i],
+            taxes: item
+                .tax_ids
+                .iter()
+                .map(|id| taxes::get_tax(conn, id))
+                .collect::<AppResult<_>>()?,
+        });
+    }
+    let subtotal: i64 = items.iter().map(|item| item.amount).sum();
+    let line_discount_amount: i64 = line_discounts.iter().sum();
+    let tax_lines = tax::compute(&lines, invoice.tax_rate, treatment, mode);
+    let tax_amount: i64 = tax_lines.iter().map(|t| t.amount).sum();
+    let total = subtotal - line_discount_amount - discount_amount + tax_amount;
+
+    for (item, line_discount) in items.iter().zip(&line_discounts) {
+        if item.discount_amount != *line_discount {
+            conn.execute(
+                "UPDATE invoice_line_items SET discount_amount = ?1 WHERE id = ?2",
+                params![line_discount, item.id],
+            )?;
+        }
+    }
+    taxes::replace_invoice_taxes(conn, invoice_id, &tax_lines)?;
     conn.execute(
-        "UPDATE invoices SET subtotal = ?1, tax_amount = ?2, total = ?3, updated_at = ?4 WHERE id = ?5",
-        params![subtotal, tax_amount, total, Utc::now().to_rfc3339(), invoice_id],
+        "UPDATE invoices SET subtotal = ?1, line_discount_amount = ?2, discount_amount = ?3, tax_amount = ?4, total = ?5, tax_treatment = ?6, updated_at = ?7
+         WHERE id = ?8",
+        params![
+            subtotal,
+            line_discount_amount,
+            discount_amount,
+            tax_amount,
+            total,
+            treatment.as_str(),
+            Utc::now().to_rfc3339(),
+            invoice_id
+        ],
     )?;
 
     get_invoice(conn, invoice_id)
@@ -133,30 +554,52 @@ pub fn set_payment_link(conn: &Connection, id: &str, link: &str) -> AppResult<In
     get_invoice(conn, id)
 }
 
+/// Deletes a draft invoice and releases its time entries so they can be invoiced again.
+/// Issued invoices are kept for the record; cancel them instead.
 pub fn delete_invoice(conn: &Connection, id: &str) -> AppResult<()> {
-    let affected = conn.execute("DELETE FROM invoices WHERE id = ?1", params![id])?;
-    if affected == 0 {
-        return Err(AppError::NotFound(format!("Invoice not found: {id}")));
+    ensure_draft(conn, id)?;
+    with_savepoint(conn, "delete_invoice", || {
+        conn.execute(
+            "UPDATE time_entries SET invoice_id = NULL, line_item_id = NULL WHERE invoice_id = ?1",
+            params![id],
+        )?;
+        conn.execute("DELETE FROM invoices WHERE id = ?1", params![id])?;
+        Ok(())
+    })
+}
+
+// Line items
+/// Checks what both adding and editing a line item require.
+fn validate_line_item(description: &str, quantity: f64) -> AppResult<()> {
+    if description.trim().is_empty() {
+        return Err(AppError::Validation("Line item description is required".to_string()));
+    }
+    if quantity.is_nan() || quantity <= 0.0 {
+        return Err(AppError::Validation(
+            "Line item quantity must be greater than zero".to_string(),
+        ));
     }
     Ok(())
 }
 
-// Line items
 pub fn add_line_item(
     conn: &Connection,
     invoice_id: &str,
     description: &str,
     quantity: f64,
-    unit_price: f64,
+    unit_price: i64,
+    unit: LineItemUnit,
     sort_order: i32,
 ) -> AppResult<InvoiceLineItem> {
+    let invoice = ensure_draft(conn, invoice_id)?;
+    validate_line_item(description, quantity)?;
     let id = Uuid::new_v4().to_string();
-    let amount = quantity * unit_price;
+    let amount = money::line_amount(quantity, unit_price, money::rounding_mode(conn)?);
 
     conn.execute(
-        "INSERT INTO invoice_line_items (id, invoice_id, description, quantity, unit_price, amount, sort_order)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-        params![id, invoice_id, description, quantity, unit_price, amount, sort_order],
+        "INSERT INTO invoice_line_items (id, invoice_id, description, quantity, unit_price, unit, amount, sort_order)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![id, invoice_id, description, quantity, unit_price, unit.as_str(), amount, sort_order],
     )?;
 
     update_invoice_totals(conn, invoice_id)?;
@@ -167,15 +610,24 @@ pub fn add_line_item(
         description: description.to_string(),
         quantity,
         unit_price,
+        currency: invoice.currency,
+        unit,
         amount,
+        discount: None,
+        discount_amount: 0,
         sort_order,
+        tax_ids: Vec::new(),
     })
 }
 
 pub fn get_line_items(conn: &Connection, invoice_id: &str) -> AppResult<Vec<InvoiceLineItem>> {
     let mut stmt = conn
-        .prepare("SELECT * FROM invoice_line_items WHERE invoice_id = ?1 ORDER BY sort_order")?;
-    let items = stmt
+        .prepare(
+            "SELECT li.*, i.currency FROM invoice_line_items li
+             JOIN invoices i ON i.id = li.invoice_id
+             WHERE li.invoice_id = ?1 ORDER BY li.sort_order",
+        )?;
+    let mut items = stmt
         .query_map(params![invoice_id], |row| {
             Ok(InvoiceLineItem {
                 id: row.get("id")?,
@@ -183,21 +635,231 @@ pub fn get_line_items(conn: &Connection, invoice_id: &str) -> AppResult<Vec<Invo
                 description: row.get("description")?,
                 quantity: row.get("quantity")?,
                 unit_price: row.get("unit_price")?,
+                currency: row.get("currency")?,
+                unit: LineItemUnit::from_str(&row.get::<_, String>("unit")?).unwrap_or_default(),
                 amount: row.get("amount")?,
+                discount: Discount::from_columns(
+                    row.get::<_, Option<String>>("discount_kind")?.as_deref(),
+                    row.get("discount_value")?,
+                ),
+                discount_amount: row.get("discount_amount")?,
                 sort_order: row.get("sort_order")?,
+                tax_ids: Vec::new(),
             })
         })?
         .collect::<Result<Vec<_>, _>>()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT t.line_item_id, t.tax_id FROM invoice_line_item_taxes t
+         JOIN invoice_line_items li ON li.id = t.line_item_id
+         JOIN taxes tx ON tx.id = t.tax_id
+         WHERE li.invoice_id = ?1
+         ORDER BY tx.is_compound ASC, tx.name ASC",
+    )?;
+    let assignments = stmt
+        .query_map(params![invoice_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (line_item_id, tax_id) in assignments {
+        if let Some(item) = items.iter_mut().find(|item| item.id == line_item_id) {
+            item.tax_ids.push(tax_id);
+        }
+    }
     Ok(items)
 }
 
-pub fn delete_line_item(conn: &Connection, id: &str, invoice_id: &str) -> AppResult<()> {
-    conn.execute(
-        "DELETE FROM invoice_line_items WHERE id = ?1",
-        params![id],
+/// Replaces the taxes on a draft invoice's line item. An empty list puts the line
+/// back on the invoice-wide `tax_rate`.
+pub fn set_line_item_taxes(
+    conn: &Connection,
+    invoice_id: &str,
+    line_item_id: &str,
+    tax_ids: &[String],
+) -> AppResult<InvoiceLineItem> {
+    ensure_draft(conn, invoice_id)?;
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM invoice_line_items WHERE id = ?1 AND invoice_id = ?2)",
+        params![line_item_id, invoice_id],
+        |row| row.get(0),
     )?;
-    update_invoice_totals(conn, invoice_id)?;
-    Ok(())
+    if !exists {
+        return Err(AppError::NotFound(format!("Line item not found: {line_item_id}")));
+    }
+    for tax_id in tax_ids {
+        let tax = taxes::get_tax(conn, tax_id)?;
+        if !tax.is_active {
+            return Err(AppError::Validation(format!(
+                "{} is inactive and can't be assigned",
+                tax.name
+            )));
+        }
+    }
+
+    with_savepoint(conn, "set_line_item_taxes", || {
+        conn.execute(
+            "DELETE FROM invoice_line_item_taxes WHERE line_item_id = ?1",
+            params![line_item_id],
+        )?;
+        for tax_id in tax_ids {
+            conn.execute(
+                "INSERT OR IGNORE INTO invoice_line_item_taxes (line_item_id, tax_id) VALUES (?1, ?2)",
+                params![line_item_id, tax_id],
+            )?;
+        }
+        update_invoice_totals(conn, invoice_id)?;
+        Ok(())
+    })?;
+
+    get_line_item(conn, invoice_id, line_item_id)
+}
+
+fn validate_discount(discount: Option<Discount>) -> AppResult<()> {
+    match discount {
+        Some(Discount::Percent(percent)) if !(0.0..=100.0).contains(&percent) => Err(
+            AppError::Validation(format!("Discount must be between 0 and 100 percent, got {percent}")),
+        ),
+        Some(Discount::Fixed(amount)) if amount < 0 => Err(AppError::Validation(
+            "A fixed discount can't be negative".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Sets or clears the discount on a draft invoice's line item.
+pub fn set_line_item_discount(
+    conn: &Connection,
+    invoice_id: &str,
+    line_item_id: &str,
+    discount: Option<Discount>,
+) -> AppResult<InvoiceLineItem> {
+    ensure_draft(conn, invoice_id)?;
+    validate_discount(discount)?;
+
+    with_savepoint(conn, "set_line_item_discount", || {
+        let changed = conn.execute(
+            "UPDATE invoice_line_items SET discount_kind = ?1, discount_value = ?2
+             WHERE id = ?3 AND invoice_id = ?4",
+            params![
+                discount.map(|d| d.kind_str()),
+                discount.map(|d| d.value()),
+                line_item_id,
+                invoice_id
+            ],
+        )?;
+        if changed == 0 {
+            return Err(AppError::NotFound(format!("Line item not found: {line_item_id}")));
+        }
+        update_invoice_totals(conn, invoice_id)?;
+        Ok(())
+    })?;
+
+    get_line_item(conn, invoice_id, line_item_id)
+}
+
+/// Sets or clears the invoice-wide discount on a draft.
+pub fn set_invoice_discount(
+    conn: &Connection,
+    invoice_id: &str,
+    discount: Option<Discount>,
+) -> AppResult<Invoice> {
+    ensure_draft(conn, invoice_id)?;
+    validate_discount(discount)?;
+
+    with_savepoint(conn, "set_invoice_discount", || {
+        conn.execute(
+            "UPDATE invoices SET discount_kind = ?1, discount_value = ?2 WHERE id = ?3",
+            params![discount.map(|d| d.kind_str()), discount.map(|d| d.value()), invoice_id],
+        )?;
+        update_invoice_totals(conn, invoice_id)
+    })
+}
+
+fn get_line_item(conn: &Connection, invoice_id: &str, line_item_id: &str) -> AppResult<InvoiceLineItem> {
+    get_line_items(conn, invoice_id)?
+        .into_iter()
+        .find(|item| item.id == line_item_id)
+        .ok_or_else(|| AppError::NotFound(format!("Line item not found: {line_item_id}")))
+}
+
+/// Edits a draft invoice's line item, recomputing its amount and the invoice totals.
+pub fn update_line_item(
+    conn: &Connection,
+    invoice_id: &str,
+    line_item_id: &str,
+    input: UpdateLineItem,
+) -> AppResult<InvoiceLineItem> {
+    ensure_draft(conn, invoice_id)?;
+    let item = get_line_item(conn, invoice_id, line_item_id)?;
+    let description = input.description.unwrap_or(item.description);
+    let quantity = input.quantity.unwrap_or(item.quantity);
+    validate_line_item(&description, quantity)?;
+    let unit_price = input.unit_price.unwrap_or(item.unit_price);
+    let unit = input.unit.unwrap_or(item.unit);
+    let amount = money::line_amount(quantity, unit_price, money::rounding_mode(conn)?);
+
+    with_savepoint(conn, "update_line_item", || {
+        conn.execute(
+            "UPDATE invoice_line_items SET description = ?1, quantity = ?2, unit_price = ?3, unit = ?4, amount = ?5
+             WHERE id = ?6",
+            params![description, quantity, unit_price, unit.as_str(), amount, line_item_id],
+        )?;
+        update_invoice_totals(conn, invoice_id)?;
+        Ok(())
+    })?;
+
+    get_line_item(conn, invoice_id, line_item_id)
+}
+
+/// Puts a draft invoice's line items in the given order. `line_item_ids` must list
+/// every line item of the invoice exactly once.
+pub fn reorder_line_items(
+    conn: &Connection,
+    invoice_id: &str,
+    line_item_ids: &[String],
+) -> AppResult<Vec<InvoiceLineItem>> {
+    ensure_draft(conn, invoice_id)?;
+    let mut current: Vec<String> = get_line_items(conn, invoice_id)?
+        .into_iter()
+        .map(|item| item.id)
+        .collect();
+    let mut requested = line_item_ids.to_vec();
+    current.sort();
+    requested.sort();
+    if current != requested {
+        return Err(AppError::Validation(
+            "The new order must list each of the invoice's line items exactly once".to_string(),
+        ));
+    }
+
+    with_savepoint(conn, "reorder_line_items", || {
+        for (i, id) in line_item_ids.iter().enumerate() {
+            conn.execute(
+                "UPDATE invoice_line_items SET sort_order = ?1 WHERE id = ?2",
+                params![i as i32, id],
+            )?;
+        }
+        // Tax lines are listed in order of first use
+        update_invoice_totals(conn, invoice_id)?;
+        Ok(())
+    })?;
+
+    get_line_items(conn, invoice_id)
+}
+
+/// Time entries billed on the line go back to being uninvoiced.
+pub fn delete_line_item(conn: &Connection, id: &str, invoice_id: &str) -> AppResult<()> {
+    ensure_draft(conn, invoice_id)?;
+    get_line_item(conn, invoice_id, id)?;
+    with_savepoint(conn, "delete_line_item", || {
+        conn.execute(
+            "UPDATE time_entries SET invoice_id = NULL, line_item_id = NULL WHERE line_item_id = ?1",
+            params![id],
+        )?;
+        conn.execute("DELETE FROM invoice_line_items WHERE id = ?1", params![id])?;
+        update_invoice_totals(conn, invoice_id)?;
+        Ok(())
+    })
 }
 
 pub fn count_invoices_this_month(conn: &Connection) -> AppResult<i32> {
@@ -216,7 +878,9 @@ mod tests {
     use super::*;
     use crate::db::clients::create_client;
     use crate::db::init_db_in_memory;
-    use crate::models::CreateClient;
+    use crate::db::clients::update_client;
+    use crate::db::settings::set_setting;
+    use crate::models::{CreateClient, CreateTax, UpdateClient, UpdateTax};
 
     fn setup() -> (Connection, String) {
         let conn = init_db_in_memory().expect("Failed to init test DB");
@@ -230,6 +894,7 @@ mod tests {
                 phone: None,
                 notes: None,
                 hourly_rate: None,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -237,11 +902,56 @@ mod tests {
     }
 
     #[test]
-    fn test_invoice_number_generation() {
-        let (conn, _) = setup();
-        let num1 = generate_invoice_number(&conn).unwrap();
-        let year = Utc::now().format("%Y").to_string();
-        assert_eq!(num1, format!("INV-{year}-001"));
+    fn test_invoice_numbers_are_never_reused() {
+        let (conn, client_id) = setup();
+        let create = |issue_date| {
+            create_invoice(&conn, &client_id, issue_date, Some("2026-02-28T00:00:00Z"), None, None).unwrap()
+        };
+
+        let first = create("2026-01-15T00:00:00Z");
+        assert_eq!(first.invoice_number, "INV-2026-001");
+        delete_invoice(&conn, &first.id).unwrap();
+        assert_eq!(create("2026-01-16T00:00:00Z").invoice_number, "INV-2026-002");
+
+        set_setting(&conn, "invoice_number_pattern", "{CLIENT}-{YYYY}-{SEQ:3}").unwrap();
+        update_client(
+            &conn,
+            &client_id,
+            UpdateClient {
+                invoice_prefix: Some("ACME".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(create("2026-01-17T00:00:00Z").invoice_number, "ACME-2026-001");
+
+        let invalid = create_invoice(&conn, &client_id, "soon", Some("2026-02-28T00:00:00Z"), None, None);
+        assert!(matches!(invalid, Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn test_invoice_currency_follows_client_then_default() {
+        let (conn, client_id) = setup();
+        let dates = ("2025-01-01T00:00:00Z", "2025-01-31T00:00:00Z");
+
+        let invoice = create_invoice(&conn, &client_id, dates.0, Some(dates.1), None, None).unwrap();
+        assert_eq!(invoice.currency, "USD");
+
+        set_setting(&conn, "default_currency", "GBP").unwrap();
+        let invoice = create_invoice(&conn, &client_id, dates.0, Some(dates.1), None, None).unwrap();
+        assert_eq!(invoice.currency, "GBP");
+
+        update_client(
+            &conn,
+            &client_id,
+            UpdateClient {
+                currency: Some("eur".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let invoice = create_invoice(&conn, &client_id, dates.0, Some(dates.1), None, None).unwrap();
+        assert_eq!(invoice.currency, "EUR");
     }
 
     #[test]
@@ -251,22 +961,22 @@ mod tests {
             &conn,
             &client_id,
             "2025-01-01T00:00:00Z",
-            "2025-01-31T00:00:00Z",
+            Some("2025-01-31T00:00:00Z"),
             None,
             Some(10.0),
         )
         .unwrap();
 
         assert_eq!(invoice.status, InvoiceStatus::Draft);
-        assert_eq!(invoice.total, 0.0);
+        assert_eq!(invoice.total, 0);
 
-        add_line_item(&conn, &invoice.id, "Web Development", 10.0, 150.0, 0).unwrap();
-        add_line_item(&conn, &invoice.id, "Design Work", 5.0, 120.0, 1).unwrap();
+        add_line_item(&conn, &invoice.id, "Web Development", 10.0, 15000, LineItemUnit::Hours, 0).unwrap();
+        add_line_item(&conn, &invoice.id, "Design Work", 5.0, 12000, LineItemUnit::Hours, 1).unwrap();
 
         let updated = get_invoice(&conn, &invoice.id).unwrap();
-        assert_eq!(updated.subtotal, 2100.0); // 1500 + 600
-        assert_eq!(updated.tax_amount, 210.0); // 10% of 2100
-        assert_eq!(updated.total, 2310.0);
+        assert_eq!(updated.subtotal, 210000); // 1500.00 + 600.00
+        assert_eq!(updated.tax_amount, 21000); // 10% of 2100.00
+        assert_eq!(updated.total, 231000);
 
         let items = get_line_items(&conn, &invoice.id).unwrap();
         assert_eq!(items.len(), 2);
@@ -279,7 +989,7 @@ mod tests {
             &conn,
             &client_id,
             "2025-01-01T00:00:00Z",
-            "2025-01-31T00:00:00Z",
+            Some("2025-01-31T00:00:00Z"),
             None,
             None,
         )
@@ -292,6 +1002,87 @@ mod tests {
         assert_eq!(paid.status, InvoiceStatus::Paid);
     }
 
+    #[test]
+    fn test_every_status_transition() {
+        use InvoiceStatus::*;
+        let all = [Draft, Sent, PartiallyPaid, Paid, Overdue, Cancelled];
+        let allowed = [
+            (Draft, Sent),
+            (Draft, Cancelled),
+            (Sent, Paid),
+            (Sent, Overdue),
+            (Sent, Cancelled),
+            (PartiallyPaid, Paid),
+            (PartiallyPaid, Overdue),
+            (PartiallyPaid, Cancelled),
+            (Overdue, Paid),
+            (Overdue, Cancelled),
+        ];
+
+        let (conn, client_id) = setup();
+        for from in &all {
+            for to in &all {
+                let invoice = create_invoice(
+                    &conn,
+                    &client_id,
+                    "2025-01-01T00:00:00Z",
+                    Some("2025-01-31T00:00:00Z"),
+                    None,
+                    None,
+                )
+                .unwrap();
+                conn.execute(
+                    "UPDATE invoices SET status = ?1 WHERE id = ?2",
+                    params![from.as_str(), invoice.id],
+                )
+                .unwrap();
+
+                let result = update_invoice_status(&conn, &invoice.id, to.clone());
+                let expected = allowed.contains(&(from.clone(), to.clone()));
+                match result {
+                    Ok(updated) => {
+                        assert!(expected, "{from:?} -> {to:?} should be rejected");
+                        assert_eq!(&updated.status, to);
+                    }
+                    Err(AppError::Validation(msg)) => {
+                        assert!(!expected, "{from:?} -> {to:?} should be allowed");
+                        assert!(msg.contains(&format!("from {} to {}", from.as_str(), to.as_str())));
+                        let unchanged = get_invoice(&conn, &invoice.id).unwrap();
+                        assert_eq!(&unchanged.status, from);
+                    }
+                    Err(e) => panic!("unexpected error for {from:?} -> {to:?}: {e}"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_issued_invoices_are_immutable() {
+        let (conn, client_id) = setup();
+        let invoice = create_invoice(
+            &conn,
+            &client_id,
+            "2025-01-01T00:00:00Z",
+            Some("2025-01-31T00:00:00Z"),
+            None,
+            None,
+        )
+        .unwrap();
+        let item = add_line_item(&conn, &invoice.id, "Work", 1.0, 10000, LineItemUnit::Hours, 0).unwrap();
+        update_invoice_status(&conn, &invoice.id, InvoiceStatus::Sent).unwrap();
+
+        let add = add_line_item(&conn, &invoice.id, "More", 1.0, 500, LineItemUnit::Hours, 1);
+        assert!(matches!(add, Err(AppError::Validation(_))));
+        let remove = delete_line_item(&conn, &item.id, &invoice.id);
+        assert!(matches!(remove, Err(AppError::Validation(_))));
+        let delete = delete_invoice(&conn, &invoice.id);
+        assert!(matches!(delete, Err(AppError::Validation(_))));
+
+        let unchanged = get_invoice(&conn, &invoice.id).unwrap();
+        assert_eq!(unchanged.total, 10000);
+        assert_eq!(get_line_items(&conn, &invoice.id).unwrap().len(), 1);
+    }
+
     #[test]
     fn test_delete_line_item_updates_totals() {
         let (conn, client_id) = setup();
@@ -299,18 +1090,463 @@ mod tests {
             &conn,
             &client_id,
             "2025-01-01T00:00:00Z",
-            "2025-01-31T00:00:00Z",
+            Some("2025-01-31T00:00:00Z"),
             None,
             None,
         )
         .unwrap();
 
-        let item = add_line_item(&conn, &invoice.id, "Work", 10.0, 100.0, 0).unwrap();
+        let item = add_line_item(&conn, &invoice.id, "Work", 10.0, 10000, LineItemUnit::Hours, 0).unwrap();
         let with_item = get_invoice(&conn, &invoice.id).unwrap();
-        assert_eq!(with_item.total, 1000.0);
+        assert_eq!(with_item.total, 100000);
 
         delete_line_item(&conn, &item.id, &invoice.id).unwrap();
         let without_item = get_invoice(&conn, &invoice.id).unwrap();
-        assert_eq!(without_item.total, 0.0);
+        assert_eq!(without_item.total, 0);
+    }
+
+    #[test]
+    fn test_totals_use_configured_rounding_mode() {
+        let (conn, client_id) = setup();
+        let dates = ("2025-01-01T00:00:00Z", "2025-01-31T00:00:00Z");
+
+        // 0.5h at 10.25 = 5.125 and 5% tax on 0.50 = 0.025: both exact halves
+        let invoice = create_invoice(&conn, &client_id, dates.0, Some(dates.1), None, Some(5.0)).unwrap();
+        let item = add_line_item(&conn, &invoice.id, "Call", 0.5, 1025, LineItemUnit::Hours, 0).unwrap();
+        assert_eq!(item.amount, 513);
+        let invoice = get_invoice(&conn, &invoice.id).unwrap();
+        assert_eq!((invoice.subtotal, invoice.tax_amount, invoice.total), (513, 26, 539));
+
+        set_setting(&conn, "rounding_mode", "half_even").unwrap();
+        let invoice = create_invoice(&conn, &client_id, dates.0, Some(dates.1), None, Some(5.0)).unwrap();
+        let item = add_line_item(&conn, &invoice.id, "Call", 0.5, 1025, LineItemUnit::Hours, 0).unwrap();
+        add_line_item(&conn, &invoice.id, "Fee", 1.0, -12, LineItemUnit::Hours, 1).unwrap();
+        assert_eq!(item.amount, 512);
+        let invoice = get_invoice(&conn, &invoice.id).unwrap();
+        // subtotal 5.00, 5% = 0.25
+        assert_eq!((invoice.subtotal, invoice.tax_amount, invoice.total), (500, 25, 525));
+    }
+
+    #[test]
+    fn test_line_item_taxes_and_client_exemptions() {
+        let (conn, client_id) = setup();
+        let dates = ("2025-01-01T00:00:00Z", "2025-01-31T00:00:00Z");
+        let new_tax = |name: &str, rate, is_compound| {
+            taxes::create_tax(
+                &conn,
+                CreateTax { name: name.to_string(), rate, is_compound },
+            )
+            .unwrap()
+            .id
+        };
+        let gst = new_tax("GST", 5.0, false);
+        let qst = new_tax("QST", 9.975, true);
+
+        let invoice = create_invoice(&conn, &client_id, dates.0, Some(dates.1), None, None).unwrap();
+        let design = add_line_item(&conn, &invoice.id, "Design", 10.0, 10000, LineItemUnit::Hours, 0).unwrap();
+        let hosting = add_line_item(&conn, &invoice.id, "Hosting", 1.0, 20000, LineItemUnit::Hours, 1).unwrap();
+        let design = set_line_item_taxes(&conn, &invoice.id, &design.id, &[qst.clone(), gst.clone()]).unwrap();
+        assert_eq!(design.tax_ids, vec![gst.clone(), qst.clone()]);
+        set_line_item_taxes(&conn, &invoice.id, &hosting.id, std::slice::from_ref(&gst)).unwrap();
+
+        // GST 5% on 1200.00; QST 9.975% on 1000.00 + 50.00 GST
+        let summary: Vec<(String, i64, i64)> = taxes::get_invoice_taxes(&conn, &invoice.id)
+            .unwrap()
+            .into_iter()
+            .map(|t| (t.name, t.taxable_amount, t.amount))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("GST".to_string(), 120000, 6000), ("QST".to_string(), 105000, 10474)]
+        );
+        let taxed = get_invoice(&conn, &invoice.id).unwrap();
+        assert_eq!((taxed.subtotal, taxed.tax_amount, taxed.total), (120000, 16474, 136474));
+
+        taxes::update_tax(
+            &conn,
+            &gst,
+            UpdateTax { name: None, rate: None, is_compound: None, is_active: Some(false) },
+        )
+        .unwrap();
+        let inactive = set_line_item_taxes(&conn, &invoice.id, &hosting.id, std::slice::from_ref(&gst));
+        assert!(matches!(inactive, Err(AppError::Validation(_))));
+
+        update_client(
+            &conn,
+            &client_id,
+            UpdateClient {
+                tax_treatment: Some(TaxTreatment::ReverseCharge),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let exempt = create_invoice(&conn, &client_id, dates.0, Some(dates.1), None, Some(20.0)).unwrap();
+        let item = add_line_item(&conn, &exempt.id, "Design", 10.0, 10000, LineItemUnit::Hours, 0).unwrap();
+        set_line_item_taxes(&conn, &exempt.id, &item.id, &[qst]).unwrap();
+        let exempt = get_invoice(&conn, &exempt.id).unwrap();
+        assert_eq!(exempt.tax_treatment, TaxTreatment::ReverseCharge);
+        assert_eq!((exempt.tax_amount, exempt.total), (0, 100000));
+        assert!(taxes::get_invoice_taxes(&conn, &exempt.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_discounts_apply_before_tax() {
+        let (conn, client_id) = setup();
+        let dates = ("2025-01-01T00:00:00Z", "2025-01-31T00:00:00Z");
+        let vat = taxes::create_tax(
+            &conn,
+            CreateTax { name: "VAT".to_string(), rate: 20.0, is_compound: false },
+        )
+        .unwrap();
+
+        let invoice = create_invoice(&conn, &client_id, dates.0, Some(dates.1), None, None).unwrap();
+        let design = add_line_item(&conn, &invoice.id, "Design", 10.0, 10000, LineItemUnit::Hours, 0).unwrap();
+        let hosting = add_line_item(&conn, &invoice.id, "Hosting", 1.0, 20000, LineItemUnit::Hours, 1).unwrap();
+        set_line_item_taxes(&conn, &invoice.id, &design.id, std::slice::from_ref(&vat.id)).unwrap();
+
+        // 10% off design: 1000.00 -> 900.00
+        let design =
+            set_line_item_discount(&conn, &invoice.id, &design.id, Some(Discount::Percent(10.0))).unwrap();
+        assert_eq!((design.amount, design.discount_amount), (100000, 10000));
+
+        // 110.00 off the remaining 1100.00, spread 900:200 across the lines
+        let invoice = set_invoice_discount(&conn, &invoice.id, Some(Discount::Fixed(11000))).unwrap();
+        assert_eq!(invoice.subtotal, 120000);
+        assert_eq!(invoice.line_discount_amount, 10000);
+        assert_eq!(invoice.discount_amount, 11000);
+        // VAT only on design: 20% of (900.00 - 90.00)
+        assert_eq!(invoice.tax_amount, 16200);
+        assert_eq!(invoice.total, 120000 - 10000 - 11000 + 16200);
+        let vat_line = &taxes::get_invoice_taxes(&conn, &invoice.id).unwrap()[0];
+        assert_eq!(vat_line.taxable_amount, 81000);
+
+        // A fixed discount can't exceed what's left
+        let invoice = set_invoice_discount(&conn, &invoice.id, Some(Discount::Fixed(500000))).unwrap();
+        assert_eq!(invoice.discount_amount, 110000);
+        assert_eq!(invoice.total, 0);
+
+        let invalid = set_invoice_discount(&conn, &invoice.id, Some(Discount::Percent(120.0)));
+        assert!(matches!(invalid, Err(AppError::Validation(_))));
+        let missing = set_line_item_discount(&conn, &invoice.id, "nope", None);
+        assert!(matches!(missing, Err(AppError::NotFound(_))));
+
+        let invoice = set_invoice_discount(&conn, &invoice.id, None).unwrap();
+        set_line_item_discount(&conn, &invoice.id, &hosting.id, None).unwrap();
+        let invoice = get_invoice(&conn, &invoice.id).unwrap();
+        assert_eq!((invoice.discount, invoice.discount_amount), (None, 0));
+        assert_eq!(invoice.total, 120000 - 10000 + 18000);
+
+        update_invoice_status(&conn, &invoice.id, InvoiceStatus::Sent).unwrap();
+        let frozen = set_invoice_discount(&conn, &invoice.id, Some(Discount::Percent(5.0)));
+        assert!(matches!(frozen, Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn test_update_and_reorder_line_items() {
+        let (conn, client_id) = setup();
+        let dates = ("2025-01-01T00:00:00Z", "2025-01-31T00:00:00Z");
+        let invoice = create_invoice(&conn, &client_id, dates.0, Some(dates.1), None, Some(10.0)).unwrap();
+        let design = add_line_item(&conn, &invoice.id, "Desing", 10.0, 10000, LineItemUnit::Hours, 0).unwrap();
+        let hosting = add_line_item(&conn, &invoice.id, "Hosting", 1.0, 20000, LineItemUnit::Fixed, 1).unwrap();
+        set_line_item_discount(&conn, &invoice.id, &design.id, Some(Discount::Percent(10.0))).unwrap();
+
+        let design = update_line_item(
+            &conn,
+            &invoice.id,
+            &design.id,
+            UpdateLineItem {
+                description: Some("Design".to_string()),
+                quantity: Some(2.0),
+                unit_price: Some(80000),
+                unit: Some(LineItemUnit::Days),
+            },
+        )
+        .unwrap();
+        assert_eq!(design.description, "Design");
+        assert_eq!((design.unit, design.amount, design.discount_amount), (LineItemUnit::Days, 160000, 16000));
+        let updated = get_invoice(&conn, &invoice.id).unwrap();
+        assert_eq!(updated.subtotal, 180000);
+        assert_eq!(updated.total, 180000 - 16000 + 16400);
+
+        let blank = UpdateLineItem { description: Some(" ".to_string()), quantity: None, unit_price: None, unit: None };
+        assert!(matches!(
+            update_line_item(&conn, &invoice.id, &design.id, blank),
+            Err(AppError::Validation(_))
+        ));
+        let zero = UpdateLineItem { description: None, quantity: Some(0.0), unit_price: None, unit: None };
+        assert!(matches!(
+            update_line_item(&conn, &invoice.id, &design.id, zero),
+            Err(AppError::Validation(_))
+        ));
+        for (description, quantity) in [(" ", 1.0), ("Support", 0.0), ("Support", -2.0), ("Support", f64::NAN)] {
+            let added = add_line_item(&conn, &invoice.id, description, quantity, 5000, LineItemUnit::Hours, 2);
+            assert!(matches!(added, Err(AppError::Validation(_))), "{description:?} x {quantity} should be rejected");
+        }
+        let unknown_unit = serde_json::from_str::<UpdateLineItem>(r#"{"unit": "weeks"}"#);
+        assert!(unknown_unit.unwrap_err().to_string().contains("Unknown line item unit: weeks"));
+        assert_eq!(get_line_items(&conn, &invoice.id).unwrap().len(), 2);
+
+        let reordered =
+            reorder_line_items(&conn, &invoice.id, &[hosting.id.clone(), design.id.clone()]).unwrap();
+        let order: Vec<(&str, i32)> =
+            reordered.iter().map(|item| (item.description.as_str(), item.sort_order)).collect();
+        assert_eq!(order, vec![("Hosting", 0), ("Design", 1)]);
+        let incomplete = reorder_line_items(&conn, &invoice.id, std::slice::from_ref(&hosting.id));
+        assert!(matches!(incomplete, Err(AppError::Validation(_))));
+        let duplicated = reorder_line_items(&conn, &invoice.id, &[hosting.id.clone(), hosting.id.clone()]);
+        assert!(matches!(duplicated, Err(AppError::Validation(_))));
+
+        update_invoice_status(&conn, &invoice.id, InvoiceStatus::Sent).unwrap();
+        let frozen = reorder_line_items(&conn, &invoice.id, &[design.id, hosting.id]);
+        assert!(matches!(frozen, Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn test_update_draft_invoice() {
+        let (conn, client_id) = setup();
+        let new_client = |name: &str, currency: &str, tax_treatment| {
+            create_client(
+                &conn,
+                CreateClient {
+                    name: name.to_string(),
+                    currency: Some(currency.to_string()),
+                    tax_treatment: Some(tax_treatment),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .id
+        };
+        let edit = |client_id: Option<&str>, issue: Option<&str>, due: Option<&str>, tax_rate| UpdateInvoice {
+            client_id: client_id.map(str::to_string),
+            issue_date: issue.map(str::to_string),
+            due_date: due.map(str::to_string),
+            notes: None,
+            tax_rate,
+            ..Default::default()
+        };
+        let invoice = create_invoice(
+            &conn,
+            &client_id,
+            "2025-01-01T00:00:00Z",
+            Some("2025-01-31T00:00:00Z"),
+            None,
+            None,
+        )
+        .unwrap();
+        add_line_item(&conn, &invoice.id, "Work", 10.0, 10000, LineItemUnit::Hours, 0).unwrap();
+
+        let updated = update_invoice(
+            &conn,
+            &invoice.id,
+            UpdateInvoice {
+                notes: Some("Net 14".to_string()),
+                ..edit(None, Some("2025-01-10T00:00:00Z"), Some("2025-01-24T00:00:00Z"), Some(10.0))
+            },
+        )
+        .unwrap();
+        assert_eq!(updated.issue_date.date_naive().to_string(), "2025-01-10");
+        assert_eq!(updated.due_date.date_naive().to_string(), "2025-01-24");
+        assert_eq!(updated.notes.as_deref(), Some("Net 14"));
+        assert_eq!((updated.tax_amount, updated.total), (10000, 110000));
+        assert_eq!(updated.invoice_number, invoice.invoice_number);
+
+        let backwards = update_invoice(&conn, &invoice.id, edit(None, None, Some("2025-01-01"), None));
+        assert!(matches!(backwards, Err(AppError::Validation(_))));
+        let bad_date = update_invoice(&conn, &invoice.id, edit(None, Some("someday"), None, None));
+        assert!(matches!(bad_date, Err(AppError::Validation(_))));
+
+        let euro_client = new_client("Berlin GmbH", "EUR", TaxTreatment::Standard);
+        let other_currency = update_invoice(&conn, &invoice.id, edit(Some(&euro_client), None, None, None));
+        assert!(matches!(other_currency, Err(AppError::Validation(_))));
+
+        let exempt_client = new_client("Charity", "USD", TaxTreatment::Exempt);
+        let moved = update_invoice(&conn, &invoice.id, edit(Some(&exempt_client), None, None, None)).unwrap();
+        assert_eq!(moved.client_id, exempt_client);
+        assert_eq!(moved.tax_treatment, TaxTreatment::Exempt);
+        assert_eq!((moved.tax_amount, moved.total), (0, 100000));
+
+        update_invoice_status(&conn, &invoice.id, InvoiceStatus::Sent).unwrap();
+        let frozen = update_invoice(&conn, &invoice.id, edit(None, None, None, Some(5.0)));
+        assert!(matches!(frozen, Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn test_due_date_follows_payment_terms() {
+        let (conn, client_id) = setup();
+        let set_terms = |payment_terms| {
+            update_client(
+                &conn,
+                &client_id,
+                UpdateClient {
+                    payment_terms: Some(payment_terms),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+        let due_on = |invoice: &Invoice| invoice.due_date.date_naive().to_string();
+
+        // App default, then a configured default, then the client's own terms
+        let invoice = create_invoice(&conn, &client_id, "2025-01-15T00:00:00Z", None, None, None).unwrap();
+        assert_eq!((due_on(&invoice), invoice.payment_terms), ("2025-02-14".to_string(), Some(PaymentTerms::Net(30))));
+        set_setting(&conn, "default_payment_terms", "eom_10").unwrap();
+        let invoice = create_invoice(&conn, &client_id, "2025-01-15T00:00:00Z", None, None, None).unwrap();
+        assert_eq!(due_on(&invoice), "2025-02-10");
+        set_terms(PaymentTerms::Net(15));
+        let invoice = create_invoice(&conn, &client_id, "2025-01-15T00:00:00Z", None, None, None).unwrap();
+        assert_eq!((due_on(&invoice), invoice.payment_terms), ("2025-01-30".to_string(), Some(PaymentTerms::Net(15))));
+
+        // Moving the issue date moves a derived due date; a hand-set due date drops the terms
+        let edit = |issue_date: Option<&str>, due_date: Option<&str>| UpdateInvoice {
+            client_id: None,
+            issue_date: issue_date.map(str::to_string),
+            due_date: due_date.map(str::to_string),
+            notes: None,
+            tax_rate: None,
+            ..Default::default()
+        };
+        let moved = update_invoice(&conn, &invoice.id, edit(Some("2025-02-01T00:00:00Z"), None)).unwrap();
+        assert_eq!(due_on(&moved), "2025-02-16");
+        let custom = update_invoice(&conn, &invoice.id, edit(None, Some("2025-03-01T00:00:00Z"))).unwrap();
+        assert_eq!((due_on(&custom), custom.payment_terms), ("2025-03-01".to_string(), None));
+        let kept = update_invoice(&conn, &invoice.id, edit(Some("2025-02-05T00:00:00Z"), None)).unwrap();
+        assert_eq!(due_on(&kept), "2025-03-01");
+
+        let explicit = create_invoice(&conn, &client_id, "2025-01-15T00:00:00Z", Some("2025-01-20T00:00:00Z"), None, None)
+            .unwrap();
+        assert_eq!((due_on(&explicit), explicit.payment_terms), ("2025-01-20".to_string(), None));
+    }
+
+    #[test]
+    fn test_duplicate_invoice() {
+        let (conn, client_id) = setup();
+        let vat = taxes::create_tax(
+            &conn,
+            CreateTax { name: "VAT".to_string(), rate: 20.0, is_compound: false },
+        )
+        .unwrap();
+        let source = create_invoice(
+            &conn,
+            &client_id,
+            "2025-01-01T00:00:00Z",
+            Some("2025-01-31T00:00:00Z"),
+            Some("Thanks!"),
+            None,
+        )
+        .unwrap();
+        let design = add_line_item(&conn, &source.id, "Design", 10.0, 10000, LineItemUnit::Hours, 0).unwrap();
+        add_line_item(&conn, &source.id, "Hosting", 1.0, 20000, LineItemUnit::Fixed, 1).unwrap();
+        set_line_item_taxes(&conn, &source.id, &design.id, std::slice::from_ref(&vat.id)).unwrap();
+        set_line_item_discount(&conn, &source.id, &design.id, Some(Discount::Percent(10.0))).unwrap();
+        set_invoice_discount(&conn, &source.id, Some(Discount::Fixed(5000))).unwrap();
+        set_payment_link(&conn, &source.id, "https://pay.example.com/1").unwrap();
+        update_invoice_status(&conn, &source.id, InvoiceStatus::Sent).unwrap();
+        let source = update_invoice_status(&conn, &source.id, InvoiceStatus::Paid).unwrap();
+
+        let copy = duplicate_invoice(&conn, &source.id, "2025-03-01T00:00:00Z", None).unwrap();
+        assert_ne!(copy.id, source.id);
+        assert_eq!(copy.invoice_number, "INV-2025-002");
+        assert_eq!(copy.status, InvoiceStatus::Draft);
+        assert_eq!(copy.issue_date.date_naive().to_string(), "2025-03-01");
+        assert_eq!(copy.due_date.date_naive().to_string(), "2025-03-31");
+        assert_eq!(copy.notes.as_deref(), Some("Thanks!"));
+        assert_eq!((copy.payment_link, copy.amount_paid), (None, 0));
+        assert_eq!(copy.discount, Some(Discount::Fixed(5000)));
+        assert_eq!((copy.subtotal, copy.tax_amount, copy.total), (source.subtotal, source.tax_amount, source.total));
+        assert_eq!(copy.balance_due, copy.total);
+
+        let lines = get_line_items(&conn, &copy.id).unwrap();
+        let summary: Vec<(&str, LineItemUnit, Option<Discount>, usize)> = lines
+            .iter()
+            .map(|item| (item.description.as_str(), item.unit, item.discount, item.tax_ids.len()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Design", LineItemUnit::Hours, Some(Discount::Percent(10.0)), 1),
+                ("Hosting", LineItemUnit::Fixed, None, 0),
+            ]
+        );
+        // The source keeps its own lines
+        assert_eq!(get_line_items(&conn, &source.id).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_cancelling_releases_time_entries() {
+        let (conn, client_id) = setup();
+        let invoice = create_invoice(&conn, &client_id, "2025-01-01T00:00:00Z", None, None, None).unwrap();
+        conn.execute(
+            "INSERT INTO projects (id, client_id, name) VALUES ('p1', ?1, 'Site')",
+            params![client_id],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO time_entries (id, project_id, start_time, end_time, duration_secs, invoice_id)
+             VALUES ('t1', 'p1', '2025-01-02T09:00:00Z', '2025-01-02T11:00:00Z', 7200, ?1)",
+            params![invoice.id],
+        )
+        .unwrap();
+        let linked = || -> Option<String> {
+            conn.query_row("SELECT invoice_id FROM time_entries WHERE id = 't1'", [], |row| row.get(0))
+                .unwrap()
+        };
+
+        update_invoice_status(&conn, &invoice.id, InvoiceStatus::Sent).unwrap();
+        assert_eq!(linked(), Some(invoice.id.clone()));
+
+        let cancelled = update_invoice_status(&conn, &invoice.id, InvoiceStatus::Cancelled).unwrap();
+        assert_eq!(cancelled.status, InvoiceStatus::Cancelled);
+        assert_eq!(linked(), None);
+    }
+
+    #[test]
+    fn test_mark_overdue_invoices() {
+        let (conn, client_id) = setup();
+        let due = |due_date: &str| {
+            create_invoice(&conn, &client_id, "2025-01-01T00:00:00Z", Some(due_date), None, None).unwrap()
+        };
+        let late = due("2025-01-31T00:00:00Z");
+        let not_due = due("2025-03-31T00:00:00Z");
+        let draft = due("2025-01-31T00:00:00Z");
+        update_invoice_status(&conn, &late.id, InvoiceStatus::Sent).unwrap();
+        update_invoice_status(&conn, &not_due.id, InvoiceStatus::Sent).unwrap();
+
+        let today = NaiveDate::from_ymd_opt(2025, 2, 1).unwrap();
+        let now: DateTime<Utc> = "2025-02-01T09:00:00Z".parse().unwrap();
+        let swept = mark_overdue_invoices(&conn, today, now).unwrap();
+        assert_eq!(swept.len(), 1);
+        assert_eq!(swept[0].id, late.id);
+        assert_eq!(swept[0].status, InvoiceStatus::Overdue);
+        assert_eq!(swept[0].overdue_at, Some(now));
+
+        assert_eq!(get_invoice(&conn, &not_due.id).unwrap().status, InvoiceStatus::Sent);
+        assert_eq!(get_invoice(&conn, &draft.id).unwrap().status, InvoiceStatus::Draft);
+        assert!(mark_overdue_invoices(&conn, today, now).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_invoice_is_not_overdue_on_its_due_date() {
+        let (conn, client_id) = setup();
+        let invoice = create_invoice(
+            &conn,
+            &client_id,
+            "2025-01-01T00:00:00Z",
+            Some("2025-01-31T00:00:00Z"),
+            None,
+            None,
+        )
+        .unwrap();
+        update_invoice_status(&conn, &invoice.id, InvoiceStatus::Sent).unwrap();
+
+        let due_date = NaiveDate::from_ymd_opt(2025, 1, 31).unwrap();
+        let late_evening: DateTime<Utc> = "2025-01-31T23:30:00Z".parse().unwrap();
+        assert!(mark_overdue_invoices(&conn, due_date, late_evening).unwrap().is_empty());
+        assert_eq!(get_invoice(&conn, &invoice.id).unwrap().status, InvoiceStatus::Sent);
+
+        let next_day = due_date.succ_opt().unwrap();
+        let swept = mark_overdue_invoices(&conn, next_day, late_evening).unwrap();
+        assert_eq!(swept.len(), 1);
+        assert_eq!(swept[0].status, InvoiceStatus::Overdue);
     }
 }
@@ -1,15 +1,49 @@
+pub mod business;
 pub mod clients;
+pub mod credit_notes;
 pub mod dashboard;
+pub mod emails;
 pub mod estimates;
 pub mod invoices;
+pub mod payments;
 pub mod projects;
+pub mod recurring_invoices;
+pub mod settings;
+pub mod taxes;
+pub mod templates;
 pub mod time_entries;
 
-use rusqlite::Connection;
+use rusqlite::{params, Connection};
 
 use crate::error::AppResult;
+use crate::services::currency;
 
-const MIGRATIONS: &[&str] = &[MIGRATION_V1];
+const MIGRATIONS: &[&str] = &[
+    MIGRATION_V1,
+    MIGRATION_V2,
+    MIGRATION_V3,
+    MIGRATION_V4,
+    MIGRATION_V5,
+    MIGRATION_V6,
+    MIGRATION_V7,
+    MIGRATION_V8,
+    MIGRATION_V9,
+    MIGRATION_V10,
+    MIGRATION_V11,
+    MIGRATION_V12,
+    MIGRATION_V13,
+    MIGRATION_V14,
+    MIGRATION_V15,
+    MIGRATION_V16,
+    MIGRATION_V17,
+    MIGRATION_V18,
+    MIGRATION_V19,
+    MIGRATION_V20,
+    MIGRATION_V21,
+    MIGRATION_V22,
+    MIGRATION_V23,
+    MIGRATION_V24,
+];
 
 const MIGRATION_V1: &str = r#"
 CREATE TABLE IF NOT EXISTS clients (
@@ -119,6 +153,478 @@ CREATE TABLE IF NOT EXISTS app_settings (
 );
 "#;
 
+// Currency codes (ISO 4217). Existing invoices predate multi-currency and were USD.
+const MIGRATION_V2: &str = r#"
+ALTER TABLE clients ADD COLUMN currency TEXT;
+ALTER TABLE invoices ADD COLUMN currency TEXT NOT NULL DEFAULT 'USD';
+"#;
+
+// Money moves from REAL major units to INTEGER minor units of the row's currency.
+// Scaled values are first rounded to 4 decimals so float noise such as
+// 0.285 * 100 = 28.4999999... cannot lose a minor unit in the conversion.
+// `currency_scale` is filled from the supported currencies by `prepare_migration`.
+const MIGRATION_V3: &str = r#"
+CREATE TEMP TABLE client_currency AS
+SELECT c.id AS client_id,
+       COALESCE(c.currency, UPPER((SELECT value FROM app_settings WHERE key = 'default_currency')), 'USD') AS code
+FROM clients c;
+INSERT OR IGNORE INTO currency_scale (code, factor)
+SELECT DISTINCT code, 100 FROM client_currency;
+INSERT OR IGNORE INTO currency_scale (code, factor)
+SELECT DISTINCT currency, 100 FROM invoices;
+
+ALTER TABLE clients ADD COLUMN hourly_rate_minor INTEGER;
+UPDATE clients SET hourly_rate_minor = (
+    SELECT CAST(ROUND(ROUND(clients.hourly_rate * s.factor, 4)) AS INTEGER)
+    FROM client_currency cc JOIN currency_scale s ON s.code = cc.code
+    WHERE cc.client_id = clients.id
+);
+ALTER TABLE clients DROP COLUMN hourly_rate;
+ALTER TABLE clients RENAME COLUMN hourly_rate_minor TO hourly_rate;
+
+ALTER TABLE projects ADD COLUMN hourly_rate_minor INTEGER;
+UPDATE projects SET hourly_rate_minor = (
+    SELECT CAST(ROUND(ROUND(projects.hourly_rate * s.factor, 4)) AS INTEGER)
+    FROM client_currency cc JOIN currency_scale s ON s.code = cc.code
+    WHERE cc.client_id = projects.client_id
+);
+ALTER TABLE projects DROP COLUMN hourly_rate;
+ALTER TABLE projects RENAME COLUMN hourly_rate_minor TO hourly_rate;
+
+ALTER TABLE invoices ADD COLUMN subtotal_minor INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE invoices ADD COLUMN tax_amount_minor INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE invoices ADD COLUMN total_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE invoices SET
+    subtotal_minor = (SELECT CAST(ROUND(ROUND(invoices.subtotal * factor, 4)) AS INTEGER) FROM currency_scale WHERE code = invoices.currency),
+    tax_amount_minor = (SELECT CAST(ROUND(ROUND(invoices.tax_amount * factor, 4)) AS INTEGER) FROM currency_scale WHERE code = invoices.currency),
+    total_minor = (SELECT CAST(ROUND(ROUND(invoices.total * factor, 4)) AS INTEGER) FROM currency_scale WHERE code = invoices.currency);
+ALTER TABLE invoices DROP COLUMN subtotal;
+ALTER TABLE invoices DROP COLUMN tax_amount;
+ALTER TABLE invoices DROP COLUMN total;
+ALTER TABLE invoices RENAME COLUMN subtotal_minor TO subtotal;
+ALTER TABLE invoices RENAME COLUMN tax_amount_minor TO tax_amount;
+ALTER TABLE invoices RENAME COLUMN total_minor TO total;
+
+ALTER TABLE invoice_line_items ADD COLUMN unit_price_minor INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE invoice_line_items ADD COLUMN amount_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE invoice_line_items SET
+    unit_price_minor = (
+        SELECT CAST(ROUND(ROUND(invoice_line_items.unit_price * s.factor, 4)) AS INTEGER)
+        FROM invoices i JOIN currency_scale s ON s.code = i.currency
+        WHERE i.id = invoice_line_items.invoice_id
+    ),
+    amount_minor = (
+        SELECT CAST(ROUND(ROUND(invoice_line_items.amount * s.factor, 4)) AS INTEGER)
+        FROM invoices i JOIN currency_scale s ON s.code = i.currency
+        WHERE i.id = invoice_line_items.invoice_id
+    );
+ALTER TABLE invoice_line_items DROP COLUMN unit_price;
+ALTER TABLE invoice_line_items DROP COLUMN amount;
+ALTER TABLE invoice_line_items RENAME COLUMN unit_price_minor TO unit_price;
+ALTER TABLE invoice_line_items RENAME COLUMN amount_minor TO amount;
+
+DROP TABLE client_currency;
+DROP TABLE currency_scale;
+"#;
+
+// Recurring invoice templates, their line items, and the occurrences already generated.
+// The occurrences primary key is what keeps the catch-up pass from issuing duplicates.
+const MIGRATION_V4: &str = r#"
+CREATE TABLE IF NOT EXISTS recurring_invoices (
+    id TEXT PRIMARY KEY NOT NULL,
+    client_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    frequency TEXT NOT NULL,
+    interval INTEGER NOT NULL DEFAULT 1,
+    day_of_month INTEGER,
+    start_date TEXT NOT NULL,
+    end_date TEXT,
+    count INTEGER,
+    tax_rate REAL,
+    notes TEXT,
+    net_days INTEGER NOT NULL DEFAULT 30,
+    is_active INTEGER NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    FOREIGN KEY (client_id) REFERENCES clients(id)
+);
+CREATE INDEX IF NOT EXISTS idx_recurring_invoices_client_id ON recurring_invoices(client_id);
+
+CREATE TABLE IF NOT EXISTS recurring_invoice_items (
+    id TEXT PRIMARY KEY NOT NULL,
+    recurring_invoice_id TEXT NOT NULL,
+    description TEXT NOT NULL,
+    quantity REAL NOT NULL,
+    unit_price INTEGER NOT NULL,
+    sort_order INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY (recurring_invoice_id) REFERENCES recurring_invoices(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_recurring_invoice_items_parent ON recurring_invoice_items(recurring_invoice_id);
+
+CREATE TABLE IF NOT EXISTS recurring_invoice_occurrences (
+    recurring_invoice_id TEXT NOT NULL,
+    occurrence_date TEXT NOT NULL,
+    invoice_id TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    PRIMARY KEY (recurring_invoice_id, occurrence_date),
+    FOREIGN KEY (recurring_invoice_id) REFERENCES recurring_invoices(id) ON DELETE CASCADE
+);
+"#;
+
+// When an invoice was swept from sent to overdue.
+const MIGRATION_V5: &str = r#"
+ALTER TABLE invoices ADD COLUMN overdue_at TEXT;
+"#;
+
+// Payment ledger. Invoices already marked paid are assumed to have been paid in full.
+const MIGRATION_V6: &str = r#"
+CREATE TABLE IF NOT EXISTS payments (
+    id TEXT PRIMARY KEY NOT NULL,
+    invoice_id TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    payment_date TEXT NOT NULL,
+    method TEXT NOT NULL,
+    reference TEXT,
+    voided_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    FOREIGN KEY (invoice_id) REFERENCES invoices(id)
+);
+CREATE INDEX IF NOT EXISTS idx_payments_invoice_id ON payments(invoice_id);
+
+ALTER TABLE invoices ADD COLUMN amount_paid INTEGER NOT NULL DEFAULT 0;
+
+INSERT INTO payments (id, invoice_id, amount, payment_date, method)
+SELECT lower(hex(randomblob(16))), id, total, date(updated_at), 'other'
+FROM invoices WHERE status = 'paid' AND total > 0;
+UPDATE invoices SET amount_paid = total WHERE status = 'paid';
+"#;
+
+// Credit notes against issued invoices. Revenue is payments less refunds, so credit
+// beyond an invoice's open balance shows up as a negative revenue entry.
+const MIGRATION_V7: &str = r#"
+CREATE TABLE IF NOT EXISTS credit_notes (
+    id TEXT PRIMARY KEY NOT NULL,
+    credit_note_number TEXT NOT NULL UNIQUE,
+    invoice_id TEXT NOT NULL,
+    client_id TEXT NOT NULL,
+    issue_date TEXT NOT NULL,
+    currency TEXT NOT NULL,
+    subtotal INTEGER NOT NULL,
+    tax_rate REAL,
+    tax_amount INTEGER NOT NULL,
+    total INTEGER NOT NULL,
+    applied_amount INTEGER NOT NULL,
+    refund_amount INTEGER NOT NULL,
+    reason TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    FOREIGN KEY (invoice_id) REFERENCES invoices(id),
+    FOREIGN KEY (client_id) REFERENCES clients(id)
+);
+CREATE INDEX IF NOT EXISTS idx_credit_notes_invoice_id ON credit_notes(invoice_id);
+
+CREATE TABLE IF NOT EXISTS credit_note_line_items (
+    id TEXT PRIMARY KEY NOT NULL,
+    credit_note_id TEXT NOT NULL,
+    description TEXT NOT NULL,
+    quantity REAL NOT NULL,
+    unit_price INTEGER NOT NULL,
+    amount INTEGER NOT NULL,
+    sort_order INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY (credit_note_id) REFERENCES credit_notes(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_credit_note_line_items_credit_note_id ON credit_note_line_items(credit_note_id);
+
+ALTER TABLE invoices ADD COLUMN amount_credited INTEGER NOT NULL DEFAULT 0;
+
+CREATE VIEW IF NOT EXISTS revenue_entries AS
+SELECT invoice_id, amount, payment_date AS entry_date FROM payments WHERE voided_at IS NULL
+UNION ALL
+SELECT invoice_id, -refund_amount, issue_date FROM credit_notes WHERE refund_amount > 0;
+"#;
+
+// Counter-backed document numbering. Counters for the default yearly `INV-`/`CN-`
+// sequences start after the highest number already issued, so nothing is reused.
+const MIGRATION_V8: &str = r#"
+CREATE TABLE IF NOT EXISTS number_sequences (
+    key TEXT PRIMARY KEY NOT NULL,
+    last_value INTEGER NOT NULL
+);
+
+ALTER TABLE clients ADD COLUMN invoice_prefix TEXT;
+
+INSERT INTO number_sequences (key, last_value)
+SELECT 'invoice:' || substr(invoice_number, 5, 4), MAX(CAST(substr(invoice_number, 10) AS INTEGER))
+FROM invoices WHERE invoice_number GLOB 'INV-[0-9][0-9][0-9][0-9]-[0-9]*'
+GROUP BY substr(invoice_number, 5, 4);
+
+INSERT INTO number_sequences (key, last_value)
+SELECT 'credit_note:' || substr(credit_note_number, 4, 4), MAX(CAST(substr(credit_note_number, 9) AS INTEGER))
+FROM credit_notes WHERE credit_note_number GLOB 'CN-[0-9][0-9][0-9][0-9]-[0-9]*'
+GROUP BY substr(credit_note_number, 4, 4);
+"#;
+
+// Named taxes assigned per line item, per-client tax treatment, and a snapshot of
+// each document's tax lines. Existing single-rate taxes become one unnamed tax line.
+const MIGRATION_V9: &str = r#"
+CREATE TABLE IF NOT EXISTS taxes (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    rate REAL NOT NULL,
+    is_compound INTEGER NOT NULL DEFAULT 0,
+    is_active INTEGER NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
+
+CREATE TABLE IF NOT EXISTS invoice_line_item_taxes (
+    line_item_id TEXT NOT NULL,
+    tax_id TEXT NOT NULL,
+    PRIMARY KEY (line_item_id, tax_id),
+    FOREIGN KEY (line_item_id) REFERENCES invoice_line_items(id) ON DELETE CASCADE,
+    FOREIGN KEY (tax_id) REFERENCES taxes(id)
+);
+CREATE INDEX IF NOT EXISTS idx_invoice_line_item_taxes_tax_id ON invoice_line_item_taxes(tax_id);
+
+CREATE TABLE IF NOT EXISTS invoice_taxes (
+    invoice_id TEXT NOT NULL,
+    sort_order INTEGER NOT NULL,
+    tax_id TEXT,
+    name TEXT NOT NULL,
+    rate REAL NOT NULL,
+    is_compound INTEGER NOT NULL DEFAULT 0,
+    taxable_amount INTEGER NOT NULL,
+    amount INTEGER NOT NULL,
+    PRIMARY KEY (invoice_id, sort_order),
+    FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS credit_note_taxes (
+    credit_note_id TEXT NOT NULL,
+    sort_order INTEGER NOT NULL,
+    tax_id TEXT,
+    name TEXT NOT NULL,
+    rate REAL NOT NULL,
+    is_compound INTEGER NOT NULL DEFAULT 0,
+    taxable_amount INTEGER NOT NULL,
+    amount INTEGER NOT NULL,
+    PRIMARY KEY (credit_note_id, sort_order),
+    FOREIGN KEY (credit_note_id) REFERENCES credit_notes(id) ON DELETE CASCADE
+);
+
+ALTER TABLE clients ADD COLUMN tax_treatment TEXT NOT NULL DEFAULT 'standard';
+ALTER TABLE invoices ADD COLUMN tax_treatment TEXT NOT NULL DEFAULT 'standard';
+ALTER TABLE credit_notes ADD COLUMN tax_treatment TEXT NOT NULL DEFAULT 'standard';
+
+INSERT INTO invoice_taxes (invoice_id, sort_order, tax_id, name, rate, taxable_amount, amount)
+SELECT id, 0, NULL, 'Tax', tax_rate, subtotal, tax_amount FROM invoices
+WHERE tax_rate IS NOT NULL AND tax_rate != 0;
+INSERT INTO credit_note_taxes (credit_note_id, sort_order, tax_id, name, rate, taxable_amount, amount)
+SELECT id, 0, NULL, 'Tax', tax_rate, subtotal, tax_amount FROM credit_notes
+WHERE tax_rate IS NOT NULL AND tax_rate != 0;
+"#;
+
+// Percentage or fixed discounts on line items and on the invoice as a whole. A
+// line's `amount` stays `quantity * unit_price`; `discount_amount` is taken off it.
+const MIGRATION_V10: &str = r#"
+ALTER TABLE invoice_line_items ADD COLUMN discount_kind TEXT;
+ALTER TABLE invoice_line_items ADD COLUMN discount_value REAL;
+ALTER TABLE invoice_line_items ADD COLUMN discount_amount INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE invoices ADD COLUMN discount_kind TEXT;
+ALTER TABLE invoices ADD COLUMN discount_value REAL;
+ALTER TABLE invoices ADD COLUMN line_discount_amount INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE invoices ADD COLUMN discount_amount INTEGER NOT NULL DEFAULT 0;
+"#;
+
+// What a line's quantity counts. Lines so far were billed by the hour.
+const MIGRATION_V11: &str = r#"
+ALTER TABLE invoice_line_items ADD COLUMN unit TEXT NOT NULL DEFAULT 'hours';
+ALTER TABLE recurring_invoice_items ADD COLUMN unit TEXT NOT NULL DEFAULT 'hours';
+"#;
+
+// Payment terms per client, and the terms each invoice's due date was derived from.
+const MIGRATION_V12: &str = r#"
+ALTER TABLE clients ADD COLUMN payment_terms TEXT;
+ALTER TABLE invoices ADD COLUMN payment_terms TEXT;
+"#;
+
+// User-managed Handlebars templates, picked per invoice or per client. Without
+// one, documents render with the built-in templates/invoice.html.
+const MIGRATION_V13: &str = r#"
+CREATE TABLE IF NOT EXISTS invoice_templates (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    body TEXT NOT NULL,
+    brand_color TEXT,
+    logo TEXT,
+    is_default INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
+
+ALTER TABLE clients ADD COLUMN template_id TEXT REFERENCES invoice_templates(id) ON DELETE SET NULL;
+ALTER TABLE invoices ADD COLUMN template_id TEXT REFERENCES invoice_templates(id) ON DELETE SET NULL;
+"#;
+
+// The business profile replaces the business_name/email/address settings, which
+// seed it. `address` holds one address line per text line. An invoice's
+// `business_override` is a JSON `BusinessDetails`.
+const MIGRATION_V14: &str = r#"
+CREATE TABLE IF NOT EXISTS business_profile (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    legal_name TEXT NOT NULL DEFAULT '',
+    email TEXT,
+    phone TEXT,
+    website TEXT,
+    address TEXT,
+    tax_id TEXT,
+    iban TEXT,
+    bank_details TEXT,
+    logo_path TEXT,
+    updated_at TEXT
+);
+
+INSERT INTO business_profile (id, legal_name, email, address)
+SELECT 1,
+       COALESCE((SELECT value FROM app_settings WHERE key = 'business_name'), ''),
+       NULLIF((SELECT value FROM app_settings WHERE key = 'business_email'), ''),
+       NULLIF((SELECT value FROM app_settings WHERE key = 'business_address'), '')
+WHERE EXISTS (SELECT 1 FROM app_settings WHERE key IN ('business_name', 'business_email', 'business_address'));
+
+ALTER TABLE invoices ADD COLUMN business_override TEXT;
+"#;
+
+// Locale codes such as 'de'; NULL uses the `default_locale` setting.
+const MIGRATION_V15: &str = r#"
+ALTER TABLE clients ADD COLUMN locale TEXT;
+"#;
+
+// Every attempt to email an invoice, kept even when the SMTP server refused it.
+const MIGRATION_V16: &str = r#"
+CREATE TABLE IF NOT EXISTS invoice_emails (
+    id TEXT PRIMARY KEY,
+    invoice_id TEXT NOT NULL REFERENCES invoices(id) ON DELETE CASCADE,
+    recipient TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('sent', 'failed')),
+    error TEXT,
+    sent_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_invoice_emails_invoice ON invoice_emails(invoice_id);
+"#;
+
+// Prior values of edited time entries, one row per edit.
+const MIGRATION_V17: &str = r#"
+CREATE TABLE IF NOT EXISTS time_entry_revisions (
+    id TEXT PRIMARY KEY NOT NULL,
+    time_entry_id TEXT NOT NULL REFERENCES time_entries(id) ON DELETE CASCADE,
+    project_id TEXT NOT NULL,
+    description TEXT,
+    start_time TEXT NOT NULL,
+    end_time TEXT NOT NULL,
+    duration_secs INTEGER NOT NULL,
+    is_billable INTEGER NOT NULL,
+    revised_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_time_entry_revisions_entry ON time_entry_revisions(time_entry_id);
+"#;
+
+// Heartbeats from the frontend, used to spot idle time in the running timer.
+const MIGRATION_V18: &str = r#"
+ALTER TABLE active_timer ADD COLUMN last_activity_at TEXT;
+ALTER TABLE active_timer ADD COLUMN paused_at TEXT;
+"#;
+
+// Named timers: `active_timer` loses its single-row CHECK. Only one timer may run
+// at a time; the others wait paused.
+const MIGRATION_V19: &str = r#"
+CREATE TABLE active_timer_v19 (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT,
+    project_id TEXT NOT NULL,
+    description TEXT,
+    start_time TEXT NOT NULL,
+    accumulated_secs INTEGER NOT NULL DEFAULT 0,
+    is_paused INTEGER NOT NULL DEFAULT 0,
+    last_activity_at TEXT,
+    paused_at TEXT,
+    FOREIGN KEY (project_id) REFERENCES projects(id)
+);
+
+INSERT INTO active_timer_v19 (id, project_id, description, start_time, accumulated_secs, is_paused, last_activity_at, paused_at)
+SELECT id, project_id, description, start_time, accumulated_secs, is_paused, last_activity_at, paused_at
+FROM active_timer;
+
+DROP TABLE active_timer;
+ALTER TABLE active_timer_v19 RENAME TO active_timer;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_active_timer_running ON active_timer(is_paused) WHERE is_paused = 0;
+"#;
+
+// Each run of a timer between pauses. Segments belong to the timer while it is
+// active and move to the time entry it produces; `started_at` keeps the timer's
+// original start now that `start_time` moves on every resume.
+const MIGRATION_V20: &str = r#"
+ALTER TABLE active_timer ADD COLUMN started_at TEXT;
+UPDATE active_timer SET started_at = start_time;
+
+CREATE TABLE IF NOT EXISTS timer_segments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timer_id INTEGER,
+    time_entry_id TEXT REFERENCES time_entries(id) ON DELETE CASCADE,
+    start_time TEXT NOT NULL,
+    end_time TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_timer_segments_timer ON timer_segments(timer_id);
+CREATE INDEX IF NOT EXISTS idx_timer_segments_entry ON timer_segments(time_entry_id);
+"#;
+
+// Why a delivered draft was not moved to `sent`.
+const MIGRATION_V21: &str = r#"
+ALTER TABLE invoice_emails ADD COLUMN warning TEXT;
+"#;
+
+// Recurring invoices carry optional payment terms instead of a fixed number of days.
+// `net_days` is no longer read; existing schedules keep their terms as `net_<days>`.
+const MIGRATION_V22: &str = r#"
+ALTER TABLE recurring_invoices ADD COLUMN payment_terms TEXT;
+UPDATE recurring_invoices SET payment_terms = 'net_' || MIN(net_days, 365);
+"#;
+
+// The line item a billed time entry counts towards, so editing the entry can re-derive
+// the line's hours. Entries billed before this migration have no line.
+const MIGRATION_V23: &str = r#"
+ALTER TABLE time_entries ADD COLUMN line_item_id TEXT REFERENCES invoice_line_items(id) ON DELETE SET NULL;
+CREATE INDEX IF NOT EXISTS idx_time_entries_line_item_id ON time_entries(line_item_id);
+"#;
+
+// Revisions outlive their time entry, so a deleted entry's history is kept.
+const MIGRATION_V24: &str = r#"
+CREATE TABLE time_entry_revisions_v24 (
+    id TEXT PRIMARY KEY NOT NULL,
+    time_entry_id TEXT NOT NULL,
+    project_id TEXT NOT NULL,
+    description TEXT,
+    start_time TEXT NOT NULL,
+    end_time TEXT NOT NULL,
+    duration_secs INTEGER NOT NULL,
+    is_billable INTEGER NOT NULL,
+    revised_at TEXT NOT NULL
+);
+
+INSERT INTO time_entry_revisions_v24 (id, time_entry_id, project_id, description, start_time, end_time, duration_secs, is_billable, revised_at)
+SELECT id, time_entry_id, project_id, description, start_time, end_time, duration_secs, is_billable, revised_at
+FROM time_entry_revisions;
+
+DROP TABLE time_entry_revisions;
+ALTER TABLE time_entry_revisions_v24 RENAME TO time_entry_revisions;
+
+CREATE INDEX IF NOT EXISTS idx_time_entry_revisions_entry ON time_entry_revisions(time_entry_id);
+"#;
+
 pub fn init_db(db_path: &str) -> AppResult<Connection> {
     let conn = Connection::open(db_path)?;
     conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA foreign_keys=ON;")?;
@@ -133,7 +639,50 @@ pub fn init_db_in_memory() -> AppResult<Connection> {
     Ok(conn)
 }
 
+/// Runs `f` inside a savepoint, rolling its writes back if it fails. Unlike a
+/// transaction this nests, so it works whether or not the caller already opened one.
+pub(crate) fn with_savepoint<T>(
+    conn: &Connection,
+    name: &str,
+    f: impl FnOnce() -> AppResult<T>,
+) -> AppResult<T> {
+    conn.execute_batch(&format!("SAVEPOINT {name}"))?;
+    match f() {
+        Ok(value) => {
+            conn.execute_batch(&format!("RELEASE {name}"))?;
+            Ok(value)
+        }
+        Err(e) => {
+            conn.execute_batch(&format!("ROLLBACK TO {name}; RELEASE {name}"))?;
+            Err(e)
+        }
+    }
+}
+
+/// Tables a migration reads from that are built from code rather than spelled out in SQL.
+fn prepare_migration(conn: &Connection, version: i32) -> AppResult<()> {
+    // V3 scales each currency's amounts by 10^minor-unit digits; unlisted codes use 100
+    if version == 3 {
+        conn.execute_batch(
+            "CREATE TEMP TABLE currency_scale (code TEXT PRIMARY KEY, factor INTEGER NOT NULL);",
+        )?;
+        for currency in currency::all() {
+            conn.execute(
+                "INSERT INTO currency_scale (code, factor) VALUES (?1, ?2)",
+                params![currency.code, 10_i64.pow(currency.minor_units)],
+            )?;
+        }
+    }
+    Ok(())
+}
+
 fn run_migrations(conn: &Connection) -> AppResult<()> {
+    migrate_to(conn, MIGRATIONS.len())
+}
+
+/// Applies the migrations up to and including `target`. Each one commits together with
+/// its version bump, so a failed migration leaves the schema at the previous version.
+fn migrate_to(conn: &Connection, target: usize) -> AppResult<()> {
     conn.execute_batch(
         "CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL DEFAULT 0);",
     )?;
@@ -146,14 +695,18 @@ fn run_migrations(conn: &Connection) -> AppResult<()> {
         )
         .unwrap_or(0);
 
-    for (i, migration) in MIGRATIONS.iter().enumerate() {
+    for (i, migration) in MIGRATIONS[..target].iter().enumerate() {
         let version = (i + 1) as i32;
         if version > current_version {
-            conn.execute_batch(migration)?;
-            conn.execute(
-                "INSERT INTO schema_version (version) VALUES (?1)",
-                [version],
-            )?;
+            with_savepoint(conn, "migration", || {
+                prepare_migration(conn, version)?;
+                conn.execute_batch(migration)?;
+                conn.execute(
+                    "INSERT INTO schema_version (version) VALUES (?1)",
+                    [version],
+                )?;
+                Ok(())
+            })?;
         }
     }
 
@@ -163,6 +716,7 @@ fn run_migrations(conn: &Connection) -> AppResult<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::PaymentTerms;
 
     #[test]
     fn test_init_db_in_memory() {
@@ -185,6 +739,13 @@ mod tests {
         assert!(tables.contains(&"estimates".to_string()));
         assert!(tables.contains(&"active_timer".to_string()));
         assert!(tables.contains(&"app_settings".to_string()));
+        assert!(tables.contains(&"recurring_invoices".to_string()));
+        assert!(tables.contains(&"payments".to_string()));
+        assert!(tables.contains(&"recurring_invoice_occurrences".to_string()));
+        assert!(tables.contains(&"credit_notes".to_string()));
+        assert!(tables.contains(&"number_sequences".to_string()));
+        assert!(tables.contains(&"taxes".to_string()));
+        assert!(tables.contains(&"invoice_taxes".to_string()));
     }
 
     #[test]
@@ -198,7 +759,74 @@ mod tests {
                 row.get(0)
             })
             .unwrap();
-        assert_eq!(version, 1);
+        assert_eq!(version, MIGRATIONS.len() as i32);
+    }
+
+    #[test]
+    fn test_money_columns_migrate_to_minor_units() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATION_V1).unwrap();
+        conn.execute_batch(MIGRATION_V2).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE schema_version (version INTEGER NOT NULL DEFAULT 0);
+             INSERT INTO schema_version (version) VALUES (1), (2);
+             INSERT INTO clients (id, name, hourly_rate) VALUES ('c1', 'US', 0.285);
+             INSERT INTO clients (id, name, hourly_rate, currency) VALUES ('c2', 'JP', 12000.0, 'JPY');
+             INSERT INTO projects (id, client_id, name, hourly_rate) VALUES ('p1', 'c2', 'Site', 9000.0);
+             INSERT INTO invoices (id, invoice_number, client_id, issue_date, due_date, subtotal, tax_rate, tax_amount, total)
+             VALUES ('i1', 'INV-1', 'c1', '2025-01-01', '2025-01-31', 12.07, 10.0, 1.207, 13.277);
+             INSERT INTO invoice_line_items (id, invoice_id, description, quantity, unit_price, amount)
+             VALUES ('l1', 'i1', 'Work', 1.15, 10.5, 12.075);",
+        )
+        .unwrap();
+
+        run_migrations(&conn).unwrap();
+
+        let rate = |id: &str| -> Option<i64> {
+            conn.query_row("SELECT hourly_rate FROM clients WHERE id = ?1", [id], |row| row.get(0))
+                .unwrap()
+        };
+        assert_eq!(rate("c1"), Some(29));
+        assert_eq!(rate("c2"), Some(12000));
+        let project_rate: i64 = conn
+            .query_row("SELECT hourly_rate FROM projects WHERE id = 'p1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(project_rate, 9000);
+
+        let totals: (i64, i64, i64) = conn
+            .query_row("SELECT subtotal, tax_amount, total FROM invoices WHERE id = 'i1'", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(totals, (1207, 121, 1328));
+        let line: (i64, i64) = conn
+            .query_row("SELECT unit_price, amount FROM invoice_line_items WHERE id = 'l1'", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(line, (1050, 1208));
+    }
+
+    #[test]
+    fn test_failed_migration_keeps_the_previous_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate_to(&conn, 2).unwrap();
+        conn.execute_batch(
+            "INSERT INTO clients (id, name, hourly_rate) VALUES ('c1', 'US', 95.5);
+             ALTER TABLE invoices ADD COLUMN subtotal_minor INTEGER;",
+        )
+        .unwrap();
+
+        // V3 converts clients and projects before it trips over the existing column
+        assert!(run_migrations(&conn).is_err());
+        let version: i32 = conn
+            .query_row("SELECT MAX(version) FROM schema_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, 2);
+        let rate: f64 = conn
+            .query_row("SELECT hourly_rate FROM clients WHERE id = 'c1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(rate, 95.5);
     }
 
     #[test]
@@ -209,4 +837,98 @@ mod tests {
             .unwrap();
         assert_eq!(fk_enabled, 1);
     }
+
+    #[test]
+    fn test_number_sequences_continue_after_existing_numbers() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate_to(&conn, 7).unwrap();
+        conn.execute_batch(
+            "INSERT INTO clients (id, name) VALUES ('c1', 'Client');
+             INSERT INTO invoices (id, invoice_number, client_id, issue_date, due_date)
+             VALUES ('i1', 'INV-2025-007', 'c1', '2025-01-01', '2025-01-31'),
+                    ('i2', 'INV-2025-012', 'c1', '2025-02-01', '2025-02-28'),
+                    ('i3', 'INV-2026-003', 'c1', '2026-01-01', '2026-01-31'),
+                    ('i4', 'legacy-1', 'c1', '2024-01-01', '2024-01-31');",
+        )
+        .unwrap();
+
+        run_migrations(&conn).unwrap();
+
+        let last = |key: &str| -> i64 {
+            conn.query_row("SELECT last_value FROM number_sequences WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .unwrap()
+        };
+        assert_eq!(last("invoice:2025"), 12);
+        assert_eq!(last("invoice:2026"), 3);
+    }
+
+    #[test]
+    fn test_business_settings_seed_the_profile() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate_to(&conn, 13).unwrap();
+        conn.execute_batch(
+            "INSERT INTO app_settings (key, value) VALUES
+                 ('business_name', 'Acme Design'),
+                 ('business_email', ''),
+                 ('business_address', '1 Main St
+Springfield');",
+        )
+        .unwrap();
+
+        run_migrations(&conn).unwrap();
+
+        let profile = business::get_business_profile(&conn).unwrap();
+        assert_eq!(profile.legal_name, "Acme Design");
+        assert_eq!(profile.email, None);
+        assert_eq!(profile.address_lines, vec!["1 Main St", "Springfield"]);
+    }
+
+    #[test]
+    fn test_running_timer_survives_named_timers_migration() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate_to(&conn, 18).unwrap();
+        conn.execute_batch(
+            "INSERT INTO clients (id, name) VALUES ('c1', 'Acme');
+             INSERT INTO projects (id, client_id, name) VALUES ('p1', 'c1', 'Site');
+             INSERT INTO active_timer (id, project_id, description, start_time, accumulated_secs, is_paused)
+             VALUES (1, 'p1', 'Wireframes', '2025-03-03T09:00:00+00:00', 120, 0);",
+        )
+        .unwrap();
+
+        run_migrations(&conn).unwrap();
+
+        let timer = time_entries::get_timer(&conn, 1).unwrap();
+        assert_eq!(timer.description.as_deref(), Some("Wireframes"));
+        assert_eq!((timer.accumulated_secs, timer.is_paused, timer.name), (120, false, None));
+        assert_eq!(timer.started_at, timer.start_time);
+        // A second running timer is refused by the schema itself
+        let second = conn.execute(
+            "INSERT INTO active_timer (project_id, start_time) VALUES ('p1', '2025-03-03T10:00:00+00:00')",
+            [],
+        );
+        assert!(second.is_err());
+    }
+
+    #[test]
+    fn test_recurring_net_days_become_payment_terms() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate_to(&conn, 21).unwrap();
+        conn.execute_batch(
+            "INSERT INTO clients (id, name) VALUES ('c1', 'Acme');
+             INSERT INTO recurring_invoices (id, client_id, name, frequency, start_date, net_days)
+             VALUES ('r1', 'c1', 'Retainer', 'monthly', '2025-01-01', 14),
+                    ('r2', 'c1', 'Hosting', 'monthly', '2025-01-01', 400);",
+        )
+        .unwrap();
+
+        run_migrations(&conn).unwrap();
+
+        let terms = |id: &str| -> Option<PaymentTerms> {
+            recurring_invoices::get_recurring_invoice(&conn, id).unwrap().payment_terms
+        };
+        assert_eq!(terms("r1"), Some(PaymentTerms::Net(14)));
+        assert_eq!(terms("r2"), Some(PaymentTerms::Net(365)));
+    }
 }
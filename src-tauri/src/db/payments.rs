@@ -0,0 +1,314 @@
+use chrono::{Local, Utc};
+use rusqlite::{params, Connection};
+use uuid::Uuid;
+
+use crate::db::{invoices, with_savepoint};
+use crate::error::{AppError, AppResult};
+use crate::models::{Invoice, InvoiceStatus, Payment, PaymentMethod, RecordPayment};
+
+fn row_to_payment(row: &rusqlite::Row) -> rusqlite::Result<Payment> {
+    let method_str: String = row.get("method")?;
+    Ok(Payment {
+        id: row.get("id")?,
+        invoice_id: row.get("invoice_id")?,
+        amount: row.get("amount")?,
+        payment_date: row.get("payment_date")?,
+        method: PaymentMethod::from_str(&method_str).unwrap_or(PaymentMethod::Other),
+        reference: row.get("reference")?,
+        voided_at: row.get("voided_at")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+pub fn get_payment(conn: &Connection, id: &str) -> AppResult<Payment> {
+    conn.query_row("SELECT * FROM payments WHERE id = ?1", params![id], row_to_payment)
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => {
+                AppError::NotFound(format!("Payment not found: {id}"))
+            }
+            _ => AppError::Database(e),
+        })
+}
+
+/// Payments for an invoice, oldest first, including voided ones.
+pub fn list_payments(conn: &Connection, invoice_id: &str) -> AppResult<Vec<Payment>> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM payments WHERE invoice_id = ?1 ORDER BY payment_date ASC, created_at ASC",
+    )?;
+    let payments = stmt
+        .query_map(params![invoice_id], row_to_payment)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(payments)
+}
+
+/// Recomputes `amount_paid` and `amount_credited` from the payment ledger and issued
+/// credit notes, and moves the invoice between `sent`, `partially_paid` and `paid` to
+/// match. Overdue invoices stay overdue until fully settled, and a paid invoice that
+/// reopens after its due date goes back to overdue.
+pub(crate) fn settle(conn: &Connection, invoice_id: &str) -> AppResult<Invoice> {
+    let amount_paid: i64 = conn.query_row(
+        "SELECT COALESCE(SUM(amount), 0) FROM payments WHERE invoice_id = ?1 AND voided_at IS NULL",
+        params![invoice_id],
+        |row| row.get(0),
+    )?;
+    let amount_credited: i64 = conn.query_row(
+        "SELECT COALESCE(SUM(applied_amount), 0) FROM credit_notes WHERE invoice_id = ?1",
+        params![invoice_id],
+        |row| row.get(0),
+    )?;
+    let settled = amount_paid + amount_credited;
+    let invoice = invoices::get_invoice(conn, invoice_id)?;
+    let status = match invoice.status {
+        InvoiceStatus::Draft | InvoiceStatus::Cancelled => invoice.status,
+        _ if settled > 0 && settled >= invoice.total => InvoiceStatus::Paid,
+        InvoiceStatus::Overdue => InvoiceStatus::Overdue,
+        InvoiceStatus::Paid if invoices::is_past_due(&invoice, Local::now().date_naive()) => {
+            InvoiceStatus::Overdue
+        }
+        _ if settled > 0 => InvoiceStatus::PartiallyPaid,
+        _ => InvoiceStatus::Sent,
+    };
+
+    conn.execute(
+        "UPDATE invoices SET amount_paid = ?1, amount_credited = ?2, status = ?3, updated_at = ?4,
+                overdue_at = CASE WHEN ?3 = 'overdue' THEN COALESCE(overdue_at, ?4) ELSE overdue_at END
+         WHERE id = ?5",
+        params![
+            amount_paid,
+            amount_credited,
+            status.as_str(),
+            Utc::now().to_rfc3339(),
+            invoice_id
+        ],
+    )?;
+    invoices::get_invoice(conn, invoice_id)
+}
+
+/// Payments are taken only on issued, unpaid invoices with a balance left to pay.
+pub fn ensure_payable(conn: &Connection, invoice_id: &str) -> AppResult<Invoice> {
+    let invoice = invoices::get_invoice(conn, invoice_id)?;
+    if !matches!(
+        invoice.status,
+        InvoiceStatus::Sent | InvoiceStatus::PartiallyPaid | InvoiceStatus::Overdue
+    ) {
+        return Err(AppError::Validation(format!(
+            "Invoice {} is {}; payments can only be taken on issued, unpaid invoices",
+            invoice.invoice_number,
+            invoice.status.as_str()
+        )));
+    }
+    if invoice.balance_due <= 0 {
+        return Err(AppError::Validation(format!(
+            "Invoice {} has no balance due",
+            invoice.invoice_number
+        )));
+    }
+    Ok(invoice)
+}
+
+pub fn record_payment(conn: &Connection, input: RecordPayment) -> AppResult<Payment> {
+    let invoice = ensure_payable(conn, &input.invoice_id)?;
+    if input.amount <= 0 {
+        return Err(AppError::Validation(
+            "Payment amount must be greater than zero".to_string(),
+        ));
+    }
+    if input.amount > invoice.balance_due {
+        return Err(AppError::Validation(format!(
+            "Payment of {} exceeds the balance due of {} on invoice {}",
+            input.amount, invoice.balance_due, invoice.invoice_number
+        )));
+    }
+
+    let id = Uuid::new_v4().to_string();
+    with_savepoint(conn, "record_payment", || {
+        conn.execute(
+            "INSERT INTO payments (id, invoice_id, amount, payment_date, method, reference, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                id,
+                input.invoice_id,
+                input.amount,
+                input.payment_date,
+                input.method.as_str(),
+                input.reference,
+                Utc::now().to_rfc3339(),
+            ],
+        )?;
+        settle(conn, &input.invoice_id)
+    })?;
+
+    get_payment(conn, &id)
+}
+
+/// Voids a payment. The record is kept for the audit trail but no longer counts
+/// towards the invoice's `amount_paid`.
+pub fn void_payment(conn: &Connection, id: &str) -> AppResult<Payment> {
+    let payment = get_payment(conn, id)?;
+    if payment.voided_at.is_some() {
+        return Err(AppError::Validation(format!(
+            "Payment {id} is already voided"
+        )));
+    }
+
+    with_savepoint(conn, "void_payment", || {
+        conn.execute(
+            "UPDATE payments SET voided_at = ?1 WHERE id = ?2",
+            params![Utc::now().to_rfc3339(), id],
+        )?;
+        settle(conn, &payment.invoice_id)
+    })?;
+
+    get_payment(conn, id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::clients::create_client;
+    use crate::db::init_db_in_memory;
+    use crate::models::{CreateClient, LineItemUnit};
+    use chrono::NaiveDate;
+
+    fn setup() -> (Connection, Invoice) {
+        setup_due("2025-01-31T00:00:00Z")
+    }
+
+    fn setup_due(due_date: &str) -> (Connection, Invoice) {
+        let conn = init_db_in_memory().expect("Failed to init test DB");
+        let client = create_client(
+            &conn,
+            CreateClient {
+                name: "Test".to_string(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let invoice = invoices::create_invoice(
+            &conn,
+            &client.id,
+            "2025-01-01T00:00:00Z",
+            Some(due_date),
+            None,
+            None,
+        )
+        .unwrap();
+        invoices::add_line_item(&conn, &invoice.id, "Work", 10.0, 10000, LineItemUnit::Hours, 0).unwrap();
+        let invoice = invoices::update_invoice_status(&conn, &invoice.id, InvoiceStatus::Sent).unwrap();
+        (conn, invoice)
+    }
+
+    fn pay(invoice_id: &str, amount: i64) -> RecordPayment {
+        RecordPayment {
+            invoice_id: invoice_id.to_string(),
+            amount,
+            payment_date: NaiveDate::from_ymd_opt(2025, 1, 20).unwrap(),
+            method: PaymentMethod::BankTransfer,
+            reference: Some("TX-1".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_partial_then_full_payment() {
+        let (conn, invoice) = setup();
+
+        record_payment(&conn, pay(&invoice.id, 40000)).unwrap();
+        let partial = invoices::get_invoice(&conn, &invoice.id).unwrap();
+        assert_eq!(partial.status, InvoiceStatus::PartiallyPaid);
+        assert_eq!((partial.amount_paid, partial.balance_due), (40000, 60000));
+
+        record_payment(&conn, pay(&invoice.id, 60000)).unwrap();
+        let paid = invoices::get_invoice(&conn, &invoice.id).unwrap();
+        assert_eq!(paid.status, InvoiceStatus::Paid);
+        assert_eq!(paid.balance_due, 0);
+        assert_eq!(list_payments(&conn, &invoice.id).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_rejects_invalid_payments() {
+        let (conn, invoice) = setup();
+        let overpay = record_payment(&conn, pay(&invoice.id, 100001));
+        assert!(matches!(overpay, Err(AppError::Validation(_))));
+        let zero = record_payment(&conn, pay(&invoice.id, 0));
+        assert!(matches!(zero, Err(AppError::Validation(_))));
+
+        record_payment(&conn, pay(&invoice.id, 100000)).unwrap();
+        let after_paid = record_payment(&conn, pay(&invoice.id, 1));
+        assert!(matches!(after_paid, Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn test_only_open_balances_are_payable() {
+        let (conn, invoice) = setup();
+        record_payment(&conn, pay(&invoice.id, 40000)).unwrap();
+        assert_eq!(ensure_payable(&conn, &invoice.id).unwrap().balance_due, 60000);
+
+        record_payment(&conn, pay(&invoice.id, 60000)).unwrap();
+        assert!(matches!(ensure_payable(&conn, &invoice.id), Err(AppError::Validation(_))));
+        let draft =
+            invoices::create_invoice(&conn, &invoice.client_id, "2025-01-01T00:00:00Z", None, None, None)
+                .unwrap();
+        invoices::add_line_item(&conn, &draft.id, "Work", 1.0, 10000, LineItemUnit::Hours, 0).unwrap();
+        assert!(matches!(ensure_payable(&conn, &draft.id), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn test_payments_nest_in_an_outer_savepoint() {
+        let (conn, invoice) = setup();
+        let result: AppResult<()> = with_savepoint(&conn, "outer", || {
+            let payment = record_payment(&conn, pay(&invoice.id, 40000))?;
+            void_payment(&conn, &payment.id)?;
+            record_payment(&conn, pay(&invoice.id, 100000))?;
+            Err(AppError::Validation("rolled back".to_string()))
+        });
+        assert!(result.is_err());
+        assert!(list_payments(&conn, &invoice.id).unwrap().is_empty());
+        assert_eq!(invoices::get_invoice(&conn, &invoice.id).unwrap().status, InvoiceStatus::Sent);
+    }
+
+    #[test]
+    fn test_void_payment_reopens_invoice() {
+        let (conn, invoice) = setup_due("2099-01-31T00:00:00Z");
+        let first = record_payment(&conn, pay(&invoice.id, 30000)).unwrap();
+        let second = record_payment(&conn, pay(&invoice.id, 70000)).unwrap();
+
+        void_payment(&conn, &second.id).unwrap();
+        let reopened = invoices::get_invoice(&conn, &invoice.id).unwrap();
+        assert_eq!(reopened.status, InvoiceStatus::PartiallyPaid);
+        assert_eq!(reopened.amount_paid, 30000);
+
+        let voided = void_payment(&conn, &first.id).unwrap();
+        assert!(voided.voided_at.is_some());
+        let unpaid = invoices::get_invoice(&conn, &invoice.id).unwrap();
+        assert_eq!(unpaid.status, InvoiceStatus::Sent);
+        assert_eq!(unpaid.balance_due, 100000);
+
+        assert!(matches!(void_payment(&conn, &first.id), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn test_void_payment_on_past_due_invoice_reopens_as_overdue() {
+        let (conn, invoice) = setup();
+        let payment = record_payment(&conn, pay(&invoice.id, 100000)).unwrap();
+        assert_eq!(invoices::get_invoice(&conn, &invoice.id).unwrap().status, InvoiceStatus::Paid);
+
+        void_payment(&conn, &payment.id).unwrap();
+        let reopened = invoices::get_invoice(&conn, &invoice.id).unwrap();
+        assert_eq!(reopened.status, InvoiceStatus::Overdue);
+        assert!(reopened.overdue_at.is_some());
+        assert_eq!(reopened.balance_due, 100000);
+    }
+
+    #[test]
+    fn test_marking_paid_records_the_balance() {
+        let (conn, invoice) = setup();
+        record_payment(&conn, pay(&invoice.id, 25000)).unwrap();
+
+        let paid = invoices::update_invoice_status(&conn, &invoice.id, InvoiceStatus::Paid).unwrap();
+        assert_eq!(paid.status, InvoiceStatus::Paid);
+        assert_eq!(paid.amount_paid, 100000);
+        let payments = list_payments(&conn, &invoice.id).unwrap();
+        assert_eq!(payments[1].amount, 75000);
+        assert_eq!(payments[1].method, PaymentMethod::Other);
+    }
+}
@@ -4,6 +4,7 @@ use uuid::Uuid;
 
 use crate::error::{AppError, AppResult};
 use crate::models::{CreateProject, Project, ProjectStatus, UpdateProject};
+use crate::services::currency;
 
 pub fn create_project(conn: &Connection, input: CreateProject) -> AppResult<Project> {
     let id = Uuid::new_v4().to_string();
@@ -29,7 +30,11 @@ pub fn create_project(conn: &Connection, input: CreateProject) -> AppResult<Proj
     get_project(conn, &id)
 }
 
-fn row_to_project(row: &rusqlite::Row) -> rusqlite::Result<Project> {
+/// Projects with their client's currency, which `row_to_project` resolves.
+const SELECT_PROJECTS: &str = "SELECT p.*, c.currency AS client_currency
+     FROM projects p JOIN clients c ON c.id = p.client_id";
+
+fn row_to_project(row: &rusqlite::Row, default_currency: &str) -> rusqlite::Result<Project> {
     let status_str: String = row.get("status")?;
     Ok(Project {
         id: row.get("id")?,
@@ -38,6 +43,9 @@ fn row_to_project(row: &rusqlite::Row) -> rusqlite::Result<Project> {
         description: row.get("description")?,
         status: ProjectStatus::from_str(&status_str).unwrap_or(ProjectStatus::Active),
         hourly_rate: row.get("hourly_rate")?,
+        currency: row
+            .get::<_, Option<String>>("client_currency")?
+            .unwrap_or_else(|| default_currency.to_string()),
         budget_hours: row.get("budget_hours")?,
         created_at: row.get("created_at")?,
         updated_at: row.get("updated_at")?,
@@ -45,8 +53,9 @@ fn row_to_project(row: &rusqlite::Row) -> rusqlite::Result<Project> {
 }
 
 pub fn get_project(conn: &Connection, id: &str) -> AppResult<Project> {
-    conn.query_row("SELECT * FROM projects WHERE id = ?1", params![id], |row| {
-        row_to_project(row)
+    let default_currency = currency::default_currency(conn)?;
+    conn.query_row(&format!("{SELECT_PROJECTS} WHERE p.id = ?1"), params![id], |row| {
+        row_to_project(row, &default_currency)
     })
     .map_err(|e| match e {
         rusqlite::Error::QueryReturnedNoRows => {
@@ -57,27 +66,29 @@ pub fn get_project(conn: &Connection, id: &str) -> AppResult<Project> {
 }
 
 pub fn list_projects(conn: &Connection, status: Option<&str>) -> AppResult<Vec<Project>> {
+    let default_currency = currency::default_currency(conn)?;
     if let Some(status) = status {
         let mut stmt =
-            conn.prepare("SELECT * FROM projects WHERE status = ?1 ORDER BY name ASC")?;
+            conn.prepare(&format!("{SELECT_PROJECTS} WHERE p.status = ?1 ORDER BY p.name ASC"))?;
         let projects = stmt
-            .query_map(params![status], |row| row_to_project(row))?
+            .query_map(params![status], |row| row_to_project(row, &default_currency))?
             .collect::<Result<Vec<_>, _>>()?;
         Ok(projects)
     } else {
-        let mut stmt = conn.prepare("SELECT * FROM projects ORDER BY name ASC")?;
+        let mut stmt = conn.prepare(&format!("{SELECT_PROJECTS} ORDER BY p.name ASC"))?;
         let projects = stmt
-            .query_map([], |row| row_to_project(row))?
+            .query_map([], |row| row_to_project(row, &default_currency))?
             .collect::<Result<Vec<_>, _>>()?;
         Ok(projects)
     }
 }
 
 pub fn list_projects_by_client(conn: &Connection, client_id: &str) -> AppResult<Vec<Project>> {
+    let default_currency = currency::default_currency(conn)?;
     let mut stmt =
-        conn.prepare("SELECT * FROM projects WHERE client_id = ?1 ORDER BY name ASC")?;
+        conn.prepare(&format!("{SELECT_PROJECTS} WHERE p.client_id = ?1 ORDER BY p.name ASC"))?;
     let projects = stmt
-        .query_map(params![client_id], |row| row_to_project(row))?
+        .query_map(params![client_id], |row| row_to_project(row, &default_currency))?
         .collect::<Result<Vec<_>, _>>()?;
     Ok(projects)
 }
@@ -147,6 +158,7 @@ mod tests {
                 phone: None,
                 notes: None,
                 hourly_rate: None,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -163,7 +175,7 @@ mod tests {
                 name: "Website Redesign".to_string(),
                 description: Some("Full redesign".to_string()),
                 status: None,
-                hourly_rate: Some(175.0),
+                hourly_rate: Some(17500),
                 budget_hours: Some(40.0),
             },
         )
@@ -171,7 +183,7 @@ mod tests {
 
         assert_eq!(project.name, "Website Redesign");
         assert_eq!(project.status, ProjectStatus::Active);
-        assert_eq!(project.hourly_rate, Some(175.0));
+        assert_eq!(project.hourly_rate, Some(17500));
     }
 
     #[test]
@@ -263,4 +275,31 @@ mod tests {
         delete_project(&conn, &project.id).unwrap();
         assert!(get_project(&conn, &project.id).is_err());
     }
+
+    #[test]
+    fn test_project_carries_its_client_currency() {
+        let (conn, client_id) = setup();
+        crate::db::settings::set_setting(&conn, "default_currency", "JPY").unwrap();
+        let project = create_project(
+            &conn,
+            CreateProject {
+                client_id: client_id.clone(),
+                name: "Priced".to_string(),
+                description: None,
+                status: None,
+                hourly_rate: Some(15000),
+                budget_hours: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(project.currency, "JPY");
+
+        conn.execute(
+            "UPDATE clients SET currency = 'BHD' WHERE id = ?1",
+            params![client_id],
+        )
+        .unwrap();
+        let projects = list_projects_by_client(&conn, &client_id).unwrap();
+        assert_eq!(projects[0].currency, "BHD");
+    }
 }
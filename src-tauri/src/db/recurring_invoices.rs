@@ -0,0 +1,224 @@
+use chrono::{NaiveDate, Utc};
+use rusqlite::{params, Connection};
+use uuid::Uuid;
+
+use crate::db::with_savepoint;
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    CreateRecurringInvoice, LineItemUnit, PaymentTerms, RecurringFrequency, RecurringInvoice,
+    RecurringLineItem, RecurringOccurrence, RecurringSchedule,
+};
+
+fn row_to_recurring_invoice(row: &rusqlite::Row) -> rusqlite::Result<RecurringInvoice> {
+    let frequency_str: String = row.get("frequency")?;
+    Ok(RecurringInvoice {
+        id: row.get("id")?,
+        client_id: row.get("client_id")?,
+        name: row.get("name")?,
+        schedule: RecurringSchedule {
+            frequency: RecurringFrequency::from_str(&frequency_str)
+                .unwrap_or(RecurringFrequency::Monthly),
+            interval: row.get("interval")?,
+            day_of_month: row.get("day_of_month")?,
+            start_date: row.get("start_date")?,
+            end_date: row.get("end_date")?,
+            count: row.get("count")?,
+        },
+        line_items: Vec::new(),
+        tax_rate: row.get("tax_rate")?,
+        notes: row.get("notes")?,
+        payment_terms: row
+            .get::<_, Option<String>>("payment_terms")?
+            .and_then(|terms| PaymentTerms::from_str(&terms)),
+        is_active: row.get::<_, i32>("is_active")? != 0,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn get_items(conn: &Connection, recurring_invoice_id: &str) -> AppResult<Vec<RecurringLineItem>> {
+    let mut stmt = conn.prepare(
+        "SELECT description, quantity, unit_price, unit FROM recurring_invoice_items
+         WHERE recurring_invoice_id = ?1 ORDER BY sort_order",
+    )?;
+    let items = stmt
+        .query_map(params![recurring_invoice_id], |row| {
+            Ok(RecurringLineItem {
+                description: row.get(0)?,
+                quantity: row.get(1)?,
+                unit_price: row.get(2)?,
+                unit: LineItemUnit::from_str(&row.get::<_, String>(3)?).unwrap_or_default(),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(items)
+}
+
+fn validate(input: &CreateRecurringInvoice) -> AppResult<()> {
+    let schedule = &input.schedule;
+    if schedule.interval == 0 {
+        return Err(AppError::Validation(
+            "Schedule interval must be at least 1".to_string(),
+        ));
+    }
+    if let Some(day) = schedule.day_of_month {
+        if !(1..=31).contains(&day) {
+            return Err(AppError::Validation(format!(
+                "Day of month must be between 1 and 31, got {day}"
+            )));
+        }
+    }
+    if schedule.end_date.is_some_and(|end| end < schedule.start_date) {
+        return Err(AppError::Validation(
+            "Schedule end date is before its start date".to_string(),
+        ));
+    }
+    if input.line_items.is_empty() {
+        return Err(AppError::Validation(
+            "A recurring invoice needs at least one line item".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub fn create_recurring_invoice(
+    conn: &Connection,
+    input: CreateRecurringInvoice,
+) -> AppResult<RecurringInvoice> {
+    validate(&input)?;
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now();
+    let schedule = &input.schedule;
+
+    with_savepoint(conn, "create_recurring_invoice", || {
+        conn.execute(
+            "INSERT INTO recurring_invoices (id, client_id, name, frequency, interval, day_of_month, start_date, end_date, count, tax_rate, notes, payment_terms, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                id,
+                input.client_id,
+                input.name,
+                schedule.frequency.as_str(),
+                schedule.interval,
+                schedule.day_of_month,
+                schedule.start_date,
+                schedule.end_date,
+                schedule.count,
+                input.tax_rate,
+                input.notes,
+                input.payment_terms.map(|terms| terms.as_string()),
+                now.to_rfc3339(),
+                now.to_rfc3339(),
+            ],
+        )?;
+        for (i, item) in input.line_items.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO recurring_invoice_items (id, recurring_invoice_id, description, quantity, unit_price, unit, sort_order)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    Uuid::new_v4().to_string(),
+                    id,
+                    item.description,
+                    item.quantity,
+                    item.unit_price,
+                    item.unit.as_str(),
+                    i as i32,
+                ],
+            )?;
+        }
+        Ok(())
+    })?;
+
+    get_recurring_invoice(conn, &id)
+}
+
+pub fn get_recurring_invoice(conn: &Connection, id: &str) -> AppResult<RecurringInvoice> {
+    let mut recurring = conn
+        .query_row(
+            "SELECT * FROM recurring_invoices WHERE id = ?1",
+            params![id],
+            row_to_recurring_invoice,
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => {
+                AppError::NotFound(format!("Recurring invoice not found: {id}"))
+            }
+            _ => AppError::Database(e),
+        })?;
+    recurring.line_items = get_items(conn, id)?;
+    Ok(recurring)
+}
+
+pub fn list_recurring_invoices(conn: &Connection) -> AppResult<Vec<RecurringInvoice>> {
+    let mut stmt = conn.prepare("SELECT * FROM recurring_invoices ORDER BY name ASC")?;
+    let mut recurring = stmt
+        .query_map([], row_to_recurring_invoice)?
+        .collect::<Result<Vec<_>, _>>()?;
+    for r in &mut recurring {
+        r.line_items = get_items(conn, &r.id)?;
+    }
+    Ok(recurring)
+}
+
+pub fn set_recurring_invoice_active(
+    conn: &Connection,
+    id: &str,
+    is_active: bool,
+) -> AppResult<RecurringInvoice> {
+    let affected = conn.execute(
+        "UPDATE recurring_invoices SET is_active = ?1, updated_at = ?2 WHERE id = ?3",
+        params![is_active as i32, Utc::now().to_rfc3339(), id],
+    )?;
+    if affected == 0 {
+        return Err(AppError::NotFound(format!("Recurring invoice not found: {id}")));
+    }
+    get_recurring_invoice(conn, id)
+}
+
+pub fn delete_recurring_invoice(conn: &Connection, id: &str) -> AppResult<()> {
+    let affected = conn.execute("DELETE FROM recurring_invoices WHERE id = ?1", params![id])?;
+    if affected == 0 {
+        return Err(AppError::NotFound(format!("Recurring invoice not found: {id}")));
+    }
+    Ok(())
+}
+
+pub fn list_occurrences(
+    conn: &Connection,
+    recurring_invoice_id: &str,
+) -> AppResult<Vec<RecurringOccurrence>> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM recurring_invoice_occurrences
+         WHERE recurring_invoice_id = ?1 ORDER BY occurrence_date ASC",
+    )?;
+    let occurrences = stmt
+        .query_map(params![recurring_invoice_id], |row| {
+            Ok(RecurringOccurrence {
+                recurring_invoice_id: row.get("recurring_invoice_id")?,
+                occurrence_date: row.get("occurrence_date")?,
+                invoice_id: row.get("invoice_id")?,
+                created_at: row.get("created_at")?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(occurrences)
+}
+
+pub fn record_occurrence(
+    conn: &Connection,
+    recurring_invoice_id: &str,
+    occurrence_date: NaiveDate,
+    invoice_id: &str,
+) -> AppResult<()> {
+    conn.execute(
+        "INSERT INTO recurring_invoice_occurrences (recurring_invoice_id, occurrence_date, invoice_id, created_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![
+            recurring_invoice_id,
+            occurrence_date,
+            invoice_id,
+            Utc::now().to_rfc3339()
+        ],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use rusqlite::{params, Connection};
+
+use crate::error::{AppError, AppResult};
+use crate::models::AppSetting;
+
+/// Secrets the frontend may set but never read back.
+const WRITE_ONLY: &[&str] = &["smtp_password"];
+
+pub fn is_write_only(key: &str) -> bool {
+    WRITE_ONLY.contains(&key)
+}
+
+pub fn get_setting(conn: &Connection, key: &str) -> AppResult<Option<String>> {
+    let result = conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![key],
+        |row| row.get::<_, String>(0),
+    );
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(AppError::Database(e)),
+    }
+}
+
+pub fn set_setting(conn: &Connection, key: &str, value: &str) -> AppResult<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![key, value],
+    )?;
+    Ok(())
+}
+
+/// Every setting except the write-only secrets.
+pub fn get_all_settings(conn: &Connection) -> AppResult<Vec<AppSetting>> {
+    let mut stmt = conn.prepare("SELECT key, value FROM app_settings")?;
+    let settings = stmt
+        .query_map([], |row| {
+            Ok(AppSetting {
+                key: row.get(0)?,
+                value: row.get(1)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(settings.into_iter().filter(|s| !is_write_only(&s.key)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db_in_memory;
+
+    #[test]
+    fn test_get_and_set_setting() {
+        let conn = init_db_in_memory().unwrap();
+        assert_eq!(get_setting(&conn, "default_currency").unwrap(), None);
+
+        set_setting(&conn, "default_currency", "EUR").unwrap();
+        set_setting(&conn, "default_currency", "GBP").unwrap();
+
+        assert_eq!(
+            get_setting(&conn, "default_currency").unwrap(),
+            Some("GBP".to_string())
+        );
+        assert_eq!(get_all_settings(&conn).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_smtp_password_is_not_read_back() {
+        let conn = init_db_in_memory().unwrap();
+        set_setting(&conn, "smtp_host", "smtp.test.example").unwrap();
+        set_setting(&conn, "smtp_password", "hunter2").unwrap();
+
+        let all = get_all_settings(&conn).unwrap();
+        assert_eq!(all.len(), 1);
+        assert!(all.iter().all(|s| s.key != "smtp_password" && s.value != "hunter2"));
+        // Still available to the mailer
+        assert_eq!(get_setting(&conn, "smtp_password").unwrap().as_deref(), Some("hunter2"));
+    }
+}
@@ -0,0 +1,237 @@
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{CreateTax, InvoiceTax, Tax, UpdateTax};
+
+fn row_to_tax(row: &rusqlite::Row) -> rusqlite::Result<Tax> {
+    Ok(Tax {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        rate: row.get("rate")?,
+        is_compound: row.get::<_, i32>("is_compound")? != 0,
+        is_active: row.get::<_, i32>("is_active")? != 0,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn validate(name: &str, rate: f64) -> AppResult<()> {
+    if name.trim().is_empty() {
+        return Err(AppError::Validation("Tax name is required".to_string()));
+    }
+    if !(0.0..=100.0).contains(&rate) {
+        return Err(AppError::Validation(format!(
+            "Tax rate must be between 0 and 100 percent, got {rate}"
+        )));
+    }
+    Ok(())
+}
+
+pub fn create_tax(conn: &Connection, input: CreateTax) -> AppResult<Tax> {
+    validate(&input.name, input.rate)?;
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now();
+
+    conn.execute(
+        "INSERT INTO taxes (id, name, rate, is_compound, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            id,
+            input.name.trim(),
+            input.rate,
+            input.is_compound as i32,
+            now.to_rfc3339(),
+            now.to_rfc3339(),
+        ],
+    )?;
+
+    get_tax(conn, &id)
+}
+
+pub fn get_tax(conn: &Connection, id: &str) -> AppResult<Tax> {
+    conn.query_row("SELECT * FROM taxes WHERE id = ?1", params![id], row_to_tax)
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => {
+                AppError::NotFound(format!("Tax not found: {id}"))
+            }
+            _ => AppError::Database(e),
+        })
+}
+
+pub fn list_taxes(conn: &Connection, include_inactive: bool) -> AppResult<Vec<Tax>> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM taxes WHERE ?1 OR is_active = 1 ORDER BY is_compound ASC, name ASC",
+    )?;
+    let taxes = stmt
+        .query_map(params![include_inactive], row_to_tax)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(taxes)
+}
+
+/// Changing a rate only affects draft invoices the next time their totals are
+/// recomputed; issued invoices keep the tax lines they were issued with.
+pub fn update_tax(conn: &Connection, id: &str, input: UpdateTax) -> AppResult<Tax> {
+    let tax = get_tax(conn, id)?;
+    let name = input.name.unwrap_or(tax.name);
+    let rate = input.rate.unwrap_or(tax.rate);
+    validate(&name, rate)?;
+
+    conn.execute(
+        "UPDATE taxes SET name = ?1, rate = ?2, is_compound = ?3, is_active = ?4, updated_at = ?5
+         WHERE id = ?6",
+        params![
+            name.trim(),
+            rate,
+            input.is_compound.unwrap_or(tax.is_compound) as i32,
+            input.is_active.unwrap_or(tax.is_active) as i32,
+            Utc::now().to_rfc3339(),
+            id,
+        ],
+    )?;
+    get_tax(conn, id)
+}
+
+/// Only taxes that were never assigned can be deleted; deactivate the others.
+pub fn delete_tax(conn: &Connection, id: &str) -> AppResult<()> {
+    let tax = get_tax(conn, id)?;
+    let in_use: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM invoice_line_item_taxes WHERE tax_id = ?1)",
+        params![id],
+        |row| row.get(0),
+    )?;
+    if in_use {
+        return Err(AppError::Validation(format!(
+            "{} is used on invoices; deactivate it instead",
+            tax.name
+        )));
+    }
+    conn.execute("DELETE FROM taxes WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+fn row_to_invoice_tax(row: &rusqlite::Row) -> rusqlite::Result<InvoiceTax> {
+    Ok(InvoiceTax {
+        tax_id: row.get("tax_id")?,
+        name: row.get("name")?,
+        rate: row.get("rate")?,
+        is_compound: row.get::<_, i32>("is_compound")? != 0,
+        taxable_amount: row.get("taxable_amount")?,
+        amount: row.get("amount")?,
+    })
+}
+
+/// The tax summary of an invoice, in the order it prints.
+pub fn get_invoice_taxes(conn: &Connection, invoice_id: &str) -> AppResult<Vec<InvoiceTax>> {
+    let mut stmt =
+        conn.prepare("SELECT * FROM invoice_taxes WHERE invoice_id = ?1 ORDER BY sort_order")?;
+    let taxes = stmt
+        .query_map(params![invoice_id], row_to_invoice_tax)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(taxes)
+}
+
+pub(crate) fn replace_invoice_taxes(
+    conn: &Connection,
+    invoice_id: &str,
+    taxes: &[InvoiceTax],
+) -> AppResult<()> {
+    conn.execute(
+        "DELETE FROM invoice_taxes WHERE invoice_id = ?1",
+        params![invoice_id],
+    )?;
+    for (i, tax) in taxes.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO invoice_taxes (invoice_id, sort_order, tax_id, name, rate, is_compound, taxable_amount, amount)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                invoice_id,
+                i as i32,
+                tax.tax_id,
+                tax.name,
+                tax.rate,
+                tax.is_compound as i32,
+                tax.taxable_amount,
+                tax.amount,
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn get_credit_note_taxes(conn: &Connection, credit_note_id: &str) -> AppResult<Vec<InvoiceTax>> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM credit_note_taxes WHERE credit_note_id = ?1 ORDER BY sort_order",
+    )?;
+    let taxes = stmt
+        .query_map(params![credit_note_id], row_to_invoice_tax)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(taxes)
+}
+
+pub(crate) fn insert_credit_note_taxes(
+    conn: &Connection,
+    credit_note_id: &str,
+    taxes: &[InvoiceTax],
+) -> AppResult<()> {
+    for (i, tax) in taxes.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO credit_note_taxes (credit_note_id, sort_order, tax_id, name, rate, is_compound, taxable_amount, amount)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                credit_note_id,
+                i as i32,
+                tax.tax_id,
+                tax.name,
+                tax.rate,
+                tax.is_compound as i32,
+                tax.taxable_amount,
+                tax.amount,
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db_in_memory;
+
+    #[test]
+    fn test_tax_crud_and_validation() {
+        let conn = init_db_in_memory().unwrap();
+        let gst = create_tax(
+            &conn,
+            CreateTax { name: "GST".to_string(), rate: 5.0, is_compound: false },
+        )
+        .unwrap();
+        create_tax(
+            &conn,
+            CreateTax { name: "QST".to_string(), rate: 9.975, is_compound: true },
+        )
+        .unwrap();
+        let invalid = create_tax(
+            &conn,
+            CreateTax { name: "Bad".to_string(), rate: 150.0, is_compound: false },
+        );
+        assert!(matches!(invalid, Err(AppError::Validation(_))));
+
+        let names: Vec<String> = list_taxes(&conn, false).unwrap().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["GST", "QST"]);
+
+        let inactive = update_tax(
+            &conn,
+            &gst.id,
+            UpdateTax { name: None, rate: Some(6.0), is_compound: None, is_active: Some(false) },
+        )
+        .unwrap();
+        assert_eq!(inactive.rate, 6.0);
+        assert_eq!(list_taxes(&conn, false).unwrap().len(), 1);
+        assert_eq!(list_taxes(&conn, true).unwrap().len(), 2);
+
+        delete_tax(&conn, &gst.id).unwrap();
+        assert!(matches!(get_tax(&conn, &gst.id), Err(AppError::NotFound(_))));
+    }
+}
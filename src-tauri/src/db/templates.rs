@@ -0,0 +1,341 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use uuid::Uuid;
+
+use crate::db::with_savepoint;
+use crate::error::{AppError, AppResult};
+use crate::models::{CreateInvoiceTemplate, InvoiceTemplate, UpdateInvoiceTemplate};
+use crate::services::{branding, pdf};
+
+fn row_to_template(row: &rusqlite::Row) -> rusqlite::Result<InvoiceTemplate> {
+    Ok(InvoiceTemplate {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        body: row.get("body")?,
+        brand_color: row.get("brand_color")?,
+        logo: row.get("logo")?,
+        is_default: row.get::<_, i32>("is_default")? != 0,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+/// A blank value clears the field.
+fn non_blank(value: &str) -> Option<&str> {
+    let value = value.trim();
+    (!value.is_empty()).then_some(value)
+}
+
+/// Checks everything a template needs to render, so a saved template can't break
+/// document exports later.
+fn validate(
+    name: &str,
+    body: &str,
+    brand_color: Option<&str>,
+    logo: Option<&str>,
+) -> AppResult<()> {
+    if name.trim().is_empty() {
+        return Err(AppError::Validation("Template name is required".to_string()));
+    }
+    if let Some(color) = brand_color {
+        branding::parse_color(color)?;
+    }
+    if let Some(logo) = logo {
+        branding::decode_logo(logo)?;
+    }
+    pdf::validate_template(body)
+}
+
+fn clear_other_defaults(conn: &Connection, id: &str) -> AppResult<()> {
+    conn.execute(
+        "UPDATE invoice_templates SET is_default = 0 WHERE id != ?1 AND is_default = 1",
+        params![id],
+    )?;
+    Ok(())
+}
+
+pub fn create_template(conn: &Connection, input: CreateInvoiceTemplate) -> AppResult<InvoiceTemplate> {
+    let brand_color = input.brand_color.as_deref().and_then(non_blank);
+    let logo = input.logo.as_deref().and_then(non_blank);
+    validate(&input.name, &input.body, brand_color, logo)?;
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now();
+
+    with_savepoint(conn, "create_template", || {
+        conn.execute(
+            "INSERT INTO invoice_templates (id, name, body, brand_color, logo, is_default, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                id,
+                input.name.trim(),
+                input.body,
+                brand_color.map(str::to_lowercase),
+                logo,
+                input.is_default as i32,
+                now.to_rfc3339(),
+                now.to_rfc3339(),
+            ],
+        )?;
+        if input.is_default {
+            clear_other_defaults(conn, &id)?;
+        }
+        get_template(conn, &id)
+    })
+}
+
+pub fn get_template(conn: &Connection, id: &str) -> AppResult<InvoiceTemplate> {
+    conn.query_row(
+        "SELECT * FROM invoice_templates WHERE id = ?1",
+        params![id],
+        row_to_template,
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => {
+            AppError::NotFound(format!("Template not found: {id}"))
+        }
+        _ => AppError::Database(e),
+    })
+}
+
+pub fn list_templates(conn: &Connection) -> AppResult<Vec<InvoiceTemplate>> {
+    let mut stmt =
+        conn.prepare("SELECT * FROM invoice_templates ORDER BY is_default DESC, name ASC")?;
+    let templates = stmt
+        .query_map([], row_to_template)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(templates)
+}
+
+pub fn update_template(
+    conn: &Connection,
+    id: &str,
+    input: UpdateInvoiceTemplate,
+) -> AppResult<InvoiceTemplate> {
+    let template = get_template(conn, id)?;
+    let name = input.name.unwrap_or(template.name);
+    let body = input.body.unwrap_or(template.body);
+    let brand_color = match &input.brand_color {
+        Some(color) => non_blank(color).map(str::to_lowercase),
+        None => template.brand_color,
+    };
+    let logo = match &input.logo {
+        Some(logo) => non_blank(logo).map(str::to_string),
+        None => template.logo,
+    };
+    let is_default = input.is_default.unwrap_or(template.is_default);
+    validate(&name, &body, brand_color.as_deref(), logo.as_deref())?;
+
+    with_savepoint(conn, "update_template", || {
+        conn.execute(
+            "UPDATE invoice_templates SET name = ?1, body = ?2, brand_color = ?3, logo = ?4, is_default = ?5, updated_at = ?6
+             WHERE id = ?7",
+            params![
+                name.trim(),
+                body,
+                brand_color,
+                logo,
+                is_default as i32,
+                Utc::now().to_rfc3339(),
+                id,
+            ],
+        )?;
+        if is_default {
+            clear_other_defaults(conn, id)?;
+        }
+        get_template(conn, id)
+    })
+}
+
+/// Clients and invoices using the template fall back to the default one.
+pub fn delete_template(conn: &Connection, id: &str) -> AppResult<()> {
+    let affected = conn.execute("DELETE FROM invoice_templates WHERE id = ?1", params![id])?;
+    if affected == 0 {
+        return Err(AppError::NotFound(format!("Template not found: {id}")));
+    }
+    Ok(())
+}
+
+/// The id to store for a client's or invoice's template; a blank id clears it.
+pub(crate) fn normalize_id(conn: &Connection, id: &str) -> AppResult<Option<String>> {
+    match non_blank(id) {
+        Some(id) => Ok(Some(get_template(conn, id)?.id)),
+        None => Ok(None),
+    }
+}
+
+/// The template a client's documents render with: the client's own, then the
+/// default. `None` means the built-in template.
+pub fn resolve_for_client(conn: &Connection, client_id: &str) -> AppResult<Option<InvoiceTemplate>> {
+    let template = conn
+        .query_row(
+            "SELECT t.* FROM invoice_templates t
+             LEFT JOIN clients c ON c.id = ?1 AND c.template_id = t.id
+             WHERE c.id IS NOT NULL OR t.is_default = 1
+             ORDER BY c.id IS NULL
+             LIMIT 1",
+            params![client_id],
+            row_to_template,
+        )
+        .optional()?;
+    Ok(template)
+}
+
+/// Like `resolve_for_client`, but the invoice's own template wins.
+pub fn resolve_for_invoice(conn: &Connection, invoice_id: &str) -> AppResult<Option<InvoiceTemplate>> {
+    let (template_id, client_id): (Option<String>, String) = conn
+        .query_row(
+            "SELECT template_id, client_id FROM invoices WHERE id = ?1",
+            params![invoice_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => {
+                AppError::NotFound(format!("Invoice not found: {invoice_id}"))
+            }
+            _ => AppError::Database(e),
+        })?;
+    match template_id {
+        Some(id) => get_template(conn, &id).map(Some),
+        None => resolve_for_client(conn, &client_id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{clients, init_db_in_memory, invoices};
+    use crate::models::{CreateClient, UpdateClient, UpdateInvoice};
+    use crate::services::branding::tests::png_logo;
+
+    fn setup() -> Connection {
+        init_db_in_memory().expect("Failed to init test DB")
+    }
+
+    fn new_template(conn: &Connection, name: &str, is_default: bool) -> InvoiceTemplate {
+        create_template(
+            conn,
+            CreateInvoiceTemplate {
+                name: name.to_string(),
+                body: format!("<h1>{name}</h1>{{{{invoice_number}}}}"),
+                brand_color: None,
+                logo: None,
+                is_default,
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_templates_are_validated_on_save() {
+        let conn = setup();
+        let input = |body: &str, brand_color: Option<&str>, logo: Option<String>| CreateInvoiceTemplate {
+            name: "Minimal".to_string(),
+            body: body.to_string(),
+            brand_color: brand_color.map(str::to_string),
+            logo,
+            is_default: false,
+        };
+
+        for bad in [
+            input("{{#each line_items}}{{description}}", None, None),
+            input("{{invoice_numbr}}", None, None),
+            input("{{#each line_items}}{{this.descripton}}{{/each}}", None, None),
+            input("{{total}}", Some("blue"), None),
+            input("{{total}}", None, Some("data:image/gif;base64,R0lGODlh".to_string())),
+        ] {
+            assert!(matches!(create_template(&conn, bad), Err(AppError::Validation(_))));
+        }
+        assert!(list_templates(&conn).unwrap().is_empty());
+
+        let template = create_template(
+            &conn,
+            input("<p style=\"color: {{brand_color}}\">{{total}}</p>", Some("#AA0000"), Some(png_logo(2, 2))),
+        )
+        .unwrap();
+        assert_eq!(template.brand_color.as_deref(), Some("#aa0000"));
+        assert!(template.logo.is_some());
+
+        let cleared = update_template(
+            &conn,
+            &template.id,
+            UpdateInvoiceTemplate {
+                name: None,
+                body: None,
+                brand_color: Some(String::new()),
+                logo: Some(" ".to_string()),
+                is_default: None,
+            },
+        )
+        .unwrap();
+        assert_eq!((cleared.brand_color, cleared.logo), (None, None));
+
+        let broken = update_template(
+            &conn,
+            &template.id,
+            UpdateInvoiceTemplate {
+                name: None,
+                body: Some("{{#if notes}}".to_string()),
+                brand_color: None,
+                logo: None,
+                is_default: None,
+            },
+        );
+        assert!(matches!(broken, Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn test_resolution_order_and_single_default() {
+        let conn = setup();
+        let client = clients::create_client(
+            &conn,
+            CreateClient {
+                name: "Acme Corp".to_string(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let invoice =
+            invoices::create_invoice(&conn, &client.id, "2025-01-15T00:00:00Z", None, None, None)
+                .unwrap();
+        let resolved = |conn: &Connection| {
+            resolve_for_invoice(conn, &invoice.id).unwrap().map(|t| t.name)
+        };
+        assert_eq!(resolved(&conn), None);
+
+        let first = new_template(&conn, "Classic", true);
+        let second = new_template(&conn, "Modern", true);
+        assert!(!get_template(&conn, &first.id).unwrap().is_default);
+        assert_eq!(resolved(&conn).as_deref(), Some("Modern"));
+
+        let client_template = new_template(&conn, "Acme", false);
+        let update_client = |template_id: &str| UpdateClient {
+            template_id: Some(template_id.to_string()),
+            ..Default::default()
+        };
+        clients::update_client(&conn, &client.id, update_client(&client_template.id)).unwrap();
+        assert_eq!(resolved(&conn).as_deref(), Some("Acme"));
+
+        invoices::update_invoice(
+            &conn,
+            &invoice.id,
+            UpdateInvoice {
+                template_id: Some(first.id.clone()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(resolved(&conn).as_deref(), Some("Classic"));
+
+        // Deleting a template falls back down the chain
+        delete_template(&conn, &first.id).unwrap();
+        assert_eq!(invoices::get_invoice(&conn, &invoice.id).unwrap().template_id, None);
+        assert_eq!(resolved(&conn).as_deref(), Some("Acme"));
+        clients::update_client(&conn, &client.id, update_client("")).unwrap();
+        assert_eq!(resolved(&conn).as_deref(), Some("Modern"));
+        delete_template(&conn, &second.id).unwrap();
+        assert_eq!(resolved(&conn), None);
+
+        let missing = clients::update_client(&conn, &client.id, update_client("nope"));
+        assert!(matches!(missing, Err(AppError::NotFound(_))));
+    }
+}
@@ -2,8 +2,18 @@ use chrono::{DateTime, Utc};
 use rusqlite::{params, Connection};
 use uuid::Uuid;
 
+use crate::db::{invoices, projects, with_savepoint};
 use crate::error::{AppError, AppResult};
-use crate::models::{ActiveTimer, CreateManualTimeEntry, TimeEntry, TimerState};
+use crate::models::{
+    ActiveTimer, CreateManualTimeEntry, IdleAction, Invoice, SwitchTimer, TimeConflict, TimeEntry,
+    TimeEntryRevision, TimeSegment, TimerState, UpdateTimeEntry,
+};
+use crate::services::invoice as invoice_service;
+use crate::services::overlap::{self, OverlapPolicy};
+use crate::services::timer::detect_idle;
+
+/// A stretch of time, start to end.
+type Span = (DateTime<Utc>, DateTime<Utc>);
 
 fn row_to_time_entry(row: &rusqlite::Row) -> rusqlite::Result<TimeEntry> {
     Ok(TimeEntry {
@@ -17,6 +27,33 @@ fn row_to_time_entry(row: &rusqlite::Row) -> rusqlite::Result<TimeEntry> {
         is_manual: row.get("is_manual")?,
         invoice_id: row.get("invoice_id")?,
         created_at: row.get("created_at")?,
+        overlaps_with: Vec::new(),
+    })
+}
+
+fn row_to_revision(row: &rusqlite::Row) -> rusqlite::Result<TimeEntryRevision> {
+    Ok(TimeEntryRevision {
+        id: row.get("id")?,
+        time_entry_id: row.get("time_entry_id")?,
+        project_id: row.get("project_id")?,
+        description: row.get("description")?,
+        start_time: row.get("start_time")?,
+        end_time: row.get("end_time")?,
+        duration_secs: row.get("duration_secs")?,
+        is_billable: row.get("is_billable")?,
+        revised_at: row.get("revised_at")?,
+    })
+}
+
+pub fn get_time_entry(conn: &Connection, id: &str) -> AppResult<TimeEntry> {
+    conn.query_row("SELECT * FROM time_entries WHERE id = ?1", params![id], |row| {
+        row_to_time_entry(row)
+    })
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => {
+            AppError::NotFound(format!("Time entry not found: {id}"))
+        }
+        _ => AppError::Database(e),
     })
 }
 
@@ -55,11 +92,12 @@ pub fn create_time_entry_from_timer(
     start_time: DateTime<Utc>,
     end_time: DateTime<Utc>,
     duration_secs: i64,
+    is_billable: bool,
 ) -> AppResult<TimeEntry> {
     let id = Uuid::new_v4().to_string();
     conn.execute(
         "INSERT INTO time_entries (id, project_id, description, start_time, end_time, duration_secs, is_billable, is_manual)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1, 0)",
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0)",
         params![
             id,
             project_id,
@@ -67,6 +105,7 @@ pub fn create_time_entry_from_timer(
             start_time.to_rfc3339(),
             end_time.to_rfc3339(),
             duration_secs,
+            is_billable,
         ],
     )?;
 
@@ -90,6 +129,10 @@ pub fn create_manual_time_entry(
             "End time must be after start time".to_string(),
         ));
     }
+    let policy = overlap::policy(conn)?;
+    let (spans, overlaps_with) =
+        resolve_overlaps(conn, vec![(input.start_time, input.end_time)], None, None, policy)?;
+    let (start_time, end_time) = spans[0];
 
     conn.execute(
         "INSERT INTO time_entries (id, project_id, description, start_time, end_time, duration_secs, is_billable, is_manual)
@@ -98,191 +141,808 @@ pub fn create_manual_time_entry(
             id,
             input.project_id,
             input.description,
-            input.start_time.to_rfc3339(),
-            input.end_time.to_rfc3339(),
-            duration_secs,
+            start_time.to_rfc3339(),
+            end_time.to_rfc3339(),
+            (end_time - start_time).num_seconds(),
             input.is_billable,
         ],
     )?;
 
-    conn.query_row(
+    let entry = conn.query_row(
         "SELECT * FROM time_entries WHERE id = ?1",
         params![id],
         |row| row_to_time_entry(row),
-    )
-    .map_err(AppError::Database)
+    )?;
+    Ok(TimeEntry { overlaps_with, ..entry })
 }
 
-pub fn delete_time_entry(conn: &Connection, id: &str) -> AppResult<()> {
-    let affected = conn.execute("DELETE FROM time_entries WHERE id = ?1", params![id])?;
-    if affected == 0 {
-        return Err(AppError::NotFound(format!("Time entry not found: {id}")));
+/// Edits an entry in place, keeping `is_manual` and recomputing `duration_secs`
+/// from the new start and end. The previous values are kept as a revision.
+/// Entries billed on an invoice that has left `draft` cannot be changed. On a draft,
+/// the entry's line item is re-derived; an entry moved to another project or made
+/// non-billable leaves the draft.
+pub fn update_time_entry(
+    conn: &Connection,
+    id: &str,
+    input: UpdateTimeEntry,
+) -> AppResult<TimeEntry> {
+    let entry = get_time_entry(conn, id)?;
+    let draft = match &entry.invoice_id {
+        Some(invoice_id) => Some(invoices::ensure_draft(conn, invoice_id)?),
+        None => None,
+    };
+
+    let project_id = input.project_id.unwrap_or_else(|| entry.project_id.clone());
+    if project_id != entry.project_id {
+        projects::get_project(conn, &project_id)?;
     }
-    Ok(())
+    let description = match input.description {
+        Some(d) if d.trim().is_empty() => None,
+        Some(d) => Some(d),
+        None => entry.description.clone(),
+    };
+    let start_time = input.start_time.unwrap_or(entry.start_time);
+    let end_time = input.end_time.unwrap_or(entry.end_time);
+    let is_billable = input.is_billable.unwrap_or(entry.is_billable);
+    if end_time <= start_time {
+        return Err(AppError::Validation(
+            "End time must be after start time".to_string(),
+        ));
+    }
+    let times_changed = start_time != entry.start_time || end_time != entry.end_time;
+    // Only a change of times is checked, so entries that already overlap can still be renamed
+    let (start_time, end_time, overlaps_with) = if times_changed {
+        let policy = overlap::policy(conn)?;
+        let (spans, overlaps_with) =
+            resolve_overlaps(conn, vec![(start_time, end_time)], Some(id), None, policy)?;
+        (spans[0].0, spans[0].1, overlaps_with)
+    } else {
+        (start_time, end_time, Vec::new())
+    };
+    // Unchanged times keep the duration a timer measured across its pauses
+    let duration_secs = if times_changed {
+        (end_time - start_time).num_seconds()
+    } else {
+        entry.duration_secs
+    };
+
+    let unchanged = project_id == entry.project_id
+        && description == entry.description
+        && start_time == entry.start_time
+        && end_time == entry.end_time
+        && is_billable == entry.is_billable
+        && duration_secs == entry.duration_secs;
+    if unchanged {
+        return Ok(entry);
+    }
+    let leaves_line = project_id != entry.project_id || !is_billable;
+    let rebilled_line = match &draft {
+        Some(invoice) if leaves_line || duration_secs != entry.duration_secs => {
+            Some((invoice.id.as_str(), billed_line_item(conn, id, invoice)?))
+        }
+        _ => None,
+    };
+
+    with_savepoint(conn, "update_time_entry", || {
+        record_revision(conn, &entry)?;
+        conn.execute(
+            "UPDATE time_entries
+             SET project_id = ?1, description = ?2, start_time = ?3, end_time = ?4, duration_secs = ?5, is_billable = ?6
+             WHERE id = ?7",
+            params![
+                project_id,
+                description,
+                start_time.to_rfc3339(),
+                end_time.to_rfc3339(),
+                duration_secs,
+                is_billable,
+                id,
+            ],
+        )?;
+        // Edited times replace the runs the timer recorded
+        if times_changed {
+            conn.execute("DELETE FROM timer_segments WHERE time_entry_id = ?1", params![id])?;
+        }
+        if let Some((invoice_id, line_item_id)) = &rebilled_line {
+            if leaves_line {
+                conn.execute(
+                    "UPDATE time_entries SET invoice_id = NULL, line_item_id = NULL WHERE id = ?1",
+                    params![id],
+                )?;
+            }
+            invoice_service::rebill_line_item(conn, invoice_id, line_item_id)?;
+        }
+        Ok(TimeEntry { overlaps_with, ..get_time_entry(conn, id)? })
+    })
+}
+
+/// Newest first. The revisions of a deleted entry are still listed.
+pub fn list_time_entry_revisions(
+    conn: &Connection,
+    time_entry_id: &str,
+) -> AppResult<Vec<TimeEntryRevision>> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM time_entry_revisions WHERE time_entry_id = ?1
+         ORDER BY revised_at DESC, rowid DESC",
+    )?;
+    let revisions = stmt
+        .query_map(params![time_entry_id], row_to_revision)?
+        .collect::<Result<Vec<_>, _>>()?;
+    if revisions.is_empty() {
+        get_time_entry(conn, time_entry_id)?;
+    }
+    Ok(revisions)
+}
+
+/// Entries other than `exclude_id` whose span shares any time with `start..end`.
+/// Entries that merely touch end to start don't count.
+fn overlapping_entries(
+    conn: &Connection,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    exclude_id: Option<&str>,
+) -> AppResult<Vec<TimeEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM time_entries
+         WHERE julianday(start_time) < julianday(?2) AND julianday(end_time) > julianday(?1)
+           AND id IS NOT ?3
+         ORDER BY julianday(start_time)",
+    )?;
+    let entries = stmt
+        .query_map(
+            params![start.to_rfc3339(), end.to_rfc3339(), exclude_id],
+            row_to_time_entry,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
+
+/// The timer runs recorded for an entry, in order; empty for manual entries.
+pub fn list_time_entry_segments(
+    conn: &Connection,
+    time_entry_id: &str,
+) -> AppResult<Vec<TimeSegment>> {
+    get_time_entry(conn, time_entry_id)?;
+    query_segments(conn, "time_entry_id = ?1", params![time_entry_id])
 }
 
+fn query_segments(
+    conn: &Connection,
+    filter: &str,
+    params: impl rusqlite::Params,
+) -> AppResult<Vec<TimeSegment>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT start_time, end_time FROM timer_segments WHERE {filter}
+         ORDER BY julianday(start_time)"
+    ))?;
+    let segments = stmt
+        .query_map(params, |row| {
+            Ok(TimeSegment {
+                start_time: row.get("start_time")?,
+                end_time: row.get("end_time")?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(segments)
+}
+
+/// The time an entry actually covers: its timer runs, or its whole span.
+fn entry_spans(conn: &Connection, entry: &TimeEntry) -> AppResult<Vec<Span>> {
+    let segments = query_segments(conn, "time_entry_id = ?1", params![entry.id])?;
+    if segments.is_empty() {
+        return Ok(vec![(entry.start_time, entry.end_time)]);
+    }
+    Ok(segments.into_iter().map(|s| (s.start_time, s.end_time)).collect())
+}
+
+fn total_secs(spans: &[Span]) -> i64 {
+    spans.iter().map(|(start, end)| (*end - *start).num_seconds()).sum()
+}
+
+/// Applies `policy` to the spans of an entry about to be saved, checking them
+/// against the time covered by every entry but `exclude_entry` and every timer but
+/// `exclude_timer`; a running timer counts as taking everything from its current
+/// run on. Returns the spans to save and, under `warn`, the ids of the entries
+/// they overlap.
+fn resolve_overlaps(
+    conn: &Connection,
+    spans: Vec<Span>,
+    exclude_entry: Option<&str>,
+    exclude_timer: Option<i32>,
+    policy: OverlapPolicy,
+) -> AppResult<(Vec<Span>, Vec<String>)> {
+    let (Some(&(start, _)), Some(&(_, end))) = (spans.first(), spans.last()) else {
+        return Ok((spans, Vec::new()));
+    };
+    let overlaps = |(s, e): &Span| spans.iter().any(|(start, end)| s < end && e > start);
+
+    let mut entries = Vec::new();
+    let mut taken = Vec::new();
+    for entry in overlapping_entries(conn, start, end, exclude_entry)? {
+        let covered: Vec<_> = entry_spans(conn, &entry)?.into_iter().filter(overlaps).collect();
+        if !covered.is_empty() {
+            taken.extend(covered);
+            entries.push(entry);
+        }
+    }
+    let mut timer_overlaps = false;
+    for timer in list_timers(conn)?.into_iter().filter(|t| Some(t.id) != exclude_timer) {
+        let covered: Vec<_> = run_spans(conn, &timer, DateTime::<Utc>::MAX_UTC)?
+            .into_iter()
+            .filter(overlaps)
+            .collect();
+        timer_overlaps |= !covered.is_empty();
+        taken.extend(covered);
+    }
+    if taken.is_empty() {
+        return Ok((spans, Vec::new()));
+    }
+
+    match policy {
+        OverlapPolicy::Reject => {
+            let mut taken: Vec<String> = entries
+                .iter()
+                .map(|e| {
+                    format!(
+                        "{} - {}",
+                        e.start_time.format("%Y-%m-%d %H:%M"),
+                        e.end_time.format("%H:%M")
+                    )
+                })
+                .collect();
+            if timer_overlaps {
+                taken.push("an active timer".to_string());
+            }
+            Err(AppError::Validation(format!(
+                "Time entry overlaps {}",
+                taken.join(", ")
+            )))
+        }
+        // Timers have no entry to point at yet; stopping them reports the overlap
+        OverlapPolicy::Warn => Ok((spans, entries.into_iter().map(|e| e.id).collect())),
+        OverlapPolicy::Trim => {
+            let spans = spans
+                .into_iter()
+                .map(|(start, end)| overlap::trim(start, end, &taken))
+                .collect::<AppResult<Vec<_>>>()?;
+            Ok((spans, Vec::new()))
+        }
+    }
+}
+
+/// Resolves overlaps for time a timer has already tracked. Stopping or switching a
+/// timer must not fail, so `reject` falls back to `warn`, as does `trim` when the
+/// runs can't be trimmed.
+fn resolve_timer_overlaps(
+    conn: &Connection,
+    spans: Vec<Span>,
+    timer_id: i32,
+) -> AppResult<(Vec<Span>, Vec<String>)> {
+    if overlap::policy(conn)? == OverlapPolicy::Trim {
+        match resolve_overlaps(conn, spans.clone(), None, Some(timer_id), OverlapPolicy::Trim) {
+            Err(AppError::Validation(_)) => {}
+            resolved => return resolved,
+        }
+    }
+    resolve_overlaps(conn, spans, None, Some(timer_id), OverlapPolicy::Warn)
+}
+
+/// Every pair of entries that share time, in order of the first entry's start.
+/// Entries recorded by a timer only cover their runs, not the pauses between.
+pub fn find_time_conflicts(conn: &Connection) -> AppResult<Vec<TimeConflict>> {
+    let mut stmt = conn.prepare("SELECT * FROM time_entries ORDER BY julianday(start_time), id")?;
+    let entries = stmt
+        .query_map([], row_to_time_entry)?
+        .collect::<Result<Vec<_>, _>>()?;
+    let spans = entries
+        .iter()
+        .map(|e| entry_spans(conn, e))
+        .collect::<AppResult<Vec<_>>>()?;
+
+    let mut conflicts = Vec::new();
+    for (i, first) in entries.iter().enumerate() {
+        for (j, second) in entries
+            .iter()
+            .enumerate()
+            .skip(i + 1)
+            .take_while(|(_, e)| e.start_time < first.end_time)
+        {
+            let overlap_secs: i64 = spans[i]
+                .iter()
+                .flat_map(|a| spans[j].iter().map(move |b| (a, b)))
+                .map(|((s1, e1), (s2, e2))| ((*e1).min(*e2) - (*s1).max(*s2)).num_seconds().max(0))
+                .sum();
+            if overlap_secs > 0 {
+                conflicts.push(TimeConflict {
+                    first: first.clone(),
+                    second: second.clone(),
+                    overlap_secs,
+                });
+            }
+        }
+    }
+    Ok(conflicts)
+}
+
+/// Deletes an entry. Its revisions are kept, the last one holding the deleted values.
+/// Entries billed on an invoice that has left `draft` cannot be deleted; on a draft,
+/// the entry's line item is re-derived without it.
+pub fn delete_time_entry(conn: &Connection, id: &str) -> AppResult<()> {
+    let entry = get_time_entry(conn, id)?;
+    let billed_line = match &entry.invoice_id {
+        Some(invoice_id) => {
+            let invoice = invoices::ensure_draft(conn, invoice_id)?;
+            let line_item_id = billed_line_item(conn, id, &invoice)?;
+            Some((invoice.id, line_item_id))
+        }
+        None => None,
+    };
+
+    with_savepoint(conn, "delete_time_entry", || {
+        record_revision(conn, &entry)?;
+        conn.execute("DELETE FROM time_entries WHERE id = ?1", params![id])?;
+        if let Some((invoice_id, line_item_id)) = &billed_line {
+            invoice_service::rebill_line_item(conn, invoice_id, line_item_id)?;
+        }
+        Ok(())
+    })
+}
+
+/// Bills the entries on `line_item_id` of `invoice_id`.
 pub fn link_entries_to_invoice(
     conn: &Connection,
     entry_ids: &[String],
     invoice_id: &str,
+    line_item_id: &str,
 ) -> AppResult<()> {
     for id in entry_ids {
         conn.execute(
-            "UPDATE time_entries SET invoice_id = ?1 WHERE id = ?2",
-            params![invoice_id, id],
+            "UPDATE time_entries SET invoice_id = ?1, line_item_id = ?2 WHERE id = ?3",
+            params![invoice_id, line_item_id, id],
         )?;
     }
     Ok(())
 }
 
-// Timer operations (active_timer singleton)
+/// The line item of `invoice` an entry is billed on. Entries billed before entries
+/// were linked to their lines can't be re-derived, so their hours can't change.
+fn billed_line_item(conn: &Connection, id: &str, invoice: &Invoice) -> AppResult<String> {
+    let line_item_id: Option<String> = conn.query_row(
+        "SELECT line_item_id FROM time_entries WHERE id = ?1",
+        params![id],
+        |row| row.get(0),
+    )?;
+    line_item_id.ok_or_else(|| {
+        AppError::Validation(format!(
+            "Time entry {id} was billed on draft invoice {} before entries were linked to line items; recreate the draft to change its hours",
+            invoice.invoice_number
+        ))
+    })
+}
+
+fn record_revision(conn: &Connection, entry: &TimeEntry) -> AppResult<()> {
+    conn.execute(
+        "INSERT INTO time_entry_revisions
+            (id, time_entry_id, project_id, description, start_time, end_time, duration_secs, is_billable, revised_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            Uuid::new_v4().to_string(),
+            entry.id,
+            entry.project_id,
+            entry.description,
+            entry.start_time.to_rfc3339(),
+            entry.end_time.to_rfc3339(),
+            entry.duration_secs,
+            entry.is_billable,
+            Utc::now().to_rfc3339(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Total seconds of the entries billed on a line item; `None` once there are none.
+pub fn billed_secs_on_line(conn: &Connection, line_item_id: &str) -> AppResult<Option<i64>> {
+    let secs = conn.query_row(
+        "SELECT SUM(duration_secs) FROM time_entries WHERE line_item_id = ?1",
+        params![line_item_id],
+        |row| row.get(0),
+    )?;
+    Ok(secs)
+}
+
+// Timer operations (active_timer rows). Several named timers can exist, but only
+// one runs at a time; the rest wait paused until resumed or switched to. Calls
+// without a timer id act on the running timer, else the one paused most recently.
+fn row_to_timer(row: &rusqlite::Row) -> rusqlite::Result<ActiveTimer> {
+    Ok(ActiveTimer {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        project_id: row.get("project_id")?,
+        description: row.get("description")?,
+        started_at: row.get("started_at")?,
+        start_time: row.get("start_time")?,
+        accumulated_secs: row.get("accumulated_secs")?,
+        is_paused: row.get("is_paused")?,
+        last_activity_at: row.get("last_activity_at")?,
+        paused_at: row.get("paused_at")?,
+    })
+}
+
+/// The running timer first, then the others by when they were paused, latest first.
+pub fn list_timers(conn: &Connection) -> AppResult<Vec<ActiveTimer>> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM active_timer
+         ORDER BY is_paused, julianday(COALESCE(paused_at, start_time)) DESC, id DESC",
+    )?;
+    let timers = stmt
+        .query_map([], row_to_timer)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(timers)
+}
+
+pub fn get_timer(conn: &Connection, id: i32) -> AppResult<ActiveTimer> {
+    conn.query_row("SELECT * FROM active_timer WHERE id = ?1", params![id], row_to_timer)
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => {
+                AppError::NotFound(format!("Timer not found: {id}"))
+            }
+            _ => AppError::Database(e),
+        })
+}
+
+pub fn get_running_timer(conn: &Connection) -> AppResult<Option<ActiveTimer>> {
+    Ok(get_active_timer(conn)?.filter(|t| !t.is_paused))
+}
+
+/// The timer calls act on when no id is given.
+pub fn get_active_timer(conn: &Connection) -> AppResult<Option<ActiveTimer>> {
+    Ok(list_timers(conn)?.into_iter().next())
+}
+
+fn target_timer(conn: &Connection, id: Option<i32>, action: &str) -> AppResult<ActiveTimer> {
+    match id {
+        Some(id) => get_timer(conn, id),
+        None => get_active_timer(conn)?
+            .ok_or_else(|| AppError::Timer(format!("No active timer to {action}"))),
+    }
+}
+
 pub fn start_timer(
     conn: &Connection,
     project_id: &str,
     description: Option<&str>,
+    name: Option<&str>,
 ) -> AppResult<ActiveTimer> {
     // Check if timer already running
-    let existing = get_active_timer(conn)?;
-    if existing.is_some() {
+    if get_running_timer(conn)?.is_some() {
         return Err(AppError::Timer(
-            "A timer is already running. Stop it first.".to_string(),
+            "A timer is already running. Stop it or switch timers.".to_string(),
         ));
     }
 
-    let now = Utc::now();
+    insert_timer(conn, project_id, description, name, Utc::now())
+}
+
+fn insert_timer(
+    conn: &Connection,
+    project_id: &str,
+    description: Option<&str>,
+    name: Option<&str>,
+    now: DateTime<Utc>,
+) -> AppResult<ActiveTimer> {
+    let name = name.map(str::trim).filter(|n| !n.is_empty());
     conn.execute(
-        "INSERT OR REPLACE INTO active_timer (id, project_id, description, start_time, accumulated_secs, is_paused)
-         VALUES (1, ?1, ?2, ?3, 0, 0)",
-        params![project_id, description, now.to_rfc3339()],
+        "INSERT INTO active_timer (name, project_id, description, started_at, start_time, accumulated_secs, is_paused, last_activity_at, paused_at)
+         VALUES (?1, ?2, ?3, ?4, ?4, 0, 0, ?4, NULL)",
+        params![name, project_id, description, now.to_rfc3339()],
     )?;
 
     Ok(ActiveTimer {
-        id: 1,
+        id: conn.last_insert_rowid() as i32,
+        name: name.map(String::from),
         project_id: project_id.to_string(),
         description: description.map(String::from),
+        started_at: now,
         start_time: now,
         accumulated_secs: 0,
         is_paused: false,
+        last_activity_at: Some(now),
+        paused_at: None,
     })
 }
 
-pub fn stop_timer(conn: &Connection) -> AppResult<TimeEntry> {
-    let timer = get_active_timer(conn)?
-        .ok_or_else(|| AppError::Timer("No active timer to stop".to_string()))?;
+/// Stops a timer and saves its entry. `idle` says what to do with idle time at
+/// the end of the run; without it the idle time is kept.
+pub fn stop_timer(
+    conn: &Connection,
+    id: Option<i32>,
+    idle: Option<IdleAction>,
+) -> AppResult<TimeEntry> {
+    let timer = target_timer(conn, id, "stop")?;
+    with_savepoint(conn, "stop_timer", || stop_at(conn, &timer, Utc::now(), idle))
+}
 
-    let now = Utc::now();
+fn stop_at(
+    conn: &Connection,
+    timer: &ActiveTimer,
+    now: DateTime<Utc>,
+    idle: Option<IdleAction>,
+) -> AppResult<TimeEntry> {
     let elapsed = if timer.is_paused {
         timer.accumulated_secs
     } else {
         timer.accumulated_secs + (now - timer.start_time).num_seconds()
     };
 
+    let entry = match idle_to_remove(conn, timer, idle, now)? {
+        Some((since, until)) => {
+            let entry = save_run(conn, timer, since, elapsed - (until - since).num_seconds())?;
+            if idle == Some(IdleAction::Split) {
+                save_idle(conn, timer, since, until)?;
+            }
+            entry
+        }
+        // A paused timer's work ended when it was paused
+        None => save_run(conn, timer, timer.paused_at.unwrap_or(now), elapsed)?,
+    };
+    conn.execute("DELETE FROM active_timer WHERE id = ?1", params![timer.id])?;
+    Ok(entry)
+}
+
+/// The idle stretch `action` takes out of the timer's run, if any.
+fn idle_to_remove(
+    conn: &Connection,
+    timer: &ActiveTimer,
+    action: Option<IdleAction>,
+    now: DateTime<Utc>,
+) -> AppResult<Option<(DateTime<Utc>, DateTime<Utc>)>> {
+    match action {
+        None | Some(IdleAction::Keep) => Ok(None),
+        Some(IdleAction::Discard | IdleAction::Split) => detect_idle(conn, timer, now),
+    }
+}
+
+/// The timer's runs up to `until`: the ones closed by pauses, then the current
+/// one if it is running. Timers paused before runs were recorded get a single
+/// span from their start.
+fn run_spans(conn: &Connection, timer: &ActiveTimer, until: DateTime<Utc>) -> AppResult<Vec<Span>> {
+    let mut spans: Vec<Span> = query_segments(conn, "timer_id = ?1", params![timer.id])?
+        .into_iter()
+        .map(|s| (s.start_time, s.end_time))
+        .collect();
+    if !timer.is_paused {
+        spans.push((timer.start_time, until));
+    } else if spans.is_empty() {
+        spans.push((timer.started_at, timer.paused_at.unwrap_or(until)));
+    }
+    let clipped: Vec<Span> = spans
+        .into_iter()
+        .filter(|(start, _)| *start < until)
+        .map(|(start, end)| (start, end.min(until)))
+        .collect();
+    if clipped.is_empty() {
+        return Ok(vec![(timer.started_at, until.max(timer.started_at))]);
+    }
+    Ok(clipped)
+}
+
+/// Saves the timer's runs up to `end` as an entry starting when the timer was
+/// first started, and moves the runs over to it.
+fn save_run(
+    conn: &Connection,
+    timer: &ActiveTimer,
+    end: DateTime<Utc>,
+    elapsed: i64,
+) -> AppResult<TimeEntry> {
+    let runs = run_spans(conn, timer, end)?;
+    let (spans, overlaps_with) = resolve_timer_overlaps(conn, runs.clone(), timer.id)?;
+    let elapsed = (elapsed - (total_secs(&runs) - total_secs(&spans))).max(0);
+
     let entry = create_time_entry_from_timer(
         conn,
         &timer.project_id,
         timer.description.as_deref(),
-        timer.start_time,
-        now,
+        spans[0].0,
+        spans[spans.len() - 1].1,
         elapsed,
+        true,
     )?;
 
-    conn.execute("DELETE FROM active_timer WHERE id = 1", [])?;
+    conn.execute("DELETE FROM timer_segments WHERE timer_id = ?1", params![timer.id])?;
+    for (start, end) in &spans {
+        conn.execute(
+            "INSERT INTO timer_segments (time_entry_id, start_time, end_time) VALUES (?1, ?2, ?3)",
+            params![entry.id, start.to_rfc3339(), end.to_rfc3339()],
+        )?;
+    }
+    Ok(TimeEntry { overlaps_with, ..entry })
+}
 
-    Ok(entry)
+/// Logs idle time split off the timer as its own non-billable entry.
+fn save_idle(
+    conn: &Connection,
+    timer: &ActiveTimer,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> AppResult<TimeEntry> {
+    let (spans, _) = resolve_timer_overlaps(conn, vec![(since, until)], timer.id)?;
+    let (start_time, end_time) = spans[0];
+    create_time_entry_from_timer(
+        conn,
+        &timer.project_id,
+        Some("Idle time"),
+        start_time,
+        end_time,
+        (end_time - start_time).num_seconds(),
+        false,
+    )
 }
 
-pub fn pause_timer(conn: &Connection) -> AppResult<ActiveTimer> {
-    let timer = get_active_timer(conn)?
-        .ok_or_else(|| AppError::Timer("No active timer to pause".to_string()))?;
+pub fn pause_timer(conn: &Connection, id: Option<i32>) -> AppResult<ActiveTimer> {
+    let timer = target_timer(conn, id, "pause")?;
 
     if timer.is_paused {
         return Err(AppError::Timer("Timer is already paused".to_string()));
     }
 
-    let now = Utc::now();
-    let elapsed = timer.accumulated_secs + (now - timer.start_time).num_seconds();
+    pause_at(conn, timer, Utc::now())
+}
 
-    conn.execute(
-        "UPDATE active_timer SET accumulated_secs = ?1, is_paused = 1 WHERE id = 1",
-        params![elapsed],
-    )?;
+fn pause_at(conn: &Connection, timer: ActiveTimer, at: DateTime<Utc>) -> AppResult<ActiveTimer> {
+    let elapsed = timer.accumulated_secs + (at - timer.start_time).num_seconds();
+
+    with_savepoint(conn, "pause_timer", || {
+        conn.execute(
+            "UPDATE active_timer SET accumulated_secs = ?1, is_paused = 1, paused_at = ?2 WHERE id = ?3",
+            params![elapsed, at.to_rfc3339(), timer.id],
+        )?;
+        conn.execute(
+            "INSERT INTO timer_segments (timer_id, start_time, end_time) VALUES (?1, ?2, ?3)",
+            params![timer.id, timer.start_time.to_rfc3339(), at.to_rfc3339()],
+        )?;
+        Ok(())
+    })?;
 
     Ok(ActiveTimer {
         accumulated_secs: elapsed,
         is_paused: true,
+        paused_at: Some(at),
         ..timer
     })
 }
 
-pub fn resume_timer(conn: &Connection) -> AppResult<ActiveTimer> {
-    let timer = get_active_timer(conn)?
-        .ok_or_else(|| AppError::Timer("No active timer to resume".to_string()))?;
+/// Resumes a paused timer. `idle` says what to do with idle time before the pause:
+/// discarding takes it off the elapsed time, and splitting also saves the run so far
+/// and the idle time as entries of their own, so the timer starts over.
+pub fn resume_timer(
+    conn: &Connection,
+    id: Option<i32>,
+    idle: Option<IdleAction>,
+) -> AppResult<ActiveTimer> {
+    let timer = target_timer(conn, id, "resume")?;
 
     if !timer.is_paused {
         return Err(AppError::Timer("Timer is not paused".to_string()));
     }
+    if get_running_timer(conn)?.is_some() {
+        return Err(AppError::Timer(
+            "Another timer is running. Switch to this one instead.".to_string(),
+        ));
+    }
+
+    with_savepoint(conn, "resume_timer", || resume_at(conn, &timer, Utc::now(), idle))
+}
+
+fn resume_at(
+    conn: &Connection,
+    timer: &ActiveTimer,
+    now: DateTime<Utc>,
+    idle: Option<IdleAction>,
+) -> AppResult<ActiveTimer> {
+    let mut started_at = timer.started_at;
+    let mut accumulated_secs = timer.accumulated_secs;
+    if let Some((since, until)) = idle_to_remove(conn, timer, idle, now)? {
+        accumulated_secs -= (until - since).num_seconds();
+        if idle == Some(IdleAction::Split) {
+            save_run(conn, timer, since, accumulated_secs)?;
+            save_idle(conn, timer, since, until)?;
+            started_at = now;
+            accumulated_secs = 0;
+        } else {
+            // The idle time comes off the end of the last run
+            conn.execute(
+                "DELETE FROM timer_segments
+                 WHERE timer_id = ?1 AND julianday(start_time) >= julianday(?2)",
+                params![timer.id, since.to_rfc3339()],
+            )?;
+            conn.execute(
+                "UPDATE timer_segments SET end_time = ?2
+                 WHERE timer_id = ?1 AND julianday(end_time) > julianday(?2)",
+                params![timer.id, since.to_rfc3339()],
+            )?;
+        }
+    }
 
-    let now = Utc::now();
     conn.execute(
-        "UPDATE active_timer SET start_time = ?1, is_paused = 0 WHERE id = 1",
-        params![now.to_rfc3339()],
+        "UPDATE active_timer
+         SET started_at = ?1, start_time = ?2, accumulated_secs = ?3, is_paused = 0, last_activity_at = ?2, paused_at = NULL
+         WHERE id = ?4",
+        params![started_at.to_rfc3339(), now.to_rfc3339(), accumulated_secs, timer.id],
     )?;
 
     Ok(ActiveTimer {
+        started_at,
         start_time: now,
+        accumulated_secs,
         is_paused: false,
-        ..timer
+        last_activity_at: Some(now),
+        paused_at: None,
+        ..timer.clone()
     })
 }
 
-pub fn get_active_timer(conn: &Connection) -> AppResult<Option<ActiveTimer>> {
-    let result = conn.query_row("SELECT * FROM active_timer WHERE id = 1", [], |row| {
-        Ok(ActiveTimer {
-            id: row.get("id")?,
-            project_id: row.get("project_id")?,
-            description: row.get("description")?,
-            start_time: row.get("start_time")?,
-            accumulated_secs: row.get("accumulated_secs")?,
-            is_paused: row.get("is_paused")?,
-        })
-    });
+/// Pauses (or stops) the running timer and resumes or starts another at the same
+/// instant, so no time falls between the two. Switching to the running timer is a
+/// no-op.
+pub fn switch_timer(conn: &Connection, input: SwitchTimer) -> AppResult<ActiveTimer> {
+    let target = input.timer_id.map(|id| get_timer(conn, id)).transpose()?;
+    if target.is_none() && input.project_id.is_none() {
+        return Err(AppError::Validation(
+            "Choose a timer to switch to or a project to start one on".to_string(),
+        ));
+    }
 
-    match result {
-        Ok(timer) => Ok(Some(timer)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(AppError::Database(e)),
+    let now = Utc::now();
+    with_savepoint(conn, "switch_timer", || {
+        if let Some(current) = get_running_timer(conn)? {
+            if Some(current.id) == input.timer_id {
+                return Ok(current);
+            }
+            if input.stop_current {
+                stop_at(conn, &current, now, None)?;
+            } else {
+                pause_at(conn, current, now)?;
+            }
+        }
+
+        match (&target, &input.project_id) {
+            (Some(timer), _) => resume_at(conn, timer, now, None),
+            (None, Some(project_id)) => insert_timer(
+                conn,
+                project_id,
+                input.description.as_deref(),
+                input.name.as_deref(),
+                now,
+            ),
+            (None, None) => unreachable!("checked above"),
+        }
+    })
+}
+
+/// Records activity reported by the frontend. A heartbeat arriving after the timer
+/// has been idle past the threshold pauses it instead, leaving the idle time for
+/// `resume_timer` or `stop_timer` to settle. Ignored unless a timer is running.
+pub fn record_timer_activity(conn: &Connection, at: DateTime<Utc>) -> AppResult<TimerState> {
+    if let Some(timer) = get_running_timer(conn)? {
+        let at = at.min(Utc::now()).max(timer.start_time);
+        if detect_idle(conn, &timer, at)?.is_some() {
+            pause_at(conn, timer, at)?;
+        } else if timer.last_activity_at.is_none_or(|last| last < at) {
+            conn.execute(
+                "UPDATE active_timer SET last_activity_at = ?1 WHERE id = ?2",
+                params![at.to_rfc3339(), timer.id],
+            )?;
+        }
     }
+    get_timer_state(conn)
 }
 
 pub fn get_timer_state(conn: &Connection) -> AppResult<TimerState> {
     match get_active_timer(conn)? {
-        Some(timer) => {
-            let elapsed = if timer.is_paused {
-                timer.accumulated_secs
-            } else {
-                timer.accumulated_secs + (Utc::now() - timer.start_time).num_seconds()
-            };
-
-            // Get project name
-            let project_name: Option<String> = conn
-                .query_row(
-                    "SELECT name FROM projects WHERE id = ?1",
-                    params![timer.project_id],
-                    |row| row.get(0),
-                )
-                .ok();
-
-            Ok(TimerState {
-                is_running: true,
-                is_paused: timer.is_paused,
-                project_id: Some(timer.project_id),
-                project_name,
-                description: timer.description,
-                elapsed_secs: elapsed,
-                start_time: Some(timer.start_time),
-            })
-        }
+        Some(timer) => timer_state(conn, timer),
         None => Ok(TimerState {
+            timer_id: None,
+            name: None,
             is_running: false,
             is_paused: false,
             project_id: None,
@@ -290,10 +950,53 @@ pub fn get_timer_state(conn: &Connection) -> AppResult<TimerState> {
             description: None,
             elapsed_secs: 0,
             start_time: None,
+            idle_since: None,
+            idle_secs: 0,
         }),
     }
 }
 
+/// Every timer's state, in `list_timers` order.
+pub fn list_timer_states(conn: &Connection) -> AppResult<Vec<TimerState>> {
+    list_timers(conn)?
+        .into_iter()
+        .map(|timer| timer_state(conn, timer))
+        .collect()
+}
+
+fn timer_state(conn: &Connection, timer: ActiveTimer) -> AppResult<TimerState> {
+    let now = Utc::now();
+    let elapsed = if timer.is_paused {
+        timer.accumulated_secs
+    } else {
+        timer.accumulated_secs + (now - timer.start_time).num_seconds()
+    };
+    let idle = detect_idle(conn, &timer, now)?;
+
+    // Get project name
+    let project_name: Option<String> = conn
+        .query_row(
+            "SELECT name FROM projects WHERE id = ?1",
+            params![timer.project_id],
+            |row| row.get(0),
+        )
+        .ok();
+
+    Ok(TimerState {
+        timer_id: Some(timer.id),
+        name: timer.name,
+        is_running: true,
+        is_paused: timer.is_paused,
+        project_id: Some(timer.project_id),
+        project_name,
+        description: timer.description,
+        elapsed_secs: elapsed,
+        start_time: Some(timer.started_at),
+        idle_since: idle.map(|(since, _)| since),
+        idle_secs: idle.map_or(0, |(since, until)| (until - since).num_seconds()),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -301,6 +1004,7 @@ mod tests {
     use crate::db::init_db_in_memory;
     use crate::db::projects::create_project;
     use crate::models::{CreateClient, CreateProject};
+    use chrono::{TimeZone, Timelike};
 
     fn setup() -> (Connection, String) {
         let conn = init_db_in_memory().expect("Failed to init test DB");
@@ -314,6 +1018,7 @@ mod tests {
                 phone: None,
                 notes: None,
                 hourly_rate: None,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -336,14 +1041,14 @@ mod tests {
     fn test_start_and_stop_timer() {
         let (conn, project_id) = setup();
 
-        start_timer(&conn, &project_id, Some("Working on feature")).unwrap();
+        start_timer(&conn, &project_id, Some("Working on feature"), None).unwrap();
 
         let state = get_timer_state(&conn).unwrap();
         assert!(state.is_running);
         assert!(!state.is_paused);
         assert_eq!(state.project_name, Some("Test Project".to_string()));
 
-        let entry = stop_timer(&conn).unwrap();
+        let entry = stop_timer(&conn, None, None).unwrap();
         assert_eq!(entry.project_id, project_id);
         assert!(!entry.is_manual);
 
@@ -355,23 +1060,223 @@ mod tests {
     fn test_pause_and_resume_timer() {
         let (conn, project_id) = setup();
 
-        start_timer(&conn, &project_id, None).unwrap();
-        let paused = pause_timer(&conn).unwrap();
+        start_timer(&conn, &project_id, None, None).unwrap();
+        let paused = pause_timer(&conn, None).unwrap();
         assert!(paused.is_paused);
 
         let state = get_timer_state(&conn).unwrap();
         assert!(state.is_paused);
 
-        let resumed = resume_timer(&conn).unwrap();
+        let resumed = resume_timer(&conn, None, None).unwrap();
         assert!(!resumed.is_paused);
     }
 
+    /// Moves the running timer's start and last activity back by whole minutes.
+    fn backdate(conn: &Connection, started_mins_ago: i64, active_mins_ago: i64) -> DateTime<Utc> {
+        let now = Utc::now();
+        let minutes = chrono::Duration::minutes;
+        conn.execute(
+            "UPDATE active_timer SET start_time = ?1, last_activity_at = ?2 WHERE is_paused = 0",
+            params![
+                (now - minutes(started_mins_ago)).to_rfc3339(),
+                (now - minutes(active_mins_ago)).to_rfc3339()
+            ],
+        )
+        .unwrap();
+        now
+    }
+
+    fn assert_about(secs: i64, expected: i64) {
+        assert!((secs - expected).abs() <= 2, "{secs} is not about {expected}");
+    }
+
+    #[test]
+    fn test_stop_timer_with_idle_time() {
+        let (conn, project_id) = setup();
+        // Every run below is backdated from now, so they overlap one another; the
+        // default reject policy must not stop the timers from being saved
+        start_timer(&conn, &project_id, Some("Kept"), None).unwrap();
+        backdate(&conn, 60, 40);
+        let state = get_timer_state(&conn).unwrap();
+        assert!(state.idle_since.is_some());
+        assert_about(state.idle_secs, 40 * 60);
+        let kept = stop_timer(&conn, None, Some(IdleAction::Keep)).unwrap();
+        assert_about(kept.duration_secs, 60 * 60);
+
+        start_timer(&conn, &project_id, Some("Discarded"), None).unwrap();
+        let now = backdate(&conn, 180, 160);
+        let entry = stop_timer(&conn, None, Some(IdleAction::Discard)).unwrap();
+        assert_about(entry.duration_secs, 20 * 60);
+        assert_about((now - entry.end_time).num_seconds(), 160 * 60);
+
+        start_timer(&conn, &project_id, Some("Split"), None).unwrap();
+        backdate(&conn, 300, 280);
+        let entry = stop_timer(&conn, None, Some(IdleAction::Split)).unwrap();
+        assert_about(entry.duration_secs, 20 * 60);
+        let entries = list_time_entries_by_project(&conn, &project_id).unwrap();
+        let idle = entries.iter().find(|e| e.start_time == entry.end_time).unwrap();
+        assert!(!idle.is_billable && !idle.is_manual);
+        assert_about(idle.duration_secs, 280 * 60);
+        assert_eq!(entries.len(), 4);
+    }
+
+    #[test]
+    fn test_heartbeat_after_idle_pauses_for_resume() {
+        let (conn, project_id) = setup();
+        crate::db::settings::set_setting(&conn, "idle_threshold_minutes", "5").unwrap();
+        start_timer(&conn, &project_id, None, None).unwrap();
+
+        backdate(&conn, 10, 3);
+        let state = record_timer_activity(&conn, Utc::now()).unwrap();
+        assert!(!state.is_paused && state.idle_since.is_none());
+
+        backdate(&conn, 30, 20);
+        let state = record_timer_activity(&conn, Utc::now()).unwrap();
+        assert!(state.is_paused);
+        assert_about(state.idle_secs, 20 * 60);
+
+        let resumed = resume_timer(&conn, None, Some(IdleAction::Discard)).unwrap();
+        assert_about(resumed.accumulated_secs, 10 * 60);
+        assert!(get_timer_state(&conn).unwrap().idle_since.is_none());
+
+        pause_timer(&conn, None).unwrap();
+        conn.execute(
+            "UPDATE active_timer SET last_activity_at = ?1",
+            params![(Utc::now() - chrono::Duration::minutes(7)).to_rfc3339()],
+        )
+        .unwrap();
+        conn.execute(
+            "UPDATE active_timer SET start_time = ?1, accumulated_secs = accumulated_secs + 480",
+            params![(Utc::now() - chrono::Duration::minutes(8)).to_rfc3339()],
+        )
+        .unwrap();
+        let resumed = resume_timer(&conn, None, Some(IdleAction::Split)).unwrap();
+        assert_eq!(resumed.accumulated_secs, 0);
+        let entries = list_time_entries_by_project(&conn, &project_id).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries.iter().filter(|e| e.is_billable).count(), 1);
+    }
+
+    #[test]
+    fn test_switch_between_named_timers() {
+        let (conn, project_id) = setup();
+        let build = start_timer(&conn, &project_id, None, Some("Build")).unwrap();
+
+        let call = switch_timer(
+            &conn,
+            SwitchTimer {
+                project_id: Some(project_id.clone()),
+                name: Some("Call".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let build = get_timer(&conn, build.id).unwrap();
+        assert!(build.is_paused);
+        assert_eq!(build.paused_at, Some(call.start_time));
+        let names: Vec<_> = list_timers(&conn).unwrap().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec![Some("Call".to_string()), Some("Build".to_string())]);
+        assert_eq!(get_timer_state(&conn).unwrap().timer_id, Some(call.id));
+
+        // Only one timer runs at a time
+        assert!(start_timer(&conn, &project_id, None, None).is_err());
+        assert!(resume_timer(&conn, Some(build.id), None).is_err());
+        let same = SwitchTimer { timer_id: Some(call.id), ..Default::default() };
+        assert_eq!(switch_timer(&conn, same).unwrap().start_time, call.start_time);
+        assert!(matches!(
+            switch_timer(&conn, SwitchTimer::default()),
+            Err(AppError::Validation(_))
+        ));
+
+        let back = SwitchTimer { timer_id: Some(build.id), ..Default::default() };
+        let build = switch_timer(&conn, back).unwrap();
+        let call = get_timer(&conn, call.id).unwrap();
+        assert_eq!(call.paused_at, Some(build.start_time));
+
+        let stop_build = SwitchTimer {
+            project_id: Some(project_id.clone()),
+            stop_current: true,
+            ..Default::default()
+        };
+        let next = switch_timer(&conn, stop_build).unwrap();
+        let entries = list_time_entries_by_project(&conn, &project_id).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].end_time, next.start_time);
+
+        // Stopping a waiting timer ends its entry when it was paused
+        let entry = stop_timer(&conn, Some(call.id), None).unwrap();
+        assert_eq!(Some(entry.end_time), call.paused_at);
+        assert!(matches!(get_timer(&conn, call.id), Err(AppError::NotFound(_))));
+        assert_eq!(list_timers(&conn).unwrap().len(), 1);
+        assert!(find_time_conflicts(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_timer_runs_are_kept_as_segments() {
+        let (conn, project_id) = setup();
+        let hours = chrono::Duration::hours;
+        let started = Utc::now().with_nanosecond(0).unwrap() - hours(3);
+
+        // Run for an hour three hours ago, then pause until now
+        start_timer(&conn, &project_id, Some("Design"), None).unwrap();
+        conn.execute(
+            "UPDATE active_timer SET started_at = ?1, start_time = ?1",
+            params![started.to_rfc3339()],
+        )
+        .unwrap();
+        pause_timer(&conn, None).unwrap();
+        conn.execute(
+            "UPDATE timer_segments SET end_time = ?1",
+            params![(started + hours(1)).to_rfc3339()],
+        )
+        .unwrap();
+        conn.execute(
+            "UPDATE active_timer SET accumulated_secs = 3600, paused_at = ?1",
+            params![(started + hours(1)).to_rfc3339()],
+        )
+        .unwrap();
+        let resumed = resume_timer(&conn, None, None).unwrap();
+        assert_eq!(resumed.started_at, started);
+        assert_eq!(get_timer_state(&conn).unwrap().start_time, Some(started));
+
+        // The pause is free for other work, the run is not
+        manual(&conn, &project_id, started + hours(1), started + hours(2)).unwrap();
+        let during_run = manual(&conn, &project_id, started, started + hours(1) / 2);
+        assert!(matches!(during_run, Err(AppError::Validation(_))));
+
+        let entry = stop_timer(&conn, None, None).unwrap();
+        assert_eq!(entry.start_time, started);
+        assert_about(entry.duration_secs, 3600);
+        let segments = list_time_entry_segments(&conn, &entry.id).unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(
+            (segments[0].start_time, segments[0].end_time),
+            (started, started + hours(1))
+        );
+        assert_eq!(segments[1].start_time, resumed.start_time);
+        assert_eq!(segments[1].end_time, entry.end_time);
+        assert!(find_time_conflicts(&conn).unwrap().is_empty());
+
+        // Editing the times replaces the runs with the new span
+        let edited = update_time_entry(
+            &conn,
+            &entry.id,
+            UpdateTimeEntry {
+                start_time: Some(started + hours(2)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(list_time_entry_segments(&conn, &edited.id).unwrap().is_empty());
+        assert_eq!(edited.duration_secs, (edited.end_time - edited.start_time).num_seconds());
+    }
+
     #[test]
     fn test_cannot_start_two_timers() {
         let (conn, project_id) = setup();
 
-        start_timer(&conn, &project_id, None).unwrap();
-        let result = start_timer(&conn, &project_id, None);
+        start_timer(&conn, &project_id, None, None).unwrap();
+        let result = start_timer(&conn, &project_id, None, None);
         assert!(result.is_err());
     }
 
@@ -400,6 +1305,236 @@ mod tests {
         assert_eq!(entries.len(), 1);
     }
 
+    #[test]
+    fn test_update_time_entry_keeps_revisions() {
+        let (conn, project_id) = setup();
+        start_timer(&conn, &project_id, Some("Typo"), None).unwrap();
+        let entry = stop_timer(&conn, None, None).unwrap();
+        let start = entry.start_time - chrono::Duration::minutes(90);
+
+        let updated = update_time_entry(
+            &conn,
+            &entry.id,
+            UpdateTimeEntry {
+                description: Some("Fixed description".to_string()),
+                start_time: Some(start),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(updated.description.as_deref(), Some("Fixed description"));
+        assert_eq!(updated.duration_secs, (entry.end_time - start).num_seconds());
+        assert!(!updated.is_manual);
+
+        let cleared = update_time_entry(
+            &conn,
+            &entry.id,
+            UpdateTimeEntry {
+                description: Some(" ".to_string()),
+                is_billable: Some(false),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!((cleared.description, cleared.is_billable), (None, false));
+
+        // A no-op edit adds no revision
+        update_time_entry(&conn, &entry.id, UpdateTimeEntry::default()).unwrap();
+
+        let revisions = list_time_entry_revisions(&conn, &entry.id).unwrap();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[0].description.as_deref(), Some("Fixed description"));
+        assert_eq!(revisions[1].description.as_deref(), Some("Typo"));
+        assert_eq!(revisions[1].start_time, entry.start_time);
+        assert_eq!(revisions[1].duration_secs, entry.duration_secs);
+
+        let backwards = update_time_entry(
+            &conn,
+            &entry.id,
+            UpdateTimeEntry {
+                end_time: Some(start),
+                ..Default::default()
+            },
+        );
+        assert!(matches!(backwards, Err(AppError::Validation(_))));
+        assert!(matches!(
+            update_time_entry(&conn, "missing", UpdateTimeEntry::default()),
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_changes_refused_once_invoice_is_sent() {
+        let (conn, project_id) = setup();
+        let client_id = projects::get_project(&conn, &project_id).unwrap().client_id;
+        let now = Utc::now();
+        let entry = create_manual_time_entry(
+            &conn,
+            CreateManualTimeEntry {
+                project_id,
+                description: None,
+                start_time: now - chrono::Duration::hours(1),
+                end_time: now,
+                is_billable: true,
+            },
+        )
+        .unwrap();
+        let invoice =
+            invoices::create_invoice(&conn, &client_id, "2025-01-01T00:00:00Z", None, None, None)
+                .unwrap();
+        let line = invoices::add_line_item(
+            &conn,
+            &invoice.id,
+            "Work",
+            1.0,
+            10000,
+            crate::models::LineItemUnit::Hours,
+            0,
+        )
+        .unwrap();
+        link_entries_to_invoice(&conn, std::slice::from_ref(&entry.id), &invoice.id, &line.id)
+            .unwrap();
+
+        let rename = || UpdateTimeEntry {
+            description: Some("Renamed".to_string()),
+            ..Default::default()
+        };
+        update_time_entry(&conn, &entry.id, rename()).unwrap();
+
+        invoices::update_invoice_status(&conn, &invoice.id, crate::models::InvoiceStatus::Sent)
+            .unwrap();
+        let result = update_time_entry(&conn, &entry.id, rename());
+        assert!(matches!(result, Err(AppError::Validation(_))));
+        let result = delete_time_entry(&conn, &entry.id);
+        assert!(matches!(result, Err(AppError::Validation(_))));
+        assert_eq!(list_time_entry_revisions(&conn, &entry.id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_deleted_entry_keeps_revisions() {
+        let (conn, project_id) = setup();
+        let now = Utc::now();
+        let entry = manual(&conn, &project_id, now - chrono::Duration::hours(1), now).unwrap();
+        update_time_entry(
+            &conn,
+            &entry.id,
+            UpdateTimeEntry {
+                description: Some("Review".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        delete_time_entry(&conn, &entry.id).unwrap();
+        assert!(matches!(get_time_entry(&conn, &entry.id), Err(AppError::NotFound(_))));
+        let revisions = list_time_entry_revisions(&conn, &entry.id).unwrap();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[0].description.as_deref(), Some("Review"));
+        assert!(matches!(delete_time_entry(&conn, &entry.id), Err(AppError::NotFound(_))));
+    }
+
+    fn manual(
+        conn: &Connection,
+        project_id: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> AppResult<TimeEntry> {
+        create_manual_time_entry(
+            conn,
+            CreateManualTimeEntry {
+                project_id: project_id.to_string(),
+                description: None,
+                start_time: start,
+                end_time: end,
+                is_billable: true,
+            },
+        )
+    }
+
+    #[test]
+    fn test_overlap_policies() {
+        let (conn, project_id) = setup();
+        let at = |hour| Utc.with_ymd_and_hms(2025, 3, 3, hour, 0, 0).unwrap();
+        let morning = manual(&conn, &project_id, at(9), at(11)).unwrap();
+
+        // Back to back is fine; overlapping is rejected by default
+        manual(&conn, &project_id, at(11), at(12)).unwrap();
+        let result = manual(&conn, &project_id, at(10), at(13));
+        assert!(matches!(result, Err(AppError::Validation(_))));
+
+        crate::db::settings::set_setting(&conn, "time_overlap_policy", "trim").unwrap();
+        let trimmed = manual(&conn, &project_id, at(10), at(14)).unwrap();
+        assert_eq!((trimmed.start_time, trimmed.end_time), (at(12), at(14)));
+        assert_eq!(trimmed.duration_secs, 2 * 3600);
+
+        crate::db::settings::set_setting(&conn, "time_overlap_policy", "warn").unwrap();
+        let warned = manual(&conn, &project_id, at(8), at(10)).unwrap();
+        assert_eq!(warned.overlaps_with, vec![morning.id.clone()]);
+        assert_eq!(warned.duration_secs, 2 * 3600);
+
+        let moved = update_time_entry(
+            &conn,
+            &trimmed.id,
+            UpdateTimeEntry {
+                start_time: Some(at(11)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(moved.overlaps_with.len(), 1);
+
+        let conflicts = find_time_conflicts(&conn).unwrap();
+        let pairs: Vec<_> = conflicts
+            .iter()
+            .map(|c| (c.first.start_time, c.second.start_time, c.overlap_secs))
+            .collect();
+        assert_eq!(pairs, vec![(at(8), at(9), 3600), (at(11), at(11), 3600)]);
+    }
+
+    #[test]
+    fn test_manual_entry_cannot_overlap_running_timer() {
+        let (conn, project_id) = setup();
+        let hours = chrono::Duration::hours;
+        let now = Utc::now();
+        start_timer(&conn, &project_id, None, None).unwrap();
+
+        let result = manual(&conn, &project_id, now - hours(1), now + hours(1));
+        assert!(matches!(result, Err(AppError::Validation(_))));
+        manual(&conn, &project_id, now - hours(2), now - hours(1)).unwrap();
+
+        crate::db::settings::set_setting(&conn, "time_overlap_policy", "trim").unwrap();
+        let trimmed = manual(&conn, &project_id, now - hours(1), now + hours(1)).unwrap();
+        assert!(trimmed.end_time <= Utc::now());
+        assert!(find_time_conflicts(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_timer_overlapping_an_entry_can_still_be_stopped() {
+        let (conn, project_id) = setup();
+        let hours = chrono::Duration::hours;
+        let now = Utc::now();
+        // Planned ahead, so timers started within the next hour run over it
+        let planned = manual(&conn, &project_id, now - hours(1), now + hours(1)).unwrap();
+
+        start_timer(&conn, &project_id, Some("First"), None).unwrap();
+        switch_timer(
+            &conn,
+            SwitchTimer {
+                timer_id: None,
+                project_id: Some(project_id.clone()),
+                description: Some("Second".to_string()),
+                name: None,
+                stop_current: true,
+            },
+        )
+        .unwrap();
+        let stopped = stop_timer(&conn, None, None).unwrap();
+        assert_eq!(stopped.overlaps_with, vec![planned.id.clone()]);
+
+        let entries = list_time_entries_by_project(&conn, &project_id).unwrap();
+        assert_eq!(entries.len(), 3);
+    }
+
     #[test]
     fn test_manual_entry_invalid_times() {
         let (conn, project_id) = setup();
@@ -26,6 +26,12 @@ pub enum AppError {
     #[error("Template error: {0}")]
     Template(#[from] handlebars::RenderError),
 
+    #[error("PDF error: {0}")]
+    Pdf(String),
+
+    #[error("Email error: {0}")]
+    Email(String),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
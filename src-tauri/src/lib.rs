@@ -5,11 +5,22 @@ mod models;
 mod services;
 
 use std::sync::Mutex;
+use std::time::Duration;
 
 use rusqlite::Connection;
+use tauri::Manager;
 
 pub struct DbState(pub Mutex<Connection>);
 
+const OVERDUE_SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+fn sweep_overdue_invoices(conn: &Connection) {
+    let today = chrono::Local::now().date_naive();
+    if let Err(e) = db::invoices::mark_overdue_invoices(conn, today, chrono::Utc::now()) {
+        log::error!("Failed to mark overdue invoices: {e}");
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let app_dir = dirs_next().unwrap_or_else(|| std::path::PathBuf::from("."));
@@ -20,9 +31,39 @@ pub fn run() {
         .expect("Failed to initialize database");
 
     tauri::Builder::default()
+        .plugin(tauri_plugin_log::Builder::new().build())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_shell::init())
         .manage(DbState(Mutex::new(conn)))
+        .setup(|app| {
+            {
+                let state = app.state::<DbState>();
+                let conn = state.0.lock().map_err(|e| e.to_string())?;
+                // Catch up on recurring invoices that fell due while the app was closed
+                let today = chrono::Local::now().date_naive();
+                if let Err(e) = services::recurring::generate_due_invoices(&conn, today) {
+                    log::error!("Failed to generate recurring invoices: {e}");
+                }
+                sweep_overdue_invoices(&conn);
+            }
+
+            // Keep statuses current while the app stays open across due dates
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let mut interval = tokio::time::interval(OVERDUE_SWEEP_INTERVAL);
+                // The first tick is immediate, and startup has just swept
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    let state = handle.state::<DbState>();
+                    match state.0.lock() {
+                        Ok(conn) => sweep_overdue_invoices(&conn),
+                        Err(e) => log::error!("Skipping overdue sweep: {e}"),
+                    }
+                }
+            });
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             // Clients
             commands::clients::create_client,
@@ -42,21 +83,61 @@ pub fn run() {
             commands::timer::stop_timer,
             commands::timer::pause_timer,
             commands::timer::resume_timer,
+            commands::timer::switch_timer,
+            commands::timer::list_timers,
             commands::timer::get_timer_state,
+            commands::timer::timer_heartbeat,
             commands::timer::create_manual_entry,
             commands::timer::list_time_entries,
+            commands::timer::update_time_entry,
+            commands::timer::list_time_entry_revisions,
+            commands::timer::list_time_entry_segments,
             commands::timer::delete_time_entry,
+            commands::timer::find_time_conflicts,
             // Invoices
             commands::invoices::create_invoice,
+            commands::invoices::create_invoice_from_entries,
             commands::invoices::get_invoice,
             commands::invoices::list_invoices,
+            commands::invoices::update_invoice,
+            commands::invoices::duplicate_invoice,
             commands::invoices::update_invoice_status,
             commands::invoices::delete_invoice,
             commands::invoices::add_line_item,
             commands::invoices::get_line_items,
+            commands::invoices::update_line_item,
+            commands::invoices::reorder_line_items,
             commands::invoices::delete_line_item,
             commands::invoices::get_uninvoiced_entries,
             commands::invoices::set_payment_link,
+            commands::invoices::mark_overdue_invoices,
+            commands::invoices::preview_invoice_number,
+            commands::invoices::set_line_item_taxes,
+            commands::invoices::get_invoice_taxes,
+            commands::invoices::set_line_item_discount,
+            commands::invoices::set_invoice_discount,
+            // Taxes
+            commands::taxes::create_tax,
+            commands::taxes::get_tax,
+            commands::taxes::list_taxes,
+            commands::taxes::update_tax,
+            commands::taxes::delete_tax,
+            // Payments
+            commands::payments::record_payment,
+            commands::payments::list_payments,
+            commands::payments::void_payment,
+            // Credit notes
+            commands::credit_notes::create_credit_note,
+            commands::credit_notes::get_credit_note,
+            commands::credit_notes::list_credit_notes,
+            // Recurring invoices
+            commands::recurring_invoices::create_recurring_invoice,
+            commands::recurring_invoices::get_recurring_invoice,
+            commands::recurring_invoices::list_recurring_invoices,
+            commands::recurring_invoices::set_recurring_invoice_active,
+            commands::recurring_invoices::delete_recurring_invoice,
+            commands::recurring_invoices::list_recurring_occurrences,
+            commands::recurring_invoices::generate_due_recurring_invoices,
             // Estimates
             commands::estimates::get_estimate,
             commands::estimates::list_estimates,
@@ -64,6 +145,20 @@ pub fn run() {
             // PDF
             commands::pdf::render_invoice_html,
             commands::pdf::export_invoice_html,
+            commands::pdf::export_invoice_pdf,
+            commands::pdf::render_credit_note_html,
+            commands::pdf::export_credit_note_pdf,
+            // Email
+            commands::email::send_invoice_email,
+            commands::email::list_invoice_emails,
+            // Templates
+            commands::templates::create_template,
+            commands::templates::get_template,
+            commands::templates::list_templates,
+            commands::templates::update_template,
+            commands::templates::delete_template,
+            commands::templates::preview_template,
+            commands::templates::get_builtin_template,
             // Stripe
             commands::stripe::create_payment_link,
             // Dashboard
@@ -72,6 +167,10 @@ pub fn run() {
             commands::dashboard::get_hours_by_project,
             commands::dashboard::get_monthly_revenue,
             commands::dashboard::get_estimate_accuracy,
+            commands::dashboard::get_aging_report,
+            // Business profile
+            commands::business::get_business_profile,
+            commands::business::update_business_profile,
             // Settings
             commands::settings::get_setting,
             commands::settings::set_setting,
@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,12 +10,27 @@ pub struct Client {
     pub address: Option<String>,
     pub phone: Option<String>,
     pub notes: Option<String>,
-    pub hourly_rate: Option<f64>,
+    /// Minor units of the client's currency per hour.
+    pub hourly_rate: Option<i64>,
+    pub currency: Option<String>,
+    /// The currency the client is billed in: `currency`, or the default currency
+    /// when the client has none.
+    pub billing_currency: String,
+    /// Replaces `{CLIENT}` in document number patterns.
+    pub invoice_prefix: Option<String>,
+    pub tax_treatment: TaxTreatment,
+    /// Falls back to the `default_payment_terms` setting when unset.
+    pub payment_terms: Option<PaymentTerms>,
+    /// Template for the client's invoices; falls back to the default template.
+    pub template_id: Option<String>,
+    /// Language and number style of the client's documents, e.g. "de"; falls back
+    /// to the `default_locale` setting.
+    pub locale: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CreateClient {
     pub name: String,
     pub email: Option<String>,
@@ -23,10 +38,16 @@ pub struct CreateClient {
     pub address: Option<String>,
     pub phone: Option<String>,
     pub notes: Option<String>,
-    pub hourly_rate: Option<f64>,
+    pub hourly_rate: Option<i64>,
+    pub currency: Option<String>,
+    pub invoice_prefix: Option<String>,
+    pub tax_treatment: Option<TaxTreatment>,
+    pub payment_terms: Option<PaymentTerms>,
+    pub template_id: Option<String>,
+    pub locale: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct UpdateClient {
     pub name: Option<String>,
     pub email: Option<String>,
@@ -34,7 +55,15 @@ pub struct UpdateClient {
     pub address: Option<String>,
     pub phone: Option<String>,
     pub notes: Option<String>,
-    pub hourly_rate: Option<f64>,
+    pub hourly_rate: Option<i64>,
+    pub currency: Option<String>,
+    pub invoice_prefix: Option<String>,
+    pub tax_treatment: Option<TaxTreatment>,
+    pub payment_terms: Option<PaymentTerms>,
+    /// A blank id clears it.
+    pub template_id: Option<String>,
+    /// A blank locale clears it.
+    pub locale: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -74,7 +103,10 @@ pub struct Project {
     pub name: String,
     pub description: Option<String>,
     pub status: ProjectStatus,
-    pub hourly_rate: Option<f64>,
+    /// Minor units of `currency` per hour.
+    pub hourly_rate: Option<i64>,
+    /// The client's billing currency.
+    pub currency: String,
     pub budget_hours: Option<f64>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -86,7 +118,7 @@ pub struct CreateProject {
     pub name: String,
     pub description: Option<String>,
     pub status: Option<ProjectStatus>,
-    pub hourly_rate: Option<f64>,
+    pub hourly_rate: Option<i64>,
     pub budget_hours: Option<f64>,
 }
 
@@ -95,7 +127,7 @@ pub struct UpdateProject {
     pub name: Option<String>,
     pub description: Option<String>,
     pub status: Option<ProjectStatus>,
-    pub hourly_rate: Option<f64>,
+    pub hourly_rate: Option<i64>,
     pub budget_hours: Option<f64>,
 }
 
@@ -111,6 +143,25 @@ pub struct TimeEntry {
     pub is_manual: bool,
     pub invoice_id: Option<String>,
     pub created_at: DateTime<Utc>,
+    /// Entries this one overlaps, reported when it was saved under the `warn`
+    /// overlap policy.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overlaps_with: Vec<String>,
+}
+
+/// One run of a timer between pauses.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TimeSegment {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+}
+
+/// Two entries covering some of the same time; `first` starts no later than `second`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeConflict {
+    pub first: TimeEntry,
+    pub second: TimeEntry,
+    pub overlap_secs: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -122,11 +173,36 @@ pub struct CreateManualTimeEntry {
     pub is_billable: bool,
 }
 
+/// Fields left `None` keep their current value; a blank description clears it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateTimeEntry {
+    pub project_id: Option<String>,
+    pub description: Option<String>,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub is_billable: Option<bool>,
+}
+
+/// The values a time entry had before one edit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeEntryRevision {
+    pub id: String,
+    pub time_entry_id: String,
+    pub project_id: String,
+    pub description: Option<String>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub duration_secs: i64,
+    pub is_billable: bool,
+    pub revised_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum InvoiceStatus {
     Draft,
     Sent,
+    PartiallyPaid,
     Paid,
     Overdue,
     Cancelled,
@@ -137,6 +213,7 @@ impl InvoiceStatus {
         match self {
             InvoiceStatus::Draft => "draft",
             InvoiceStatus::Sent => "sent",
+            InvoiceStatus::PartiallyPaid => "partially_paid",
             InvoiceStatus::Paid => "paid",
             InvoiceStatus::Overdue => "overdue",
             InvoiceStatus::Cancelled => "cancelled",
@@ -147,12 +224,32 @@ impl InvoiceStatus {
         match s {
             "draft" => Some(InvoiceStatus::Draft),
             "sent" => Some(InvoiceStatus::Sent),
+            "partially_paid" => Some(InvoiceStatus::PartiallyPaid),
             "paid" => Some(InvoiceStatus::Paid),
             "overdue" => Some(InvoiceStatus::Overdue),
             "cancelled" => Some(InvoiceStatus::Cancelled),
             _ => None,
         }
     }
+
+    /// The allowed manual status transitions. `paid` and `cancelled` are terminal;
+    /// `partially_paid` is only reached by recording a payment.
+    pub fn can_transition_to(&self, next: &InvoiceStatus) -> bool {
+        use InvoiceStatus::*;
+        matches!(
+            (self, next),
+            (Draft, Sent)
+                | (Draft, Cancelled)
+                | (Sent, Paid)
+                | (Sent, Overdue)
+                | (Sent, Cancelled)
+                | (PartiallyPaid, Paid)
+                | (PartiallyPaid, Overdue)
+                | (PartiallyPaid, Cancelled)
+                | (Overdue, Paid)
+                | (Overdue, Cancelled)
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -163,25 +260,639 @@ pub struct Invoice {
     pub status: InvoiceStatus,
     pub issue_date: DateTime<Utc>,
     pub due_date: DateTime<Utc>,
-    pub subtotal: f64,
+    /// The terms `due_date` was derived from; `None` when it was set by hand.
+    pub payment_terms: Option<PaymentTerms>,
+    pub currency: String,
+    /// Amounts are integer minor units of `currency` (cents, or whole yen for JPY).
+    /// Sum of line amounts before any discount.
+    pub subtotal: i64,
+    /// Sum of the line items' discounts.
+    pub line_discount_amount: i64,
+    /// Invoice-wide discount, taken off the subtotal after line discounts.
+    pub discount: Option<Discount>,
+    pub discount_amount: i64,
+    /// Invoice-wide rate applied to lines that have no taxes of their own.
     pub tax_rate: Option<f64>,
-    pub tax_amount: f64,
-    pub total: f64,
+    /// The client's tax treatment when the invoice was last edited.
+    pub tax_treatment: TaxTreatment,
+    /// Sum of the invoice's tax lines; see `InvoiceTax`.
+    pub tax_amount: i64,
+    pub total: i64,
+    /// Sum of non-voided payments.
+    pub amount_paid: i64,
+    /// The part of issued credit notes applied against this invoice's balance.
+    pub amount_credited: i64,
+    pub balance_due: i64,
     pub notes: Option<String>,
     pub payment_link: Option<String>,
+    /// When the overdue sweep moved the invoice from `sent` to `overdue`.
+    pub overdue_at: Option<DateTime<Utc>>,
+    /// Overrides the client's template.
+    pub template_id: Option<String>,
+    /// Business profile fields this invoice prints differently.
+    pub business_override: Option<BusinessDetails>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// Changes to a draft invoice; `None` keeps the current value. A `tax_rate` of 0
+/// stops taxing lines that have no taxes of their own.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateInvoice {
+    pub client_id: Option<String>,
+    pub issue_date: Option<String>,
+    pub due_date: Option<String>,
+    pub notes: Option<String>,
+    pub tax_rate: Option<f64>,
+    /// A blank id clears it.
+    pub template_id: Option<String>,
+    /// Replaces the invoice's business override; one with no fields set clears it.
+    pub business_override: Option<BusinessDetails>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InvoiceLineItem {
     pub id: String,
     pub invoice_id: String,
     pub description: String,
     pub quantity: f64,
-    pub unit_price: f64,
-    pub amount: f64,
+    /// Minor units of `currency`, the invoice's currency.
+    pub unit_price: i64,
+    pub currency: String,
+    pub unit: LineItemUnit,
+    /// `quantity * unit_price`, before `discount_amount` is taken off.
+    pub amount: i64,
+    pub discount: Option<Discount>,
+    pub discount_amount: i64,
     pub sort_order: i32,
+    /// Taxes assigned to this line. Empty means the invoice-wide `tax_rate` applies.
+    pub tax_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentMethod {
+    BankTransfer,
+    Card,
+    Cash,
+    Check,
+    Stripe,
+    Other,
+}
+
+impl PaymentMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentMethod::BankTransfer => "bank_transfer",
+            PaymentMethod::Card => "card",
+            PaymentMethod::Cash => "cash",
+            PaymentMethod::Check => "check",
+            PaymentMethod::Stripe => "stripe",
+            PaymentMethod::Other => "other",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "bank_transfer" => Some(PaymentMethod::BankTransfer),
+            "card" => Some(PaymentMethod::Card),
+            "cash" => Some(PaymentMethod::Cash),
+            "check" => Some(PaymentMethod::Check),
+            "stripe" => Some(PaymentMethod::Stripe),
+            "other" => Some(PaymentMethod::Other),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payment {
+    pub id: String,
+    pub invoice_id: String,
+    /// Minor units of the invoice currency.
+    pub amount: i64,
+    pub payment_date: NaiveDate,
+    pub method: PaymentMethod,
+    pub reference: Option<String>,
+    pub voided_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordPayment {
+    pub invoice_id: String,
+    pub amount: i64,
+    pub payment_date: NaiveDate,
+    pub method: PaymentMethod,
+    pub reference: Option<String>,
+}
+
+/// When an invoice falls due, counted from its issue date. Stored and sent over IPC
+/// as `net_30`, `due_on_receipt` or `eom_10` (`eom` alone for the end of the month).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub enum PaymentTerms {
+    DueOnReceipt,
+    /// Due this many days after the issue date.
+    Net(u32),
+    /// Due this many days after the end of the issue date's month.
+    EndOfMonth(u32),
+}
+
+impl PaymentTerms {
+    /// Longest supported term, in days.
+    pub const MAX_DAYS: u32 = 365;
+
+    pub fn as_string(&self) -> String {
+        match self {
+            PaymentTerms::DueOnReceipt => "due_on_receipt".to_string(),
+            PaymentTerms::Net(days) => format!("net_{days}"),
+            PaymentTerms::EndOfMonth(0) => "eom".to_string(),
+            PaymentTerms::EndOfMonth(days) => format!("eom_{days}"),
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        let days = |n: &str| n.parse::<u32>().ok().filter(|d| *d <= Self::MAX_DAYS);
+        match s {
+            "due_on_receipt" => Some(PaymentTerms::DueOnReceipt),
+            "eom" => Some(PaymentTerms::EndOfMonth(0)),
+            _ => {
+                if let Some(n) = s.strip_prefix("net_") {
+                    days(n).map(PaymentTerms::Net)
+                } else {
+                    days(s.strip_prefix("eom_")?).map(PaymentTerms::EndOfMonth)
+                }
+            }
+        }
+    }
+
+    /// How the terms read on an invoice, e.g. "Net 30".
+    pub fn label(&self) -> String {
+        match self {
+            PaymentTerms::DueOnReceipt => "Due on receipt".to_string(),
+            PaymentTerms::Net(days) => format!("Net {days}"),
+            PaymentTerms::EndOfMonth(0) => "Due at end of month".to_string(),
+            PaymentTerms::EndOfMonth(days) => format!("End of month + {days} days"),
+        }
+    }
+}
+
+impl TryFrom<String> for PaymentTerms {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        PaymentTerms::from_str(&value).ok_or_else(|| format!("Invalid payment terms: {value}"))
+    }
+}
+
+impl From<PaymentTerms> for String {
+    fn from(terms: PaymentTerms) -> Self {
+        terms.as_string()
+    }
+}
+
+/// What a line item's quantity counts.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub enum LineItemUnit {
+    #[default]
+    Hours,
+    Days,
+    Items,
+    /// A flat fee; the quantity is normally 1.
+    Fixed,
+}
+
+impl LineItemUnit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineItemUnit::Hours => "hours",
+            LineItemUnit::Days => "days",
+            LineItemUnit::Items => "items",
+            LineItemUnit::Fixed => "fixed",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "hours" => Some(LineItemUnit::Hours),
+            "days" => Some(LineItemUnit::Days),
+            "items" => Some(LineItemUnit::Items),
+            "fixed" => Some(LineItemUnit::Fixed),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<String> for LineItemUnit {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        LineItemUnit::from_str(&value).ok_or_else(|| {
+            format!("Unknown line item unit: {value}. Use hours, days, items or fixed")
+        })
+    }
+}
+
+impl From<LineItemUnit> for String {
+    fn from(unit: LineItemUnit) -> Self {
+        unit.as_str().to_string()
+    }
+}
+
+/// Changes to a draft invoice's line item; `None` keeps the current value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateLineItem {
+    pub description: Option<String>,
+    pub quantity: Option<f64>,
+    pub unit_price: Option<i64>,
+    pub unit: Option<LineItemUnit>,
+}
+
+/// A reduction applied before tax, to a line item or to a whole invoice.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum Discount {
+    /// Percent off, 0-100.
+    Percent(f64),
+    /// Minor units of the invoice currency.
+    Fixed(i64),
+}
+
+impl Discount {
+    pub fn kind_str(&self) -> &'static str {
+        match self {
+            Discount::Percent(_) => "percent",
+            Discount::Fixed(_) => "fixed",
+        }
+    }
+
+    pub fn value(&self) -> f64 {
+        match self {
+            Discount::Percent(percent) => *percent,
+            Discount::Fixed(amount) => *amount as f64,
+        }
+    }
+
+    /// Rebuilds a discount from its `discount_kind` and `discount_value` columns.
+    pub fn from_columns(kind: Option<&str>, value: Option<f64>) -> Option<Self> {
+        match (kind?, value?) {
+            ("percent", percent) => Some(Discount::Percent(percent)),
+            ("fixed", amount) => Some(Discount::Fixed(amount.round() as i64)),
+            _ => None,
+        }
+    }
+}
+
+/// How tax applies to a client's invoices.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaxTreatment {
+    #[default]
+    Standard,
+    /// No tax is charged; the invoice carries the exemption wording.
+    Exempt,
+    /// No tax is charged; the client accounts for it and the invoice says so.
+    ReverseCharge,
+}
+
+impl TaxTreatment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaxTreatment::Standard => "standard",
+            TaxTreatment::Exempt => "exempt",
+            TaxTreatment::ReverseCharge => "reverse_charge",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "standard" => Some(TaxTreatment::Standard),
+            "exempt" => Some(TaxTreatment::Exempt),
+            "reverse_charge" => Some(TaxTreatment::ReverseCharge),
+            _ => None,
+        }
+    }
+}
+
+/// A named tax such as GST, PST or VAT that can be assigned to line items.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tax {
+    pub id: String,
+    pub name: String,
+    /// Percent, e.g. `5.0`.
+    pub rate: f64,
+    /// Compound taxes are charged on the line amount plus its non-compound taxes.
+    pub is_compound: bool,
+    /// Inactive taxes stay on existing invoices but can't be assigned to new lines.
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateTax {
+    pub name: String,
+    pub rate: f64,
+    pub is_compound: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateTax {
+    pub name: Option<String>,
+    pub rate: Option<f64>,
+    pub is_compound: Option<bool>,
+    pub is_active: Option<bool>,
+}
+
+/// One row of a document's tax summary, snapshotted so later edits to the tax
+/// definition don't change issued documents.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct InvoiceTax {
+    /// `None` for the invoice-wide `tax_rate`.
+    pub tax_id: Option<String>,
+    pub name: String,
+    pub rate: f64,
+    pub is_compound: bool,
+    /// Minor units the rate was applied to.
+    pub taxable_amount: i64,
+    pub amount: i64,
+}
+
+/// A credit note issued against an invoice. Amounts are negative minor units of
+/// `currency`; `applied_amount` and `refund_amount` are positive and together equal
+/// `-total`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreditNote {
+    pub id: String,
+    pub credit_note_number: String,
+    pub invoice_id: String,
+    pub client_id: String,
+    pub issue_date: NaiveDate,
+    pub currency: String,
+    pub subtotal: i64,
+    pub tax_rate: Option<f64>,
+    /// Copied from the credited invoice.
+    pub tax_treatment: TaxTreatment,
+    pub tax_amount: i64,
+    pub total: i64,
+    /// Reduces the original invoice's balance due.
+    pub applied_amount: i64,
+    /// Credit beyond the invoice's open balance, owed back to the client.
+    pub refund_amount: i64,
+    pub reason: Option<String>,
+    pub line_items: Vec<CreditNoteLineItem>,
+    pub taxes: Vec<InvoiceTax>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreditNoteLineItem {
+    pub id: String,
+    pub credit_note_id: String,
+    pub description: String,
+    pub quantity: f64,
+    /// Negative minor units.
+    pub unit_price: i64,
+    pub amount: i64,
+    pub sort_order: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreditNoteLineInput {
+    pub description: String,
+    pub quantity: f64,
+    /// Negative minor units; a credit note only ever reduces what the client owes.
+    pub unit_price: i64,
+    /// Taxes to reverse on this line. Empty means the invoice-wide `tax_rate` applies.
+    #[serde(default)]
+    pub tax_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCreditNote {
+    pub invoice_id: String,
+    pub issue_date: NaiveDate,
+    pub reason: Option<String>,
+    pub line_items: Vec<CreditNoteLineInput>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LineItemGrouping {
+    Project,
+    Day,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateInvoiceFromEntries {
+    pub client_id: String,
+    pub entry_ids: Option<Vec<String>>,
+    pub start_date: Option<DateTime<Utc>>,
+    pub end_date: Option<DateTime<Utc>>,
+    pub group_by: LineItemGrouping,
+    pub issue_date: String,
+    /// Derived from the client's payment terms when omitted.
+    #[serde(default)]
+    pub due_date: Option<String>,
+    pub notes: Option<String>,
+    pub tax_rate: Option<f64>,
+}
+
+/// How fractional minor units are rounded. Set via the `rounding_mode` setting.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    /// Halves round away from zero (2.5 -> 3, -2.5 -> -3).
+    #[default]
+    HalfUp,
+    /// Halves round to the nearest even digit (2.5 -> 2, 3.5 -> 4), a.k.a. banker's rounding.
+    HalfEven,
+}
+
+impl RoundingMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoundingMode::HalfUp => "half_up",
+            RoundingMode::HalfEven => "half_even",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "half_up" => Some(RoundingMode::HalfUp),
+            "half_even" => Some(RoundingMode::HalfEven),
+            _ => None,
+        }
+    }
+}
+
+/// When a document number sequence starts again from 1.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SequenceReset {
+    /// A separate counter per calendar year of the issue date.
+    #[default]
+    Yearly,
+    Never,
+}
+
+impl SequenceReset {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SequenceReset::Yearly => "yearly",
+            SequenceReset::Never => "never",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "yearly" => Some(SequenceReset::Yearly),
+            "never" => Some(SequenceReset::Never),
+            _ => None,
+        }
+    }
+}
+
+/// A user-managed Handlebars template for invoices and credit notes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceTemplate {
+    pub id: String,
+    pub name: String,
+    pub body: String,
+    /// `#rrggbb`, exposed to templates as `brand_color` and used for the PDF accents.
+    pub brand_color: Option<String>,
+    /// A `data:image/png;base64,...` or `data:image/jpeg;base64,...` URI.
+    pub logo: Option<String>,
+    /// Used for clients and invoices that don't pick a template.
+    pub is_default: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateInvoiceTemplate {
+    pub name: String,
+    pub body: String,
+    pub brand_color: Option<String>,
+    pub logo: Option<String>,
+    #[serde(default)]
+    pub is_default: bool,
+}
+
+/// A blank `brand_color` or `logo` clears it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateInvoiceTemplate {
+    pub name: Option<String>,
+    pub body: Option<String>,
+    pub brand_color: Option<String>,
+    pub logo: Option<String>,
+    pub is_default: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PageSize {
+    #[default]
+    A4,
+    Letter,
+}
+
+impl PageSize {
+    /// Page width and height in millimetres.
+    pub fn dimensions_mm(&self) -> (f32, f32) {
+        match self {
+            PageSize::A4 => (210.0, 297.0),
+            PageSize::Letter => (215.9, 279.4),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecurringFrequency {
+    Weekly,
+    Monthly,
+    Quarterly,
+}
+
+impl RecurringFrequency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecurringFrequency::Weekly => "weekly",
+            RecurringFrequency::Monthly => "monthly",
+            RecurringFrequency::Quarterly => "quarterly",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "weekly" => Some(RecurringFrequency::Weekly),
+            "monthly" => Some(RecurringFrequency::Monthly),
+            "quarterly" => Some(RecurringFrequency::Quarterly),
+            _ => None,
+        }
+    }
+}
+
+/// When a recurring invoice is issued. Monthly and quarterly schedules land on
+/// `day_of_month` (clamped to the month's last day), defaulting to the start date's day.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecurringSchedule {
+    pub frequency: RecurringFrequency,
+    /// Every `interval` weeks/months/quarters.
+    pub interval: u32,
+    pub day_of_month: Option<u32>,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    /// Stop after this many occurrences.
+    pub count: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecurringLineItem {
+    pub description: String,
+    pub quantity: f64,
+    pub unit_price: i64,
+    #[serde(default)]
+    pub unit: LineItemUnit,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecurringInvoice {
+    pub id: String,
+    pub client_id: String,
+    pub name: String,
+    pub schedule: RecurringSchedule,
+    pub line_items: Vec<RecurringLineItem>,
+    pub tax_rate: Option<f64>,
+    pub notes: Option<String>,
+    /// Terms for the generated invoices; the client's terms apply when `None`.
+    pub payment_terms: Option<PaymentTerms>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateRecurringInvoice {
+    pub client_id: String,
+    pub name: String,
+    pub schedule: RecurringSchedule,
+    pub line_items: Vec<RecurringLineItem>,
+    pub tax_rate: Option<f64>,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub payment_terms: Option<PaymentTerms>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecurringOccurrence {
+    pub recurring_invoice_id: String,
+    pub occurrence_date: NaiveDate,
+    pub invoice_id: String,
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -202,22 +913,147 @@ pub struct Estimate {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ActiveTimer {
     pub id: i32,
+    pub name: Option<String>,
     pub project_id: String,
     pub description: Option<String>,
+    /// When the timer was first started.
+    pub started_at: DateTime<Utc>,
+    /// Start of the current run, moved on every resume.
     pub start_time: DateTime<Utc>,
     pub accumulated_secs: i64,
     pub is_paused: bool,
+    /// Latest activity the frontend reported; `None` until the first heartbeat
+    /// of a timer started before activity was tracked.
+    pub last_activity_at: Option<DateTime<Utc>>,
+    pub paused_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TimerState {
+    pub timer_id: Option<i32>,
+    pub name: Option<String>,
     pub is_running: bool,
     pub is_paused: bool,
     pub project_id: Option<String>,
     pub project_name: Option<String>,
     pub description: Option<String>,
     pub elapsed_secs: i64,
+    /// When the timer was first started, before any pauses.
     pub start_time: Option<DateTime<Utc>>,
+    /// Start of the idle stretch at the end of the current run, once it exceeds
+    /// the idle threshold.
+    pub idle_since: Option<DateTime<Utc>>,
+    pub idle_secs: i64,
+}
+
+/// Moves work to another timer: `timer_id` resumes a waiting timer, otherwise a
+/// new one starts on `project_id`. The running timer is paused, or stopped and
+/// saved if `stop_current` is set, at the same instant.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SwitchTimer {
+    pub timer_id: Option<i32>,
+    pub project_id: Option<String>,
+    pub description: Option<String>,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub stop_current: bool,
+}
+
+/// What to do with idle time when the timer is resumed or stopped.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum IdleAction {
+    /// Count it as worked time.
+    Keep,
+    /// Drop it from the entry.
+    Discard,
+    /// Drop it from the entry and log it as a separate non-billable entry.
+    Split,
+}
+
+/// The business issuing documents. There is one profile; every invoice and credit
+/// note renders with it unless the invoice overrides some of its fields.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BusinessProfile {
+    pub legal_name: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub website: Option<String>,
+    /// Printed one per line.
+    pub address_lines: Vec<String>,
+    /// VAT, GST or other tax registration number.
+    pub tax_id: Option<String>,
+    /// Stored without spaces, upper case.
+    pub iban: Option<String>,
+    /// Bank name, account number, SWIFT/BIC and the like, printed as written.
+    pub bank_details: Option<String>,
+    /// A PNG file, used when the document's template has no logo of its own.
+    pub logo_path: Option<String>,
+    /// `None` until the profile is first saved.
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Fields of a `BusinessProfile` to change, or to override for one invoice. `None`
+/// keeps the current value; a blank string or an empty address clears it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BusinessDetails {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub legal_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub website: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address_lines: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tax_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iban: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bank_details: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logo_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailStatus {
+    Sent,
+    Failed,
+}
+
+impl EmailStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailStatus::Sent => "sent",
+            EmailStatus::Failed => "failed",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "sent" => Some(EmailStatus::Sent),
+            "failed" => Some(EmailStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// One attempt to email an invoice, successful or not.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceEmail {
+    pub id: String,
+    pub invoice_id: String,
+    pub recipient: String,
+    pub subject: String,
+    pub status: EmailStatus,
+    /// The SMTP server's or connection's error when the send failed.
+    pub error: Option<String>,
+    /// Set when the message was delivered but the draft could not be issued.
+    pub warning: Option<String>,
+    pub sent_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,174 @@
+// Brand colours and logos for invoice templates.
+//
+// Logos are stored as `data:image/png;base64,...` URIs so a template row is
+// self-contained: the HTML output embeds the URI as-is, and the PDF layout decodes
+// it into an image. Transparent pixels are flattened onto the white page.
+use std::path::Path;
+
+use base64::Engine;
+use printpdf::{ColorBits, ColorSpace, ImageXObject, Px};
+
+use crate::error::{AppError, AppResult};
+
+/// Matches the accents of the built-in template.
+pub const DEFAULT_BRAND_COLOR: &str = "#2563eb";
+
+const LOGO_PREFIX: &str = "data:image/png;base64,";
+const MAX_LOGO_BYTES: usize = 512 * 1024;
+const MAX_LOGO_SIDE: u32 = 2048;
+
+/// Parses a `#rrggbb` colour.
+pub fn parse_color(value: &str) -> AppResult<(u8, u8, u8)> {
+    let hex = value
+        .strip_prefix('#')
+        .filter(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .ok_or_else(|| {
+            AppError::Validation(format!("Brand color must look like #2563eb, got {value}"))
+        })?;
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0);
+    Ok((channel(0), channel(2), channel(4)))
+}
+
+/// A decoded logo as 8-bit RGB pixels.
+pub struct Logo {
+    pub width: u32,
+    pub height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Logo {
+    pub fn into_image(self) -> ImageXObject {
+        ImageXObject {
+            width: Px(self.width as usize),
+            height: Px(self.height as usize),
+            color_space: ColorSpace::Rgb,
+            bits_per_component: ColorBits::Bit8,
+            interpolate: true,
+            image_data: self.pixels,
+            image_filter: None,
+            smask: None,
+            clipping_bbox: None,
+        }
+    }
+}
+
+fn invalid_logo(reason: &str) -> AppError {
+    AppError::Validation(format!("Logo {reason}"))
+}
+
+/// Decodes a PNG data URI, rejecting anything too large to embed in every document.
+pub fn decode_logo(uri: &str) -> AppResult<Logo> {
+    let payload = uri
+        .trim()
+        .strip_prefix(LOGO_PREFIX)
+        .ok_or_else(|| invalid_logo("must be a PNG image as a data:image/png;base64 URI"))?;
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(payload)
+        .map_err(|_| invalid_logo("is not valid base64"))?;
+    if bytes.len() > MAX_LOGO_BYTES {
+        return Err(invalid_logo(&format!(
+            "is {} KB; the limit is {} KB",
+            bytes.len() / 1024,
+            MAX_LOGO_BYTES / 1024
+        )));
+    }
+
+    let mut decoder = png::Decoder::new(bytes.as_slice());
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder
+        .read_info()
+        .map_err(|e| invalid_logo(&format!("could not be read: {e}")))?;
+    let (width, height) = reader.info().size();
+    if width > MAX_LOGO_SIDE || height > MAX_LOGO_SIDE {
+        return Err(invalid_logo(&format!(
+            "is {width}x{height} pixels; the limit is {MAX_LOGO_SIDE}x{MAX_LOGO_SIDE}"
+        )));
+    }
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buf)
+        .map_err(|e| invalid_logo(&format!("could not be read: {e}")))?;
+    buf.truncate(info.buffer_size());
+
+    let channels = info.color_type.samples();
+    let over_white = |c: u8, alpha: u8| {
+        let (c, alpha) = (u32::from(c), u32::from(alpha));
+        ((c * alpha + 255 * (255 - alpha)) / 255) as u8
+    };
+    let pixels = buf
+        .chunks_exact(channels)
+        .flat_map(|px| {
+            let (rgb, alpha) = match px {
+                [grey] => ([*grey; 3], 255),
+                [grey, alpha] => ([*grey; 3], *alpha),
+                [r, g, b] => ([*r, *g, *b], 255),
+                [r, g, b, alpha, ..] => ([*r, *g, *b], *alpha),
+                [] => ([255; 3], 255),
+            };
+            rgb.map(|c| over_white(c, alpha))
+        })
+        .collect();
+
+    Ok(Logo { width, height, pixels })
+}
+
+/// Reads a PNG file into a data URI, checked like any other logo.
+pub fn logo_from_file(path: &Path) -> AppResult<String> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| invalid_logo(&format!("file {} can't be read: {e}", path.display())))?;
+    let uri = format!(
+        "{LOGO_PREFIX}{}",
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    );
+    decode_logo(&uri)?;
+    Ok(uri)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A `width`x`height` PNG data URI, half-transparent red.
+    pub(crate) fn png_logo(width: u32, height: u32) -> String {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, width, height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            let pixels = [255, 0, 0, 128].repeat((width * height) as usize);
+            writer.write_image_data(&pixels).unwrap();
+        }
+        format!(
+            "{LOGO_PREFIX}{}",
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        )
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#2563eb").unwrap(), (37, 99, 235));
+        assert_eq!(parse_color("#FFFFFF").unwrap(), (255, 255, 255));
+        for bad in ["2563eb", "#fff", "#12345g", "red", ""] {
+            assert!(matches!(parse_color(bad), Err(AppError::Validation(_))), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_decode_logo_flattens_transparency() {
+        let logo = decode_logo(&png_logo(3, 2)).unwrap();
+        assert_eq!((logo.width, logo.height), (3, 2));
+        assert_eq!(logo.pixels.len(), 3 * 2 * 3);
+        assert_eq!(&logo.pixels[..3], &[255, 127, 127]);
+
+        for bad in [
+            "https://example.com/logo.png",
+            "data:image/gif;base64,R0lGODlh",
+            "data:image/png;base64,not base64!",
+            "data:image/png;base64,aGVsbG8=",
+        ] {
+            assert!(matches!(decode_logo(bad), Err(AppError::Validation(_))), "{bad}");
+        }
+        assert!(matches!(decode_logo(&png_logo(3000, 1)), Err(AppError::Validation(_))));
+    }
+}
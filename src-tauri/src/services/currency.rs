@@ -0,0 +1,189 @@
+use rusqlite::Connection;
+
+use crate::db::settings;
+use crate::error::{AppError, AppResult};
+
+pub const FALLBACK_CURRENCY: &str = "USD";
+
+/// ISO 4217 currency with its display symbol and number of minor-unit digits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Currency {
+    pub code: &'static str,
+    pub symbol: &'static str,
+    pub minor_units: u32,
+}
+
+const fn currency(code: &'static str, symbol: &'static str, minor_units: u32) -> Currency {
+    Currency {
+        code,
+        symbol,
+        minor_units,
+    }
+}
+
+const CURRENCIES: &[Currency] = &[
+    currency("USD", "$", 2),
+    currency("EUR", "€", 2),
+    currency("GBP", "£", 2),
+    currency("CAD", "CA$", 2),
+    currency("AUD", "A$", 2),
+    currency("NZD", "NZ$", 2),
+    currency("CHF", "CHF", 2),
+    currency("SEK", "kr", 2),
+    currency("NOK", "kr", 2),
+    currency("DKK", "kr", 2),
+    currency("PLN", "zł", 2),
+    currency("CZK", "Kč", 2),
+    currency("HUF", "Ft", 2),
+    currency("INR", "₹", 2),
+    currency("CNY", "CN¥", 2),
+    currency("HKD", "HK$", 2),
+    currency("SGD", "S$", 2),
+    currency("TWD", "NT$", 2),
+    currency("BRL", "R$", 2),
+    currency("MXN", "MX$", 2),
+    currency("ZAR", "R", 2),
+    currency("ILS", "₪", 2),
+    currency("AED", "AED", 2),
+    currency("JPY", "¥", 0),
+    currency("KRW", "₩", 0),
+    currency("VND", "₫", 0),
+    currency("CLP", "CLP$", 0),
+    currency("ISK", "kr", 0),
+    currency("BHD", "BD", 3),
+    currency("KWD", "KD", 3),
+    currency("JOD", "JD", 3),
+    currency("OMR", "OMR", 3),
+    currency("TND", "DT", 3),
+];
+
+/// Every supported currency.
+pub fn all() -> &'static [Currency] {
+    CURRENCIES
+}
+
+pub fn lookup(code: &str) -> Option<&'static Currency> {
+    CURRENCIES
+        .iter()
+        .find(|c| c.code.eq_ignore_ascii_case(code))
+}
+
+/// Normalizes a user-supplied code (`"eur"` -> `"EUR"`) and rejects unsupported ones.
+pub fn normalize_code(code: &str) -> AppResult<String> {
+    lookup(code.trim())
+        .map(|c| c.code.to_string())
+        .ok_or_else(|| AppError::Validation(format!("Unsupported currency code: {code}")))
+}
+
+/// Number of minor-unit digits for a currency; unknown codes are treated as two-decimal.
+pub fn minor_digits(code: &str) -> u32 {
+    lookup(code).map_or(2, |c| c.minor_units)
+}
+
+/// The currency an invoice for this client is billed in: the client's own currency,
+/// or the app default when the client has none.
+pub fn resolve(conn: &Connection, client_currency: Option<&str>) -> AppResult<String> {
+    match client_currency {
+        Some(code) => Ok(code.to_string()),
+        None => default_currency(conn),
+    }
+}
+
+const DEFAULT_SETTING: &str = "default_currency";
+
+/// The app-wide default currency from settings, falling back to USD.
+pub fn default_currency(conn: &Connection) -> AppResult<String> {
+    let configured = settings::get_setting(conn, DEFAULT_SETTING)?;
+    Ok(configured
+        .and_then(|code| lookup(&code).map(|c| c.code.to_string()))
+        .unwrap_or_else(|| FALLBACK_CURRENCY.to_string()))
+}
+
+/// Rejects a `default_currency` setting that isn't a supported ISO 4217 code.
+pub fn validate_setting(key: &str, value: &str) -> AppResult<()> {
+    if key == DEFAULT_SETTING {
+        normalize_code(value)?;
+    }
+    Ok(())
+}
+
+/// Re-expresses `amount` minor units of `from` in minor units of `to`, keeping its value
+/// in major units. Decimals `to` doesn't have are rounded half away from zero.
+pub fn rescale(amount: i64, from: &str, to: &str) -> i64 {
+    let (from, to) = (minor_digits(from), minor_digits(to));
+    if to >= from {
+        return amount.saturating_mul(10i64.pow(to - from));
+    }
+    let divisor = 10u64.pow(from - to);
+    let rounded = (amount.unsigned_abs() + divisor / 2) / divisor;
+    amount.signum() * rounded as i64
+}
+
+/// Parses a decimal string in major units (`"150"`, `"99.95"`) into exact minor units.
+/// More decimals than the currency allows is an error rather than a silent rounding.
+pub fn parse_amount(input: &str, code: &str) -> AppResult<i64> {
+    let invalid = || AppError::Validation(format!("Invalid {code} amount: {input}"));
+    let digits = minor_digits(code) as usize;
+    let trimmed = input.trim();
+    let (negative, unsigned) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed),
+    };
+    let (major, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if major.is_empty() || !all_digits(major) || !all_digits(fraction) || fraction.len() > digits {
+        return Err(invalid());
+    }
+
+    let major: i64 = major.parse().map_err(|_| invalid())?;
+    let minor: i64 = format!("{fraction:0<digits$}").parse().unwrap_or(0);
+    let amount = major
+        .checked_mul(10i64.pow(digits as u32))
+        .and_then(|m| m.checked_add(minor))
+        .ok_or_else(invalid)?;
+    Ok(if negative { -amount } else { amount })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db_in_memory;
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(parse_amount("19.99", "USD").unwrap(), 1999);
+        assert_eq!(parse_amount("150", "USD").unwrap(), 15000);
+        assert_eq!(parse_amount("0.5", "EUR").unwrap(), 50);
+        assert_eq!(parse_amount("-3.1", "USD").unwrap(), -310);
+        assert_eq!(parse_amount("1500", "JPY").unwrap(), 1500);
+        assert_eq!(parse_amount("1.5", "BHD").unwrap(), 1500);
+        assert!(parse_amount("1.999", "USD").is_err());
+        assert!(parse_amount("1.5", "JPY").is_err());
+        assert!(parse_amount("abc", "USD").is_err());
+        assert!(parse_amount(".5", "USD").is_err());
+    }
+
+    #[test]
+    fn test_rescale() {
+        assert_eq!(rescale(9550, "USD", "EUR"), 9550);
+        assert_eq!(rescale(9550, "USD", "JPY"), 96);
+        assert_eq!(rescale(-9549, "USD", "JPY"), -95);
+        assert_eq!(rescale(12000, "JPY", "KWD"), 12000000);
+        assert_eq!(rescale(1005, "BHD", "USD"), 101);
+    }
+
+    #[test]
+    fn test_normalize_code_and_default() {
+        assert_eq!(normalize_code(" gbp ").unwrap(), "GBP");
+        assert!(normalize_code("ABC").is_err());
+
+        let conn = init_db_in_memory().unwrap();
+        assert_eq!(default_currency(&conn).unwrap(), "USD");
+        settings::set_setting(&conn, "default_currency", "eur").unwrap();
+        assert_eq!(default_currency(&conn).unwrap(), "EUR");
+
+        assert!(validate_setting(DEFAULT_SETTING, "chf").is_ok());
+        assert!(validate_setting(DEFAULT_SETTING, "EURO").is_err());
+        assert!(validate_setting("other", "EURO").is_ok());
+    }
+}
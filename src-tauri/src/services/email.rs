@@ -0,0 +1,464 @@
+// Emailing invoices over SMTP.
+//
+// The server is configured through the `smtp_*` settings, of which `smtp_password`
+// is write-only: the settings commands never return it to the frontend. Subject
+// and body are Handlebars templates over the same data as invoice templates, taken
+// from the `email_subject_template` and `email_body_template` settings or else the
+// client's locale bundle. Sending happens outside the database lock; the caller
+// logs the outcome with `db::emails::record_email`.
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use handlebars::Handlebars;
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use rusqlite::Connection;
+
+use crate::db::{business, clients, invoices, settings};
+use crate::error::{AppError, AppResult};
+use crate::models::{InvoiceStatus, PageSize};
+use crate::services::pdf::{self, InvoiceTemplateData};
+use crate::services::pdf_layout;
+
+const HOST: &str = "smtp_host";
+const PORT: &str = "smtp_port";
+const SECURITY: &str = "smtp_security";
+const USERNAME: &str = "smtp_username";
+const PASSWORD: &str = "smtp_password";
+const FROM: &str = "smtp_from";
+const SUBJECT_TEMPLATE: &str = "email_subject_template";
+const BODY_TEMPLATE: &str = "email_body_template";
+
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How the connection to the SMTP server is secured.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SmtpSecurity {
+    /// Plain text; only for local relays and test servers.
+    None,
+    StartTls,
+    /// TLS from the first byte, usually on port 465.
+    Tls,
+}
+
+impl SmtpSecurity {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "none" => Some(SmtpSecurity::None),
+            "starttls" => Some(SmtpSecurity::StartTls),
+            "tls" => Some(SmtpSecurity::Tls),
+            _ => None,
+        }
+    }
+
+    fn default_port(&self) -> u16 {
+        match self {
+            SmtpSecurity::None => 25,
+            SmtpSecurity::StartTls => 587,
+            SmtpSecurity::Tls => 465,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: Mailbox,
+}
+
+fn setting(conn: &Connection, key: &str) -> AppResult<Option<String>> {
+    Ok(settings::get_setting(conn, key)?
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty()))
+}
+
+fn parse_mailbox(value: &str) -> AppResult<Mailbox> {
+    value
+        .parse()
+        .map_err(|e| AppError::Validation(format!("Not a valid email address: {value} ({e})")))
+}
+
+/// The configured server. STARTTLS is the default; the port defaults to the usual
+/// one for the security mode, and the sender to the business profile's email.
+pub fn smtp_settings(conn: &Connection) -> AppResult<SmtpSettings> {
+    let host = setting(conn, HOST)?.ok_or_else(|| {
+        AppError::Validation("Email is not set up: add an SMTP server in Settings".to_string())
+    })?;
+    let security = setting(conn, SECURITY)?
+        .and_then(|value| SmtpSecurity::from_str(&value))
+        .unwrap_or(SmtpSecurity::StartTls);
+    let port = setting(conn, PORT)?
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| security.default_port());
+    let from = match setting(conn, FROM)? {
+        Some(from) => parse_mailbox(&from)?,
+        None => {
+            let profile = business::get_business_profile(conn)?;
+            let email = profile.email.ok_or_else(|| {
+                AppError::Validation(
+                    "Set a sender address in Settings or an email in the business profile"
+                        .to_string(),
+                )
+            })?;
+            let name = Some(profile.legal_name).filter(|name| !name.is_empty());
+            Mailbox::new(name, parse_mailbox(&email)?.email)
+        }
+    };
+    Ok(SmtpSettings {
+        host,
+        port,
+        security,
+        username: setting(conn, USERNAME)?,
+        password: setting(conn, PASSWORD)?,
+        from,
+    })
+}
+
+/// Rejects malformed email settings. Blank values are allowed and restore the default.
+pub fn validate_setting(key: &str, value: &str) -> AppResult<()> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(());
+    }
+    match key {
+        PORT if !matches!(value.parse::<u16>(), Ok(port) if port > 0) => Err(AppError::Validation(
+            format!("Invalid {key}: {value}. Use a port number such as 587"),
+        )),
+        SECURITY if SmtpSecurity::from_str(value).is_none() => Err(AppError::Validation(format!(
+            "Invalid {key}: {value}. Use none, starttls or tls"
+        ))),
+        FROM => parse_mailbox(value).map(|_| ()),
+        SUBJECT_TEMPLATE | BODY_TEMPLATE => renderer(true)
+            .render_template(value, &pdf::sample_data())
+            .map(|_| ())
+            .map_err(|e| AppError::Validation(format!("Invalid {key}: {e}"))),
+        _ => Ok(()),
+    }
+}
+
+/// Emails are plain text, so nothing is HTML-escaped.
+fn renderer(strict: bool) -> Handlebars<'static> {
+    let mut hbs = Handlebars::new();
+    hbs.register_escape_fn(handlebars::no_escape);
+    hbs.set_strict_mode(strict);
+    hbs
+}
+
+/// A composed message and where it goes.
+pub struct OutgoingEmail {
+    pub smtp: SmtpSettings,
+    /// The client's address, as logged.
+    pub recipient: String,
+    pub subject: String,
+    pub body: String,
+    /// The invoice's `updated_at` when the message was rendered.
+    pub invoice_updated_at: DateTime<Utc>,
+    message: Message,
+}
+
+/// Renders the invoice email with the invoice PDF attached, ready to `send`.
+pub fn compose_invoice_email(
+    conn: &Connection,
+    invoice_id: &str,
+    page_size: PageSize,
+) -> AppResult<OutgoingEmail> {
+    let smtp = smtp_settings(conn)?;
+    let invoice = invoices::get_invoice(conn, invoice_id)?;
+    if invoice.status == InvoiceStatus::Cancelled {
+        return Err(AppError::Validation(format!(
+            "Invoice {} is cancelled",
+            invoice.invoice_number
+        )));
+    }
+    let client = clients::get_client(conn, &invoice.client_id)?;
+    let recipient = client
+        .email
+        .map(|email| email.trim().to_string())
+        .filter(|email| !email.is_empty())
+        .ok_or_else(|| {
+            AppError::Validation(format!("Client {} has no email address", client.name))
+        })?;
+    let to = Mailbox::new(Some(client.name), parse_mailbox(&recipient)?.email);
+
+    let data = attachment_data(conn, invoice_id)?;
+    let subject_template =
+        setting(conn, SUBJECT_TEMPLATE)?.unwrap_or_else(|| data.labels.email_subject.clone());
+    let body_template =
+        setting(conn, BODY_TEMPLATE)?.unwrap_or_else(|| data.labels.email_body.clone());
+    let subject = render_subject(&subject_template, &data)?;
+    let body = renderer(false).render_template(&body_template, &data)?;
+    let attachment = pdf_layout::render(&data, page_size)?;
+
+    let pdf_type = ContentType::parse("application/pdf")
+        .map_err(|e| AppError::Email(e.to_string()))?;
+    let message = Message::builder()
+        .from(smtp.from.clone())
+        .to(to)
+        .subject(subject.clone())
+        .multipart(
+            MultiPart::mixed()
+                .singlepart(SinglePart::plain(body.clone()))
+                .singlepart(
+                    Attachment::new(pdf::export_file_name(&data.invoice_number, "pdf"))
+                        .body(attachment, pdf_type),
+                ),
+        )
+        .map_err(|e| AppError::Email(e.to_string()))?;
+
+    Ok(OutgoingEmail {
+        smtp,
+        recipient,
+        subject,
+        body,
+        invoice_updated_at: invoice.updated_at,
+        message,
+    })
+}
+
+/// The invoice as the client receives it. Sending a draft issues it, so a draft is
+/// rendered as sent rather than stamped DRAFT.
+fn attachment_data(conn: &Connection, invoice_id: &str) -> AppResult<InvoiceTemplateData> {
+    let (mut data, _) = pdf::build_template_data(conn, invoice_id)?;
+    if data.status == InvoiceStatus::Draft.as_str() {
+        data.status = InvoiceStatus::Sent.as_str().to_string();
+        data.status_label = data.labels.status(Some(InvoiceStatus::Sent)).to_string();
+    }
+    Ok(data)
+}
+
+/// Subjects are a single line, however the template is laid out.
+fn render_subject(template: &str, data: &InvoiceTemplateData) -> AppResult<String> {
+    let subject = renderer(false).render_template(template, data)?;
+    Ok(subject.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Delivers `email` to the configured SMTP server.
+pub async fn send(email: &OutgoingEmail) -> AppResult<()> {
+    let smtp = &email.smtp;
+    let builder = match smtp.security {
+        SmtpSecurity::None => {
+            Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host))
+        }
+        SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.host),
+        SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&smtp.host),
+    }
+    .map_err(|e| AppError::Email(e.to_string()))?;
+
+    let mut builder = builder.port(smtp.port).timeout(Some(SEND_TIMEOUT));
+    if let Some(username) = &smtp.username {
+        builder = builder.credentials(Credentials::new(
+            username.clone(),
+            smtp.password.clone().unwrap_or_default(),
+        ));
+    }
+    builder
+        .build()
+        .send(email.message.clone())
+        .await
+        .map_err(|e| AppError::Email(e.to_string()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    use super::*;
+    use crate::db::emails::record_email;
+    use crate::db::settings::set_setting;
+    use crate::db::{clients as db_clients, init_db_in_memory, invoices as db_invoices};
+    use crate::models::{BusinessDetails, CreateClient, LineItemUnit};
+
+    /// A local SMTP server that accepts one message and returns it as received.
+    fn smtp_stand_in() -> (u16, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            writer.write_all(b"220 localhost ESMTP stand-in\r\n").unwrap();
+
+            let (mut received, mut line, mut in_data) = (String::new(), String::new(), false);
+            loop {
+                line.clear();
+                if reader.read_line(&mut line).unwrap() == 0 {
+                    break;
+                }
+                if in_data {
+                    if line == ".\r\n" {
+                        in_data = false;
+                        writer.write_all(b"250 Queued\r\n").unwrap();
+                    } else {
+                        received.push_str(&line);
+                    }
+                    continue;
+                }
+                let command = line.to_ascii_uppercase();
+                let reply: &[u8] = if command.starts_with("EHLO") {
+                    b"250-localhost\r\n250 8BITMIME\r\n"
+                } else if command.starts_with("DATA") {
+                    in_data = true;
+                    b"354 Go ahead\r\n"
+                } else if command.starts_with("QUIT") {
+                    writer.write_all(b"221 Bye\r\n").unwrap();
+                    break;
+                } else {
+                    b"250 OK\r\n"
+                };
+                writer.write_all(reply).unwrap();
+            }
+            received
+        });
+        (port, server)
+    }
+
+    fn setup(email: Option<&str>, locale: Option<&str>) -> (Connection, String) {
+        let conn = init_db_in_memory().unwrap();
+        business::update_business_profile(
+            &conn,
+            BusinessDetails {
+                legal_name: Some("Studio Nord".to_string()),
+                email: Some("billing@studionord.example".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let client = db_clients::create_client(
+            &conn,
+            CreateClient {
+                name: "Acme Corp".to_string(),
+                email: email.map(str::to_string),
+                currency: Some("EUR".to_string()),
+                locale: locale.map(str::to_string),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let invoice = db_invoices::create_invoice(
+            &conn,
+            &client.id,
+            "2025-01-15T00:00:00Z",
+            Some("2025-02-14T00:00:00Z"),
+            None,
+            None,
+        )
+        .unwrap();
+        db_invoices::add_line_item(&conn, &invoice.id, "Web development", 10.0, 15000, LineItemUnit::Hours, 0).unwrap();
+        db_invoices::set_payment_link(&conn, &invoice.id, "https://pay.example.com/inv-1").unwrap();
+        (conn, invoice.id)
+    }
+
+    #[test]
+    fn test_send_invoice_through_local_smtp_server() {
+        let (conn, invoice_id) = setup(Some("ap@acme.example"), None);
+        let (port, server) = smtp_stand_in();
+        set_setting(&conn, HOST, "127.0.0.1").unwrap();
+        set_setting(&conn, PORT, &port.to_string()).unwrap();
+        set_setting(&conn, SECURITY, "none").unwrap();
+
+        let email = compose_invoice_email(&conn, &invoice_id, PageSize::A4).unwrap();
+        let invoice = db_invoices::get_invoice(&conn, &invoice_id).unwrap();
+        let number = invoice.invoice_number;
+        // The draft goes out as the issued invoice
+        assert_eq!(invoice.status, InvoiceStatus::Draft);
+        let attached = attachment_data(&conn, &invoice_id).unwrap();
+        assert_eq!((attached.status.as_str(), attached.status_label.as_str()), ("sent", "Sent"));
+        assert_eq!(email.recipient, "ap@acme.example");
+        assert_eq!(email.subject, format!("Invoice {number} from Studio Nord"));
+        assert!(email.body.starts_with("Hello Acme Corp,"));
+        assert!(email.body.contains("for €1,500.00, due on Feb 14, 2025."));
+        assert!(email.body.contains("https://pay.example.com/inv-1"));
+
+        let result = tokio::runtime::Runtime::new().unwrap().block_on(send(&email));
+        assert!(result.is_ok(), "{result:?}");
+        let received = server.join().unwrap();
+        assert!(received.contains("From: \"Studio Nord\" <billing@studionord.example>"));
+        assert!(received.contains("To: \"Acme Corp\" <ap@acme.example>"));
+        assert!(received.contains(&format!("Subject: Invoice {number} from Studio Nord")));
+        assert!(received.contains(&format!("filename=\"{number}.pdf\"")));
+        assert!(received.contains("Content-Type: application/pdf"));
+
+        let logged = record_email(&conn, &invoice_id, email.invoice_updated_at, &email.recipient, &email.subject, None).unwrap();
+        assert_eq!(logged.recipient, "ap@acme.example");
+        assert_eq!(db_invoices::get_invoice(&conn, &invoice_id).unwrap().status, InvoiceStatus::Sent);
+    }
+
+    #[test]
+    fn test_unreachable_server_fails_the_send() {
+        let (conn, invoice_id) = setup(Some("ap@acme.example"), None);
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        set_setting(&conn, HOST, "127.0.0.1").unwrap();
+        set_setting(&conn, PORT, &port.to_string()).unwrap();
+        set_setting(&conn, SECURITY, "none").unwrap();
+
+        let email = compose_invoice_email(&conn, &invoice_id, PageSize::A4).unwrap();
+        let result = tokio::runtime::Runtime::new().unwrap().block_on(send(&email));
+        assert!(matches!(result, Err(AppError::Email(_))));
+    }
+
+    #[test]
+    fn test_compose_in_client_locale_and_custom_templates() {
+        let (conn, invoice_id) = setup(Some("buchhaltung@acme.example"), Some("de"));
+        set_setting(&conn, HOST, "smtp.example.com").unwrap();
+
+        let email = compose_invoice_email(&conn, &invoice_id, PageSize::A4).unwrap();
+        assert!(email.subject.starts_with("Rechnung "));
+        assert!(email.body.contains("über 1.500,00\u{a0}€, zahlbar bis zum 14.02.2025."));
+        assert_eq!(email.smtp.port, 587);
+        assert_eq!(email.smtp.security, SmtpSecurity::StartTls);
+
+        set_setting(&conn, SUBJECT_TEMPLATE, "{{invoice_number}}\n  due {{due_date}}").unwrap();
+        set_setting(&conn, BODY_TEMPLATE, "Total: {{total}} <no escaping>").unwrap();
+        let email = compose_invoice_email(&conn, &invoice_id, PageSize::A4).unwrap();
+        assert!(email.subject.ends_with(" due 14.02.2025"));
+        assert_eq!(email.body, "Total: 1.500,00\u{a0}€ <no escaping>");
+    }
+
+    #[test]
+    fn test_compose_requires_configuration_and_recipient() {
+        let (conn, invoice_id) = setup(None, None);
+        let compose = |conn: &Connection| compose_invoice_email(conn, &invoice_id, PageSize::A4);
+        assert!(matches!(compose(&conn), Err(AppError::Validation(_))));
+
+        set_setting(&conn, HOST, "smtp.example.com").unwrap();
+        let missing_email = compose(&conn).err().unwrap().to_string();
+        assert!(missing_email.contains("Acme Corp has no email address"), "{missing_email}");
+
+        db_invoices::update_invoice_status(&conn, &invoice_id, InvoiceStatus::Cancelled).unwrap();
+        assert!(compose(&conn).err().unwrap().to_string().contains("cancelled"));
+    }
+
+    #[test]
+    fn test_validate_setting() {
+        for (key, value) in [
+            (PORT, "2525"),
+            (PORT, ""),
+            (SECURITY, "tls"),
+            (FROM, "Studio Nord <billing@studionord.example>"),
+            (SUBJECT_TEMPLATE, "Invoice {{invoice_number}}"),
+            (BODY_TEMPLATE, "{{#if payment_link}}{{payment_link}}{{/if}}"),
+            (HOST, "smtp.example.com"),
+        ] {
+            assert!(validate_setting(key, value).is_ok(), "{key}={value}");
+        }
+        for (key, value) in [
+            (PORT, "0"),
+            (PORT, "smtp"),
+            (SECURITY, "ssl"),
+            (FROM, "not an address"),
+            (SUBJECT_TEMPLATE, "Invoice {{invoice_numbr}}"),
+            (BODY_TEMPLATE, "{{#if total}}"),
+        ] {
+            assert!(matches!(validate_setting(key, value), Err(AppError::Validation(_))), "{key}={value}");
+        }
+    }
+}
@@ -1 +1,466 @@
 // Invoice business logic service - calculation helpers
+use std::collections::HashMap;
+
+use rusqlite::Connection;
+
+use crate::db::{clients, invoices, projects, settings, time_entries, with_savepoint};
+use crate::services::currency;
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    CreateInvoiceFromEntries, Invoice, LineItemGrouping, LineItemUnit, Project, TimeEntry,
+    UpdateLineItem,
+};
+
+/// A line item computed from a group of time entries, before it is persisted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineItemDraft {
+    pub description: String,
+    pub quantity: f64,
+    pub unit_price: i64,
+    pub entry_ids: Vec<String>,
+}
+
+/// Billing rate fallback chain: project rate, then client rate, then the app-wide default.
+pub fn resolve_hourly_rate(
+    project: &Project,
+    client_rate: Option<i64>,
+    default_rate: Option<i64>,
+) -> Option<i64> {
+    project.hourly_rate.or(client_rate).or(default_rate)
+}
+
+/// The `default_hourly_rate` setting is a decimal string in major units ("150", "95.50"),
+/// interpreted in the currency being invoiced.
+fn default_hourly_rate(conn: &Connection, currency_code: &str) -> AppResult<Option<i64>> {
+    let value = settings::get_setting(conn, "default_hourly_rate")?;
+    Ok(value.and_then(|value| currency::parse_amount(&value, currency_code).ok()))
+}
+
+fn secs_to_hours(secs: i64) -> f64 {
+    (secs as f64 / 3600.0 * 100.0).round() / 100.0
+}
+
+fn format_day(entry: &TimeEntry) -> String {
+    entry.start_time.format("%b %d, %Y").to_string()
+}
+
+/// Groups time entries into line items. Entries are expected in chronological order;
+/// lines keep the order in which their first entry appears.
+pub fn group_entries(
+    entries: &[TimeEntry],
+    projects: &HashMap<String, (Project, i64)>,
+    grouping: &LineItemGrouping,
+) -> Vec<LineItemDraft> {
+    struct Group<'a> {
+        project: &'a Project,
+        rate: i64,
+        secs: i64,
+        first: &'a TimeEntry,
+        last: &'a TimeEntry,
+        entry_ids: Vec<String>,
+    }
+
+    let mut groups: Vec<Group> = Vec::new();
+    let mut index: HashMap<(String, Option<chrono::NaiveDate>), usize> = HashMap::new();
+
+    for entry in entries {
+        let Some((project, rate)) = projects.get(&entry.project_id) else {
+            continue;
+        };
+        let day = match grouping {
+            LineItemGrouping::Project => None,
+            LineItemGrouping::Day => Some(entry.start_time.date_naive()),
+        };
+        let key = (entry.project_id.clone(), day);
+        let i = *index.entry(key).or_insert_with(|| {
+            groups.push(Group {
+                project,
+                rate: *rate,
+                secs: 0,
+                first: entry,
+                last: entry,
+                entry_ids: Vec::new(),
+            });
+            groups.len() - 1
+        });
+        let group = &mut groups[i];
+        group.secs += entry.duration_secs;
+        group.last = entry;
+        group.entry_ids.push(entry.id.clone());
+    }
+
+    groups
+        .into_iter()
+        .map(|group| {
+            let description = match grouping {
+                LineItemGrouping::Day => {
+                    format!("{} - {}", format_day(group.first), group.project.name)
+                }
+                LineItemGrouping::Project => {
+                    let (first, last) = (format_day(group.first), format_day(group.last));
+                    if first == last {
+                        format!("{} ({first})", group.project.name)
+                    } else {
+                        format!("{} ({first} - {last})", group.project.name)
+                    }
+                }
+            };
+            LineItemDraft {
+                description,
+                quantity: secs_to_hours(group.secs),
+                unit_price: group.rate,
+                entry_ids: group.entry_ids,
+            }
+        })
+        .collect()
+}
+
+fn select_entries(
+    available: Vec<TimeEntry>,
+    input: &CreateInvoiceFromEntries,
+) -> AppResult<Vec<TimeEntry>> {
+    if input.entry_ids.is_none() && input.start_date.is_none() && input.end_date.is_none() {
+        return Err(AppError::Validation(
+            "Select time entries or a date range to invoice".to_string(),
+        ));
+    }
+
+    if let Some(ids) = &input.entry_ids {
+        if let Some(missing) = ids.iter().find(|id| !available.iter().any(|e| &e.id == *id)) {
+            return Err(AppError::Validation(format!(
+                "Time entry {missing} is not an uninvoiced billable entry for this client"
+            )));
+        }
+    }
+
+    let selected: Vec<TimeEntry> = available
+        .into_iter()
+        .filter(|e| input.entry_ids.as_ref().is_none_or(|ids| ids.contains(&e.id)))
+        .filter(|e| input.start_date.is_none_or(|start| e.start_time >= start))
+        .filter(|e| input.end_date.is_none_or(|end| e.start_time < end))
+        .collect();
+
+    if selected.is_empty() {
+        return Err(AppError::Validation(
+            "No uninvoiced billable time entries match the selection".to_string(),
+        ));
+    }
+    Ok(selected)
+}
+
+/// Creates a draft invoice from uninvoiced time entries. The invoice, its line items and
+/// the entry links are written in a single transaction.
+pub fn create_invoice_from_entries(
+    conn: &Connection,
+    input: CreateInvoiceFromEntries,
+) -> AppResult<Invoice> {
+    let client = clients::get_client(conn, &input.client_id)?;
+    let available = time_entries::list_uninvoiced_entries_by_client(conn, &client.id)?;
+    let selected = select_entries(available, &input)?;
+
+    let currency_code = currency::resolve(conn, client.currency.as_deref())?;
+    let default_rate = default_hourly_rate(conn, &currency_code)?;
+    let mut rates: HashMap<String, (Project, i64)> = HashMap::new();
+    for entry in &selected {
+        if rates.contains_key(&entry.project_id) {
+            continue;
+        }
+        let project = projects::get_project(conn, &entry.project_id)?;
+        let rate = resolve_hourly_rate(&project, client.hourly_rate, default_rate).ok_or_else(
+            || {
+                AppError::Validation(format!(
+                    "No hourly rate set for project '{}', its client, or the app default",
+                    project.name
+                ))
+            },
+        )?;
+        rates.insert(project.id.clone(), (project, rate));
+    }
+
+    let drafts = group_entries(&selected, &rates, &input.group_by);
+
+    with_savepoint(conn, "create_invoice_from_entries", || {
+        let invoice = invoices::create_invoice(
+            conn,
+            &client.id,
+            &input.issue_date,
+            input.due_date.as_deref(),
+            input.notes.as_deref(),
+            input.tax_rate,
+        )?;
+        for (i, draft) in drafts.iter().enumerate() {
+            let line = invoices::add_line_item(
+                conn,
+                &invoice.id,
+                &draft.description,
+                draft.quantity,
+                draft.unit_price,
+                LineItemUnit::Hours,
+                i as i32,
+            )?;
+            time_entries::link_entries_to_invoice(conn, &draft.entry_ids, &invoice.id, &line.id)?;
+        }
+        invoices::update_invoice_totals(conn, &invoice.id)
+    })
+}
+
+/// Re-derives the hours of a draft's line item from the time entries still billed on it.
+/// The line is removed once no entries are left on it.
+pub fn rebill_line_item(conn: &Connection, invoice_id: &str, line_item_id: &str) -> AppResult<()> {
+    match time_entries::billed_secs_on_line(conn, line_item_id)? {
+        Some(secs) => {
+            let hours = UpdateLineItem {
+                description: None,
+                quantity: Some(secs_to_hours(secs)),
+                unit_price: None,
+                unit: None,
+            };
+            invoices::update_line_item(conn, invoice_id, line_item_id, hours)?;
+            Ok(())
+        }
+        None => invoices::delete_line_item(conn, line_item_id, invoice_id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::clients::create_client;
+    use crate::db::init_db_in_memory;
+    use crate::db::projects::create_project;
+    use crate::models::{CreateClient, CreateManualTimeEntry, CreateProject, UpdateTimeEntry};
+    use chrono::{DateTime, TimeZone, Utc};
+
+    fn setup() -> (Connection, String, String, String) {
+        let conn = init_db_in_memory().expect("Failed to init test DB");
+        let client = create_client(
+            &conn,
+            CreateClient {
+                name: "Test".to_string(),
+                email: None,
+                company: None,
+                address: None,
+                phone: None,
+                notes: None,
+                hourly_rate: Some(10000),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let web = create_project(
+            &conn,
+            CreateProject {
+                client_id: client.id.clone(),
+                name: "Website".to_string(),
+                description: None,
+                status: None,
+                hourly_rate: Some(15000),
+                budget_hours: None,
+            },
+        )
+        .unwrap();
+        let support = create_project(
+            &conn,
+            CreateProject {
+                client_id: client.id.clone(),
+                name: "Support".to_string(),
+                description: None,
+                status: None,
+                hourly_rate: None,
+                budget_hours: None,
+            },
+        )
+        .unwrap();
+        (conn, client.id, web.id, support.id)
+    }
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, day, hour, 0, 0).unwrap()
+    }
+
+    fn log(conn: &Connection, project_id: &str, day: u32, hour: u32, hours: i64) -> TimeEntry {
+        time_entries::create_manual_time_entry(
+            conn,
+            CreateManualTimeEntry {
+                project_id: project_id.to_string(),
+                description: None,
+                start_time: at(day, hour),
+                end_time: at(day, hour) + chrono::Duration::hours(hours),
+                is_billable: true,
+            },
+        )
+        .unwrap()
+    }
+
+    fn input(client_id: &str, group_by: LineItemGrouping) -> CreateInvoiceFromEntries {
+        CreateInvoiceFromEntries {
+            client_id: client_id.to_string(),
+            entry_ids: None,
+            start_date: None,
+            end_date: None,
+            group_by,
+            issue_date: "2025-04-01T00:00:00Z".to_string(),
+            due_date: None,
+            notes: None,
+            tax_rate: None,
+        }
+    }
+
+    #[test]
+    fn test_invoice_from_entries_grouped_by_project() {
+        let (conn, client_id, web_id, support_id) = setup();
+        let a = log(&conn, &web_id, 3, 9, 2);
+        let b = log(&conn, &support_id, 3, 14, 1);
+        let c = log(&conn, &web_id, 4, 9, 3);
+
+        let invoice = create_invoice_from_entries(
+            &conn,
+            CreateInvoiceFromEntries {
+                entry_ids: Some(vec![a.id, b.id, c.id]),
+                ..input(&client_id, LineItemGrouping::Project)
+            },
+        )
+        .unwrap();
+
+        let items = invoices::get_line_items(&conn, &invoice.id).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].description, "Website (Mar 03, 2025 - Mar 04, 2025)");
+        assert_eq!(items[0].quantity, 5.0);
+        assert_eq!(items[0].unit_price, 15000);
+        // Support has no project rate and falls back to the client rate
+        assert_eq!(items[1].unit_price, 10000);
+        assert_eq!(invoice.total, 85000);
+
+        let remaining = time_entries::list_uninvoiced_entries_by_client(&conn, &client_id).unwrap();
+        assert!(remaining.is_empty());
+    }
+
+    #[test]
+    fn test_invoice_from_entries_grouped_by_day_with_range() {
+        let (conn, client_id, web_id, _) = setup();
+        log(&conn, &web_id, 3, 9, 2);
+        log(&conn, &web_id, 3, 13, 1);
+        log(&conn, &web_id, 4, 9, 1);
+        log(&conn, &web_id, 10, 9, 4);
+
+        let invoice = create_invoice_from_entries(
+            &conn,
+            CreateInvoiceFromEntries {
+                start_date: Some(at(1, 0)),
+                end_date: Some(at(8, 0)),
+                ..input(&client_id, LineItemGrouping::Day)
+            },
+        )
+        .unwrap();
+
+        let items = invoices::get_line_items(&conn, &invoice.id).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].description, "Mar 03, 2025 - Website");
+        assert_eq!(items[0].quantity, 3.0);
+        assert_eq!(invoice.total, 60000);
+
+        let remaining = time_entries::list_uninvoiced_entries_by_client(&conn, &client_id).unwrap();
+        assert_eq!(remaining.len(), 1);
+    }
+
+    #[test]
+    fn test_invoice_from_entries_rejects_invoiced_entries() {
+        let (conn, client_id, web_id, _) = setup();
+        let entry = log(&conn, &web_id, 3, 9, 2);
+        let selection = CreateInvoiceFromEntries {
+            entry_ids: Some(vec![entry.id]),
+            ..input(&client_id, LineItemGrouping::Project)
+        };
+
+        create_invoice_from_entries(&conn, selection.clone()).unwrap();
+        let result = create_invoice_from_entries(&conn, selection);
+        assert!(matches!(result, Err(AppError::Validation(_))));
+        assert_eq!(invoices::list_invoices(&conn, None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_deleting_draft_releases_entries() {
+        let (conn, client_id, web_id, _) = setup();
+        let entry = log(&conn, &web_id, 3, 9, 2);
+        let invoice = create_invoice_from_entries(
+            &conn,
+            CreateInvoiceFromEntries {
+                entry_ids: Some(vec![entry.id]),
+                ..input(&client_id, LineItemGrouping::Project)
+            },
+        )
+        .unwrap();
+
+        invoices::delete_invoice(&conn, &invoice.id).unwrap();
+        let available = time_entries::list_uninvoiced_entries_by_client(&conn, &client_id).unwrap();
+        assert_eq!(available.len(), 1);
+    }
+
+    #[test]
+    fn test_editing_billed_entries_rederives_the_draft() {
+        let (conn, client_id, web_id, support_id) = setup();
+        let a = log(&conn, &web_id, 3, 9, 2);
+        let b = log(&conn, &support_id, 3, 14, 1);
+        let c = log(&conn, &web_id, 4, 9, 3);
+        let invoice = create_invoice_from_entries(
+            &conn,
+            CreateInvoiceFromEntries {
+                entry_ids: Some(vec![a.id.clone(), b.id.clone(), c.id.clone()]),
+                ..input(&client_id, LineItemGrouping::Project)
+            },
+        )
+        .unwrap();
+        assert_eq!(invoice.total, 85000);
+
+        let edit = |id: &str, changes: UpdateTimeEntry| {
+            time_entries::update_time_entry(&conn, id, changes).unwrap();
+            invoices::get_invoice(&conn, &invoice.id).unwrap().total
+        };
+        let shorter = UpdateTimeEntry {
+            end_time: Some(at(3, 10)),
+            ..Default::default()
+        };
+        assert_eq!(edit(&a.id, shorter), 70000);
+        assert_eq!(invoices::get_line_items(&conn, &invoice.id).unwrap()[0].quantity, 4.0);
+
+        // Entries that no longer bill on their line leave the draft
+        let unbillable = UpdateTimeEntry {
+            is_billable: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(edit(&b.id, unbillable), 60000);
+        assert_eq!(invoices::get_line_items(&conn, &invoice.id).unwrap().len(), 1);
+        let moved = UpdateTimeEntry {
+            project_id: Some(support_id.clone()),
+            ..Default::default()
+        };
+        assert_eq!(edit(&c.id, moved), 15000);
+        let available = time_entries::list_uninvoiced_entries_by_client(&conn, &client_id).unwrap();
+        assert_eq!(available.len(), 1);
+        assert_eq!(available[0].id, c.id);
+
+        let line = &invoices::get_line_items(&conn, &invoice.id).unwrap()[0];
+        invoices::delete_line_item(&conn, &line.id, &invoice.id).unwrap();
+        assert_eq!(time_entries::get_time_entry(&conn, &a.id).unwrap().invoice_id, None);
+    }
+
+    #[test]
+    fn test_deleting_billed_entry_rederives_the_draft() {
+        let (conn, client_id, web_id, _) = setup();
+        let a = log(&conn, &web_id, 3, 9, 2);
+        log(&conn, &web_id, 4, 9, 3);
+        let invoice = create_invoice_from_entries(
+            &conn,
+            CreateInvoiceFromEntries {
+                start_date: Some(at(1, 0)),
+                ..input(&client_id, LineItemGrouping::Project)
+            },
+        )
+        .unwrap();
+        assert_eq!(invoice.total, 75000);
+
+        time_entries::delete_time_entry(&conn, &a.id).unwrap();
+        assert_eq!(invoices::get_invoice(&conn, &invoice.id).unwrap().total, 45000);
+        assert_eq!(invoices::get_line_items(&conn, &invoice.id).unwrap()[0].quantity, 3.0);
+    }
+}
@@ -0,0 +1,365 @@
+// Document locales: the language of printed labels and the way dates, numbers and
+// amounts are written.
+//
+// A client's own locale wins; otherwise the `default_locale` setting applies, and
+// English when that is unset. Labels come from the JSON bundles in `locales/`,
+// which are compiled in and must each define every key of `Labels`.
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::db::settings;
+use crate::error::{AppError, AppResult};
+use crate::models::{InvoiceStatus, LineItemUnit, PaymentTerms};
+use crate::services::currency;
+
+const SETTING: &str = "default_locale";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Locale {
+    pub code: &'static str,
+    /// chrono format string; numeric for locales other than English, since chrono
+    /// only prints English month names.
+    date_format: &'static str,
+    decimal_separator: char,
+    group_separator: char,
+    /// `12,50 €` rather than `€12.50`.
+    symbol_after: bool,
+    /// `19 %` rather than `19%`.
+    spaced_percent: bool,
+    bundle: &'static str,
+}
+
+// Amounts and their symbol or percent sign are kept on one line with a no-break
+// space; French groups thousands with a narrow one.
+const NBSP: char = '\u{a0}';
+const NARROW_NBSP: char = '\u{202f}';
+
+const LOCALES: &[Locale] = &[
+    Locale {
+        code: "en",
+        date_format: "%b %d, %Y",
+        decimal_separator: '.',
+        group_separator: ',',
+        symbol_after: false,
+        spaced_percent: false,
+        bundle: include_str!("../../locales/en.json"),
+    },
+    Locale {
+        code: "de",
+        date_format: "%d.%m.%Y",
+        decimal_separator: ',',
+        group_separator: '.',
+        symbol_after: true,
+        spaced_percent: true,
+        bundle: include_str!("../../locales/de.json"),
+    },
+    Locale {
+        code: "fr",
+        date_format: "%d/%m/%Y",
+        decimal_separator: ',',
+        group_separator: NARROW_NBSP,
+        symbol_after: true,
+        spaced_percent: true,
+        bundle: include_str!("../../locales/fr.json"),
+    },
+];
+
+/// Every label printed on an invoice or credit note, and the default invoice email.
+/// `{page}`, `{pages}` and `{days}` are filled in when the label is used; the email
+/// subject and body are Handlebars templates over the invoice's template data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Labels {
+    pub invoice: String,
+    pub credit_note: String,
+    pub bill_to: String,
+    pub date: String,
+    pub due_date: String,
+    pub terms: String,
+    pub status: String,
+    pub credits_invoice: String,
+    pub description: String,
+    pub quantity: String,
+    pub rate: String,
+    pub amount: String,
+    pub subtotal: String,
+    pub total: String,
+    pub notes: String,
+    pub payment_details: String,
+    pub tax: String,
+    pub tax_id: String,
+    pub iban: String,
+    pub pay_online: String,
+    pub generated_by: String,
+    pub page: String,
+    pub discount: String,
+    pub line_discounts: String,
+    pub unit_hour: String,
+    pub unit_hours: String,
+    pub unit_day: String,
+    pub unit_days: String,
+    pub unit_fixed: String,
+    pub status_draft: String,
+    pub status_sent: String,
+    pub status_partially_paid: String,
+    pub status_paid: String,
+    pub status_overdue: String,
+    pub status_cancelled: String,
+    pub status_issued: String,
+    pub terms_due_on_receipt: String,
+    pub terms_net: String,
+    pub terms_end_of_month: String,
+    pub terms_end_of_month_plus: String,
+    pub tax_exempt_note: String,
+    pub reverse_charge_note: String,
+    pub email_subject: String,
+    pub email_body: String,
+}
+
+impl Labels {
+    pub fn page(&self, page: usize, pages: usize) -> String {
+        self.page
+            .replace("{page}", &page.to_string())
+            .replace("{pages}", &pages.to_string())
+    }
+
+    pub fn unit(&self, unit: LineItemUnit, quantity: f64) -> &str {
+        let one = quantity == 1.0;
+        match unit {
+            LineItemUnit::Hours if one => &self.unit_hour,
+            LineItemUnit::Hours => &self.unit_hours,
+            LineItemUnit::Days if one => &self.unit_day,
+            LineItemUnit::Days => &self.unit_days,
+            LineItemUnit::Items => "",
+            LineItemUnit::Fixed => &self.unit_fixed,
+        }
+    }
+
+    /// Credit notes are always "issued", which isn't an invoice status.
+    pub fn status(&self, status: Option<InvoiceStatus>) -> &str {
+        match status {
+            Some(InvoiceStatus::Draft) => &self.status_draft,
+            Some(InvoiceStatus::Sent) => &self.status_sent,
+            Some(InvoiceStatus::PartiallyPaid) => &self.status_partially_paid,
+            Some(InvoiceStatus::Paid) => &self.status_paid,
+            Some(InvoiceStatus::Overdue) => &self.status_overdue,
+            Some(InvoiceStatus::Cancelled) => &self.status_cancelled,
+            None => &self.status_issued,
+        }
+    }
+
+    pub fn payment_terms(&self, terms: PaymentTerms) -> String {
+        match terms {
+            PaymentTerms::DueOnReceipt => self.terms_due_on_receipt.clone(),
+            PaymentTerms::Net(days) => self.terms_net.replace("{days}", &days.to_string()),
+            PaymentTerms::EndOfMonth(0) => self.terms_end_of_month.clone(),
+            PaymentTerms::EndOfMonth(days) => {
+                self.terms_end_of_month_plus.replace("{days}", &days.to_string())
+            }
+        }
+    }
+}
+
+pub fn lookup(code: &str) -> Option<&'static Locale> {
+    LOCALES.iter().find(|l| l.code.eq_ignore_ascii_case(code))
+}
+
+/// The code to store for a client (`"DE"` -> `"de"`); a blank code clears it.
+pub fn normalize_code(code: &str) -> AppResult<Option<String>> {
+    let code = code.trim();
+    if code.is_empty() {
+        return Ok(None);
+    }
+    lookup(code)
+        .map(|l| Some(l.code.to_string()))
+        .ok_or_else(|| AppError::Validation(format!("Unsupported locale: {code}. Use {}", codes())))
+}
+
+fn codes() -> String {
+    LOCALES.iter().map(|l| l.code).collect::<Vec<_>>().join(", ")
+}
+
+/// The locale of documents for this client.
+pub fn resolve(conn: &Connection, client_locale: Option<&str>) -> AppResult<&'static Locale> {
+    match client_locale.and_then(lookup) {
+        Some(locale) => Ok(locale),
+        None => default_locale(conn),
+    }
+}
+
+/// The app-wide default locale from settings, falling back to English, the first
+/// locale.
+pub fn default_locale(conn: &Connection) -> AppResult<&'static Locale> {
+    let configured = settings::get_setting(conn, SETTING)?;
+    Ok(configured
+        .and_then(|code| lookup(code.trim()))
+        .unwrap_or(&LOCALES[0]))
+}
+
+/// Rejects an unsupported `default_locale` setting.
+pub fn validate_setting(key: &str, value: &str) -> AppResult<()> {
+    if key == SETTING && lookup(value.trim()).is_none() {
+        return Err(AppError::Validation(format!("Invalid {key}: {value}. Use {}", codes())));
+    }
+    Ok(())
+}
+
+impl Locale {
+    pub fn labels(&self) -> AppResult<Labels> {
+        Ok(serde_json::from_str(self.bundle)?)
+    }
+
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        date.format(self.date_format).to_string()
+    }
+
+    /// Quantities and rates: as many decimals as the value has, grouped thousands.
+    pub fn format_number(&self, value: f64) -> String {
+        let digits = format!("{}", value.abs());
+        let (whole, fraction) = digits.split_once('.').unwrap_or((&digits, ""));
+        let sign = if value < 0.0 { "-" } else { "" };
+        let mut number = format!("{sign}{}", self.group(whole));
+        if !fraction.is_empty() {
+            number.push(self.decimal_separator);
+            number.push_str(fraction);
+        }
+        number
+    }
+
+    pub fn format_percent(&self, value: f64) -> String {
+        let separator = if self.spaced_percent { NBSP.to_string() } else { String::new() };
+        format!("{}{separator}%", self.format_number(value))
+    }
+
+    /// Formats an amount in minor units with the currency's number of decimals and
+    /// its symbol. Before the amount, alphabetic symbols are separated by a space
+    /// (`CHF 12.50`); after it, every symbol is (`12,50 €`).
+    pub fn format_money(&self, amount: i64, code: &str) -> String {
+        let digits = currency::minor_digits(code);
+        let factor = 10u64.pow(digits);
+        let (major, minor) = (amount.unsigned_abs() / factor, amount.unsigned_abs() % factor);
+        let mut value = self.group(&major.to_string());
+        if digits > 0 {
+            value.push(self.decimal_separator);
+            value.push_str(&format!("{minor:0width$}", width = digits as usize));
+        }
+        let sign = if amount < 0 { "-" } else { "" };
+        let symbol = currency::lookup(code).map_or(code, |c| c.symbol);
+
+        if self.symbol_after {
+            format!("{sign}{value}{NBSP}{symbol}")
+        } else if symbol.chars().last().is_some_and(|ch| ch.is_alphabetic()) {
+            format!("{sign}{symbol} {value}")
+        } else {
+            format!("{sign}{symbol}{value}")
+        }
+    }
+
+    fn group(&self, whole: &str) -> String {
+        let mut grouped = String::with_capacity(whole.len() + whole.len() / 3);
+        for (i, digit) in whole.chars().enumerate() {
+            if i > 0 && (whole.len() - i).is_multiple_of(3) {
+                grouped.push(self.group_separator);
+            }
+            grouped.push(digit);
+        }
+        grouped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db_in_memory;
+    use crate::db::settings::set_setting;
+
+    fn locale(code: &str) -> &'static Locale {
+        lookup(code).unwrap()
+    }
+
+    #[test]
+    fn test_every_bundle_defines_every_label() {
+        for locale in LOCALES {
+            let labels = locale.labels().unwrap_or_else(|e| panic!("{}: {e}", locale.code));
+            assert!(!labels.invoice.is_empty());
+            assert!(labels.page.contains("{page}") && labels.page.contains("{pages}"));
+            assert!(labels.terms_net.contains("{days}"));
+        }
+    }
+
+    #[test]
+    fn test_format_money_per_locale() {
+        let en = locale("en");
+        assert_eq!(en.format_money(123450, "USD"), "$1,234.50");
+        assert_eq!(en.format_money(9900, "eur"), "€99.00");
+        assert_eq!(en.format_money(1500, "JPY"), "¥1,500");
+        assert_eq!(en.format_money(12345, "KWD"), "KD 12.345");
+        assert_eq!(en.format_money(-2005, "GBP"), "-£20.05");
+        assert_eq!(en.format_money(1000, "XYZ"), "XYZ 10.00");
+        assert_eq!(en.format_money(123456789, "USD"), "$1,234,567.89");
+
+        let de = locale("de");
+        assert_eq!(de.format_money(123450, "EUR"), "1.234,50\u{a0}€");
+        assert_eq!(de.format_money(-2005, "CHF"), "-20,05\u{a0}CHF");
+        assert_eq!(de.format_money(150000, "JPY"), "150.000\u{a0}¥");
+
+        let fr = locale("fr");
+        assert_eq!(fr.format_money(123450, "EUR"), "1\u{202f}234,50\u{a0}€");
+        assert_eq!(fr.format_money(99, "EUR"), "0,99\u{a0}€");
+    }
+
+    #[test]
+    fn test_format_dates_and_numbers_per_locale() {
+        let date = NaiveDate::from_ymd_opt(2025, 3, 7).unwrap();
+        assert_eq!(locale("en").format_date(date), "Mar 07, 2025");
+        assert_eq!(locale("de").format_date(date), "07.03.2025");
+        assert_eq!(locale("fr").format_date(date), "07/03/2025");
+
+        assert_eq!(locale("en").format_number(1.5), "1.5");
+        assert_eq!(locale("en").format_number(2500.0), "2,500");
+        assert_eq!(locale("de").format_number(1.5), "1,5");
+        assert_eq!(locale("fr").format_number(-0.25), "-0,25");
+
+        assert_eq!(locale("en").format_percent(8.5), "8.5%");
+        assert_eq!(locale("de").format_percent(19.0), "19\u{a0}%");
+        assert_eq!(locale("fr").format_percent(5.5), "5,5\u{a0}%");
+    }
+
+    #[test]
+    fn test_translated_labels() {
+        let de = locale("de").labels().unwrap();
+        assert_eq!(de.invoice, "RECHNUNG");
+        assert_eq!(de.page(2, 3), "Seite 2 von 3");
+        assert_eq!(de.payment_terms(PaymentTerms::Net(14)), "Zahlbar innerhalb von 14 Tagen");
+        assert_eq!(de.unit(LineItemUnit::Days, 2.0), "Tage");
+
+        let fr = locale("fr").labels().unwrap();
+        assert_eq!(fr.invoice, "FACTURE");
+        assert_eq!(fr.status(Some(InvoiceStatus::Paid)), "Payée");
+        assert_eq!(fr.payment_terms(PaymentTerms::EndOfMonth(10)), "Fin de mois + 10 jours");
+
+        let en = locale("en").labels().unwrap();
+        assert_eq!(en.payment_terms(PaymentTerms::Net(30)), PaymentTerms::Net(30).label());
+        assert_eq!(en.status(None), "Issued");
+    }
+
+    #[test]
+    fn test_resolve_and_default_setting() {
+        let conn = init_db_in_memory().unwrap();
+        assert_eq!(resolve(&conn, None).unwrap().code, "en");
+        assert_eq!(resolve(&conn, Some("fr")).unwrap().code, "fr");
+
+        set_setting(&conn, SETTING, "de").unwrap();
+        assert_eq!(resolve(&conn, None).unwrap().code, "de");
+        assert_eq!(resolve(&conn, Some("fr")).unwrap().code, "fr");
+
+        assert_eq!(normalize_code(" FR ").unwrap().as_deref(), Some("fr"));
+        assert_eq!(normalize_code("").unwrap(), None);
+        assert!(matches!(normalize_code("xx"), Err(AppError::Validation(_))));
+        assert!(validate_setting(SETTING, "de").is_ok());
+        assert!(validate_setting(SETTING, "klingon").is_err());
+        assert!(validate_setting(SETTING, " ").is_err());
+        assert!(validate_setting("default_currency", "klingon").is_ok());
+    }
+}
@@ -1,6 +1,17 @@
 pub mod ai_estimator;
+pub mod branding;
+pub mod currency;
+pub mod email;
 pub mod invoice;
 pub mod licensing;
+pub mod locale;
+pub mod money;
+pub mod numbering;
+pub mod overlap;
 pub mod pdf;
+pub mod pdf_layout;
+pub mod recurring;
 pub mod stripe;
+pub mod tax;
+pub mod terms;
 pub mod timer;
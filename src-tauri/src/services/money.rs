@@ -0,0 +1,175 @@
+// Exact money arithmetic on integer minor units.
+//
+// Rounding rules:
+// - each line amount is `quantity * unit_price` rounded to a whole minor unit,
+// - the subtotal is the exact sum of the rounded line amounts,
+// - line discounts come off each line, then the invoice discount comes off what is
+//   left; a percentage discount is rounded to a whole minor unit,
+// - tax is computed once on the discounted amount and rounded to a whole minor unit,
+// - the total is `subtotal - discounts + tax`, so it always equals what the invoice prints.
+// Halves are rounded with the `rounding_mode` setting (half-up unless configured).
+use rusqlite::Connection;
+
+use crate::db::settings;
+use crate::error::{AppError, AppResult};
+use crate::models::{Discount, RoundingMode};
+
+/// Quantities (hours) and tax rates (percent) are fixed-point with four decimals.
+const FIXED_SCALE: i128 = 10_000;
+
+fn to_fixed(value: f64) -> i128 {
+    (value * FIXED_SCALE as f64).round() as i128
+}
+
+/// Divides and rounds to the nearest integer, resolving exact halves with `mode`.
+fn div_round(numerator: i128, denominator: i128, mode: RoundingMode) -> i64 {
+    let quotient = numerator / denominator;
+    let twice_remainder = (numerator % denominator).abs() * 2;
+    let away = match twice_remainder.cmp(&denominator) {
+        std::cmp::Ordering::Less => false,
+        std::cmp::Ordering::Greater => true,
+        std::cmp::Ordering::Equal => match mode {
+            RoundingMode::HalfUp => true,
+            RoundingMode::HalfEven => quotient % 2 != 0,
+        },
+    };
+    let rounded = if away {
+        quotient + numerator.signum()
+    } else {
+        quotient
+    };
+    rounded as i64
+}
+
+const ROUNDING_SETTING: &str = "rounding_mode";
+
+pub fn rounding_mode(conn: &Connection) -> AppResult<RoundingMode> {
+    let configured = settings::get_setting(conn, ROUNDING_SETTING)?;
+    Ok(configured
+        .and_then(|mode| RoundingMode::from_str(mode.trim()))
+        .unwrap_or_default())
+}
+
+/// Rejects an unknown `rounding_mode` setting.
+pub fn validate_setting(key: &str, value: &str) -> AppResult<()> {
+    if key == ROUNDING_SETTING && RoundingMode::from_str(value.trim()).is_none() {
+        return Err(AppError::Validation(format!(
+            "Invalid {key}: {value}. Use half_up or half_even"
+        )));
+    }
+    Ok(())
+}
+
+pub fn line_amount(quantity: f64, unit_price: i64, mode: RoundingMode) -> i64 {
+    div_round(to_fixed(quantity) * unit_price as i128, FIXED_SCALE, mode)
+}
+
+pub fn tax_amount(subtotal: i64, rate_percent: f64, mode: RoundingMode) -> i64 {
+    div_round(subtotal as i128 * to_fixed(rate_percent), FIXED_SCALE * 100, mode)
+}
+
+/// The discount on `base`, never more than `base` itself and never negative.
+pub fn discount_amount(base: i64, discount: Option<Discount>, mode: RoundingMode) -> i64 {
+    let amount = match discount {
+        None => 0,
+        Some(Discount::Percent(percent)) => tax_amount(base, percent, mode),
+        Some(Discount::Fixed(amount)) => amount,
+    };
+    amount.clamp(0, base.max(0))
+}
+
+/// Splits `amount` across `weights` in proportion, so the parts always sum to
+/// `amount`. Leftover minor units go to the largest remainders. Non-positive
+/// weights get nothing. A negative amount is split like its magnitude, with the
+/// sign applied to each part.
+pub fn allocate(amount: i64, weights: &[i64]) -> Vec<i64> {
+    let total: i128 = weights.iter().map(|w| (*w).max(0) as i128).sum();
+    if total == 0 {
+        return vec![0; weights.len()];
+    }
+    let magnitude = amount.unsigned_abs() as i128;
+    let mut parts = Vec::with_capacity(weights.len());
+    let mut remainders = Vec::with_capacity(weights.len());
+    for (i, weight) in weights.iter().enumerate() {
+        let share = magnitude * (*weight).max(0) as i128;
+        parts.push(share / total);
+        remainders.push((share % total, i));
+    }
+    let leftover = magnitude - parts.iter().sum::<i128>();
+    remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    for (_, i) in remainders.into_iter().take(leftover as usize) {
+        parts[i] += 1;
+    }
+    let sign = amount.signum() as i128;
+    parts.into_iter().map(|part| (sign * part) as i64).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db_in_memory;
+
+    #[test]
+    fn test_line_amount_is_exact() {
+        // 1.15 * 10.50 is 12.074999... in f64 and used to come out a cent short
+        assert_eq!(line_amount(1.15, 1050, RoundingMode::HalfUp), 1208);
+        assert_eq!(line_amount(2.5, 15000, RoundingMode::HalfUp), 37500);
+        assert_eq!(line_amount(0.33, 1000, RoundingMode::HalfUp), 330);
+        assert_eq!(line_amount(-1.0, 1999, RoundingMode::HalfUp), -1999);
+    }
+
+    #[test]
+    fn test_half_up_and_half_even() {
+        // 0.5h at 10.25 = 5.125
+        assert_eq!(line_amount(0.5, 1025, RoundingMode::HalfUp), 513);
+        assert_eq!(line_amount(0.5, 1025, RoundingMode::HalfEven), 512);
+        // 0.5h at 10.35 = 5.175
+        assert_eq!(line_amount(0.5, 1035, RoundingMode::HalfEven), 518);
+        assert_eq!(line_amount(-0.5, 1025, RoundingMode::HalfUp), -513);
+        assert_eq!(line_amount(-0.5, 1025, RoundingMode::HalfEven), -512);
+    }
+
+    #[test]
+    fn test_tax_amount() {
+        assert_eq!(tax_amount(210000, 10.0, RoundingMode::HalfUp), 21000);
+        // 8.25% of 19.99 = 1.649175
+        assert_eq!(tax_amount(1999, 8.25, RoundingMode::HalfUp), 165);
+        // 5% of 0.50 = 0.025
+        assert_eq!(tax_amount(50, 5.0, RoundingMode::HalfUp), 3);
+        assert_eq!(tax_amount(50, 5.0, RoundingMode::HalfEven), 2);
+    }
+
+    #[test]
+    fn test_discount_amount() {
+        assert_eq!(discount_amount(10000, None, RoundingMode::HalfUp), 0);
+        // 12.5% of 0.99 = 0.12375
+        assert_eq!(discount_amount(99, Some(Discount::Percent(12.5)), RoundingMode::HalfUp), 12);
+        assert_eq!(discount_amount(10000, Some(Discount::Fixed(2500)), RoundingMode::HalfUp), 2500);
+        // A fixed discount can't take a line below zero
+        assert_eq!(discount_amount(1000, Some(Discount::Fixed(2500)), RoundingMode::HalfUp), 1000);
+        assert_eq!(discount_amount(-1000, Some(Discount::Percent(10.0)), RoundingMode::HalfUp), 0);
+    }
+
+    #[test]
+    fn test_allocate_sums_exactly() {
+        assert_eq!(allocate(100, &[1, 1, 1]), vec![34, 33, 33]);
+        assert_eq!(allocate(1000, &[30000, 10000]), vec![750, 250]);
+        assert_eq!(allocate(500, &[10000, -2000, 0]), vec![500, 0, 0]);
+        assert_eq!(allocate(500, &[0, 0]), vec![0, 0]);
+        assert_eq!(allocate(-100, &[1, 1, 1]), vec![-34, -33, -33]);
+        assert_eq!(allocate(-1, &[1, 1]), vec![-1, 0]);
+        assert_eq!(allocate(i64::MIN, &[1]), vec![i64::MIN]);
+    }
+
+    #[test]
+    fn test_rounding_mode_setting() {
+        let conn = init_db_in_memory().unwrap();
+        assert_eq!(rounding_mode(&conn).unwrap(), RoundingMode::HalfUp);
+        settings::set_setting(&conn, "rounding_mode", "half_even").unwrap();
+        assert_eq!(rounding_mode(&conn).unwrap(), RoundingMode::HalfEven);
+
+        assert!(validate_setting(ROUNDING_SETTING, "half_up").is_ok());
+        assert!(validate_setting(ROUNDING_SETTING, "bankers").is_err());
+        assert!(validate_setting("other", "bankers").is_ok());
+    }
+}
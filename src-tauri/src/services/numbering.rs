@@ -0,0 +1,369 @@
+// Gap-free document numbering from persistent counters.
+//
+// Numbers are rendered from a pattern setting such as `INV-{YYYY}-{SEQ:3}`:
+// - `{YYYY}`, `{YY}`, `{MM}` come from the document's issue date,
+// - `{CLIENT}` is the client's invoice prefix (empty if the client has none),
+// - `{SEQ}` is the counter, `{SEQ:n}` zero-pads it to n digits.
+// Each counter is keyed by document kind, plus the client prefix when the pattern
+// uses `{CLIENT}`, plus the year when the sequence resets yearly. The counter is
+// bumped by a single upsert on the caller's connection, so it commits or rolls back
+// together with the document insert.
+use chrono::{Datelike, NaiveDate};
+use rusqlite::{params, Connection};
+
+use crate::db::settings;
+use crate::error::{AppError, AppResult};
+use crate::models::SequenceReset;
+
+const MAX_PADDING: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocumentKind {
+    Invoice,
+    CreditNote,
+}
+
+impl DocumentKind {
+    fn key(&self) -> &'static str {
+        match self {
+            DocumentKind::Invoice => "invoice",
+            DocumentKind::CreditNote => "credit_note",
+        }
+    }
+
+    fn default_pattern(&self) -> &'static str {
+        match self {
+            DocumentKind::Invoice => "INV-{YYYY}-{SEQ:3}",
+            DocumentKind::CreditNote => "CN-{YYYY}-{SEQ:3}",
+        }
+    }
+
+    pub fn pattern_setting(&self) -> &'static str {
+        match self {
+            DocumentKind::Invoice => "invoice_number_pattern",
+            DocumentKind::CreditNote => "credit_note_number_pattern",
+        }
+    }
+
+    pub fn reset_setting(&self) -> &'static str {
+        match self {
+            DocumentKind::Invoice => "invoice_number_reset",
+            DocumentKind::CreditNote => "credit_note_number_reset",
+        }
+    }
+
+    /// The table and column holding numbers already issued.
+    fn issued_numbers(&self) -> (&'static str, &'static str) {
+        match self {
+            DocumentKind::Invoice => ("invoices", "invoice_number"),
+            DocumentKind::CreditNote => ("credit_notes", "credit_note_number"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Literal(String),
+    Year,
+    ShortYear,
+    Month,
+    Client,
+    Seq { width: usize },
+}
+
+/// Numbers name exported files, so they can't hold path separators or `..`.
+fn is_path_like(text: &str) -> bool {
+    text.contains(['/', '\\']) || text.contains("..")
+}
+
+/// Rejects a client prefix that would put a path into the numbers it appears in.
+pub fn validate_client_prefix(prefix: &str) -> AppResult<()> {
+    if is_path_like(prefix) {
+        return Err(AppError::Validation(format!(
+            "Invalid invoice prefix \"{prefix}\": it can't contain '/', '\\' or '..'"
+        )));
+    }
+    Ok(())
+}
+
+fn parse_pattern(pattern: &str) -> AppResult<Vec<Token>> {
+    let invalid = |reason: &str| {
+        AppError::Validation(format!("Invalid number pattern \"{pattern}\": {reason}"))
+    };
+    let mut tokens = Vec::new();
+    let mut rest = pattern;
+    let literal = |text: &str| {
+        if is_path_like(text) {
+            return Err(invalid("it can't contain '/', '\\' or '..'"));
+        }
+        Ok(Token::Literal(text.to_string()))
+    };
+    while let Some(open) = rest.find('{') {
+        if open > 0 {
+            tokens.push(literal(&rest[..open])?);
+        }
+        let close = rest[open..]
+            .find('}')
+            .ok_or_else(|| invalid("unclosed '{'"))?;
+        let token = match &rest[open + 1..open + close] {
+            "YYYY" => Token::Year,
+            "YY" => Token::ShortYear,
+            "MM" => Token::Month,
+            "CLIENT" => Token::Client,
+            "SEQ" => Token::Seq { width: 0 },
+            other => match other.strip_prefix("SEQ:").map(str::parse::<usize>) {
+                Some(Ok(width)) if (1..=MAX_PADDING).contains(&width) => Token::Seq { width },
+                _ => return Err(invalid(&format!("unknown token {{{other}}}"))),
+            },
+        };
+        tokens.push(token);
+        rest = &rest[open + close + 1..];
+    }
+    if rest.contains('}') {
+        return Err(invalid("unmatched '}'"));
+    }
+    if !rest.is_empty() {
+        tokens.push(literal(rest)?);
+    }
+
+    match tokens
+        .iter()
+        .filter(|t| matches!(t, Token::Seq { .. }))
+        .count()
+    {
+        1 => Ok(tokens),
+        0 => Err(invalid("it needs a {SEQ} token")),
+        _ => Err(invalid("it can only contain one {SEQ} token")),
+    }
+}
+
+/// Checks a pattern and reset policy together: a yearly sequence restarts at 1 each
+/// year, so its numbers are only unique if the pattern includes the year.
+fn validate(pattern: &str, reset: SequenceReset) -> AppResult<Vec<Token>> {
+    let tokens = parse_pattern(pattern)?;
+    if reset == SequenceReset::Yearly
+        && !tokens
+            .iter()
+            .any(|t| matches!(t, Token::Year | Token::ShortYear))
+    {
+        return Err(AppError::Validation(format!(
+            "Number pattern \"{pattern}\" resets yearly, so it needs a {{YYYY}} or {{YY}} token"
+        )));
+    }
+    Ok(tokens)
+}
+
+fn render(tokens: &[Token], date: NaiveDate, client_prefix: &str, seq: i64) -> String {
+    tokens
+        .iter()
+        .map(|token| match token {
+            Token::Literal(text) => text.clone(),
+            Token::Year => format!("{:04}", date.year()),
+            Token::ShortYear => format!("{:02}", date.year() % 100),
+            Token::Month => format!("{:02}", date.month()),
+            Token::Client => client_prefix.to_string(),
+            Token::Seq { width } => format!("{seq:0width$}"),
+        })
+        .collect()
+}
+
+fn configured(conn: &Connection, kind: DocumentKind) -> AppResult<(String, SequenceReset)> {
+    let pattern = settings::get_setting(conn, kind.pattern_setting())?
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| kind.default_pattern().to_string());
+    let reset = match settings::get_setting(conn, kind.reset_setting())? {
+        Some(value) => SequenceReset::from_str(value.trim()).ok_or_else(|| {
+            AppError::Validation(format!("Invalid {}: {value}", kind.reset_setting()))
+        })?,
+        None => SequenceReset::default(),
+    };
+    Ok((pattern, reset))
+}
+
+fn sequence_key(
+    kind: DocumentKind,
+    tokens: &[Token],
+    reset: SequenceReset,
+    client_prefix: &str,
+    date: NaiveDate,
+) -> String {
+    let mut key = kind.key().to_string();
+    if tokens.contains(&Token::Client) {
+        key.push(':');
+        key.push_str(client_prefix);
+    }
+    if reset == SequenceReset::Yearly {
+        key.push_str(&format!(":{:04}", date.year()));
+    }
+    key
+}
+
+/// Takes the next number for a document issued on `date`. Call this on the same
+/// connection (and transaction, if any) that inserts the document.
+pub fn next_number(
+    conn: &Connection,
+    kind: DocumentKind,
+    client_prefix: Option<&str>,
+    date: NaiveDate,
+) -> AppResult<String> {
+    let (pattern, reset) = configured(conn, kind)?;
+    let tokens = validate(&pattern, reset)?;
+    let client_prefix = client_prefix.unwrap_or_default();
+    let key = sequence_key(kind, &tokens, reset, client_prefix, date);
+    let (table, column) = kind.issued_numbers();
+
+    // A number can only already exist if the pattern was changed to overlap an older
+    // one; skip past it rather than fail the insert.
+    loop {
+        let seq: i64 = conn.query_row(
+            "INSERT INTO number_sequences (key, last_value) VALUES (?1, 1)
+             ON CONFLICT(key) DO UPDATE SET last_value = last_value + 1
+             RETURNING last_value",
+            params![key],
+            |row| row.get(0),
+        )?;
+        let number = render(&tokens, date, client_prefix, seq);
+        let taken: bool = conn.query_row(
+            &format!("SELECT EXISTS(SELECT 1 FROM {table} WHERE {column} = ?1)"),
+            params![number],
+            |row| row.get(0),
+        )?;
+        if !taken {
+            return Ok(number);
+        }
+    }
+}
+
+/// The number the next document would get, without taking it.
+pub fn preview_number(
+    conn: &Connection,
+    kind: DocumentKind,
+    client_prefix: Option<&str>,
+    date: NaiveDate,
+) -> AppResult<String> {
+    let (pattern, reset) = configured(conn, kind)?;
+    let tokens = validate(&pattern, reset)?;
+    let client_prefix = client_prefix.unwrap_or_default();
+    let key = sequence_key(kind, &tokens, reset, client_prefix, date);
+    let last: i64 = conn
+        .query_row(
+            "SELECT last_value FROM number_sequences WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        )
+        .unwrap_or(0);
+    Ok(render(&tokens, date, client_prefix, last + 1))
+}
+
+/// Rejects a numbering setting that would make number generation fail, checking it
+/// against the current value of its counterpart (pattern vs. reset policy).
+pub fn validate_setting(conn: &Connection, key: &str, value: &str) -> AppResult<()> {
+    for kind in [DocumentKind::Invoice, DocumentKind::CreditNote] {
+        let (pattern, reset) = configured(conn, kind)
+            .unwrap_or_else(|_| (kind.default_pattern().to_string(), SequenceReset::default()));
+        if key == kind.pattern_setting() {
+            return validate(value.trim(), reset).map(|_| ());
+        }
+        if key == kind.reset_setting() {
+            let reset = SequenceReset::from_str(value.trim())
+                .ok_or_else(|| AppError::Validation(format!("Invalid {key}: {value}")))?;
+            return validate(&pattern, reset).map(|_| ());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db_in_memory;
+    use crate::db::settings::set_setting;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_default_pattern_resets_yearly() {
+        let conn = init_db_in_memory().unwrap();
+        let next = |d| next_number(&conn, DocumentKind::Invoice, None, d).unwrap();
+
+        assert_eq!(next(date(2025, 12, 30)), "INV-2025-001");
+        assert_eq!(next(date(2025, 12, 31)), "INV-2025-002");
+        assert_eq!(next(date(2026, 1, 2)), "INV-2026-001");
+        // Back-dated documents continue their own year's sequence
+        assert_eq!(next(date(2025, 6, 1)), "INV-2025-003");
+        assert_eq!(
+            next_number(&conn, DocumentKind::CreditNote, None, date(2026, 1, 2)).unwrap(),
+            "CN-2026-001"
+        );
+    }
+
+    #[test]
+    fn test_custom_pattern_with_client_prefix_and_no_reset() {
+        let conn = init_db_in_memory().unwrap();
+        set_setting(&conn, "invoice_number_pattern", "{CLIENT}-{YY}{MM}-{SEQ:4}").unwrap();
+        set_setting(&conn, "invoice_number_reset", "never").unwrap();
+        let next = |prefix, d| next_number(&conn, DocumentKind::Invoice, prefix, d).unwrap();
+
+        assert_eq!(next(Some("ACME"), date(2025, 3, 9)), "ACME-2503-0001");
+        assert_eq!(next(Some("BETA"), date(2025, 3, 9)), "BETA-2503-0001");
+        assert_eq!(next(Some("ACME"), date(2026, 1, 5)), "ACME-2601-0002");
+        assert_eq!(
+            preview_number(&conn, DocumentKind::Invoice, Some("ACME"), date(2026, 2, 1)).unwrap(),
+            "ACME-2602-0003"
+        );
+        assert_eq!(next(Some("ACME"), date(2026, 2, 1)), "ACME-2602-0003");
+    }
+
+    #[test]
+    fn test_counter_rolls_back_with_the_transaction() {
+        let conn = init_db_in_memory().unwrap();
+        {
+            let tx = conn.unchecked_transaction().unwrap();
+            next_number(&tx, DocumentKind::Invoice, None, date(2025, 1, 1)).unwrap();
+            // dropped without commit
+        }
+        assert_eq!(
+            next_number(&conn, DocumentKind::Invoice, None, date(2025, 1, 1)).unwrap(),
+            "INV-2025-001"
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_patterns() {
+        let conn = init_db_in_memory().unwrap();
+        for pattern in [
+            "INV-{YYYY}",
+            "{YYYY}-{SEQ}-{SEQ}",
+            "{YYYY}-{NUM}",
+            "{YYYY}-{SEQ",
+            "{SEQ:0}-{YYYY}",
+            "INV/{YYYY}/{SEQ}",
+            "..\\{YYYY}-{SEQ}",
+            "{YYYY}..{SEQ}",
+        ] {
+            assert!(
+                matches!(
+                    validate_setting(&conn, "invoice_number_pattern", pattern),
+                    Err(AppError::Validation(_))
+                ),
+                "{pattern} should be rejected"
+            );
+        }
+        // Without a year token the sequence must never reset
+        assert!(validate_setting(&conn, "invoice_number_pattern", "INV-{SEQ:5}").is_err());
+        set_setting(&conn, "invoice_number_reset", "never").unwrap();
+        assert!(validate_setting(&conn, "invoice_number_pattern", "INV-{SEQ:5}").is_ok());
+        assert!(validate_setting(&conn, "invoice_number_reset", "monthly").is_err());
+        assert!(validate_setting(&conn, "default_currency", "EUR").is_ok());
+    }
+
+    #[test]
+    fn test_rejects_path_like_client_prefixes() {
+        assert!(validate_client_prefix("ACME.CO").is_ok());
+        for prefix in ["A/B", "A\\B", "..", "A..B"] {
+            assert!(validate_client_prefix(prefix).is_err(), "{prefix} should be rejected");
+        }
+    }
+}
@@ -0,0 +1,121 @@
+// What happens when a time entry covers minutes another entry already covers.
+//
+// The `time_overlap_policy` setting picks one of: `reject` (the default) refuses
+// the save, `warn` saves it and reports the overlap, and `trim` shortens the new
+// span to the free time around the existing entries.
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+
+use crate::db::settings;
+use crate::error::{AppError, AppResult};
+
+const SETTING: &str = "time_overlap_policy";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverlapPolicy {
+    Reject,
+    Warn,
+    Trim,
+}
+
+impl OverlapPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OverlapPolicy::Reject => "reject",
+            OverlapPolicy::Warn => "warn",
+            OverlapPolicy::Trim => "trim",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "reject" => Some(OverlapPolicy::Reject),
+            "warn" => Some(OverlapPolicy::Warn),
+            "trim" => Some(OverlapPolicy::Trim),
+            _ => None,
+        }
+    }
+}
+
+pub fn policy(conn: &Connection) -> AppResult<OverlapPolicy> {
+    let configured = settings::get_setting(conn, SETTING)?;
+    Ok(configured
+        .and_then(|policy| OverlapPolicy::from_str(policy.trim()))
+        .unwrap_or(OverlapPolicy::Reject))
+}
+
+/// Shrinks `start..end` until it no longer overlaps any of `taken`. Only the ends
+/// move, so a span that would have to be split around an entry is refused, as is
+/// one with no free time left.
+pub fn trim(
+    mut start: DateTime<Utc>,
+    mut end: DateTime<Utc>,
+    taken: &[(DateTime<Utc>, DateTime<Utc>)],
+) -> AppResult<(DateTime<Utc>, DateTime<Utc>)> {
+    loop {
+        let Some(&(taken_start, taken_end)) =
+            taken.iter().find(|(s, e)| *s < end && *e > start)
+        else {
+            return Ok((start, end));
+        };
+        if taken_start <= start {
+            start = taken_end;
+        } else if taken_end >= end {
+            end = taken_start;
+        } else {
+            return Err(AppError::Validation(
+                "Time entry would have to be split around an existing entry".to_string(),
+            ));
+        }
+        if end <= start {
+            return Err(AppError::Validation(
+                "Time entry is fully covered by existing entries".to_string(),
+            ));
+        }
+    }
+}
+
+/// Rejects an unknown `time_overlap_policy` setting.
+pub fn validate_setting(key: &str, value: &str) -> AppResult<()> {
+    if key == SETTING && OverlapPolicy::from_str(value.trim()).is_none() {
+        return Err(AppError::Validation(format!(
+            "Invalid {key}: {value}. Use reject, warn or trim"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db_in_memory;
+    use crate::db::settings::set_setting;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, 3, hour, min, 0).unwrap()
+    }
+
+    #[test]
+    fn test_trim() {
+        let taken = [(at(9, 0), at(10, 0)), (at(12, 0), at(13, 0))];
+        assert_eq!(trim(at(10, 0), at(12, 0), &taken).unwrap(), (at(10, 0), at(12, 0)));
+        assert_eq!(trim(at(9, 30), at(11, 0), &taken).unwrap(), (at(10, 0), at(11, 0)));
+        assert_eq!(trim(at(11, 0), at(12, 30), &taken).unwrap(), (at(11, 0), at(12, 0)));
+        assert_eq!(trim(at(9, 30), at(12, 30), &taken).unwrap(), (at(10, 0), at(12, 0)));
+        assert!(matches!(trim(at(8, 0), at(11, 0), &taken), Err(AppError::Validation(_))));
+        assert!(matches!(trim(at(9, 15), at(9, 45), &taken), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn test_policy_setting() {
+        let conn = init_db_in_memory().unwrap();
+        assert_eq!(policy(&conn).unwrap(), OverlapPolicy::Reject);
+        set_setting(&conn, SETTING, "trim").unwrap();
+        assert_eq!(policy(&conn).unwrap(), OverlapPolicy::Trim);
+
+        assert!(validate_setting(SETTING, "warn").is_ok());
+        assert!(validate_setting(SETTING, "merge").is_err());
+        assert!(validate_setting("other", "merge").is_ok());
+    }
+}
@@ -1,119 +1,510 @@
 use handlebars::Handlebars;
 use rusqlite::Connection;
 use serde::Serialize;
+use std::collections::HashMap;
 use std::path::PathBuf;
 
-use crate::db::{clients, invoices};
+use crate::db::{business, clients, credit_notes, invoices, taxes, templates};
 use crate::error::{AppError, AppResult};
+use crate::models::{
+    BusinessProfile, Discount, Invoice, InvoiceTax, InvoiceTemplate, LineItemUnit, PageSize,
+    PaymentTerms,
+};
+use crate::services::locale::{self, Labels, Locale};
+use crate::services::{branding, pdf_layout, tax};
+
+/// Used when neither the invoice, its client nor the default names a template.
+pub const BUILTIN_TEMPLATE: &str = include_str!("../../templates/invoice.html");
+
+#[derive(Debug, Serialize)]
+pub(crate) struct InvoiceTemplateData {
+    /// `#rrggbb` from the template, or the built-in blue.
+    pub brand_color: String,
+    /// PNG data URI from the template, or else the business profile's logo file.
+    pub logo: Option<String>,
+    /// The client's locale code, e.g. "de", for `<html lang>`.
+    pub locale: String,
+    /// Translated labels; templates use them as `{{labels.bill_to}}` and so on.
+    pub labels: Labels,
+    #[serde(flatten)]
+    pub business: BusinessData,
+    /// "INVOICE" or "CREDIT NOTE", translated.
+    pub document_title: String,
+    /// The document's own number, so a credit note's `CN-` number.
+    pub invoice_number: String,
+    pub client_name: String,
+    pub client_company: String,
+    pub client_email: String,
+    pub client_address: String,
+    pub issue_date: String,
+    /// Empty for credit notes.
+    pub due_date: String,
+    /// e.g. "Net 30", when the due date came from payment terms.
+    pub payment_terms: Option<String>,
+    /// For credit notes, the invoice being credited.
+    pub credited_invoice_number: Option<String>,
+    /// e.g. "partially_paid"; suits CSS classes.
+    pub status: String,
+    /// The status in the document's language.
+    pub status_label: String,
+    pub currency: String,
+    pub line_items: Vec<LineItemData>,
+    pub subtotal: String,
+    /// Line discounts, then the invoice discount, as negative amounts.
+    pub discounts: Vec<TotalRowData>,
+    /// One row per tax, e.g. "GST (5%)".
+    pub taxes: Vec<TotalRowData>,
+    /// Exemption or reverse-charge wording required for the client.
+    pub tax_note: Option<String>,
+    pub total: String,
+    pub notes: Option<String>,
+    pub payment_link: Option<String>,
+}
+
+/// The issuing business; templates see these fields as `business_name`,
+/// `business_email` and so on.
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct BusinessData {
+    #[serde(rename = "business_name")]
+    pub name: String,
+    #[serde(rename = "business_email")]
+    pub email: String,
+    /// The address lines joined with commas.
+    #[serde(rename = "business_address")]
+    pub address: String,
+    #[serde(rename = "business_address_lines")]
+    pub address_lines: Vec<String>,
+    #[serde(rename = "business_phone")]
+    pub phone: String,
+    #[serde(rename = "business_website")]
+    pub website: String,
+    #[serde(rename = "business_tax_id")]
+    pub tax_id: String,
+    /// Grouped in fours.
+    #[serde(rename = "business_iban")]
+    pub iban: String,
+    #[serde(rename = "business_bank_details")]
+    pub bank_details: String,
+}
+
+impl From<BusinessProfile> for BusinessData {
+    fn from(profile: BusinessProfile) -> Self {
+        BusinessData {
+            name: profile.legal_name,
+            email: profile.email.unwrap_or_default(),
+            address: profile.address_lines.join(", "),
+            address_lines: profile.address_lines,
+            phone: profile.phone.unwrap_or_default(),
+            website: profile.website.unwrap_or_default(),
+            tax_id: profile.tax_id.unwrap_or_default(),
+            iban: profile.iban.as_deref().map(business::format_iban).unwrap_or_default(),
+            bank_details: profile.bank_details.unwrap_or_default(),
+        }
+    }
+}
 
 #[derive(Debug, Serialize)]
-struct InvoiceTemplateData {
-    business_name: String,
-    business_email: String,
-    business_address: String,
-    invoice_number: String,
-    client_name: String,
-    client_company: String,
-    client_email: String,
-    client_address: String,
-    issue_date: String,
-    due_date: String,
-    status: String,
-    line_items: Vec<LineItemData>,
-    subtotal: String,
-    tax_rate: Option<f64>,
-    tax_amount: String,
-    total: String,
-    notes: Option<String>,
-    payment_link: Option<String>,
+pub(crate) struct LineItemData {
+    pub description: String,
+    pub quantity: String,
+    /// e.g. "hrs" or "days"; empty for a plain count.
+    pub unit: String,
+    pub unit_price: String,
+    pub amount: String,
+    /// e.g. "Discount (10%): -$5.00"; empty if the line has no discount.
+    pub discount: String,
+    /// Names of the taxes on this line, comma-separated; empty if none are assigned.
+    pub tax_names: String,
 }
 
 #[derive(Debug, Serialize)]
-struct LineItemData {
-    description: String,
-    quantity: String,
-    unit_price: String,
-    amount: String,
+pub(crate) struct TotalRowData {
+    pub label: String,
+    pub amount: String,
 }
 
-fn format_money(amount: f64) -> String {
-    format!("{:.2}", amount)
+fn tax_lines(taxes: &[InvoiceTax], currency: &str, locale: &Locale) -> Vec<TotalRowData> {
+    taxes
+        .iter()
+        .map(|t| TotalRowData {
+            label: format!("{} ({})", t.name, locale.format_percent(t.rate)),
+            amount: locale.format_money(t.amount, currency),
+        })
+        .collect()
 }
 
-fn format_date_short(date_str: &str) -> String {
-    // Parse ISO date and format nicely
-    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(date_str) {
-        dt.format("%b %d, %Y").to_string()
-    } else {
-        date_str.to_string()
+fn discount_label(discount: Discount, labels: &Labels, locale: &Locale) -> String {
+    match discount {
+        Discount::Percent(percent) => {
+            format!("{} ({})", labels.discount, locale.format_percent(percent))
+        }
+        Discount::Fixed(_) => labels.discount.clone(),
     }
 }
 
-pub fn render_invoice_html(
+fn brand_color(template: Option<&InvoiceTemplate>) -> String {
+    template
+        .and_then(|t| t.brand_color.clone())
+        .unwrap_or_else(|| branding::DEFAULT_BRAND_COLOR.to_string())
+}
+
+/// The business profile with the invoice's override applied.
+fn issuing_business(conn: &Connection, invoice: &Invoice) -> AppResult<BusinessProfile> {
+    let profile = business::get_business_profile(conn)?;
+    Ok(match &invoice.business_override {
+        Some(details) => business::with_details(profile, details),
+        None => profile,
+    })
+}
+
+/// The template's logo, else the profile's. A logo file that has gone missing since
+/// it was saved is skipped rather than failing the export.
+fn logo(template: Option<&InvoiceTemplate>, profile: &BusinessProfile) -> Option<String> {
+    template.and_then(|t| t.logo.clone()).or_else(|| {
+        let path = profile.logo_path.as_deref()?;
+        branding::logo_from_file(std::path::Path::new(path)).ok()
+    })
+}
+
+pub(crate) fn build_template_data(
     conn: &Connection,
     invoice_id: &str,
-    business_name: &str,
-    business_email: &str,
-    business_address: &str,
-) -> AppResult<String> {
+) -> AppResult<(InvoiceTemplateData, Option<InvoiceTemplate>)> {
     let invoice = invoices::get_invoice(conn, invoice_id)?;
     let client = clients::get_client(conn, &invoice.client_id)?;
+    let template = templates::resolve_for_invoice(conn, invoice_id)?;
+    let business = issuing_business(conn, &invoice)?;
+    let locale = locale::resolve(conn, client.locale.as_deref())?;
+    let labels = locale.labels()?;
+    let money = |amount: i64| locale.format_money(amount, &invoice.currency);
     let line_items = invoices::get_line_items(conn, invoice_id)?;
-
-    let template_str = include_str!("../../templates/invoice.html");
-    let mut hbs = Handlebars::new();
-    hbs.register_template_string("invoice", template_str)
-        .map_err(|e| AppError::Template(handlebars::RenderError::from(e)))?;
+    let tax_names: HashMap<String, String> = taxes::list_taxes(conn, true)?
+        .into_iter()
+        .map(|t| (t.id, t.name))
+        .collect();
 
     let data = InvoiceTemplateData {
-        business_name: business_name.to_string(),
-        business_email: business_email.to_string(),
-        business_address: business_address.to_string(),
+        brand_color: brand_color(template.as_ref()),
+        logo: logo(template.as_ref(), &business),
+        locale: locale.code.to_string(),
+        business: business.into(),
+        document_title: labels.invoice.clone(),
         invoice_number: invoice.invoice_number,
         client_name: client.name,
         client_company: client.company.unwrap_or_default(),
         client_email: client.email.unwrap_or_default(),
         client_address: client.address.unwrap_or_default(),
-        issue_date: format_date_short(&invoice.issue_date.to_rfc3339()),
-        due_date: format_date_short(&invoice.due_date.to_rfc3339()),
+        issue_date: locale.format_date(invoice.issue_date.date_naive()),
+        due_date: locale.format_date(invoice.due_date.date_naive()),
+        payment_terms: invoice.payment_terms.map(|terms| labels.payment_terms(terms)),
+        credited_invoice_number: None,
         status: invoice.status.as_str().to_string(),
+        status_label: labels.status(Some(invoice.status)).to_string(),
+        currency: invoice.currency.clone(),
         line_items: line_items
             .into_iter()
             .map(|li| LineItemData {
                 description: li.description,
-                quantity: format!("{}", li.quantity),
-                unit_price: format_money(li.unit_price),
-                amount: format_money(li.amount),
+                quantity: locale.format_number(li.quantity),
+                unit: labels.unit(li.unit, li.quantity).to_string(),
+                unit_price: money(li.unit_price),
+                amount: money(li.amount),
+                discount: match li.discount {
+                    Some(discount) if li.discount_amount != 0 => format!(
+                        "{}: {}",
+                        discount_label(discount, &labels, locale),
+                        money(-li.discount_amount)
+                    ),
+                    _ => String::new(),
+                },
+                tax_names: li
+                    .tax_ids
+                    .iter()
+                    .filter_map(|id| tax_names.get(id).cloned())
+                    .collect::<Vec<_>>()
+                    .join(", "),
             })
             .collect(),
-        subtotal: format_money(invoice.subtotal),
-        tax_rate: invoice.tax_rate,
-        tax_amount: format_money(invoice.tax_amount),
-        total: format_money(invoice.total),
+        subtotal: money(invoice.subtotal),
+        discounts: [
+            (labels.line_discounts.clone(), invoice.line_discount_amount),
+            (
+                invoice
+                    .discount
+                    .map(|discount| discount_label(discount, &labels, locale))
+                    .unwrap_or_default(),
+                invoice.discount_amount,
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, amount)| *amount != 0)
+        .map(|(label, amount)| TotalRowData {
+            label,
+            amount: money(-amount),
+        })
+        .collect(),
+        taxes: tax_lines(&taxes::get_invoice_taxes(conn, invoice_id)?, &invoice.currency, locale),
+        tax_note: tax::legal_note(conn, invoice.tax_treatment, &labels)?,
+        total: money(invoice.total),
         notes: invoice.notes,
         payment_link: invoice.payment_link,
+        labels,
     };
+    Ok((data, template))
+}
+
+pub fn render_invoice_html(conn: &Connection, invoice_id: &str) -> AppResult<String> {
+    let (data, template) = build_template_data(conn, invoice_id)?;
+    render_html(template.as_ref(), &data)
+}
+
+fn render_html(template: Option<&InvoiceTemplate>, data: &InvoiceTemplateData) -> AppResult<String> {
+    let template_str = template.map_or(BUILTIN_TEMPLATE, |t| t.body.as_str());
+    let mut hbs = Handlebars::new();
+    hbs.register_template_string("invoice", template_str)
+        .map_err(|e| AppError::Template(handlebars::RenderError::from(e)))?;
 
-    let html = hbs.render("invoice", &data)?;
+    let html = hbs.render("invoice", data)?;
     Ok(html)
 }
 
-pub fn save_invoice_html(
+/// Compiles `body` and renders it in strict mode against sample invoice and credit
+/// note data, so misspelt fields fail here instead of printing blanks.
+pub fn validate_template(body: &str) -> AppResult<()> {
+    let mut hbs = Handlebars::new();
+    hbs.set_strict_mode(true);
+    hbs.register_template_string("invoice", body)
+        .map_err(|e| AppError::Validation(format!("Template does not compile: {e}")))?;
+    for data in [sample_data(), sample_credit_note_data()] {
+        hbs.render("invoice", &data)
+            .map_err(|e| AppError::Validation(format!("Template does not render: {e}")))?;
+    }
+    Ok(())
+}
+
+/// Renders `body` with sample data, for previewing a template before saving it.
+pub fn preview_template(body: &str, brand_color: Option<&str>, logo: Option<&str>) -> AppResult<String> {
+    validate_template(body)?;
+    let mut data = sample_data();
+    if let Some(color) = brand_color.map(str::trim).filter(|c| !c.is_empty()) {
+        branding::parse_color(color)?;
+        data.brand_color = color.to_lowercase();
+    }
+    if let Some(logo) = logo.map(str::trim).filter(|l| !l.is_empty()) {
+        branding::decode_logo(logo)?;
+        data.logo = Some(logo.to_string());
+    }
+
+    let mut hbs = Handlebars::new();
+    hbs.register_template_string("invoice", body)
+        .map_err(|e| AppError::Template(handlebars::RenderError::from(e)))?;
+    Ok(hbs.render("invoice", &data)?)
+}
+
+/// An English invoice that exercises every optional section of a template.
+pub(crate) fn sample_data() -> InvoiceTemplateData {
+    let locale = locale::lookup("en").expect("English is always available");
+    let labels = locale.labels().expect("the English bundle is valid");
+    let money = |amount: i64| locale.format_money(amount, "USD");
+    let line = |description: &str, quantity: f64, unit_price: i64, discount: &str, tax_names: &str| {
+        LineItemData {
+            description: description.to_string(),
+            quantity: locale.format_number(quantity),
+            unit: labels.unit(LineItemUnit::Hours, quantity).to_string(),
+            unit_price: money(unit_price),
+            amount: money((quantity * unit_price as f64) as i64),
+            discount: discount.to_string(),
+            tax_names: tax_names.to_string(),
+        }
+    };
+    InvoiceTemplateData {
+        brand_color: branding::DEFAULT_BRAND_COLOR.to_string(),
+        logo: None,
+        locale: locale.code.to_string(),
+        business: BusinessData {
+            name: "Your Business LLC".to_string(),
+            email: "you@example.com".to_string(),
+            address: "1 Market St, San Francisco, CA 94105".to_string(),
+            address_lines: vec!["1 Market St".to_string(), "San Francisco, CA 94105".to_string()],
+            phone: "+1 415 555 0100".to_string(),
+            website: "https://example.com".to_string(),
+            tax_id: "12-3456789".to_string(),
+            iban: "GB82 WEST 1234 5698 7654 32".to_string(),
+            bank_details: "Example Bank, SWIFT EXAMGB2L".to_string(),
+        },
+        document_title: labels.invoice.clone(),
+        invoice_number: "INV-2025-001".to_string(),
+        client_name: "Jane Doe".to_string(),
+        client_company: "Acme Corporation".to_string(),
+        client_email: "billing@acme.example".to_string(),
+        client_address: "123 Main St, Springfield".to_string(),
+        issue_date: "Jan 15, 2025".to_string(),
+        due_date: "Feb 14, 2025".to_string(),
+        payment_terms: Some(labels.payment_terms(PaymentTerms::Net(30))),
+        credited_invoice_number: None,
+        status: "sent".to_string(),
+        status_label: labels.status_sent.clone(),
+        currency: "USD".to_string(),
+        line_items: vec![
+            line("Web development", 20.0, 15000, "", "Sales Tax"),
+            line("Design review", 2.0, 12000, "Discount (10%): -$24.00", "Sales Tax"),
+        ],
+        subtotal: money(324000),
+        discounts: vec![TotalRowData {
+            label: labels.line_discounts.clone(),
+            amount: money(-2400),
+        }],
+        taxes: vec![TotalRowData {
+            label: format!("Sales Tax ({})", locale.format_percent(8.5)),
+            amount: money(27336),
+        }],
+        tax_note: Some("Prices include sales tax where applicable.".to_string()),
+        total: money(348936),
+        notes: Some("Thank you for your business!".to_string()),
+        payment_link: Some("https://pay.example.com/inv-2025-001".to_string()),
+        labels,
+    }
+}
+
+fn sample_credit_note_data() -> InvoiceTemplateData {
+    let invoice = sample_data();
+    InvoiceTemplateData {
+        document_title: invoice.labels.credit_note.clone(),
+        invoice_number: "CN-2025-001".to_string(),
+        due_date: String::new(),
+        payment_terms: None,
+        credited_invoice_number: Some("INV-2025-001".to_string()),
+        status: "issued".to_string(),
+        status_label: invoice.labels.status(None).to_string(),
+        payment_link: None,
+        ..invoice
+    }
+}
+
+pub fn render_invoice_pdf(conn: &Connection, invoice_id: &str, page_size: PageSize) -> AppResult<Vec<u8>> {
+    let (data, _) = build_template_data(conn, invoice_id)?;
+    pdf_layout::render(&data, page_size)
+}
+
+/// A document number as a file name. Numbers from older settings may still hold path
+/// separators, which must not take the file out of `output_dir`.
+pub(crate) fn export_file_name(number: &str, extension: &str) -> String {
+    let stem: String = number
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':') || c.is_control() { '-' } else { c })
+        .collect();
+    format!("{stem}.{extension}")
+}
+
+pub fn save_invoice_html(conn: &Connection, invoice_id: &str, output_dir: &PathBuf) -> AppResult<PathBuf> {
+    let html = render_invoice_html(conn, invoice_id)?;
+    let invoice = invoices::get_invoice(conn, invoice_id)?;
+
+    let filename = export_file_name(&invoice.invoice_number, "html");
+    let output_path = output_dir.join(filename);
+
+    std::fs::create_dir_all(output_dir)?;
+    std::fs::write(&output_path, html)?;
+
+    Ok(output_path)
+}
+
+pub fn save_invoice_pdf(
     conn: &Connection,
     invoice_id: &str,
-    business_name: &str,
-    business_email: &str,
-    business_address: &str,
     output_dir: &PathBuf,
+    page_size: PageSize,
 ) -> AppResult<PathBuf> {
-    let html = render_invoice_html(conn, invoice_id, business_name, business_email, business_address)?;
+    let pdf = render_invoice_pdf(conn, invoice_id, page_size)?;
     let invoice = invoices::get_invoice(conn, invoice_id)?;
 
-    let filename = format!("{}.html", invoice.invoice_number);
+    let filename = export_file_name(&invoice.invoice_number, "pdf");
     let output_path = output_dir.join(filename);
 
     std::fs::create_dir_all(output_dir)?;
-    std::fs::write(&output_path, html)?;
+    std::fs::write(&output_path, pdf)?;
+
+    Ok(output_path)
+}
+
+/// Credit notes render through the credited invoice's template: same layout, negative
+/// amounts, and a reference to the credited invoice in place of the due date.
+fn build_credit_note_data(
+    conn: &Connection,
+    credit_note_id: &str,
+) -> AppResult<(InvoiceTemplateData, Option<InvoiceTemplate>)> {
+    let credit_note = credit_notes::get_credit_note(conn, credit_note_id)?;
+    let invoice = invoices::get_invoice(conn, &credit_note.invoice_id)?;
+    let client = clients::get_client(conn, &credit_note.client_id)?;
+    let template = templates::resolve_for_invoice(conn, &invoice.id)?;
+    let business = issuing_business(conn, &invoice)?;
+    let locale = locale::resolve(conn, client.locale.as_deref())?;
+    let labels = locale.labels()?;
+    let currency = credit_note.currency;
+    let money = |amount: i64| locale.format_money(amount, &currency);
+
+    let data = InvoiceTemplateData {
+        brand_color: brand_color(template.as_ref()),
+        logo: logo(template.as_ref(), &business),
+        locale: locale.code.to_string(),
+        business: business.into(),
+        document_title: labels.credit_note.clone(),
+        invoice_number: credit_note.credit_note_number,
+        client_name: client.name,
+        client_company: client.company.unwrap_or_default(),
+        client_email: client.email.unwrap_or_default(),
+        client_address: client.address.unwrap_or_default(),
+        issue_date: locale.format_date(credit_note.issue_date),
+        due_date: String::new(),
+        payment_terms: None,
+        credited_invoice_number: Some(invoice.invoice_number),
+        status: "issued".to_string(),
+        status_label: labels.status(None).to_string(),
+        line_items: credit_note
+            .line_items
+            .into_iter()
+            .map(|li| LineItemData {
+                description: li.description,
+                quantity: locale.format_number(li.quantity),
+                unit: String::new(),
+                unit_price: money(li.unit_price),
+                amount: money(li.amount),
+                discount: String::new(),
+                tax_names: String::new(),
+            })
+            .collect(),
+        subtotal: money(credit_note.subtotal),
+        discounts: Vec::new(),
+        taxes: tax_lines(&credit_note.taxes, &currency, locale),
+        tax_note: tax::legal_note(conn, credit_note.tax_treatment, &labels)?,
+        total: money(credit_note.total),
+        notes: credit_note.reason,
+        payment_link: None,
+        labels,
+        currency,
+    };
+    Ok((data, template))
+}
+
+pub fn render_credit_note_html(conn: &Connection, credit_note_id: &str) -> AppResult<String> {
+    let (data, template) = build_credit_note_data(conn, credit_note_id)?;
+    render_html(template.as_ref(), &data)
+}
+
+pub fn save_credit_note_pdf(
+    conn: &Connection,
+    credit_note_id: &str,
+    output_dir: &PathBuf,
+    page_size: PageSize,
+) -> AppResult<PathBuf> {
+    let (data, _) = build_credit_note_data(conn, credit_note_id)?;
+    let pdf = pdf_layout::render(&data, page_size)?;
+
+    let filename = export_file_name(&data.invoice_number, "pdf");
+    let output_path = output_dir.join(filename);
+
+    std::fs::create_dir_all(output_dir)?;
+    std::fs::write(&output_path, pdf)?;
 
     Ok(output_path)
 }
@@ -121,8 +512,13 @@ pub fn save_invoice_html(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::db::{self, clients as db_clients, invoices as db_invoices};
-    use crate::models::CreateClient;
+    use crate::db::{self, clients as db_clients, credit_notes as db_credit_notes, invoices as db_invoices};
+    use crate::db::templates as db_templates;
+    use crate::models::{
+        BusinessDetails, CreateClient, CreateCreditNote, CreateInvoiceTemplate, CreateTax,
+        CreditNoteLineInput, InvoiceStatus, TaxTreatment, UpdateClient, UpdateInvoice,
+    };
+    use crate::services::branding::tests::png_logo;
 
     #[test]
     fn test_render_invoice_html() {
@@ -138,6 +534,7 @@ mod tests {
                 phone: None,
                 notes: None,
                 hourly_rate: None,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -146,29 +543,447 @@ mod tests {
             &conn,
             &client.id,
             "2025-01-15T00:00:00Z",
-            "2025-02-15T00:00:00Z",
+            Some("2025-02-15T00:00:00Z"),
             Some("Thank you for your business!"),
             Some(10.0),
         )
         .unwrap();
 
-        db_invoices::add_line_item(&conn, &invoice.id, "Web Development", 20.0, 150.0, 0).unwrap();
-        db_invoices::add_line_item(&conn, &invoice.id, "Design Work", 8.0, 120.0, 1).unwrap();
-
-        let html = render_invoice_html(
+        db_invoices::add_line_item(&conn, &invoice.id, "Web Development", 20.0, 15000, LineItemUnit::Hours, 0).unwrap();
+        db_invoices::add_line_item(&conn, &invoice.id, "Design Work", 8.0, 12000, LineItemUnit::Hours, 1).unwrap();
+        business::update_business_profile(
             &conn,
-            &invoice.id,
-            "My Business",
-            "me@business.com",
-            "456 Oak Ave",
+            BusinessDetails {
+                legal_name: Some("My Business".to_string()),
+                email: Some("me@business.com".to_string()),
+                address_lines: Some(vec!["456 Oak Ave".to_string()]),
+                ..Default::default()
+            },
         )
         .unwrap();
 
+        let html = render_invoice_html(&conn, &invoice.id).unwrap();
+
         assert!(html.contains("My Business"));
+        assert!(html.contains("456 Oak Ave"));
         assert!(html.contains("Acme Corp"));
         assert!(html.contains("Web Development"));
         assert!(html.contains("Design Work"));
         assert!(html.contains("Thank you for your business!"));
-        assert!(html.contains("3960.00")); // subtotal: 3000 + 960
+        assert!(html.contains("$3,960.00")); // subtotal: 3000 + 960
+        assert!(html.contains("20 hrs"));
+        assert!(html.contains("Tax (10%)"));
+        assert!(html.contains("$396.00"));
+        assert!(!html.contains("Discount"));
+
+        let items = db_invoices::get_line_items(&conn, &invoice.id).unwrap();
+        db_invoices::set_line_item_discount(&conn, &invoice.id, &items[1].id, Some(Discount::Fixed(9600)))
+            .unwrap();
+        db_invoices::set_invoice_discount(&conn, &invoice.id, Some(Discount::Percent(5.0))).unwrap();
+        let html = render_invoice_html(&conn, &invoice.id).unwrap();
+        assert!(html.contains("Discount: -$96.00"));
+        assert!(html.contains("Line discounts"));
+        assert!(html.contains("Discount (5%)"));
+        // 5% of 3960.00 - 96.00
+        assert!(html.contains("-$193.20"));
+    }
+
+    #[test]
+    fn test_render_business_profile_with_invoice_override() {
+        let conn = db::init_db_in_memory().unwrap();
+        business::update_business_profile(
+            &conn,
+            BusinessDetails {
+                legal_name: Some("Studio Nord GmbH".to_string()),
+                email: Some("hello@studionord.de".to_string()),
+                phone: Some("+49 30 1234567".to_string()),
+                website: Some("studionord.de".to_string()),
+                address_lines: Some(vec!["Torstraße 1".to_string(), "10119 Berlin".to_string()]),
+                tax_id: Some("DE123456789".to_string()),
+                iban: Some("DE89370400440532013000".to_string()),
+                bank_details: Some("Commerzbank, BIC COBADEFFXXX".to_string()),
+                logo_path: None,
+            },
+        )
+        .unwrap();
+        let client = db_clients::create_client(
+            &conn,
+            CreateClient {
+                name: "Acme Corp".to_string(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let invoice = db_invoices::create_invoice(&conn, &client.id, "2025-01-15T00:00:00Z", None, None, None).unwrap();
+
+        let html = render_invoice_html(&conn, &invoice.id).unwrap();
+        for expected in [
+            "Studio Nord GmbH",
+            "<div class=\"business-info\">Torstraße 1</div>",
+            "<div class=\"business-info\">10119 Berlin</div>",
+            "+49 30 1234567",
+            "Tax ID: DE123456789",
+            "Payment Details",
+            "Commerzbank, BIC COBADEFFXXX",
+            "IBAN: DE89 3704 0044 0532 0130 00",
+        ] {
+            assert!(html.contains(expected), "{expected}");
+        }
+
+        let override_details = BusinessDetails {
+            legal_name: Some("Nord Consulting".to_string()),
+            iban: Some("GB82 WEST 1234 5698 7654 32".to_string()),
+            bank_details: Some(String::new()),
+            ..Default::default()
+        };
+        let edit = |business_override| UpdateInvoice {
+            business_override,
+            ..Default::default()
+        };
+        let updated = db_invoices::update_invoice(&conn, &invoice.id, edit(Some(override_details))).unwrap();
+        assert_eq!(
+            updated.business_override.and_then(|o| o.iban).as_deref(),
+            Some("GB82WEST12345698765432")
+        );
+
+        let html = render_invoice_html(&conn, &invoice.id).unwrap();
+        assert!(html.contains("Nord Consulting"));
+        assert!(!html.contains("Studio Nord GmbH"));
+        assert!(html.contains("IBAN: GB82 WEST 1234 5698 7654 32"));
+        assert!(!html.contains("Commerzbank"));
+        assert!(html.contains("Tax ID: DE123456789"));
+
+        let invalid = BusinessDetails { iban: Some("GB00 WEST 1234".to_string()), ..Default::default() };
+        assert!(matches!(
+            db_invoices::update_invoice(&conn, &invoice.id, edit(Some(invalid))),
+            Err(AppError::Validation(_))
+        ));
+
+        let cleared = db_invoices::update_invoice(&conn, &invoice.id, edit(Some(BusinessDetails::default()))).unwrap();
+        assert_eq!(cleared.business_override, None);
+        assert!(render_invoice_html(&conn, &invoice.id).unwrap().contains("Studio Nord GmbH"));
+    }
+
+    #[test]
+    fn test_render_invoice_tax_summary_and_reverse_charge_note() {
+        let conn = db::init_db_in_memory().unwrap();
+        let client = db_clients::create_client(
+            &conn,
+            CreateClient {
+                name: "Montréal Inc".to_string(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let gst = taxes::create_tax(
+            &conn,
+            CreateTax { name: "GST".to_string(), rate: 5.0, is_compound: false },
+        )
+        .unwrap();
+        let invoice = db_invoices::create_invoice(
+            &conn,
+            &client.id,
+            "2025-01-15T00:00:00Z",
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let item = db_invoices::add_line_item(&conn, &invoice.id, "Consulting", 2.0, 10000, LineItemUnit::Hours, 0).unwrap();
+        db_invoices::set_line_item_taxes(&conn, &invoice.id, &item.id, &[gst.id]).unwrap();
+
+        let html = render_invoice_html(&conn, &invoice.id).unwrap();
+        assert!(html.contains("GST (5%)"));
+        assert!(html.contains("Net 30"));
+        assert!(html.contains("Feb 14, 2025"));
+        assert!(html.contains("$10.00"));
+        assert!(html.contains("Tax: GST"));
+        assert!(!html.contains("Reverse charge"));
+
+        let eu_client = db_clients::create_client(
+            &conn,
+            CreateClient {
+                name: "Berlin GmbH".to_string(),
+                tax_treatment: Some(TaxTreatment::ReverseCharge),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let invoice = db_invoices::create_invoice(
+            &conn,
+            &eu_client.id,
+            "2025-01-15T00:00:00Z",
+            Some("2025-02-15T00:00:00Z"),
+            None,
+            Some(19.0),
+        )
+        .unwrap();
+        db_invoices::add_line_item(&conn, &invoice.id, "Consulting", 2.0, 10000, LineItemUnit::Hours, 0).unwrap();
+
+        let html = render_invoice_html(&conn, &invoice.id).unwrap();
+        assert!(html.contains("Reverse charge"));
+        assert!(!html.contains("(19%)"));
+    }
+
+    #[test]
+    fn test_render_with_client_template_and_branding() {
+        let conn = db::init_db_in_memory().unwrap();
+        let template = db_templates::create_template(
+            &conn,
+            CreateInvoiceTemplate {
+                name: "Compact".to_string(),
+                body: "<h1 style=\"color: {{brand_color}}\">{{document_title}} {{invoice_number}}</h1>\
+                       {{#if logo}}<img src=\"{{logo}}\">{{/if}}\
+                       {{#each line_items}}<p>{{this.description}}</p>{{/each}}<b>{{total}}</b>"
+                    .to_string(),
+                brand_color: Some("#aa0000".to_string()),
+                logo: Some(png_logo(4, 4)),
+                is_default: false,
+            },
+        )
+        .unwrap();
+        let client = db_clients::create_client(
+            &conn,
+            CreateClient {
+                name: "Acme Corp".to_string(),
+                template_id: Some(template.id.clone()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let invoice = db_invoices::create_invoice(
+            &conn,
+            &client.id,
+            "2025-01-15T00:00:00Z",
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        db_invoices::add_line_item(&conn, &invoice.id, "Consulting", 2.0, 10000, LineItemUnit::Hours, 0).unwrap();
+
+        let html = render_invoice_html(&conn, &invoice.id).unwrap();
+        assert!(html.starts_with("<h1 style=\"color: #aa0000\">INVOICE INV-2025-001</h1>"));
+        assert!(html.contains("<img src=\"data:image/png;base64,"));
+        assert!(html.contains("<p>Consulting</p><b>$200.00</b>"));
+        assert!(!html.contains("Generated by FreelanceInvoice"));
+
+        let output_dir = std::env::temp_dir().join(format!("freelanceinvoice-{}", invoice.id));
+        let path = save_invoice_pdf(&conn, &invoice.id, &output_dir, PageSize::A4).unwrap();
+        assert!(std::fs::read(&path).unwrap().starts_with(b"%PDF"));
+        std::fs::remove_dir_all(output_dir).ok();
+
+        // Other clients keep the built-in template, in the built-in colour
+        let other = db_clients::create_client(
+            &conn,
+            CreateClient {
+                name: "Globex".to_string(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let invoice = db_invoices::create_invoice(&conn, &other.id, "2025-01-15T00:00:00Z", None, None, None).unwrap();
+        let html = render_invoice_html(&conn, &invoice.id).unwrap();
+        assert!(html.contains("Generated by FreelanceInvoice"));
+        assert!(html.contains("color: #2563eb"));
+        assert!(!html.contains("<img"));
+    }
+
+    #[test]
+    fn test_preview_template() {
+        let html = preview_template(BUILTIN_TEMPLATE, Some("#00AA00"), Some(&png_logo(2, 2))).unwrap();
+        assert!(html.contains("INV-2025-001"));
+        assert!(html.contains("Web development"));
+        assert!(html.contains("$3,489.36"));
+        assert!(html.contains("color: #00aa00"));
+        assert!(html.contains("class=\"logo\""));
+
+        let html = preview_template("{{client_company}}", None, None).unwrap();
+        assert_eq!(html, "Acme Corporation");
+
+        assert!(matches!(preview_template("{{clientname}}", None, None), Err(AppError::Validation(_))));
+        assert!(matches!(preview_template("{{total}}", Some("green"), None), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn test_render_invoice_html_in_client_currency() {
+        let conn = db::init_db_in_memory().unwrap();
+        let client = db_clients::create_client(
+            &conn,
+            CreateClient {
+                name: "Kyoto KK".to_string(),
+                currency: Some("JPY".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let invoice = db_invoices::create_invoice(
+            &conn,
+            &client.id,
+            "2025-01-15T00:00:00Z",
+            Some("2025-02-15T00:00:00Z"),
+            None,
+            None,
+        )
+        .unwrap();
+        db_invoices::add_line_item(&conn, &invoice.id, "Consulting", 3.0, 12000, LineItemUnit::Hours, 0).unwrap();
+
+        let html = render_invoice_html(&conn, &invoice.id).unwrap();
+        assert!(html.contains("¥36,000"));
+        assert!(!html.contains("$"));
+    }
+
+    #[test]
+    fn test_render_invoice_in_client_locale() {
+        let conn = db::init_db_in_memory().unwrap();
+        let client = db_clients::create_client(
+            &conn,
+            CreateClient {
+                name: "Studio Nord GmbH".to_string(),
+                currency: Some("EUR".to_string()),
+                tax_treatment: Some(TaxTreatment::ReverseCharge),
+                payment_terms: Some(PaymentTerms::Net(14)),
+                locale: Some("DE".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(client.locale.as_deref(), Some("de"));
+        let invoice =
+            db_invoices::create_invoice(&conn, &client.id, "2025-01-15T00:00:00Z", None, None, None)
+                .unwrap();
+        db_invoices::add_line_item(&conn, &invoice.id, "Konzeption", 1.5, 80000, LineItemUnit::Days, 0).unwrap();
+
+        let html = render_invoice_html(&conn, &invoice.id).unwrap();
+        assert!(html.contains("<html lang=\"de\">"));
+        assert!(html.contains("RECHNUNG"));
+        assert!(html.contains("Rechnung an"));
+        assert!(html.contains("15.01.2025"));
+        assert!(html.contains("29.01.2025"));
+        assert!(html.contains("Zahlbar innerhalb von 14 Tagen"));
+        assert!(html.contains("1,5 Tage"));
+        assert!(html.contains("1.200,00\u{a0}€"));
+        assert!(html.contains("Steuerschuldnerschaft des Leistungsempfängers"));
+        assert!(html.contains("Entwurf"));
+        assert!(!html.contains("Bill To"));
+        assert!(render_invoice_pdf(&conn, &invoice.id, PageSize::A4).unwrap().starts_with(b"%PDF"));
+
+        let update = |locale: &str| UpdateClient {
+            locale: Some(locale.to_string()),
+            ..Default::default()
+        };
+        db_clients::update_client(&conn, &client.id, update("fr")).unwrap();
+        let html = render_invoice_html(&conn, &invoice.id).unwrap();
+        assert!(html.contains("FACTURE"));
+        assert!(html.contains("Facturé à"));
+        assert!(html.contains("15/01/2025"));
+        assert!(html.contains("1\u{202f}200,00\u{a0}€"));
+        assert!(html.contains("Autoliquidation"));
+
+        // Without a locale of its own the client gets the default one
+        db_clients::update_client(&conn, &client.id, update(" ")).unwrap();
+        assert!(render_invoice_html(&conn, &invoice.id).unwrap().contains("Bill To"));
+        crate::db::settings::set_setting(&conn, "default_locale", "de").unwrap();
+        assert!(render_invoice_html(&conn, &invoice.id).unwrap().contains("Rechnung an"));
+
+        let unsupported = db_clients::update_client(&conn, &client.id, update("tlh"));
+        assert!(matches!(unsupported, Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn test_save_invoice_pdf() {
+        let conn = db::init_db_in_memory().unwrap();
+        let client = db_clients::create_client(
+            &conn,
+            CreateClient {
+                name: "Acme Corp".to_string(),
+                email: None,
+                company: None,
+                address: None,
+                phone: None,
+                notes: None,
+                hourly_rate: None,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let invoice = db_invoices::create_invoice(
+            &conn,
+            &client.id,
+            "2025-01-15T00:00:00Z",
+            Some("2025-02-15T00:00:00Z"),
+            None,
+            None,
+        )
+        .unwrap();
+        db_invoices::add_line_item(&conn, &invoice.id, "Web Development", 20.0, 15000, LineItemUnit::Hours, 0).unwrap();
+
+        let output_dir = std::env::temp_dir().join(format!("freelanceinvoice-{}", invoice.id));
+        let path = save_invoice_pdf(&conn, &invoice.id, &output_dir, PageSize::Letter).unwrap();
+
+        assert_eq!(path.file_name().unwrap(), format!("{}.pdf", invoice.invoice_number).as_str());
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(bytes.starts_with(b"%PDF"));
+        std::fs::remove_dir_all(output_dir).ok();
+    }
+
+    #[test]
+    fn test_export_file_name_stays_in_the_output_dir() {
+        assert_eq!(export_file_name("INV-2025-001", "pdf"), "INV-2025-001.pdf");
+        assert_eq!(export_file_name("../ACME/2503/0001", "pdf"), "..-ACME-2503-0001.pdf");
+        assert_eq!(export_file_name("..\\C:0001", "html"), "..-C-0001.html");
+    }
+
+    #[test]
+    fn test_render_credit_note() {
+        let conn = db::init_db_in_memory().unwrap();
+        let client = db_clients::create_client(
+            &conn,
+            CreateClient {
+                name: "Acme Corp".to_string(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let invoice = db_invoices::create_invoice(
+            &conn,
+            &client.id,
+            "2025-01-15T00:00:00Z",
+            Some("2025-02-15T00:00:00Z"),
+            None,
+            None,
+        )
+        .unwrap();
+        db_invoices::add_line_item(&conn, &invoice.id, "Web Development", 20.0, 15000, LineItemUnit::Hours, 0).unwrap();
+        db_invoices::update_invoice_status(&conn, &invoice.id, InvoiceStatus::Sent).unwrap();
+        let credit_note = db_credit_notes::create_credit_note(
+            &conn,
+            CreateCreditNote {
+                invoice_id: invoice.id.clone(),
+                issue_date: chrono::NaiveDate::from_ymd_opt(2025, 2, 1).unwrap(),
+                reason: Some("Two hours billed twice".to_string()),
+                line_items: vec![CreditNoteLineInput {
+                    description: "Web Development (duplicate)".to_string(),
+                    quantity: 2.0,
+                    unit_price: -15000,
+                    tax_ids: Vec::new(),
+                }],
+            },
+        )
+        .unwrap();
+
+        let html = render_credit_note_html(&conn, &credit_note.id).unwrap();
+        assert!(html.contains("CREDIT NOTE"));
+        assert!(html.contains("CN-2025-001"));
+        assert!(html.contains(&invoice.invoice_number));
+        assert!(html.contains("-$300.00"));
+        assert!(html.contains("Two hours billed twice"));
+        assert!(!html.contains("Due Date"));
+
+        let output_dir = std::env::temp_dir().join(format!("freelanceinvoice-{}", credit_note.id));
+        let path = save_credit_note_pdf(&conn, &credit_note.id, &output_dir, PageSize::A4).unwrap();
+        assert_eq!(path.file_name().unwrap(), "CN-2025-001.pdf");
+        assert!(std::fs::read(&path).unwrap().starts_with(b"%PDF"));
+        std::fs::remove_dir_all(output_dir).ok();
     }
 }
@@ -0,0 +1,634 @@
+use std::io::Cursor;
+
+use printpdf::path::PaintMode;
+use printpdf::{
+    Color, Image, ImageTransform, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference,
+    PdfLayerReference, Point, Rect, Rgb,
+};
+use ttf_parser::Face;
+
+use crate::error::{AppError, AppResult};
+use crate::models::PageSize;
+use crate::services::branding::{self, Logo};
+use crate::services::locale::Labels;
+use crate::services::pdf::InvoiceTemplateData;
+
+// DejaVu Sans is embedded so the PDF renders identically on every machine
+// (see fonts/LICENSE-DejaVu.txt).
+const REGULAR_FONT: &[u8] = include_bytes!("../../fonts/DejaVuSans.ttf");
+const BOLD_FONT: &[u8] = include_bytes!("../../fonts/DejaVuSans-Bold.ttf");
+
+const PT_TO_MM: f32 = 25.4 / 72.0;
+const MARGIN: f32 = 20.0;
+const FOOTER_HEIGHT: f32 = 12.0;
+const COLUMN_WIDTH: f32 = 30.0;
+const CELL_PADDING: f32 = 3.0;
+const LOGO_MAX_WIDTH: f32 = 50.0;
+const LOGO_MAX_HEIGHT: f32 = 16.0;
+
+// Palette mirrors templates/invoice.html; the brand colour comes from the template.
+const TEXT: (u8, u8, u8) = (31, 41, 55);
+const MUTED: (u8, u8, u8) = (107, 114, 128);
+const LABEL: (u8, u8, u8) = (156, 163, 175);
+const HEADER_FILL: (u8, u8, u8) = (249, 250, 251);
+const BORDER: (u8, u8, u8) = (229, 231, 235);
+
+fn rgb((r, g, b): (u8, u8, u8)) -> Color {
+    Color::Rgb(Rgb::new(
+        r as f32 / 255.0,
+        g as f32 / 255.0,
+        b as f32 / 255.0,
+        None,
+    ))
+}
+
+fn pdf_err(e: printpdf::Error) -> AppError {
+    AppError::Pdf(e.to_string())
+}
+
+struct Font {
+    pdf: IndirectFontRef,
+    face: Face<'static>,
+}
+
+impl Font {
+    fn load(doc: &PdfDocumentReference, bytes: &'static [u8]) -> AppResult<Self> {
+        let face = Face::parse(bytes, 0).map_err(|e| AppError::Pdf(e.to_string()))?;
+        let pdf = doc.add_external_font(Cursor::new(bytes)).map_err(pdf_err)?;
+        Ok(Font { pdf, face })
+    }
+
+    /// Rendered width of `text` in millimetres at `size` points.
+    fn width(&self, text: &str, size: f32) -> f32 {
+        let units: u32 = text
+            .chars()
+            .map(|c| {
+                self.face
+                    .glyph_index(c)
+                    .and_then(|g| self.face.glyph_hor_advance(g))
+                    .unwrap_or(0) as u32
+            })
+            .sum();
+        units as f32 / self.face.units_per_em() as f32 * size * PT_TO_MM
+    }
+
+    /// Greedy word wrap. Words wider than `max_width` are broken by character; no-break
+    /// spaces, as in `1.234,50 €`, don't end a word.
+    fn wrap(&self, text: &str, size: f32, max_width: f32) -> Vec<String> {
+        let mut lines = Vec::new();
+        for paragraph in text.lines() {
+            let mut line = String::new();
+            for word in paragraph.split_ascii_whitespace() {
+                let candidate = if line.is_empty() {
+                    word.to_string()
+                } else {
+                    format!("{line} {word}")
+                };
+                if self.width(&candidate, size) <= max_width {
+                    line = candidate;
+                    continue;
+                }
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                for c in word.chars() {
+                    line.push(c);
+                    if self.width(&line, size) > max_width && line.chars().count() > 1 {
+                        line.pop();
+                        lines.push(std::mem::replace(&mut line, c.to_string()));
+                    }
+                }
+            }
+            lines.push(line);
+        }
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Weight {
+    Regular,
+    Bold,
+}
+
+struct Writer {
+    doc: PdfDocumentReference,
+    regular: Font,
+    bold: Font,
+    width: f32,
+    height: f32,
+    layers: Vec<PdfLayerReference>,
+    /// Baseline cursor, in millimetres from the bottom of the current page.
+    y: f32,
+    brand: (u8, u8, u8),
+}
+
+impl Writer {
+    fn new(title: &str, page_size: PageSize, brand: (u8, u8, u8)) -> AppResult<Self> {
+        let (width, height) = page_size.dimensions_mm();
+        let (doc, page, layer) = PdfDocument::new(title, Mm(width), Mm(height), "Page 1");
+        let regular = Font::load(&doc, REGULAR_FONT)?;
+        let bold = Font::load(&doc, BOLD_FONT)?;
+        let layer = doc.get_page(page).get_layer(layer);
+        Ok(Writer {
+            doc,
+            regular,
+            bold,
+            width,
+            height,
+            layers: vec![layer],
+            y: height - MARGIN,
+            brand,
+        })
+    }
+
+    fn layer(&self) -> &PdfLayerReference {
+        self.layers.last().expect("writer always has a page")
+    }
+
+    fn font(&self, weight: Weight) -> &Font {
+        match weight {
+            Weight::Regular => &self.regular,
+            Weight::Bold => &self.bold,
+        }
+    }
+
+    fn right_edge(&self) -> f32 {
+        self.width - MARGIN
+    }
+
+    fn bottom_limit(&self) -> f32 {
+        MARGIN + FOOTER_HEIGHT
+    }
+
+    fn new_page(&mut self) {
+        let label = format!("Page {}", self.layers.len() + 1);
+        let (page, layer) = self.doc.add_page(Mm(self.width), Mm(self.height), label);
+        self.layers.push(self.doc.get_page(page).get_layer(layer));
+        self.y = self.height - MARGIN;
+    }
+
+    /// Starts a new page when fewer than `needed` millimetres remain. Returns whether it did.
+    fn ensure_space(&mut self, needed: f32) -> bool {
+        if self.y - needed < self.bottom_limit() {
+            self.new_page();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn text(&self, text: &str, size: f32, x: f32, y: f32, weight: Weight, color: (u8, u8, u8)) {
+        let layer = self.layer();
+        layer.set_fill_color(rgb(color));
+        layer.use_text(text, size, Mm(x), Mm(y), &self.font(weight).pdf);
+    }
+
+    fn text_right(
+        &self,
+        text: &str,
+        size: f32,
+        right: f32,
+        y: f32,
+        weight: Weight,
+        color: (u8, u8, u8),
+    ) {
+        let x = right - self.font(weight).width(text, size);
+        self.text(text, size, x, y, weight, color);
+    }
+
+    fn rule(&self, x1: f32, x2: f32, y: f32, thickness: f32, color: (u8, u8, u8)) {
+        let layer = self.layer();
+        layer.set_outline_color(rgb(color));
+        layer.set_outline_thickness(thickness);
+        layer.add_line(Line {
+            points: vec![
+                (Point::new(Mm(x1), Mm(y)), false),
+                (Point::new(Mm(x2), Mm(y)), false),
+            ],
+            is_closed: false,
+        });
+    }
+
+    fn fill(&self, x1: f32, y1: f32, x2: f32, y2: f32, color: (u8, u8, u8)) {
+        let layer = self.layer();
+        layer.set_fill_color(rgb(color));
+        layer.add_rect(Rect::new(Mm(x1), Mm(y1), Mm(x2), Mm(y2)).with_mode(PaintMode::Fill));
+    }
+
+    /// Places `logo` with its bottom-left corner at (`x`, `y`), `width` millimetres wide.
+    fn image(&self, logo: Logo, x: f32, y: f32, width: f32) {
+        let dpi = logo.width as f32 * 25.4 / width;
+        Image::from(logo.into_image()).add_to_layer(
+            self.layer().clone(),
+            ImageTransform {
+                translate_x: Some(Mm(x)),
+                translate_y: Some(Mm(y)),
+                dpi: Some(dpi),
+                ..Default::default()
+            },
+        );
+    }
+}
+
+/// Lays out the invoice onto as many pages as the line items need and returns the PDF bytes.
+pub(crate) fn render(data: &InvoiceTemplateData, page_size: PageSize) -> AppResult<Vec<u8>> {
+    let title = format!("{} {}", data.document_title, data.invoice_number);
+    let brand = branding::parse_color(&data.brand_color)?;
+    let mut w = Writer::new(&title, page_size, brand)?;
+
+    draw_header(&mut w, data)?;
+    draw_meta(&mut w, data);
+    draw_line_items(&mut w, data);
+    draw_totals(&mut w, data);
+    draw_notes(&mut w, data);
+    draw_footers(&w, &data.labels);
+
+    w.doc.save_to_bytes().map_err(pdf_err)
+}
+
+fn draw_header(w: &mut Writer, data: &InvoiceTemplateData) -> AppResult<()> {
+    let top = w.y;
+    let right = w.right_edge();
+
+    let mut left_top = top;
+    if let Some(logo) = data.logo.as_deref() {
+        let logo = branding::decode_logo(logo)?;
+        let aspect = logo.width as f32 / logo.height as f32;
+        let height = LOGO_MAX_HEIGHT.min(LOGO_MAX_WIDTH / aspect);
+        w.image(logo, MARGIN, top - height, height * aspect);
+        left_top -= height + 4.0;
+    }
+
+    let business = &data.business;
+    w.text(&business.name, 18.0, MARGIN, left_top - 6.0, Weight::Bold, w.brand);
+    let mut left_y = left_top - 12.0;
+    let tax_id = if business.tax_id.is_empty() {
+        String::new()
+    } else {
+        format!("{}: {}", data.labels.tax_id, business.tax_id)
+    };
+    let contact = [&business.email, &business.phone, &business.website, &tax_id];
+    for info in business.address_lines.iter().chain(contact) {
+        for line in w.regular.wrap(info, 9.0, 90.0) {
+            if line.is_empty() {
+                continue;
+            }
+            w.text(&line, 9.0, MARGIN, left_y, Weight::Regular, MUTED);
+            left_y -= 4.5;
+        }
+    }
+
+    w.text_right(&data.document_title, 22.0, right, top - 7.0, Weight::Bold, TEXT);
+    w.text_right(&data.invoice_number, 10.0, right, top - 13.0, Weight::Regular, MUTED);
+
+    w.y = left_y.min(top - 13.0) - 10.0;
+    Ok(())
+}
+
+fn draw_meta(w: &mut Writer, data: &InvoiceTemplateData) {
+    let top = w.y;
+    let right = w.right_edge();
+
+    let labels = &data.labels;
+    w.text(&labels.bill_to.to_uppercase(), 8.0, MARGIN, top, Weight::Bold, LABEL);
+    let mut left_y = top - 5.5;
+    w.text(&data.client_name, 10.0, MARGIN, left_y, Weight::Bold, TEXT);
+    for info in [&data.client_company, &data.client_email, &data.client_address] {
+        for line in w.regular.wrap(info, 10.0, 90.0) {
+            if line.is_empty() {
+                continue;
+            }
+            left_y -= 5.0;
+            w.text(&line, 10.0, MARGIN, left_y, Weight::Regular, TEXT);
+        }
+    }
+
+    let mut right_y = top;
+    let status = data.status_label.to_uppercase();
+    let mut rows = vec![(&labels.date, &data.issue_date)];
+    if let Some(number) = &data.credited_invoice_number {
+        rows.push((&labels.credits_invoice, number));
+    }
+    if !data.due_date.is_empty() {
+        rows.push((&labels.due_date, &data.due_date));
+    }
+    if let Some(terms) = &data.payment_terms {
+        rows.push((&labels.terms, terms));
+    }
+    rows.push((&labels.status, &status));
+    for (label, value) in rows {
+        w.text_right(&label.to_uppercase(), 8.0, right, right_y, Weight::Bold, LABEL);
+        w.text_right(value, 10.0, right, right_y - 5.5, Weight::Regular, TEXT);
+        right_y -= 13.0;
+    }
+
+    w.y = left_y.min(right_y + 7.5) - 12.0;
+}
+
+fn draw_table_header(w: &mut Writer, labels: &Labels) {
+    let right = w.right_edge();
+    let top = w.y;
+    w.fill(MARGIN, top - 8.0, right, top, HEADER_FILL);
+    w.rule(MARGIN, right, top - 8.0, 1.0, BORDER);
+
+    let baseline = top - 5.3;
+    let description = labels.description.to_uppercase();
+    w.text(&description, 8.0, MARGIN + CELL_PADDING, baseline, Weight::Bold, MUTED);
+    for (i, label) in [&labels.quantity, &labels.rate, &labels.amount].iter().enumerate() {
+        let col_right = right - CELL_PADDING - (2 - i) as f32 * COLUMN_WIDTH;
+        w.text_right(&label.to_uppercase(), 8.0, col_right, baseline, Weight::Bold, MUTED);
+    }
+    w.y = top - 8.0;
+}
+
+fn draw_line_items(w: &mut Writer, data: &InvoiceTemplateData) {
+    let right = w.right_edge();
+    let description_width = right - MARGIN - 3.0 * COLUMN_WIDTH - 2.0 * CELL_PADDING;
+    let line_height = 4.5;
+
+    w.ensure_space(8.0 + 9.0);
+    draw_table_header(w, &data.labels);
+
+    for item in &data.line_items {
+        let lines = w.regular.wrap(&item.description, 10.0, description_width);
+        let mut notes = Vec::new();
+        if !item.discount.is_empty() {
+            notes.push(item.discount.clone());
+        }
+        if !item.tax_names.is_empty() {
+            notes.push(format!("{}: {}", data.labels.tax, item.tax_names));
+        }
+        let text_lines = lines.len() + notes.len();
+        let row_height = text_lines as f32 * line_height + 4.5;
+        if w.ensure_space(row_height) {
+            draw_table_header(w, &data.labels);
+        }
+
+        let first_baseline = w.y - 6.0;
+        for (i, line) in lines.iter().enumerate() {
+            let y = first_baseline - i as f32 * line_height;
+            w.text(line, 10.0, MARGIN + CELL_PADDING, y, Weight::Regular, TEXT);
+        }
+        for (i, note) in notes.iter().enumerate() {
+            let y = first_baseline - (lines.len() + i) as f32 * line_height;
+            w.text(note, 8.0, MARGIN + CELL_PADDING, y, Weight::Regular, MUTED);
+        }
+        let cells = [
+            format!("{} {}", item.quantity, item.unit).trim_end().to_string(),
+            item.unit_price.clone(),
+            item.amount.clone(),
+        ];
+        for (i, cell) in cells.iter().enumerate() {
+            let col_right = right - CELL_PADDING - (2 - i) as f32 * COLUMN_WIDTH;
+            w.text_right(cell, 10.0, col_right, first_baseline, Weight::Regular, TEXT);
+        }
+
+        w.y -= row_height;
+        w.rule(MARGIN, right, w.y, 0.5, BORDER);
+    }
+}
+
+fn draw_totals(w: &mut Writer, data: &InvoiceTemplateData) {
+    let mut rows = vec![(data.labels.subtotal.clone(), data.subtotal.clone())];
+    for discount in &data.discounts {
+        rows.push((discount.label.clone(), discount.amount.clone()));
+    }
+    for tax in &data.taxes {
+        rows.push((tax.label.clone(), tax.amount.clone()));
+    }
+
+    let needed = 8.0 + rows.len() as f32 * 6.0 + 10.0;
+    w.ensure_space(needed);
+
+    let right = w.right_edge();
+    let left = right - 75.0;
+    let mut y = w.y - 8.0;
+    for (label, value) in &rows {
+        w.text(label, 10.0, left, y, Weight::Regular, TEXT);
+        w.text_right(value, 10.0, right, y, Weight::Regular, TEXT);
+        y -= 6.0;
+    }
+
+    w.rule(left, right, y + 2.0, 1.5, TEXT);
+    y -= 4.0;
+    w.text(&data.labels.total, 12.0, left, y, Weight::Bold, TEXT);
+    w.text_right(&data.total, 12.0, right, y, Weight::Bold, TEXT);
+    w.y = y - 6.0;
+}
+
+fn draw_notes(w: &mut Writer, data: &InvoiceTemplateData) {
+    let content_width = w.right_edge() - MARGIN;
+
+    if let Some(note) = data.tax_note.as_deref() {
+        for line in w.regular.wrap(note, 9.0, content_width) {
+            w.ensure_space(5.0);
+            w.y -= 5.0;
+            w.text(&line, 9.0, MARGIN, w.y, Weight::Bold, TEXT);
+        }
+    }
+
+    if let Some(notes) = data.notes.as_deref().filter(|n| !n.is_empty()) {
+        let lines = w.regular.wrap(notes, 10.0, content_width);
+        w.ensure_space(12.0 + 5.0);
+        w.y -= 6.0;
+        let label = data.labels.notes.to_uppercase();
+        w.text(&label, 8.0, MARGIN, w.y, Weight::Bold, LABEL);
+        for line in lines {
+            w.ensure_space(5.0);
+            w.y -= 5.0;
+            w.text(&line, 10.0, MARGIN, w.y, Weight::Regular, TEXT);
+        }
+    }
+
+    let business = &data.business;
+    let mut payment_details = Vec::new();
+    if !business.bank_details.is_empty() {
+        payment_details.push(business.bank_details.clone());
+    }
+    if !business.iban.is_empty() {
+        payment_details.push(format!("{}: {}", data.labels.iban, business.iban));
+    }
+    if !payment_details.is_empty() {
+        let lines: Vec<String> = payment_details
+            .iter()
+            .flat_map(|details| w.regular.wrap(details, 10.0, content_width))
+            .collect();
+        w.ensure_space(12.0 + 5.0);
+        w.y -= 6.0;
+        let label = data.labels.payment_details.to_uppercase();
+        w.text(&label, 8.0, MARGIN, w.y, Weight::Bold, LABEL);
+        for line in lines {
+            w.ensure_space(5.0);
+            w.y -= 5.0;
+            w.text(&line, 10.0, MARGIN, w.y, Weight::Regular, TEXT);
+        }
+    }
+
+    if let Some(link) = data.payment_link.as_deref().filter(|l| !l.is_empty()) {
+        let pay_online = format!("{}: {link}", data.labels.pay_online);
+        let lines = w.regular.wrap(&pay_online, 10.0, content_width);
+        w.y -= 4.0;
+        for line in lines {
+            w.ensure_space(5.0);
+            w.y -= 5.0;
+            w.text(&line, 10.0, MARGIN, w.y, Weight::Regular, w.brand);
+        }
+    }
+}
+
+fn draw_footers(w: &Writer, labels: &Labels) {
+    let total = w.layers.len();
+    let center = w.width / 2.0;
+    for (i, layer) in w.layers.iter().enumerate() {
+        let footer = labels.generated_by.as_str();
+        let x = center - w.regular.width(footer, 8.0) / 2.0;
+        layer.set_fill_color(rgb(LABEL));
+        layer.use_text(footer, 8.0, Mm(x), Mm(MARGIN), &w.regular.pdf);
+
+        if total > 1 {
+            let page = labels.page(i + 1, total);
+            let x = w.right_edge() - w.regular.width(&page, 8.0);
+            layer.use_text(page, 8.0, Mm(x), Mm(MARGIN), &w.regular.pdf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::locale;
+    use crate::services::pdf::{BusinessData, LineItemData, TotalRowData};
+
+    fn sample(items: usize) -> InvoiceTemplateData {
+        InvoiceTemplateData {
+            brand_color: "#2563eb".to_string(),
+            logo: None,
+            locale: "en".to_string(),
+            labels: locale::lookup("en").unwrap().labels().unwrap(),
+            business: BusinessData {
+                name: "My Business".to_string(),
+                email: "me@business.com".to_string(),
+                address: "456 Oak Ave".to_string(),
+                address_lines: vec!["456 Oak Ave".to_string()],
+                tax_id: "DE123456789".to_string(),
+                iban: "DE89 3704 0044 0532 0130 00".to_string(),
+                ..Default::default()
+            },
+            document_title: "INVOICE".to_string(),
+            invoice_number: "INV-2025-001".to_string(),
+            client_name: "Acme Corp".to_string(),
+            client_company: "Acme Corporation".to_string(),
+            client_email: "billing@acme.com".to_string(),
+            client_address: "123 Main St".to_string(),
+            issue_date: "Jan 15, 2025".to_string(),
+            due_date: "Feb 15, 2025".to_string(),
+            payment_terms: Some("Net 30".to_string()),
+            credited_invoice_number: None,
+            status: "draft".to_string(),
+            status_label: "Draft".to_string(),
+            currency: "EUR".to_string(),
+            line_items: (0..items)
+                .map(|i| LineItemData {
+                    description: format!("Development work, sprint {i}: feature implementation and review"),
+                    quantity: "8".to_string(),
+                    unit_price: "€150.00".to_string(),
+                    amount: "€1200.00".to_string(),
+                    unit: "hrs".to_string(),
+                    discount: "Discount (10%): -€120.00".to_string(),
+                    tax_names: "VAT".to_string(),
+                })
+                .collect(),
+            subtotal: format!("€{:.2}", items as f64 * 1200.0),
+            discounts: Vec::new(),
+            taxes: vec![TotalRowData {
+                label: "VAT (10%)".to_string(),
+                amount: format!("€{:.2}", items as f64 * 120.0),
+            }],
+            tax_note: None,
+            total: format!("€{:.2}", items as f64 * 1320.0),
+            notes: Some("Thank you for your business!".to_string()),
+            payment_link: None,
+        }
+    }
+
+    fn page_count(pdf: &[u8]) -> usize {
+        printpdf::lopdf::Document::load_mem(pdf)
+            .expect("valid PDF")
+            .get_pages()
+            .len()
+    }
+
+    #[test]
+    fn test_render_single_page_pdf() {
+        let pdf = render(&sample(3), PageSize::A4).unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+        assert_eq!(page_count(&pdf), 1);
+    }
+
+    #[test]
+    fn test_render_with_logo_and_brand_color() {
+        let data = InvoiceTemplateData {
+            brand_color: "#aa0000".to_string(),
+            logo: Some(crate::services::branding::tests::png_logo(40, 10)),
+            ..sample(2)
+        };
+        let pdf = render(&data, PageSize::A4).unwrap();
+        let doc = printpdf::lopdf::Document::load_mem(&pdf).expect("valid PDF");
+        let images = doc
+            .objects
+            .values()
+            .filter_map(|object| object.as_stream().ok())
+            .filter(|stream| stream.dict.get(b"Subtype").and_then(|s| s.as_name()).ok() == Some(b"Image"))
+            .count();
+        assert_eq!(images, 1);
+
+        let bad_color = InvoiceTemplateData { brand_color: "blue".to_string(), ..sample(1) };
+        assert!(render(&bad_color, PageSize::A4).is_err());
+    }
+
+    #[test]
+    fn test_long_tables_break_across_pages() {
+        let a4 = render(&sample(80), PageSize::A4).unwrap();
+        let letter = render(&sample(80), PageSize::Letter).unwrap();
+        assert!(page_count(&a4) > 1);
+        assert!(page_count(&letter) >= page_count(&a4));
+    }
+
+    #[test]
+    fn test_render_localized_pdf() {
+        let face = Face::parse(REGULAR_FONT, 0).unwrap();
+        for c in ['\u{a0}', '\u{202f}', '€', 'ß', 'É', 'Ü'] {
+            assert!(face.glyph_index(c).is_some(), "{c:?} has no glyph");
+        }
+
+        for code in ["de", "fr"] {
+            let labels = locale::lookup(code).unwrap().labels().unwrap();
+            let data = InvoiceTemplateData {
+                locale: code.to_string(),
+                document_title: labels.invoice.clone(),
+                status_label: labels.status_draft.clone(),
+                labels,
+                total: "1\u{202f}320,00\u{a0}€".to_string(),
+                ..sample(60)
+            };
+            let pdf = render(&data, PageSize::A4).unwrap();
+            assert!(page_count(&pdf) > 1);
+        }
+    }
+
+    #[test]
+    fn test_wrap_breaks_long_text() {
+        let (doc, _, _) = PdfDocument::new("test", Mm(210.0), Mm(297.0), "Layer");
+        let font = Font::load(&doc, REGULAR_FONT).unwrap();
+        let lines = font.wrap("one two three four five six seven eight nine ten", 10.0, 30.0);
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|l| font.width(l, 10.0) <= 30.0));
+
+        let lines = font.wrap("Total 1.234,50\u{a0}€", 10.0, 22.0);
+        assert_eq!(lines, vec!["Total", "1.234,50\u{a0}€"]);
+    }
+}
@@ -0,0 +1,263 @@
+// Recurring invoice schedules and the catch-up pass that materializes due invoices
+use std::collections::HashSet;
+
+use chrono::{Datelike, Days, Months, NaiveDate};
+use rusqlite::Connection;
+
+use crate::db::{invoices, recurring_invoices, with_savepoint};
+use crate::error::AppResult;
+use crate::models::{Invoice, RecurringFrequency, RecurringInvoice, RecurringSchedule};
+
+fn last_day_of_month(year: i32, month: u32) -> u32 {
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|first| first.checked_add_months(Months::new(1)))
+        .and_then(|next| next.pred_opt())
+        .map_or(28, |last| last.day())
+}
+
+/// The `step`-th candidate date of a schedule, before start/end/count filtering.
+fn nth_date(schedule: &RecurringSchedule, step: u32) -> Option<NaiveDate> {
+    let start = schedule.start_date;
+    let months_per_step = match schedule.frequency {
+        RecurringFrequency::Weekly => {
+            let days = 7 * u64::from(schedule.interval) * u64::from(step);
+            return start.checked_add_days(Days::new(days));
+        }
+        RecurringFrequency::Monthly => schedule.interval,
+        RecurringFrequency::Quarterly => schedule.interval * 3,
+    };
+    let first_of_month = start.with_day(1)?;
+    let month = first_of_month.checked_add_months(Months::new(months_per_step * step))?;
+    let day = schedule
+        .day_of_month
+        .unwrap_or(start.day())
+        .min(last_day_of_month(month.year(), month.month()));
+    month.with_day(day)
+}
+
+/// All occurrence dates of a schedule up to and including `until`, honouring the
+/// schedule's end date and occurrence count.
+pub fn occurrence_dates(schedule: &RecurringSchedule, until: NaiveDate) -> Vec<NaiveDate> {
+    let last = schedule.end_date.map_or(until, |end| end.min(until));
+    let mut dates = Vec::new();
+    let mut step = 0;
+    while schedule.count.is_none_or(|count| dates.len() < count as usize) {
+        let Some(date) = nth_date(schedule, step) else {
+            break;
+        };
+        step += 1;
+        if date > last {
+            break;
+        }
+        // A day_of_month earlier than the start date's day skips the first month
+        if date >= schedule.start_date {
+            dates.push(date);
+        }
+    }
+    dates
+}
+
+fn materialize(
+    conn: &Connection,
+    recurring: &RecurringInvoice,
+    date: NaiveDate,
+) -> AppResult<Invoice> {
+    let issue_date = date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+
+    with_savepoint(conn, "materialize_recurring", || {
+        let invoice = invoices::create_invoice_on_terms(
+            conn,
+            &recurring.client_id,
+            &issue_date.to_rfc3339(),
+            recurring.payment_terms,
+            recurring.notes.as_deref(),
+            recurring.tax_rate,
+        )?;
+        for (i, item) in recurring.line_items.iter().enumerate() {
+            invoices::add_line_item(
+                conn,
+                &invoice.id,
+                &item.description,
+                item.quantity,
+                item.unit_price,
+                item.unit,
+                i as i32,
+            )?;
+        }
+        recurring_invoices::record_occurrence(conn, &recurring.id, date, &invoice.id)?;
+        invoices::get_invoice(conn, &invoice.id)
+    })
+}
+
+/// Creates a draft invoice for every occurrence of every active schedule up to `today`
+/// that has not been generated yet. Safe to run repeatedly; each occurrence is recorded
+/// in the same transaction as its invoice.
+pub fn generate_due_invoices(conn: &Connection, today: NaiveDate) -> AppResult<Vec<Invoice>> {
+    let mut created = Vec::new();
+    for recurring in recurring_invoices::list_recurring_invoices(conn)? {
+        if !recurring.is_active {
+            continue;
+        }
+        let generated: HashSet<NaiveDate> =
+            recurring_invoices::list_occurrences(conn, &recurring.id)?
+                .into_iter()
+                .map(|o| o.occurrence_date)
+                .collect();
+        for date in occurrence_dates(&recurring.schedule, today) {
+            if !generated.contains(&date) {
+                created.push(materialize(conn, &recurring, date)?);
+            }
+        }
+    }
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::clients::create_client;
+    use crate::db::init_db_in_memory;
+    use crate::db::recurring_invoices::{
+        create_recurring_invoice, list_occurrences, set_recurring_invoice_active,
+    };
+    use crate::models::{
+        CreateClient, CreateRecurringInvoice, LineItemUnit, PaymentTerms, RecurringLineItem,
+    };
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn schedule(frequency: RecurringFrequency, start: NaiveDate) -> RecurringSchedule {
+        RecurringSchedule {
+            frequency,
+            interval: 1,
+            day_of_month: None,
+            start_date: start,
+            end_date: None,
+            count: None,
+        }
+    }
+
+    #[test]
+    fn test_monthly_clamps_to_month_end() {
+        let s = RecurringSchedule {
+            day_of_month: Some(31),
+            ..schedule(RecurringFrequency::Monthly, date(2025, 1, 10))
+        };
+        assert_eq!(
+            occurrence_dates(&s, date(2025, 4, 30)),
+            vec![date(2025, 1, 31), date(2025, 2, 28), date(2025, 3, 31), date(2025, 4, 30)]
+        );
+    }
+
+    #[test]
+    fn test_day_before_start_skips_first_month() {
+        let s = RecurringSchedule {
+            day_of_month: Some(1),
+            ..schedule(RecurringFrequency::Monthly, date(2025, 1, 15))
+        };
+        assert_eq!(
+            occurrence_dates(&s, date(2025, 3, 1)),
+            vec![date(2025, 2, 1), date(2025, 3, 1)]
+        );
+    }
+
+    #[test]
+    fn test_weekly_quarterly_end_date_and_count() {
+        let fortnightly = RecurringSchedule {
+            interval: 2,
+            count: Some(3),
+            ..schedule(RecurringFrequency::Weekly, date(2025, 1, 6))
+        };
+        assert_eq!(
+            occurrence_dates(&fortnightly, date(2026, 1, 1)),
+            vec![date(2025, 1, 6), date(2025, 1, 20), date(2025, 2, 3)]
+        );
+
+        let quarterly = RecurringSchedule {
+            end_date: Some(date(2025, 10, 14)),
+            ..schedule(RecurringFrequency::Quarterly, date(2025, 1, 15))
+        };
+        assert_eq!(
+            occurrence_dates(&quarterly, date(2026, 12, 31)),
+            vec![date(2025, 1, 15), date(2025, 4, 15), date(2025, 7, 15)]
+        );
+    }
+
+    fn setup_retainer(conn: &Connection) -> RecurringInvoice {
+        setup_retainer_on_terms(conn, Some(PaymentTerms::Net(14)))
+    }
+
+    fn setup_retainer_on_terms(conn: &Connection, terms: Option<PaymentTerms>) -> RecurringInvoice {
+        let client = create_client(
+            conn,
+            CreateClient {
+                name: "Retainer Co".to_string(),
+                payment_terms: Some(PaymentTerms::EndOfMonth(10)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        create_recurring_invoice(
+            conn,
+            CreateRecurringInvoice {
+                client_id: client.id,
+                name: "Monthly retainer".to_string(),
+                schedule: RecurringSchedule {
+                    day_of_month: Some(1),
+                    ..schedule(RecurringFrequency::Monthly, date(2025, 1, 1))
+                },
+                line_items: vec![RecurringLineItem {
+                    description: "Retainer".to_string(),
+                    quantity: 1.0,
+                    unit_price: 250000,
+                    unit: LineItemUnit::Fixed,
+                }],
+                tax_rate: Some(10.0),
+                notes: None,
+                payment_terms: terms,
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_catch_up_generates_each_occurrence_once() {
+        let conn = init_db_in_memory().unwrap();
+        let retainer = setup_retainer(&conn);
+
+        let created = generate_due_invoices(&conn, date(2025, 3, 10)).unwrap();
+        assert_eq!(created.len(), 3);
+        assert_eq!(created[1].issue_date.date_naive(), date(2025, 2, 1));
+        assert_eq!(created[1].due_date.date_naive(), date(2025, 2, 15));
+        assert_eq!(created[1].payment_terms, Some(PaymentTerms::Net(14)));
+        assert_eq!(created[1].total, 275000);
+        let lines = invoices::get_line_items(&conn, &created[1].id).unwrap();
+        assert_eq!(lines[0].unit, LineItemUnit::Fixed);
+
+        assert!(generate_due_invoices(&conn, date(2025, 3, 31)).unwrap().is_empty());
+        assert_eq!(generate_due_invoices(&conn, date(2025, 4, 1)).unwrap().len(), 1);
+        assert_eq!(list_occurrences(&conn, &retainer.id).unwrap().len(), 4);
+        assert_eq!(invoices::list_invoices(&conn, None).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_schedule_without_terms_uses_client_terms() {
+        let conn = init_db_in_memory().unwrap();
+        setup_retainer_on_terms(&conn, None);
+
+        let created = generate_due_invoices(&conn, date(2025, 1, 1)).unwrap();
+        assert_eq!(created[0].payment_terms, Some(PaymentTerms::EndOfMonth(10)));
+        assert_eq!(created[0].due_date.date_naive(), date(2025, 2, 10));
+    }
+
+    #[test]
+    fn test_paused_schedules_are_skipped() {
+        let conn = init_db_in_memory().unwrap();
+        let retainer = setup_retainer(&conn);
+        set_recurring_invoice_active(&conn, &retainer.id, false).unwrap();
+
+        assert!(generate_due_invoices(&conn, date(2025, 3, 10)).unwrap().is_empty());
+    }
+}
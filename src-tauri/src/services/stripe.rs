@@ -9,7 +9,7 @@ struct CheckoutSession {
 
 pub async fn create_checkout_session(
     api_key: &str,
-    amount_cents: i64,
+    amount_minor: i64,
     currency: &str,
     description: &str,
     success_url: &str,
@@ -28,7 +28,7 @@ pub async fn create_checkout_session(
         ("line_items[0][price_data][currency]", currency.to_string()),
         (
             "line_items[0][price_data][unit_amount]",
-            amount_cents.to_string(),
+            amount_minor.to_string(),
         ),
         (
             "line_items[0][price_data][product_data][name]",
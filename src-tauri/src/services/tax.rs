@@ -0,0 +1,224 @@
+// Tax calculation across line items.
+//
+// Each tax is charged once on the sum of the lines it applies to, following the
+// rounding rules in `services::money`. Non-compound taxes come first; a compound tax
+// is charged on its lines plus the non-compound taxes on those same lines (e.g. a
+// provincial tax levied on top of GST). Lines without taxes of their own fall back
+// to the document-wide rate. Exempt and reverse-charge clients are charged nothing.
+use rusqlite::Connection;
+
+use crate::db::settings;
+use crate::error::AppResult;
+use crate::models::{InvoiceTax, RoundingMode, Tax, TaxTreatment};
+use crate::services::locale::Labels;
+use crate::services::money;
+
+/// A line's net amount and the taxes assigned to it.
+pub struct TaxableLine {
+    pub amount: i64,
+    pub taxes: Vec<Tax>,
+}
+
+fn tax_line(tax: &Tax) -> InvoiceTax {
+    InvoiceTax {
+        tax_id: Some(tax.id.clone()),
+        name: tax.name.clone(),
+        rate: tax.rate,
+        is_compound: tax.is_compound,
+        taxable_amount: 0,
+        amount: 0,
+    }
+}
+
+/// The tax summary for a set of lines. Taxes that apply to no line, or whose taxable
+/// amount nets to zero, are left out.
+pub fn compute(
+    lines: &[TaxableLine],
+    default_rate: Option<f64>,
+    treatment: TaxTreatment,
+    mode: RoundingMode,
+) -> Vec<InvoiceTax> {
+    if treatment != TaxTreatment::Standard {
+        return Vec::new();
+    }
+
+    // Distinct taxes in order of first use, and which of them each line carries
+    let mut summary: Vec<InvoiceTax> = Vec::new();
+    let mut line_taxes: Vec<Vec<usize>> = Vec::with_capacity(lines.len());
+    for line in lines {
+        let mut assigned = Vec::new();
+        let taxes: Vec<InvoiceTax> = if line.taxes.is_empty() {
+            default_rate
+                .filter(|rate| *rate != 0.0)
+                .map(|rate| InvoiceTax {
+                    tax_id: None,
+                    name: "Tax".to_string(),
+                    rate,
+                    is_compound: false,
+                    taxable_amount: 0,
+                    amount: 0,
+                })
+                .into_iter()
+                .collect()
+        } else {
+            line.taxes.iter().map(tax_line).collect()
+        };
+        for tax in taxes {
+            let index = match summary.iter().position(|t| t.tax_id == tax.tax_id) {
+                Some(index) => index,
+                None => {
+                    summary.push(tax);
+                    summary.len() - 1
+                }
+            };
+            if !assigned.contains(&index) {
+                assigned.push(index);
+            }
+        }
+        line_taxes.push(assigned);
+    }
+
+    let base_of = |predicate: &dyn Fn(&[usize]) -> bool| -> i64 {
+        lines
+            .iter()
+            .zip(&line_taxes)
+            .filter(|(_, taxes)| predicate(taxes))
+            .map(|(line, _)| line.amount)
+            .sum()
+    };
+
+    let mut results = Vec::with_capacity(summary.len());
+    for (index, tax) in summary.iter().enumerate().filter(|(_, t)| !t.is_compound) {
+        let taxable_amount = base_of(&|taxes| taxes.contains(&index));
+        results.push((index, taxable_amount, money::tax_amount(taxable_amount, tax.rate, mode)));
+    }
+    for (index, tax) in summary.iter().enumerate().filter(|(_, t)| t.is_compound) {
+        let lines_base = base_of(&|taxes| taxes.contains(&index));
+        let stacked: i64 = summary
+            .iter()
+            .enumerate()
+            .filter(|(_, other)| !other.is_compound)
+            .map(|(other, other_tax)| {
+                let shared = base_of(&|taxes| taxes.contains(&index) && taxes.contains(&other));
+                money::tax_amount(shared, other_tax.rate, mode)
+            })
+            .sum();
+        let taxable_amount = lines_base + stacked;
+        results.push((index, taxable_amount, money::tax_amount(taxable_amount, tax.rate, mode)));
+    }
+
+    results
+        .into_iter()
+        .filter(|(_, taxable_amount, _)| *taxable_amount != 0)
+        .map(|(index, taxable_amount, amount)| InvoiceTax {
+            taxable_amount,
+            amount,
+            ..summary[index].clone()
+        })
+        .collect()
+}
+
+/// The wording an invoice must carry for a client's tax treatment, configurable via
+/// the `tax_exempt_note` and `reverse_charge_note` settings and otherwise in the
+/// document's language.
+pub fn legal_note(
+    conn: &Connection,
+    treatment: TaxTreatment,
+    labels: &Labels,
+) -> AppResult<Option<String>> {
+    let (key, default) = match treatment {
+        TaxTreatment::Standard => return Ok(None),
+        TaxTreatment::Exempt => ("tax_exempt_note", &labels.tax_exempt_note),
+        TaxTreatment::ReverseCharge => ("reverse_charge_note", &labels.reverse_charge_note),
+    };
+    let configured = settings::get_setting(conn, key)?.filter(|note| !note.trim().is_empty());
+    Ok(Some(configured.unwrap_or_else(|| default.clone())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db_in_memory;
+    use crate::db::settings::set_setting;
+    use crate::services::locale;
+    use chrono::Utc;
+
+    fn tax(id: &str, rate: f64, is_compound: bool) -> Tax {
+        Tax {
+            id: id.to_string(),
+            name: id.to_uppercase(),
+            rate,
+            is_compound,
+            is_active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn summary(taxes: &[InvoiceTax]) -> Vec<(&str, i64, i64)> {
+        taxes
+            .iter()
+            .map(|t| (t.name.as_str(), t.taxable_amount, t.amount))
+            .collect()
+    }
+
+    #[test]
+    fn test_taxes_apply_to_their_own_lines() {
+        let (gst, pst) = (tax("gst", 5.0, false), tax("pst", 7.0, false));
+        let lines = [
+            TaxableLine { amount: 100000, taxes: vec![gst.clone(), pst.clone()] },
+            TaxableLine { amount: 50000, taxes: vec![gst.clone()] },
+        ];
+        let taxes = compute(&lines, None, TaxTreatment::Standard, RoundingMode::HalfUp);
+        assert_eq!(summary(&taxes), vec![("GST", 150000, 7500), ("PST", 100000, 7000)]);
+    }
+
+    #[test]
+    fn test_compound_tax_is_charged_on_tax() {
+        // 9.975% on (1000.00 + 5% GST) = 104.74
+        let lines = [TaxableLine {
+            amount: 100000,
+            taxes: vec![tax("qst", 9.975, true), tax("gst", 5.0, false)],
+        }];
+        let taxes = compute(&lines, None, TaxTreatment::Standard, RoundingMode::HalfUp);
+        assert_eq!(summary(&taxes), vec![("GST", 100000, 5000), ("QST", 105000, 10474)]);
+    }
+
+    #[test]
+    fn test_default_rate_covers_lines_without_taxes() {
+        let vat = tax("vat", 20.0, false);
+        let lines = [
+            TaxableLine { amount: 10000, taxes: vec![] },
+            TaxableLine { amount: 20000, taxes: vec![vat] },
+        ];
+        let taxes = compute(&lines, Some(10.0), TaxTreatment::Standard, RoundingMode::HalfUp);
+        assert_eq!(summary(&taxes), vec![("Tax", 10000, 1000), ("VAT", 20000, 4000)]);
+        assert_eq!(taxes[0].tax_id, None);
+    }
+
+    #[test]
+    fn test_exempt_and_reverse_charge_clients_pay_no_tax() {
+        let conn = init_db_in_memory().unwrap();
+        let lines = [TaxableLine { amount: 10000, taxes: vec![tax("vat", 20.0, false)] }];
+        for treatment in [TaxTreatment::Exempt, TaxTreatment::ReverseCharge] {
+            assert!(compute(&lines, Some(10.0), treatment, RoundingMode::HalfUp).is_empty());
+        }
+
+        let labels = locale::lookup("en").unwrap().labels().unwrap();
+        assert_eq!(legal_note(&conn, TaxTreatment::Standard, &labels).unwrap(), None);
+        assert!(legal_note(&conn, TaxTreatment::ReverseCharge, &labels)
+            .unwrap()
+            .unwrap()
+            .contains("Article 196"));
+        let german = locale::lookup("de").unwrap().labels().unwrap();
+        assert!(legal_note(&conn, TaxTreatment::ReverseCharge, &german)
+            .unwrap()
+            .unwrap()
+            .starts_with("Steuerschuldnerschaft des Leistungsempfängers"));
+        set_setting(&conn, "tax_exempt_note", "Exempt under section 12").unwrap();
+        assert_eq!(
+            legal_note(&conn, TaxTreatment::Exempt, &german).unwrap().as_deref(),
+            Some("Exempt under section 12")
+        );
+    }
+}
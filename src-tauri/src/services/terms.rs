@@ -0,0 +1,103 @@
+// Payment terms and the due dates they produce.
+//
+// A client's own terms win; otherwise the `default_payment_terms` setting applies,
+// and Net 30 when that is unset.
+use chrono::{Datelike, Days, Months, NaiveDate};
+use rusqlite::Connection;
+
+use crate::db::settings;
+use crate::error::{AppError, AppResult};
+use crate::models::PaymentTerms;
+
+const SETTING: &str = "default_payment_terms";
+const FALLBACK_TERMS: PaymentTerms = PaymentTerms::Net(30);
+
+/// The terms for an invoice to this client.
+pub fn resolve(conn: &Connection, client_terms: Option<PaymentTerms>) -> AppResult<PaymentTerms> {
+    match client_terms {
+        Some(terms) => Ok(terms),
+        None => default_terms(conn),
+    }
+}
+
+/// The app-wide default terms from settings, falling back to Net 30.
+pub fn default_terms(conn: &Connection) -> AppResult<PaymentTerms> {
+    let configured = settings::get_setting(conn, SETTING)?;
+    Ok(configured
+        .and_then(|terms| PaymentTerms::from_str(terms.trim()))
+        .unwrap_or(FALLBACK_TERMS))
+}
+
+pub fn due_date(terms: PaymentTerms, issue_date: NaiveDate) -> NaiveDate {
+    match terms {
+        PaymentTerms::DueOnReceipt => issue_date,
+        PaymentTerms::Net(days) => issue_date + Days::new(u64::from(days)),
+        PaymentTerms::EndOfMonth(days) => {
+            let first_of_month = issue_date.with_day(1).unwrap_or(issue_date);
+            let end_of_month = first_of_month + Months::new(1) - Days::new(1);
+            end_of_month + Days::new(u64::from(days))
+        }
+    }
+}
+
+/// Rejects an unparseable `default_payment_terms` setting.
+pub fn validate_setting(key: &str, value: &str) -> AppResult<()> {
+    if key == SETTING && PaymentTerms::from_str(value.trim()).is_none() {
+        return Err(AppError::Validation(format!(
+            "Invalid {key}: {value}. Use due_on_receipt, net_<days>, eom or eom_<days>"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db_in_memory;
+    use crate::db::settings::set_setting;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_due_dates() {
+        let issued = date(2025, 1, 15);
+        assert_eq!(due_date(PaymentTerms::DueOnReceipt, issued), issued);
+        assert_eq!(due_date(PaymentTerms::Net(15), issued), date(2025, 1, 30));
+        assert_eq!(due_date(PaymentTerms::Net(60), issued), date(2025, 3, 16));
+        assert_eq!(due_date(PaymentTerms::EndOfMonth(0), issued), date(2025, 1, 31));
+        assert_eq!(due_date(PaymentTerms::EndOfMonth(10), date(2024, 2, 3)), date(2024, 3, 10));
+        assert_eq!(due_date(PaymentTerms::EndOfMonth(0), date(2025, 12, 31)), date(2025, 12, 31));
+    }
+
+    #[test]
+    fn test_terms_round_trip_and_labels() {
+        for (text, label) in [
+            ("due_on_receipt", "Due on receipt"),
+            ("net_30", "Net 30"),
+            ("eom", "Due at end of month"),
+            ("eom_15", "End of month + 15 days"),
+        ] {
+            let terms = PaymentTerms::from_str(text).unwrap();
+            assert_eq!(terms.as_string(), text);
+            assert_eq!(terms.label(), label);
+        }
+        assert_eq!(PaymentTerms::from_str("net_"), None);
+        assert_eq!(PaymentTerms::from_str("net_400"), None);
+        assert_eq!(PaymentTerms::from_str("net30"), None);
+    }
+
+    #[test]
+    fn test_default_terms_setting() {
+        let conn = init_db_in_memory().unwrap();
+        assert_eq!(resolve(&conn, None).unwrap(), PaymentTerms::Net(30));
+        set_setting(&conn, SETTING, "eom_5").unwrap();
+        assert_eq!(resolve(&conn, None).unwrap(), PaymentTerms::EndOfMonth(5));
+        assert_eq!(resolve(&conn, Some(PaymentTerms::Net(15))).unwrap(), PaymentTerms::Net(15));
+
+        assert!(validate_setting(SETTING, "net_45").is_ok());
+        assert!(matches!(validate_setting(SETTING, "soon"), Err(AppError::Validation(_))));
+        assert!(validate_setting("other", "soon").is_ok());
+    }
+}
@@ -1,3 +1,122 @@
 // Timer service - business logic for timer persistence
 // The core timer logic lives in db::time_entries
 // This module handles periodic save (every 30 seconds) coordination
+//
+// It also decides when the running timer has gone idle: the frontend reports
+// activity as heartbeats, and a run with no activity for longer than the
+// `idle_threshold_minutes` setting (10 by default, 0 turns detection off) has
+// its tail flagged as idle.
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::Connection;
+
+use crate::db::settings;
+use crate::error::{AppError, AppResult};
+use crate::models::ActiveTimer;
+
+const IDLE_SETTING: &str = "idle_threshold_minutes";
+const DEFAULT_IDLE_MINUTES: i64 = 10;
+
+/// `None` when idle detection is off.
+pub fn idle_threshold(conn: &Connection) -> AppResult<Option<Duration>> {
+    let minutes = settings::get_setting(conn, IDLE_SETTING)?
+        .and_then(|m| m.trim().parse::<i64>().ok())
+        .filter(|m| *m >= 0)
+        .unwrap_or(DEFAULT_IDLE_MINUTES);
+    Ok((minutes > 0).then(|| Duration::minutes(minutes)))
+}
+
+/// The idle stretch at the end of the timer's current run, from the last activity
+/// until now or until it was paused. `None` unless it is longer than `threshold`.
+pub fn idle_span(
+    timer: &ActiveTimer,
+    threshold: Duration,
+    now: DateTime<Utc>,
+) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let since = timer
+        .last_activity_at
+        .map_or(timer.start_time, |at| at.max(timer.start_time));
+    let until = timer.paused_at.unwrap_or(now);
+    (until - since > threshold).then_some((since, until))
+}
+
+/// The timer's idle stretch under the configured threshold.
+pub fn detect_idle(
+    conn: &Connection,
+    timer: &ActiveTimer,
+    now: DateTime<Utc>,
+) -> AppResult<Option<(DateTime<Utc>, DateTime<Utc>)>> {
+    Ok(idle_threshold(conn)?.and_then(|threshold| idle_span(timer, threshold, now)))
+}
+
+/// Rejects an `idle_threshold_minutes` setting that isn't a whole number of minutes.
+pub fn validate_setting(key: &str, value: &str) -> AppResult<()> {
+    if key == IDLE_SETTING && !matches!(value.trim().parse::<i64>(), Ok(m) if m >= 0) {
+        return Err(AppError::Validation(format!(
+            "Invalid {key}: {value}. Use a number of minutes, or 0 to turn idle detection off"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db_in_memory;
+    use crate::db::settings::set_setting;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, 3, hour, min, 0).unwrap()
+    }
+
+    fn timer(
+        last_activity_at: Option<DateTime<Utc>>,
+        paused_at: Option<DateTime<Utc>>,
+    ) -> ActiveTimer {
+        ActiveTimer {
+            id: 1,
+            name: None,
+            project_id: "p".to_string(),
+            description: None,
+            started_at: at(9, 0),
+            start_time: at(9, 0),
+            accumulated_secs: 0,
+            is_paused: paused_at.is_some(),
+            last_activity_at,
+            paused_at,
+        }
+    }
+
+    #[test]
+    fn test_idle_span() {
+        let ten = Duration::minutes(10);
+        assert_eq!(idle_span(&timer(Some(at(9, 55)), None), ten, at(10, 0)), None);
+        assert_eq!(
+            idle_span(&timer(Some(at(9, 30)), None), ten, at(10, 0)),
+            Some((at(9, 30), at(10, 0)))
+        );
+        // Paused timers stop going idle when they were paused
+        assert_eq!(
+            idle_span(&timer(Some(at(9, 30)), Some(at(9, 35))), ten, at(11, 0)),
+            None
+        );
+        assert_eq!(
+            idle_span(&timer(None, Some(at(9, 45))), ten, at(11, 0)),
+            Some((at(9, 0), at(9, 45)))
+        );
+    }
+
+    #[test]
+    fn test_idle_threshold_setting() {
+        let conn = init_db_in_memory().unwrap();
+        assert_eq!(idle_threshold(&conn).unwrap(), Some(Duration::minutes(10)));
+        set_setting(&conn, IDLE_SETTING, "3").unwrap();
+        assert_eq!(idle_threshold(&conn).unwrap(), Some(Duration::minutes(3)));
+        set_setting(&conn, IDLE_SETTING, "0").unwrap();
+        assert_eq!(idle_threshold(&conn).unwrap(), None);
+
+        assert!(validate_setting(IDLE_SETTING, "15").is_ok());
+        assert!(validate_setting(IDLE_SETTING, "-1").is_err());
+        assert!(validate_setting(IDLE_SETTING, "soon").is_err());
+    }
+}
@@ -1,7 +1,10 @@
 import type { Client, InvoiceLineItem } from "../../types";
 import { formatCurrency, formatDate } from "../../lib/formatters";
 
-type LineItemData = Omit<InvoiceLineItem, "id" | "invoice_id">;
+type LineItemData = Omit<
+  InvoiceLineItem,
+  "id" | "invoice_id" | "currency"
+>;
 
 interface InvoicePreviewProps {
   businessName: string;
@@ -9,6 +12,7 @@ interface InvoicePreviewProps {
   businessAddress: string;
   client: Client | null;
   lineItems: LineItemData[];
+  currency: string;
   issueDate: string;
   dueDate: string;
   subtotal: number;
@@ -25,6 +29,7 @@ export function InvoicePreview({
   businessAddress,
   client,
   lineItems,
+  currency,
   issueDate,
   dueDate,
   subtotal,
@@ -134,10 +139,10 @@ export function InvoicePreview({
                   {item.quantity}
                 </td>
                 <td className="py-3 text-sm text-gray-800 text-right">
-                  {formatCurrency(item.unit_price)}
+                  {formatCurrency(item.unit_price, currency)}
                 </td>
                 <td className="py-3 text-sm font-medium text-gray-900 text-right">
-                  {formatCurrency(item.amount)}
+                  {formatCurrency(item.amount, currency)}
                 </td>
               </tr>
             ))
@@ -151,20 +156,22 @@ export function InvoicePreview({
           <div className="flex justify-between py-2 text-sm">
             <span className="text-gray-600">Subtotal</span>
             <span className="text-gray-900 font-medium">
-              {formatCurrency(subtotal)}
+              {formatCurrency(subtotal, currency)}
             </span>
           </div>
           {taxRate > 0 && (
             <div className="flex justify-between py-2 text-sm border-b border-gray-100">
               <span className="text-gray-600">Tax ({taxRate}%)</span>
               <span className="text-gray-900 font-medium">
-                {formatCurrency(taxAmount)}
+                {formatCurrency(taxAmount, currency)}
               </span>
             </div>
           )}
           <div className="flex justify-between py-3 text-base font-bold border-t-2 border-gray-800 mt-1">
             <span className="text-gray-900">Total</span>
-            <span className="text-gray-900">{formatCurrency(total)}</span>
+            <span className="text-gray-900">
+              {formatCurrency(total, currency)}
+            </span>
           </div>
         </div>
       </div>
@@ -3,11 +3,15 @@ import type { InvoiceLineItem } from "../../types";
 import { formatCurrency } from "../../lib/formatters";
 import { Button } from "../shared/Button";
 
-type LineItemData = Omit<InvoiceLineItem, "id" | "invoice_id">;
+type LineItemData = Omit<
+  InvoiceLineItem,
+  "id" | "invoice_id" | "currency"
+>;
 
 interface LineItemRowProps {
   item: LineItemData;
   index: number;
+  currency: string;
   onUpdate: (index: number, item: LineItemData) => void;
   onRemove: (index: number) => void;
 }
@@ -15,6 +19,7 @@ interface LineItemRowProps {
 export function LineItemRow({
   item,
   index,
+  currency,
   onUpdate,
   onRemove,
 }: LineItemRowProps) {
@@ -71,7 +76,7 @@ export function LineItemRow({
         />
       </td>
       <td className="py-2 px-2 w-32 text-right text-sm font-medium text-gray-900">
-        {formatCurrency(item.amount)}
+        {formatCurrency(item.amount, currency)}
       </td>
       <td className="py-2 pl-2 w-12">
         <Button
@@ -0,0 +1,117 @@
+import { describe, it, expect, vi, beforeEach } from "vitest";
+
+vi.mock("@tauri-apps/api/core", () => ({
+  invoke: vi.fn(),
+}));
+
+import { invoke } from "@tauri-apps/api/core";
+import type { Invoice } from "../types";
+import {
+  addLineItem,
+  getDashboardSummary,
+  listClients,
+  listInvoices,
+} from "./commands";
+
+// Answers each command with a canned backend response
+const respond = (responses: Record<string, unknown>) =>
+  vi
+    .mocked(invoke)
+    .mockImplementation(((cmd: string) =>
+      Promise.resolve(responses[cmd])) as typeof invoke);
+
+describe("money at the command boundary", () => {
+  beforeEach(() => {
+    vi.mocked(invoke).mockReset();
+  });
+
+  it("sends line item prices in minor units of the invoice currency", async () => {
+    respond({
+      add_line_item: { currency: "USD", unit_price: 15050, amount: 30100 },
+    });
+    const invoice = { id: "inv-1", currency: "USD" } as Invoice;
+
+    const item = await addLineItem(invoice, "Design", 2, 150.5, 0);
+
+    expect(invoke).toHaveBeenCalledTimes(1);
+    expect(invoke).toHaveBeenCalledWith(
+      "add_line_item",
+      expect.objectContaining({ unitPrice: 15050 })
+    );
+    expect(item.unit_price).toBe(150.5);
+    expect(item.amount).toBe(301);
+  });
+
+  it("leaves zero-decimal currencies whole", async () => {
+    respond({
+      add_line_item: { currency: "JPY", unit_price: 15000, amount: 30000 },
+    });
+    const invoice = { id: "inv-2", currency: "JPY" } as Invoice;
+
+    const item = await addLineItem(invoice, "Design", 2, 15000, 0);
+
+    expect(invoke).toHaveBeenCalledWith(
+      "add_line_item",
+      expect.objectContaining({ unitPrice: 15000 })
+    );
+    expect(item.amount).toBe(30000);
+  });
+
+  it("returns invoice totals in major units", async () => {
+    respond({
+      list_invoices: [
+        { currency: "EUR", subtotal: 150000, tax_amount: 28500, total: 178500 },
+      ],
+    });
+
+    const [invoice] = await listInvoices();
+
+    expect(invoice.total).toBe(1785);
+    expect(invoice.tax_amount).toBe(285);
+  });
+
+  it("reads client rates in each client's billing currency", async () => {
+    respond({
+      list_clients: [
+        {
+          id: "c1",
+          currency: null,
+          billing_currency: "BHD",
+          hourly_rate: 45500,
+        },
+        {
+          id: "c2",
+          currency: "USD",
+          billing_currency: "USD",
+          hourly_rate: 12000,
+        },
+      ],
+    });
+
+    const clients = await listClients();
+
+    expect(invoke).toHaveBeenCalledTimes(1);
+    expect(clients.map((c) => c.hourly_rate)).toEqual([45.5, 120]);
+  });
+
+  it("keeps dashboard totals per currency", async () => {
+    respond({
+      get_dashboard_summary: {
+        revenue_by_currency: [
+          { currency: "EUR", amount: 125000 },
+          { currency: "JPY", amount: 30000 },
+        ],
+        outstanding_by_currency: [],
+        overdue_by_currency: [],
+        discounts_by_currency: [],
+      },
+    });
+
+    const summary = await getDashboardSummary();
+
+    expect(summary.revenue_by_currency).toEqual([
+      { currency: "EUR", amount: 1250 },
+      { currency: "JPY", amount: 30000 },
+    ]);
+  });
+});
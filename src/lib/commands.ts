@@ -14,36 +14,104 @@ import type {
   InvoiceLineItem,
   Estimate,
   AppSetting,
+  CurrencyAmount,
   DashboardSummary,
   RevenueByClient,
   HoursByProject,
   MonthlyRevenue,
   EstimateAccuracy,
 } from "../types";
+import { fromMinorUnits, toMinorUnits } from "./money";
+
+// Money crosses this boundary converted: the backend takes and returns integer
+// minor units, the UI works in major units. Every record carries the currency
+// its amounts are in, so reads convert from the record itself; writes take the
+// currency from the record the caller is editing.
+const rateFrom = (rate: number | null, currency: string) =>
+  rate == null ? null : fromMinorUnits(rate, currency);
+const rateTo = (rate: number | null | undefined, currency: string) =>
+  rate == null ? rate : toMinorUnits(rate, currency);
+
+const clientFrom = (client: Client): Client => ({
+  ...client,
+  hourly_rate: rateFrom(client.hourly_rate, client.billing_currency),
+});
+
+const projectFrom = (project: Project): Project => ({
+  ...project,
+  hourly_rate: rateFrom(project.hourly_rate, project.currency),
+});
+
+const invoiceFrom = (invoice: Invoice): Invoice => ({
+  ...invoice,
+  subtotal: fromMinorUnits(invoice.subtotal, invoice.currency),
+  tax_amount: fromMinorUnits(invoice.tax_amount, invoice.currency),
+  total: fromMinorUnits(invoice.total, invoice.currency),
+});
+
+const lineItemFrom = (item: InvoiceLineItem): InvoiceLineItem => ({
+  ...item,
+  unit_price: fromMinorUnits(item.unit_price, item.currency),
+  amount: fromMinorUnits(item.amount, item.currency),
+});
+
+const amountsFrom = (amounts: CurrencyAmount[]) =>
+  amounts.map((a) => ({ ...a, amount: fromMinorUnits(a.amount, a.currency) }));
 
 // Clients
-export const createClient = (input: CreateClient) =>
-  invoke<Client>("create_client", { input });
-export const getClient = (id: string) =>
-  invoke<Client>("get_client", { id });
-export const listClients = () =>
-  invoke<Client[]>("list_clients");
-export const updateClient = (id: string, input: UpdateClient) =>
-  invoke<Client>("update_client", { id, input });
+/** `currency` is the one `input.hourly_rate` is in. */
+export const createClient = async (input: CreateClient, currency: string) =>
+  clientFrom(
+    await invoke<Client>("create_client", {
+      input: { ...input, hourly_rate: rateTo(input.hourly_rate, currency) },
+    })
+  );
+export const getClient = async (id: string) =>
+  clientFrom(await invoke<Client>("get_client", { id }));
+export const listClients = async () =>
+  (await invoke<Client[]>("list_clients")).map(clientFrom);
+export const updateClient = async (client: Client, input: UpdateClient) => {
+  const currency = input.currency ?? client.billing_currency;
+  return clientFrom(
+    await invoke<Client>("update_client", {
+      id: client.id,
+      input: { ...input, hourly_rate: rateTo(input.hourly_rate, currency) },
+    })
+  );
+};
 export const deleteClient = (id: string) =>
   invoke<void>("delete_client", { id });
 
 // Projects
-export const createProject = (input: CreateProject) =>
-  invoke<Project>("create_project", { input });
-export const getProject = (id: string) =>
-  invoke<Project>("get_project", { id });
-export const listProjects = (status?: string) =>
-  invoke<Project[]>("list_projects", { status: status ?? null });
-export const listProjectsByClient = (clientId: string) =>
-  invoke<Project[]>("list_projects_by_client", { clientId });
-export const updateProject = (id: string, input: UpdateProject) =>
-  invoke<Project>("update_project", { id, input });
+export const createProject = async (input: CreateProject, client: Client) =>
+  projectFrom(
+    await invoke<Project>("create_project", {
+      input: {
+        ...input,
+        hourly_rate: rateTo(input.hourly_rate, client.billing_currency),
+      },
+    })
+  );
+export const getProject = async (id: string) =>
+  projectFrom(await invoke<Project>("get_project", { id }));
+export const listProjects = async (status?: string) =>
+  (
+    await invoke<Project[]>("list_projects", { status: status ?? null })
+  ).map(projectFrom);
+export const listProjectsByClient = async (clientId: string) =>
+  (await invoke<Project[]>("list_projects_by_client", { clientId })).map(
+    projectFrom
+  );
+export const updateProject = async (project: Project, input: UpdateProject) =>
+  projectFrom(
+    await invoke<Project>("update_project", {
+      id: project.id,
+      input: {
+        ...input,
+        hourly_rate: rateTo(input.hourly_rate, project.currency),
+      },
+    })
+  );
 export const deleteProject = (id: string) =>
   invoke<void>("delete_project", { id });
 
@@ -69,50 +137,58 @@ export const deleteTimeEntry = (id: string) =>
   invoke<void>("delete_time_entry", { id });
 
 // Invoices
-export const createInvoice = (
+export const createInvoice = async (
   clientId: string,
   issueDate: string,
   dueDate: string,
   notes?: string,
   taxRate?: number
 ) =>
-  invoke<Invoice>("create_invoice", {
-    clientId,
-    issueDate,
-    dueDate,
-    notes: notes ?? null,
-    taxRate: taxRate ?? null,
-  });
-export const getInvoice = (id: string) =>
-  invoke<Invoice>("get_invoice", { id });
-export const listInvoices = (status?: string) =>
-  invoke<Invoice[]>("list_invoices", { status: status ?? null });
-export const updateInvoiceStatus = (id: string, status: string) =>
-  invoke<Invoice>("update_invoice_status", { id, status });
+  invoiceFrom(
+    await invoke<Invoice>("create_invoice", {
+      clientId,
+      issueDate,
+      dueDate,
+      notes: notes ?? null,
+      taxRate: taxRate ?? null,
+    })
+  );
+export const getInvoice = async (id: string) =>
+  invoiceFrom(await invoke<Invoice>("get_invoice", { id }));
+export const listInvoices = async (status?: string) =>
+  (await invoke<Invoice[]>("list_invoices", { status: status ?? null })).map(
+    invoiceFrom
+  );
+export const updateInvoiceStatus = async (id: string, status: string) =>
+  invoiceFrom(await invoke<Invoice>("update_invoice_status", { id, status }));
 export const deleteInvoice = (id: string) =>
   invoke<void>("delete_invoice", { id });
-export const addLineItem = (
-  invoiceId: string,
+export const addLineItem = async (
+  invoice: Invoice,
   description: string,
   quantity: number,
   unitPrice: number,
   sortOrder: number
 ) =>
-  invoke<InvoiceLineItem>("add_line_item", {
-    invoiceId,
-    description,
-    quantity,
-    unitPrice,
-    sortOrder,
-  });
-export const getLineItems = (invoiceId: string) =>
-  invoke<InvoiceLineItem[]>("get_line_items", { invoiceId });
+  lineItemFrom(
+    await invoke<InvoiceLineItem>("add_line_item", {
+      invoiceId: invoice.id,
+      description,
+      quantity,
+      unitPrice: toMinorUnits(unitPrice, invoice.currency),
+      sortOrder,
+    })
+  );
+export const getLineItems = async (invoiceId: string) =>
+  (await invoke<InvoiceLineItem[]>("get_line_items", { invoiceId })).map(
+    lineItemFrom
+  );
 export const deleteLineItem = (id: string, invoiceId: string) =>
   invoke<void>("delete_line_item", { id, invoiceId });
 export const getUninvoicedEntries = (clientId: string) =>
   invoke<TimeEntry[]>("get_uninvoiced_entries", { clientId });
-export const setPaymentLink = (id: string, link: string) =>
-  invoke<Invoice>("set_payment_link", { id, link });
+export const setPaymentLink = async (id: string, link: string) =>
+  invoiceFrom(await invoke<Invoice>("set_payment_link", { id, link }));
 
 // Estimates
 export const getEstimate = (id: string) =>
@@ -121,32 +197,10 @@ export const listEstimates = () =>
   invoke<Estimate[]>("list_estimates");
 
 // PDF
-export const renderInvoiceHtml = (
-  invoiceId: string,
-  businessName: string,
-  businessEmail: string,
-  businessAddress: string
-) =>
-  invoke<string>("render_invoice_html", {
-    invoiceId,
-    businessName,
-    businessEmail,
-    businessAddress,
-  });
-export const exportInvoiceHtml = (
-  invoiceId: string,
-  businessName: string,
-  businessEmail: string,
-  businessAddress: string,
-  outputDir: string
-) =>
-  invoke<string>("export_invoice_html", {
-    invoiceId,
-    businessName,
-    businessEmail,
-    businessAddress,
-    outputDir,
-  });
+export const renderInvoiceHtml = (invoiceId: string) =>
+  invoke<string>("render_invoice_html", { invoiceId });
+export const exportInvoiceHtml = (invoiceId: string, outputDir: string) =>
+  invoke<string>("export_invoice_html", { invoiceId, outputDir });
 
 // AI Estimation
 export const runAiEstimate = (apiKey: string, projectDescription: string) =>
@@ -157,14 +211,30 @@ export const createPaymentLink = (apiKey: string, invoiceId: string) =>
   invoke<string>("create_payment_link", { apiKey, invoiceId });
 
 // Dashboard
-export const getDashboardSummary = () =>
-  invoke<DashboardSummary>("get_dashboard_summary", {});
-export const getRevenueByClient = () =>
-  invoke<RevenueByClient[]>("get_revenue_by_client", {});
+export const getDashboardSummary = async () => {
+  const summary = await invoke<DashboardSummary>("get_dashboard_summary", {});
+  return {
+    ...summary,
+    revenue_by_currency: amountsFrom(summary.revenue_by_currency),
+    outstanding_by_currency: amountsFrom(summary.outstanding_by_currency),
+    overdue_by_currency: amountsFrom(summary.overdue_by_currency),
+    discounts_by_currency: amountsFrom(summary.discounts_by_currency),
+  };
+};
+export const getRevenueByClient = async () =>
+  (await invoke<RevenueByClient[]>("get_revenue_by_client", {})).map((r) => ({
+    ...r,
+    total_revenue: fromMinorUnits(r.total_revenue, r.currency),
+    effective_rate: fromMinorUnits(r.effective_rate, r.currency),
+  }));
 export const getHoursByProject = (days?: number) =>
   invoke<HoursByProject[]>("get_hours_by_project", { days: days ?? null });
-export const getMonthlyRevenue = (months?: number) =>
-  invoke<MonthlyRevenue[]>("get_monthly_revenue", { months: months ?? null });
+export const getMonthlyRevenue = async (months?: number) =>
+  (
+    await invoke<MonthlyRevenue[]>("get_monthly_revenue", {
+      months: months ?? null,
+    })
+  ).map((m) => ({ ...m, revenue: fromMinorUnits(m.revenue, m.currency) }));
 export const getEstimateAccuracy = () =>
   invoke<EstimateAccuracy[]>("get_estimate_accuracy", {});
 
@@ -37,12 +37,17 @@ describe("formatHours", () => {
 
 describe("formatCurrency", () => {
   it("formats dollars", () => {
-    expect(formatCurrency(1500)).toBe("$1,500.00");
-    expect(formatCurrency(0)).toBe("$0.00");
-    expect(formatCurrency(99.99)).toBe("$99.99");
+    expect(formatCurrency(1500, "USD")).toBe("$1,500.00");
+    expect(formatCurrency(0, "USD")).toBe("$0.00");
+    expect(formatCurrency(99.99, "USD")).toBe("$99.99");
   });
 
   it("formats negative amounts", () => {
-    expect(formatCurrency(-100)).toBe("-$100.00");
+    expect(formatCurrency(-100, "USD")).toBe("-$100.00");
+  });
+
+  it("formats in the given currency", () => {
+    expect(formatCurrency(1500, "EUR")).toBe("€1,500.00");
+    expect(formatCurrency(1500, "JPY")).toBe("¥1,500");
   });
 });
@@ -14,10 +14,10 @@ export function formatHours(totalSecs: number): string {
   return `${hours}h`;
 }
 
-export function formatCurrency(amount: number): string {
+export function formatCurrency(amount: number, currency: string): string {
   return new Intl.NumberFormat("en-US", {
     style: "currency",
-    currency: "USD",
+    currency,
   }).format(amount);
 }
 
@@ -0,0 +1,32 @@
+import { describe, it, expect } from "vitest";
+import { minorDigits, toMinorUnits, fromMinorUnits } from "./money";
+
+describe("minorDigits", () => {
+  it("follows the currency's minor unit", () => {
+    expect(minorDigits("USD")).toBe(2);
+    expect(minorDigits("jpy")).toBe(0);
+    expect(minorDigits("BHD")).toBe(3);
+  });
+
+  it("treats unknown codes as two-decimal", () => {
+    expect(minorDigits("XYZ")).toBe(2);
+  });
+});
+
+describe("toMinorUnits", () => {
+  it("converts major units to whole minor units", () => {
+    expect(toMinorUnits(150, "USD")).toBe(15000);
+    expect(toMinorUnits(19.99, "EUR")).toBe(1999);
+    expect(toMinorUnits(1500, "JPY")).toBe(1500);
+    expect(toMinorUnits(1.5, "BHD")).toBe(1500);
+  });
+});
+
+describe("fromMinorUnits", () => {
+  it("converts minor units back to major units", () => {
+    expect(fromMinorUnits(15000, "USD")).toBe(150);
+    expect(fromMinorUnits(1999, "EUR")).toBe(19.99);
+    expect(fromMinorUnits(1500, "JPY")).toBe(1500);
+    expect(fromMinorUnits(-310, "USD")).toBe(-3.1);
+  });
+});
@@ -0,0 +1,27 @@
+// The backend stores money as integer minor units of each currency (cents, or
+// whole yen for JPY); the UI works in major units. These mirror the backend's
+// currency table: anything not listed has two decimals.
+const MINOR_DIGITS: Record<string, number> = {
+  JPY: 0,
+  KRW: 0,
+  VND: 0,
+  CLP: 0,
+  ISK: 0,
+  BHD: 3,
+  KWD: 3,
+  JOD: 3,
+  OMR: 3,
+  TND: 3,
+};
+
+export function minorDigits(currency: string): number {
+  return MINOR_DIGITS[currency.trim().toUpperCase()] ?? 2;
+}
+
+export function toMinorUnits(amount: number, currency: string): number {
+  return Math.round(amount * 10 ** minorDigits(currency));
+}
+
+export function fromMinorUnits(amount: number, currency: string): number {
+  return amount / 10 ** minorDigits(currency);
+}
@@ -12,10 +12,12 @@ import { Button } from "../components/shared/Button";
 import { Modal } from "../components/shared/Modal";
 import { EmptyState } from "../components/shared/EmptyState";
 import { ClientForm } from "../components/clients/ClientForm";
+import { useAppStore } from "../stores/appStore";
 
 export function ClientsPage() {
   const [clients, setClients] = useState<Client[]>([]);
   const [loading, setLoading] = useState(true);
+  const defaultCurrency = useAppStore((s) => s.defaultCurrency);
   const [error, setError] = useState<string | null>(null);
   const [modalOpen, setModalOpen] = useState(false);
   const [selectedClient, setSelectedClient] = useState<Client | undefined>(
@@ -56,9 +58,10 @@ export function ClientsPage() {
 
   async function handleSubmit(data: CreateClient | UpdateClient) {
     if (selectedClient) {
-      await updateClient(selectedClient.id, data as UpdateClient);
+      await updateClient(selectedClient, data as UpdateClient);
     } else {
-      await createClient(data as CreateClient);
+      const input = data as CreateClient;
+      await createClient(input, input.currency ?? defaultCurrency);
     }
     closeModal();
     await fetchClients();
@@ -94,7 +97,9 @@ export function ClientsPage() {
         key: "rate",
         header: "Rate",
         render: (c: Client) =>
-          c.hourly_rate != null ? formatCurrency(c.hourly_rate) + "/hr" : "-",
+          c.hourly_rate != null
+            ? formatCurrency(c.hourly_rate, c.billing_currency) + "/hr"
+            : "-",
       },
       {
         key: "created",
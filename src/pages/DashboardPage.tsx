@@ -6,6 +6,7 @@ import {
   YAxis,
   CartesianGrid,
   Tooltip,
+  Legend,
   ResponsiveContainer,
   PieChart,
   Pie,
@@ -13,7 +14,9 @@ import {
 } from "recharts";
 import { formatCurrency } from "../lib/formatters";
 import * as commands from "../lib/commands";
+import { useAppStore } from "../stores/appStore";
 import type {
+  CurrencyAmount,
   DashboardSummary,
   RevenueByClient,
   MonthlyRevenue,
@@ -29,6 +32,33 @@ const COLORS = [
   "#f43f5e",
 ];
 
+// Amounts in different currencies are never added up: each card lists one line
+// per currency, and the charts keep a series (or a pie) per currency.
+function formatAmounts(amounts: CurrencyAmount[], fallback: string): string[] {
+  return amounts.length === 0
+    ? [formatCurrency(0, fallback)]
+    : amounts.map((a) => formatCurrency(a.amount, a.currency));
+}
+
+function monthlyByCurrency(rows: MonthlyRevenue[]) {
+  const currencies = [...new Set(rows.map((r) => r.currency))].sort();
+  const months = new Map<string, Record<string, string | number>>();
+  for (const row of rows) {
+    const month = months.get(row.month) ?? { month: row.month };
+    month[row.currency] = row.revenue;
+    months.set(row.month, month);
+  }
+  return { currencies, data: [...months.values()] };
+}
+
+function clientsByCurrency(rows: RevenueByClient[]) {
+  const groups = new Map<string, RevenueByClient[]>();
+  for (const row of rows) {
+    groups.set(row.currency, [...(groups.get(row.currency) ?? []), row]);
+  }
+  return [...groups.entries()].sort(([a], [b]) => a.localeCompare(b));
+}
+
 export function DashboardPage() {
   const [summary, setSummary] = useState<DashboardSummary | null>(null);
   const [revenueByClient, setRevenueByClient] = useState<RevenueByClient[]>(
@@ -37,6 +67,7 @@ export function DashboardPage() {
   const [monthlyRevenue, setMonthlyRevenue] = useState<MonthlyRevenue[]>([]);
   const [hoursByProject, setHoursByProject] = useState<HoursByProject[]>([]);
   const [loading, setLoading] = useState(true);
+  const defaultCurrency = useAppStore((s) => s.defaultCurrency);
 
   const loadDashboard = useCallback(async () => {
     setLoading(true);
@@ -70,44 +101,73 @@ export function DashboardPage() {
     );
   }
 
+  const overdue = summary?.overdue_by_currency ?? [];
   const stats = [
     {
       label: "Total Revenue",
-      value: formatCurrency(summary?.total_revenue ?? 0),
+      values: formatAmounts(
+        summary?.revenue_by_currency ?? [],
+        defaultCurrency
+      ),
       color: "text-green-600",
     },
     {
       label: "Outstanding",
-      value: formatCurrency(summary?.outstanding_amount ?? 0),
+      values: formatAmounts(
+        summary?.outstanding_by_currency ?? [],
+        defaultCurrency
+      ),
+      note:
+        overdue.length > 0
+          ? `${formatAmounts(overdue, defaultCurrency).join(", ")} overdue`
+          : undefined,
       color: "text-warning-600",
     },
+    {
+      label: "Discounts Given",
+      values: formatAmounts(
+        summary?.discounts_by_currency ?? [],
+        defaultCurrency
+      ),
+      color: "text-gray-900",
+    },
     {
       label: "Hours This Week",
-      value: `${(summary?.hours_this_week ?? 0).toFixed(1)}h`,
+      values: [`${(summary?.hours_this_week ?? 0).toFixed(1)}h`],
       color: "text-primary-600",
     },
     {
       label: "Active Projects",
-      value: String(summary?.active_projects ?? 0),
+      values: [String(summary?.active_projects ?? 0)],
       color: "text-gray-900",
     },
   ];
+  const monthly = monthlyByCurrency(monthlyRevenue);
+  const clientGroups = clientsByCurrency(revenueByClient);
 
   return (
     <div>
       <h1 className="text-2xl font-bold text-gray-900 mb-6">Dashboard</h1>
 
       {/* Stat Cards */}
-      <div className="grid grid-cols-1 md:grid-cols-4 gap-4 mb-8">
+      <div className="grid grid-cols-1 md:grid-cols-3 lg:grid-cols-5 gap-4 mb-8">
         {stats.map((stat) => (
           <div
             key={stat.label}
             className="bg-white rounded-xl border border-gray-200 p-6"
           >
             <p className="text-sm text-gray-500">{stat.label}</p>
-            <p className={`text-2xl font-bold mt-1 ${stat.color}`}>
-              {stat.value}
-            </p>
+            {stat.values.map((value) => (
+              <p
+                key={value}
+                className={`text-2xl font-bold mt-1 ${stat.color}`}
+              >
+                {value}
+              </p>
+            ))}
+            {stat.note && (
+              <p className="text-sm text-danger-600 mt-1">{stat.note}</p>
+            )}
           </div>
         ))}
       </div>
@@ -124,14 +184,24 @@ export function DashboardPage() {
             </p>
           ) : (
             <ResponsiveContainer width="100%" height={250}>
-              <BarChart data={monthlyRevenue}>
+              <BarChart data={monthly.data}>
                 <CartesianGrid strokeDasharray="3 3" />
                 <XAxis dataKey="month" tick={{ fontSize: 12 }} />
                 <YAxis tick={{ fontSize: 12 }} />
                 <Tooltip
-                  formatter={(value) => formatCurrency(Number(value))}
+                  formatter={(value, currency) =>
+                    formatCurrency(Number(value), String(currency))
+                  }
                 />
-                <Bar dataKey="revenue" fill="#6366f1" radius={[4, 4, 0, 0]} />
+                {monthly.currencies.length > 1 && <Legend />}
+                {monthly.currencies.map((currency, index) => (
+                  <Bar
+                    key={currency}
+                    dataKey={currency}
+                    fill={COLORS[index % COLORS.length]}
+                    radius={[4, 4, 0, 0]}
+                  />
+                ))}
               </BarChart>
             </ResponsiveContainer>
           )}
@@ -147,31 +217,42 @@ export function DashboardPage() {
               No client revenue data yet.
             </p>
           ) : (
-            <ResponsiveContainer width="100%" height={250}>
-              <PieChart>
-                <Pie
-                  data={revenueByClient}
-                  dataKey="total_revenue"
-                  nameKey="client_name"
-                  cx="50%"
-                  cy="50%"
-                  outerRadius={80}
-                  label={({ name, percent }) =>
-                    `${name} (${((percent ?? 0) * 100).toFixed(0)}%)`
-                  }
-                >
-                  {revenueByClient.map((_, index) => (
-                    <Cell
-                      key={`cell-${index}`}
-                      fill={COLORS[index % COLORS.length]}
+            clientGroups.map(([currency, clients]) => (
+              <div key={currency}>
+                {clientGroups.length > 1 && (
+                  <h3 className="text-sm font-medium text-gray-500">
+                    {currency}
+                  </h3>
+                )}
+                <ResponsiveContainer width="100%" height={250}>
+                  <PieChart>
+                    <Pie
+                      data={clients}
+                      dataKey="total_revenue"
+                      nameKey="client_name"
+                      cx="50%"
+                      cy="50%"
+                      outerRadius={80}
+                      label={({ name, percent }) =>
+                        `${name} (${((percent ?? 0) * 100).toFixed(0)}%)`
+                      }
+                    >
+                      {clients.map((_, index) => (
+                        <Cell
+                          key={`cell-${index}`}
+                          fill={COLORS[index % COLORS.length]}
+                        />
+                      ))}
+                    </Pie>
+                    <Tooltip
+                      formatter={(value) =>
+                        formatCurrency(Number(value), currency)
+                      }
                     />
-                  ))}
-                </Pie>
-                <Tooltip
-                  formatter={(value) => formatCurrency(Number(value))}
-                />
-              </PieChart>
-            </ResponsiveContainer>
+                  </PieChart>
+                </ResponsiveContainer>
+              </div>
+            ))
           )}
         </div>
       </div>
@@ -8,7 +8,7 @@ import {
   createInvoice,
   addLineItem,
 } from "../lib/commands";
-import { formatHours } from "../lib/formatters";
+import { formatCurrency, formatHours } from "../lib/formatters";
 import { useInvoiceStore } from "../stores/invoiceStore";
 import { useAppStore } from "../stores/appStore";
 import { Button } from "../components/shared/Button";
@@ -17,7 +17,10 @@ import { Select } from "../components/shared/Select";
 import { LineItemRow } from "../components/invoices/LineItemRow";
 import { InvoicePreview } from "../components/invoices/InvoicePreview";
 
-type LineItemData = Omit<InvoiceLineItem, "id" | "invoice_id">;
+type LineItemData = Omit<
+  InvoiceLineItem,
+  "id" | "invoice_id" | "currency"
+>;
 
 export function InvoiceBuilderPage() {
   const [clients, setClients] = useState<Client[]>([]);
@@ -50,7 +53,9 @@ export function InvoiceBuilderPage() {
     total,
   } = useInvoiceStore();
 
-  const { businessName, businessEmail, businessAddress } = useAppStore();
+  const { businessName, businessEmail, businessAddress, defaultCurrency } =
+    useAppStore();
+  const currency = selectedClient?.billing_currency ?? defaultCurrency;
 
   // Load clients on mount
   const fetchClients = useCallback(async () => {
@@ -199,7 +204,7 @@ export function InvoiceBuilderPage() {
       for (let i = 0; i < lineItems.length; i++) {
         const item = lineItems[i];
         await addLineItem(
-          invoice.id,
+          invoice,
           item.description,
           item.quantity,
           item.unit_price,
@@ -269,6 +274,7 @@ export function InvoiceBuilderPage() {
           businessAddress={businessAddress}
           client={selectedClient}
           lineItems={lineItems}
+          currency={currency}
           issueDate={issueDate}
           dueDate={dueDate}
           subtotal={subtotal()}
@@ -362,6 +368,7 @@ export function InvoiceBuilderPage() {
                         key={idx}
                         item={item}
                         index={idx}
+                        currency={currency}
                         onUpdate={handleUpdateItem}
                         onRemove={removeLineItem}
                       />
@@ -391,30 +398,21 @@ export function InvoiceBuilderPage() {
                 <div className="flex justify-between text-sm">
                   <span className="text-gray-600">Subtotal</span>
                   <span className="font-medium text-gray-900">
-                    {new Intl.NumberFormat("en-US", {
-                      style: "currency",
-                      currency: "USD",
-                    }).format(subtotal())}
+                    {formatCurrency(subtotal(), currency)}
                   </span>
                 </div>
                 {taxRate > 0 && (
                   <div className="flex justify-between text-sm">
                     <span className="text-gray-600">Tax ({taxRate}%)</span>
                     <span className="font-medium text-gray-900">
-                      {new Intl.NumberFormat("en-US", {
-                        style: "currency",
-                        currency: "USD",
-                      }).format(taxAmount())}
+                      {formatCurrency(taxAmount(), currency)}
                     </span>
                   </div>
                 )}
                 <div className="flex justify-between text-base font-bold border-t border-gray-200 pt-2">
                   <span className="text-gray-900">Total</span>
                   <span className="text-gray-900">
-                    {new Intl.NumberFormat("en-US", {
-                      style: "currency",
-                      currency: "USD",
-                    }).format(total())}
+                    {formatCurrency(total(), currency)}
                   </span>
                 </div>
               </div>
@@ -7,7 +7,6 @@ import { EmptyState } from "../components/shared/EmptyState";
 import { Modal } from "../components/shared/Modal";
 import { formatCurrency, formatDate } from "../lib/formatters";
 import * as commands from "../lib/commands";
-import { useAppStore } from "../stores/appStore";
 import type { Invoice, InvoiceStatus } from "../types";
 
 const STATUS_TABS: { label: string; value: InvoiceStatus | "all" }[] = [
@@ -26,7 +25,6 @@ export function InvoicesPage() {
   );
   const [loading, setLoading] = useState(true);
   const [previewHtml, setPreviewHtml] = useState<string | null>(null);
-  const { businessName, businessEmail, businessAddress } = useAppStore();
 
   const loadInvoices = useCallback(async () => {
     setLoading(true);
@@ -45,12 +43,7 @@ export function InvoicesPage() {
 
   async function handlePreview(invoice: Invoice) {
     try {
-      const html = await commands.renderInvoiceHtml(
-        invoice.id,
-        businessName,
-        businessEmail,
-        businessAddress
-      );
+      const html = await commands.renderInvoiceHtml(invoice.id);
       setPreviewHtml(html);
     } catch (err) {
       console.error("Failed to render invoice:", err);
@@ -93,7 +86,9 @@ export function InvoicesPage() {
       key: "total",
       header: "Total",
       render: (inv: Invoice) => (
-        <span className="font-medium">{formatCurrency(inv.total)}</span>
+        <span className="font-medium">
+          {formatCurrency(inv.total, inv.currency)}
+        </span>
       ),
     },
     {
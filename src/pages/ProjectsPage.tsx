@@ -85,9 +85,10 @@ export function ProjectsPage() {
     setSubmitting(true);
     try {
       if (editingProject) {
-        await updateProject(editingProject.id, data as UpdateProject);
+        await updateProject(editingProject, data as UpdateProject);
       } else {
-        await createProject(data as CreateProject);
+        const input = data as CreateProject;
+        await createProject(input, clientMap[input.client_id]);
       }
       closeModal();
       await fetchData();
@@ -140,7 +141,9 @@ export function ProjectsPage() {
       key: "rate",
       header: "Rate",
       render: (p: Project) =>
-        p.hourly_rate !== null ? formatCurrency(p.hourly_rate) : "--",
+        p.hourly_rate !== null
+          ? formatCurrency(p.hourly_rate, p.currency)
+          : "--",
     },
     {
       key: "budget",
@@ -16,6 +16,7 @@ describe("appStore", () => {
       businessEmail: "",
       businessAddress: "",
       defaultHourlyRate: 100,
+      defaultCurrency: "USD",
       claudeApiKey: "",
       stripeApiKey: "",
       theme: "system",
@@ -29,6 +30,7 @@ describe("appStore", () => {
     expect(state.tier).toBe("free");
     expect(state.businessName).toBe("");
     expect(state.defaultHourlyRate).toBe(100);
+    expect(state.defaultCurrency).toBe("USD");
     expect(state.theme).toBe("system");
     expect(state.loading).toBe(true);
   });
@@ -39,6 +41,7 @@ describe("appStore", () => {
       { key: "business_name", value: "Acme Corp" },
       { key: "business_email", value: "hi@acme.com" },
       { key: "default_hourly_rate", value: "150" },
+      { key: "default_currency", value: "eur" },
       { key: "theme", value: "dark" },
     ]);
 
@@ -49,6 +52,7 @@ describe("appStore", () => {
     expect(state.businessName).toBe("Acme Corp");
     expect(state.businessEmail).toBe("hi@acme.com");
     expect(state.defaultHourlyRate).toBe(150);
+    expect(state.defaultCurrency).toBe("EUR");
     expect(state.theme).toBe("dark");
     expect(state.loading).toBe(false);
   });
@@ -117,4 +121,12 @@ describe("appStore", () => {
 
     expect(useAppStore.getState().defaultHourlyRate).toBe(100);
   });
+
+  it("saveSetting normalizes the default currency", async () => {
+    vi.mocked(commands.setSetting).mockResolvedValue(undefined);
+
+    await useAppStore.getState().saveSetting("default_currency", " gbp ");
+
+    expect(useAppStore.getState().defaultCurrency).toBe("GBP");
+  });
 });
@@ -7,6 +7,7 @@ type Theme = "light" | "dark" | "system";
 const DEFAULT_TIER: Tier = "free";
 const DEFAULT_THEME: Theme = "system";
 const DEFAULT_HOURLY_RATE = 100;
+const DEFAULT_CURRENCY = "USD";
 
 const isTier = (value: string | undefined): value is Tier =>
   value === "free" || value === "pro" || value === "premium";
@@ -25,12 +26,16 @@ const parseHourlyRate = (value: string | undefined): number => {
   return Number.isFinite(parsed) ? parsed : DEFAULT_HOURLY_RATE;
 };
 
+const parseCurrency = (value: string | undefined): string =>
+  value?.trim().toUpperCase() || DEFAULT_CURRENCY;
+
 interface AppStore {
   tier: Tier;
   businessName: string;
   businessEmail: string;
   businessAddress: string;
   defaultHourlyRate: number;
+  defaultCurrency: string;
   claudeApiKey: string;
   stripeApiKey: string;
   theme: Theme;
@@ -46,6 +51,7 @@ export const useAppStore = create<AppStore>()((set) => ({
   businessEmail: "",
   businessAddress: "",
   defaultHourlyRate: DEFAULT_HOURLY_RATE,
+  defaultCurrency: DEFAULT_CURRENCY,
   claudeApiKey: "",
   stripeApiKey: "",
   theme: DEFAULT_THEME,
@@ -62,6 +68,7 @@ export const useAppStore = create<AppStore>()((set) => ({
         businessEmail: map.get("business_email") ?? "",
         businessAddress: map.get("business_address") ?? "",
         defaultHourlyRate: parseHourlyRate(map.get("default_hourly_rate")),
+        defaultCurrency: parseCurrency(map.get("default_currency")),
         claudeApiKey: map.get("claude_api_key") ?? "",
         stripeApiKey: map.get("stripe_api_key") ?? "",
         theme: parseTheme(map.get("theme")),
@@ -81,15 +88,19 @@ export const useAppStore = create<AppStore>()((set) => ({
       business_email: "businessEmail",
       business_address: "businessAddress",
       default_hourly_rate: "defaultHourlyRate",
+      default_currency: "defaultCurrency",
       claude_api_key: "claudeApiKey",
       stripe_api_key: "stripeApiKey",
       theme: "theme",
     };
     const stateKey = keyMap[key];
     if (stateKey) {
+      const parsers: Record<string, (value: string) => unknown> = {
+        defaultHourlyRate: parseHourlyRate,
+        defaultCurrency: parseCurrency,
+      };
       set({
-        [stateKey]:
-          stateKey === "defaultHourlyRate" ? parseHourlyRate(value) : value,
+        [stateKey]: parsers[stateKey]?.(value) ?? value,
       } as Partial<AppStore>);
     }
   },
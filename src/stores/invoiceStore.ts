@@ -1,24 +1,26 @@
 import { create } from "zustand";
 import type { InvoiceLineItem } from "../types";
 
+type LineItemData = Omit<
+  InvoiceLineItem,
+  "id" | "invoice_id" | "currency"
+>;
+
 interface InvoiceBuilderStore {
   clientId: string | null;
   issueDate: string;
   dueDate: string;
   notes: string;
   taxRate: number;
-  lineItems: Omit<InvoiceLineItem, "id" | "invoice_id">[];
+  lineItems: LineItemData[];
 
   setClientId: (id: string | null) => void;
   setIssueDate: (date: string) => void;
   setDueDate: (date: string) => void;
   setNotes: (notes: string) => void;
   setTaxRate: (rate: number) => void;
-  addLineItem: (item: Omit<InvoiceLineItem, "id" | "invoice_id">) => void;
-  updateLineItem: (
-    index: number,
-    item: Omit<InvoiceLineItem, "id" | "invoice_id">
-  ) => void;
+  addLineItem: (item: LineItemData) => void;
+  updateLineItem: (index: number, item: LineItemData) => void;
   removeLineItem: (index: number) => void;
   reset: () => void;
 
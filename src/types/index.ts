@@ -6,7 +6,12 @@ export interface Client {
   address: string | null;
   phone: string | null;
   notes: string | null;
+  /** Major units of `billing_currency` per hour, converted in `lib/commands`. */
   hourly_rate: number | null;
+  /** ISO 4217 code; `null` bills in the default currency. */
+  currency: string | null;
+  /** `currency`, or the default currency when the client has none. */
+  billing_currency: string;
   created_at: string;
   updated_at: string;
 }
@@ -19,6 +24,7 @@ export interface CreateClient {
   phone?: string | null;
   notes?: string | null;
   hourly_rate?: number | null;
+  currency?: string | null;
 }
 
 export interface UpdateClient {
@@ -29,6 +35,7 @@ export interface UpdateClient {
   phone?: string | null;
   notes?: string | null;
   hourly_rate?: number | null;
+  currency?: string | null;
 }
 
 export type ProjectStatus = "active" | "completed" | "archived" | "on_hold";
@@ -39,7 +46,10 @@ export interface Project {
   name: string;
   description: string | null;
   status: ProjectStatus;
+  /** Major units of `currency` per hour. */
   hourly_rate: number | null;
+  /** The client's billing currency. */
+  currency: string;
   budget_hours: number | null;
   created_at: string;
   updated_at: string;
@@ -92,6 +102,8 @@ export interface Invoice {
   status: InvoiceStatus;
   issue_date: string;
   due_date: string;
+  currency: string;
+  /** Amounts are major units of `currency`. */
   subtotal: number;
   tax_rate: number | null;
   tax_amount: number;
@@ -107,7 +119,9 @@ export interface InvoiceLineItem {
   invoice_id: string;
   description: string;
   quantity: number;
+  /** Major units of `currency`, the invoice's currency. */
   unit_price: number;
+  currency: string;
   amount: number;
   sort_order: number;
 }
@@ -152,9 +166,19 @@ export interface AppSetting {
 
 export type Tier = "free" | "pro" | "premium";
 
+/** Totals are kept per currency rather than summed across currencies. */
+export interface CurrencyAmount {
+  currency: string;
+  /** Major units of `currency`. */
+  amount: number;
+}
+
 export interface DashboardSummary {
-  total_revenue: number;
-  outstanding_amount: number;
+  revenue_by_currency: CurrencyAmount[];
+  outstanding_by_currency: CurrencyAmount[];
+  /** The part of `outstanding_by_currency` that is past due. */
+  overdue_by_currency: CurrencyAmount[];
+  discounts_by_currency: CurrencyAmount[];
   hours_this_week: number;
   hours_this_month: number;
   active_projects: number;
@@ -163,7 +187,10 @@ export interface DashboardSummary {
 
 export interface RevenueByClient {
   client_name: string;
+  currency: string;
+  /** Major units of `currency`; `effective_rate` is per hour. */
   total_revenue: number;
+  /** Hours billed in `currency`, plus unbilled hours in the client's billing currency. */
   total_hours: number;
   effective_rate: number;
 }
@@ -176,6 +203,8 @@ export interface HoursByProject {
 
 export interface MonthlyRevenue {
   month: string;
+  currency: string;
+  /** Major units of `currency`. */
   revenue: number;
 }
 